@@ -3,6 +3,10 @@
 Common definitions for the `@rules_rust//cargo` package
 """
 
+load(
+    "//cargo/private:cargo_artifact.bzl",
+    _cargo_artifact = "cargo_artifact",
+)
 load(
     "//cargo/private:cargo_bootstrap.bzl",
     _cargo_bootstrap_repository = "cargo_bootstrap_repository",
@@ -22,6 +26,8 @@ load(
 )
 load("//cargo/private:cargo_toml_env_vars.bzl", _cargo_toml_env_vars = "cargo_toml_env_vars")
 
+cargo_artifact = _cargo_artifact
+
 cargo_bootstrap_repository = _cargo_bootstrap_repository
 cargo_env = _cargo_env
 
@@ -0,0 +1,41 @@
+"""Rules for Cargo artifact dependencies"""
+
+def _cargo_artifact_transition_impl(_settings, attr):
+    return {"//command_line_option:platforms": str(attr.platform)}
+
+_cargo_artifact_transition = transition(
+    implementation = _cargo_artifact_transition_impl,
+    inputs = [],
+    outputs = ["//command_line_option:platforms"],
+)
+
+def _cargo_artifact_impl(ctx):
+    # The transition makes `actual` a list with a single element.
+    actual = ctx.attr.actual[0]
+    return [
+        DefaultInfo(
+            files = actual[DefaultInfo].files,
+            runfiles = actual[DefaultInfo].default_runfiles,
+        ),
+    ]
+
+cargo_artifact = rule(
+    implementation = _cargo_artifact_impl,
+    doc = (
+        "A rule for building the artifact of a Cargo " +
+        "[artifact dependency](https://doc.rust-lang.org/nightly/cargo/reference/unstable.html#artifact-dependencies) " +
+        "for a specific platform, as requested by its `target` key. The files of `actual` are " +
+        "forwarded so they can be used as `data` of other targets."
+    ),
+    attrs = {
+        "actual": attr.label(
+            doc = "The binary or library target producing the artifact.",
+            cfg = _cargo_artifact_transition,
+            mandatory = True,
+        ),
+        "platform": attr.label(
+            doc = "The platform to build `actual` for.",
+            mandatory = True,
+        ),
+    },
+)
@@ -139,6 +139,72 @@ This will result in all dependencies being updated for a project. The `CARGO_BAZ
 environment variable can also be used to customize how dependencies are updated.
 For more details about repin, [please refer to the documentation](https://bazelbuild.github.io/rules_rust/crate_universe_workspace.html#crates_vendor).
 
+#### Merging lockfiles
+
+The `merge-lockfile` command of `cargo-bazel` can be used as a git merge driver to resolve conflicts
+in `cargo-bazel-lock.json` files. Changes from both sides to different crates, dependencies or
+conditions are combined, while changes which can't be combined (such as both sides updating the same
+crate to different versions) are reported and need to be resolved by repinning. The checksum of a
+merged lockfile is cleared, so a repin is still required before the next build.
+
+```ini
+# .gitattributes
+cargo-bazel-lock.json merge=cargo-bazel-lock
+
+# .git/config
+[merge "cargo-bazel-lock"]
+    name = cargo-bazel lockfile merge driver
+    driver = cargo-bazel merge-lockfile %O %A %B
+```
+
+#### Finding outdated crates
+
+The `outdated` command of `cargo-bazel` lists every crate in a lockfile along with the latest semver
+compatible and the latest overall version, the workspace members depending on it and whether it's a
+direct dependency. Versions are read from the registry indexes Cargo has already cached locally, so
+crates missing from the cache are reported without versions. Pass `--format=json` for a machine
+readable report:
+
+```shell
+cargo-bazel outdated \\
+    --lockfile=cargo-bazel-lock.json \\
+    --cargo-lockfile=Cargo.lock \\
+    --format=json
+```
+
+#### Generating BUILD files for workspace members
+
+Instead of writing targets for workspace members by hand, the `members` command of `cargo-bazel`
+can create and update them from each member's Cargo.toml. Libraries, binaries, integration tests
+and build scripts are generated with dependencies on the aliases of the crates repository and on
+other workspace members:
+
+```shell
+cargo-bazel members \\
+    --config=<path to cargo-bazel.json in the crates repository> \\
+    --lockfile=cargo-bazel-lock.json \\
+    --workspace-dir=.
+```
+
+Existing BUILD files are updated in place. Only attributes derived from Cargo.toml, such as `srcs`,
+`deps` or `edition`, are rewritten and any other attributes are preserved. Add a `# keep` comment
+after an attribute, or on the line above a target, to stop it from being updated.
+
+#### Annotations in Cargo.toml
+
+Annotations for third-party crates can also be declared in the root Cargo.toml of the workspace.
+Tables are keyed by a crate name and version requirement and accept the same fields as
+`crate.annotation`:
+
+```toml
+[workspace.metadata.bazel.annotations."openssl-sys 0.9"]
+gen_build_script = false
+rustc_flags = ["--cfg=ossl111"]
+```
+
+These are merged with any `crate.annotation` tags for the same crate. Where both set a single
+value, such as `gen_build_script`, the value from MODULE.bazel is used.
+
 ### Direct Dependencies
 
 In cases where Rust targets have heavy interactions with other Bazel targets ([Cc](https://docs.bazel.build/versions/main/be/c-cpp.html), [Proto](https://rules-proto-grpc.com/en/4.5.0/lang/rust.html),
@@ -197,6 +263,14 @@ This method has the following consequences:
 * if you use shared dependency tree with your project these binary dependencies will interfere with yours (may conflict)
 * you have to use  nightly `host_tools` to generate dependencies because
 
+Binaries, `cdylib`s and `staticlib`s requested through an `artifact` key are always generated for
+the crate providing them, without needing `gen_binaries` or `gen_shared_library`. Crates from git
+or local paths may have artifact dependencies of their own. Their artifacts are added to the
+`compile_data` of the crate, or to the `tools` (`data` with a `target`) of its build script, and the
+`CARGO_BIN_FILE_<DEP>_<NAME>`, `CARGO_CDYLIB_FILE_<DEP>` and `CARGO_STATICLIB_FILE_<DEP>` variables
+are set through `rustc_env` or `build_script_env`. Artifacts with a `target` triple are built by a
+`cargo_artifact` target which transitions to the platform rendered from `platforms_template`.
+
 Alternatively you can specify this in a separate `repo` with `cargo.from_specs` syntax:
 
 ```python
@@ -392,6 +466,7 @@ load(
     "CARGO_BAZEL_GENERATOR_URL",
     "CRATES_REPOSITORY_ENVIRON",
     "GENERATOR_ENV_VARS",
+    "collect_custom_targets",
     "determine_repin",
     "execute_generator",
     generate_render_config = "render_config",
@@ -592,6 +667,9 @@ def _generate_hub_and_spokes(
             annotations = annotations,
             generate_build_scripts = cfg.generate_build_scripts,
             supported_platform_triples = cfg.supported_platform_triples,
+            platform_cfgs = cfg.platform_cfgs,
+            strict_package_metadata = cfg.strict_package_metadata,
+            custom_targets = collect_custom_targets(module_ctx, cfg.custom_targets),
             generate_target_compatible_with = True,
             repository_name = cfg.name,
             output_pkg = cfg.name,
@@ -902,14 +980,22 @@ def _crate_impl(module_ctx):
             # split it into two parameters.
             if annotation_dict.pop("gen_all_binaries"):
                 annotation_dict["gen_binaries"] = True
+            if annotation_dict.pop("gen_all_tests"):
+                annotation_dict["gen_tests"] = True
+            if annotation_dict.pop("gen_all_examples"):
+                annotation_dict["gen_examples"] = True
+            if annotation_dict.pop("gen_all_benches"):
+                annotation_dict["gen_benches"] = True
             annotation_dict["gen_build_script"] = _OPT_BOOL_VALUES[annotation_dict["gen_build_script"]]
+            annotation_dict["gen_shared_library"] = _OPT_BOOL_VALUES[annotation_dict["gen_shared_library"]]
+            annotation_dict["gen_static_library"] = _OPT_BOOL_VALUES[annotation_dict["gen_static_library"]]
 
             # Process the override targets for the annotation.
             # In the non-bzlmod approach, this is given as a dict
             # with the possible keys "`proc_macro`, `build_script`, `lib`, `bin`".
             # With the tag-based approach used in Bzlmod, we run into an issue
             # where there is no dict type that takes a string as a key and a Label as the value.
-            # To work around this, we split the override option into four, and reconstruct the
+            # To work around this, we split the override option into one attribute per key, and reconstruct the
             # dictionary here during processing
             annotation_dict["override_targets"] = dict()
             replacement = annotation_dict.pop("override_target_lib")
@@ -928,6 +1014,14 @@ def _crate_impl(module_ctx):
             if replacement:
                 annotation_dict["override_targets"]["bin"] = str(replacement)
 
+            replacement = annotation_dict.pop("override_target_cdylib")
+            if replacement:
+                annotation_dict["override_targets"]["cdylib"] = str(replacement)
+
+            replacement = annotation_dict.pop("override_target_staticlib")
+            if replacement:
+                annotation_dict["override_targets"]["staticlib"] = str(replacement)
+
             annotation = _crate_universe_crate.annotation(**{
                 k: v
                 for k, v in annotation_dict.items()
@@ -1055,6 +1149,15 @@ def _crate_impl(module_ctx):
 _FROM_COMMON_ATTRS = {
     "cargo_config": CRATES_VENDOR_ATTRS["cargo_config"],
     "cargo_lockfile": CRATES_VENDOR_ATTRS["cargo_lockfile"],
+    "custom_targets": attr.label_keyed_string_dict(
+        doc = (
+            "A mapping of custom target specification JSON files to the label of a `config_setting` matching " +
+            "the target. As with `rustc --target`, each target is named after its file (without the `.json` " +
+            "extension) and is added to `supported_platform_triples`. Labels must be absolute, including the " +
+            "repository (e.g. `@//platforms:my_target`), as they're rendered into the generated BUILD files."
+        ),
+        allow_files = [".json"],
+    ),
     "generate_binaries": CRATES_VENDOR_ATTRS["generate_binaries"],
     "generate_build_scripts": CRATES_VENDOR_ATTRS["generate_build_scripts"],
     "host_tools": attr.label(
@@ -1077,6 +1180,7 @@ _FROM_COMMON_ATTRS = {
             "If set, this file must exist within the workspace (but can be empty) before this rule will work."
         ),
     ),
+    "platform_cfgs": CRATES_VENDOR_ATTRS["platform_cfgs"],
     "skip_cargo_lockfile_overwrite": attr.bool(
         doc = (
             "Whether to skip writing the cargo lockfile back after resolving. " +
@@ -1085,6 +1189,7 @@ _FROM_COMMON_ATTRS = {
         ),
         default = False,
     ),
+    "strict_package_metadata": CRATES_VENDOR_ATTRS["strict_package_metadata"],
     "supported_platform_triples": attr.string_list(
         doc = "A set of all platform triples to consider when generating dependencies.",
         default = SUPPORTED_PLATFORM_TRIPLES,
@@ -1190,9 +1295,21 @@ _annotation = tag_class(
         "extra_aliased_targets": attr.string_dict(
             doc = "A list of targets to add to the generated aliases in the root crate_universe repository.",
         ),
+        "gen_all_benches": attr.bool(
+            doc = "If true, generates `rust_test` targets for all of the crates benches",
+        ),
         "gen_all_binaries": attr.bool(
             doc = "If true, generates `rust_binary` targets for all of the crates bins",
         ),
+        "gen_all_examples": attr.bool(
+            doc = "If true, generates `rust_binary` targets for all of the crates examples",
+        ),
+        "gen_all_tests": attr.bool(
+            doc = "If true, generates `rust_test` targets for all of the crates integration tests",
+        ),
+        "gen_benches": attr.string_list(
+            doc = "As a list, the subset of the crate's benches that should get `rust_test` targets produced.",
+        ),
         "gen_binaries": attr.string_list(
             doc = "As a list, the subset of the crate's bins that should get `rust_binary` targets produced.",
         ),
@@ -1201,18 +1318,40 @@ _annotation = tag_class(
             values = _OPT_BOOL_VALUES.keys(),
             default = "auto",
         ),
+        "gen_examples": attr.string_list(
+            doc = "As a list, the subset of the crate's examples that should get `rust_binary` targets produced.",
+        ),
+        "gen_shared_library": attr.string(
+            doc = "Whether or not to produce a `rust_shared_library` target for crates whose library has the `cdylib` crate type. Supported values are 'on', 'off', and 'auto'. 'auto' does not produce one.",
+            values = _OPT_BOOL_VALUES.keys(),
+            default = "auto",
+        ),
+        "gen_static_library": attr.string(
+            doc = "Whether or not to produce a `rust_static_library` target for crates whose library has the `staticlib` crate type. Supported values are 'on', 'off', and 'auto'. 'auto' does not produce one.",
+            values = _OPT_BOOL_VALUES.keys(),
+            default = "auto",
+        ),
+        "gen_tests": attr.string_list(
+            doc = "As a list, the subset of the crate's integration tests that should get `rust_test` targets produced.",
+        ),
         "override_target_bin": attr.label(
             doc = "An optional alternate target to use when something depends on this crate to allow the parent repo to provide its own version of this dependency.",
         ),
         "override_target_build_script": attr.label(
             doc = "An optional alternate target to use when something depends on this crate to allow the parent repo to provide its own version of this dependency.",
         ),
+        "override_target_cdylib": attr.label(
+            doc = "An optional alternate target to use in place of the crate's `rust_shared_library`.",
+        ),
         "override_target_lib": attr.label(
             doc = "An optional alternate target to use when something depends on this crate to allow the parent repo to provide its own version of this dependency.",
         ),
         "override_target_proc_macro": attr.label(
             doc = "An optional alternate target to use when something depends on this crate to allow the parent repo to provide its own version of this dependency.",
         ),
+        "override_target_staticlib": attr.label(
+            doc = "An optional alternate target to use in place of the crate's `rust_static_library`.",
+        ),
         "patch_args": attr.string_list(
             doc = "The `patch_args` attribute of a Bazel repository rule. See [http_archive.patch_args](https://docs.bazel.build/versions/main/repo/http.html#http_archive-patch_args)",
         ),
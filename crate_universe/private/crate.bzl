@@ -108,8 +108,13 @@ def _annotation(
         deps = None,
         extra_aliased_targets = None,
         gen_binaries = None,
+        gen_tests = None,
+        gen_examples = None,
+        gen_benches = None,
         disable_pipelining = False,
         gen_build_script = None,
+        gen_shared_library = None,
+        gen_static_library = None,
         patch_args = None,
         patch_tool = None,
         patches = None,
@@ -158,9 +163,22 @@ def _annotation(
             crate_universe repository.
         gen_binaries (list or bool, optional): As a list, the subset of the crate's bins that should get `rust_binary`
             targets produced. Or `True` to generate all, `False` to generate none.
+        gen_tests (list or bool, optional): As a list, the subset of the crate's integration tests that should get
+            `rust_test` targets named `{test}__test` produced. Or `True` to generate all, `False` to generate none.
+            Tests can read any file of the package at runtime. Cargo only resolves dev-dependencies for workspace
+            members, so no tests, examples or benches are generated for other crates which declare any.
+        gen_examples (list or bool, optional): As a list, the subset of the crate's examples that should get
+            `rust_binary` targets named `{example}__example` produced. Or `True` to generate all, `False` to
+            generate none.
+        gen_benches (list or bool, optional): As a list, the subset of the crate's benches that should get
+            `rust_test` targets named `{bench}__bench` produced. Or `True` to generate all, `False` to generate none.
         disable_pipelining (bool, optional): If True, disables pipelining for library targets for this crate.
         gen_build_script (bool, optional): An authoritative flag to determine whether or not to produce
             `cargo_build_script` targets for the current crate.
+        gen_shared_library (bool, optional): Whether or not to produce a `rust_shared_library` target for crates
+            whose library has the `cdylib` crate type. The target is named `{crate_name}__cdylib`.
+        gen_static_library (bool, optional): Whether or not to produce a `rust_static_library` target for crates
+            whose library has the `staticlib` crate type. The target is named `{crate_name}__staticlib`.
         patch_args (list, optional): The `patch_args` attribute of a Bazel repository rule. See
             [http_archive.patch_args](https://docs.bazel.build/versions/main/repo/http.html#http_archive-patch_args)
         patch_tool (string, optional): The `patch_tool` attribute of a Bazel repository rule. See
@@ -176,7 +194,8 @@ def _annotation(
         shallow_since (str, optional): An optional timestamp used for crates originating from a git repository
             instead of a crate registry. This flag optimizes fetching the source code.
         override_targets (dict, optional): A dictionary of alternate targets to use when something depends on this crate to allow
-            the parent repo to provide its own version of this dependency. Keys can be `proc-macro`, `custom-build`, `lib`, `bin`.
+            the parent repo to provide its own version of this dependency. Keys can be `proc-macro`, `custom-build`, `lib`, `bin`, `cdylib`,
+            `staticlib`.
 
     Returns:
         string: A json encoded string containing the specified version and separately all other inputs.
@@ -214,8 +233,13 @@ def _annotation(
             deps = _stringify_list(deps),
             extra_aliased_targets = extra_aliased_targets,
             gen_binaries = gen_binaries,
+            gen_tests = gen_tests,
+            gen_examples = gen_examples,
+            gen_benches = gen_benches,
             disable_pipelining = disable_pipelining,
             gen_build_script = gen_build_script,
+            gen_shared_library = gen_shared_library,
+            gen_static_library = gen_static_library,
             patch_args = patch_args,
             patch_tool = patch_tool,
             patches = _stringify_list(patches),
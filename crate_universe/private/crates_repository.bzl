@@ -268,6 +268,15 @@ CARGO_BAZEL_REPIN=1 CARGO_BAZEL_REPIN_ONLY=crate_index bazel sync --only=crate_i
             doc = "Whether or not the toolchain names of windows toolchains are expected to be in a `compressed` format.",
             default = True,
         ),
+        "custom_targets": attr.label_keyed_string_dict(
+            doc = (
+                "A mapping of custom target specification JSON files to the label of a `config_setting` matching " +
+                "the target. As with `rustc --target`, each target is named after its file (without the `.json` " +
+                "extension) and is added to `supported_platform_triples`. Labels must be absolute, including the " +
+                "repository (e.g. `@//platforms:my_target`), as they're rendered into the generated BUILD files."
+            ),
+            allow_files = [".json"],
+        ),
         "generate_binaries": attr.bool(
             doc = (
                 "Whether to generate `rust_binary` targets for all the binary crates in every package. " +
@@ -327,6 +336,15 @@ CARGO_BAZEL_REPIN=1 CARGO_BAZEL_REPIN_ONLY=crate_index bazel sync --only=crate_i
         "packages": attr.string_dict(
             doc = "A set of crates (packages) specifications to depend on. See [crate.spec](#crate.spec).",
         ),
+        "platform_cfgs": attr.string_list_dict(
+            doc = (
+                "A mapping of platform triples to additional `cfg` options which are enabled for them. E.g. flags " +
+                "passed with `--cfg` in `RUSTFLAGS` (`tokio_unstable`), `target_feature = \"crt-static\"` or " +
+                "`panic = \"abort\"`. Dependencies under `[target.'cfg(..)'.dependencies]` which are gated on these " +
+                "options are otherwise never enabled. Options for keys with a single value such as `panic` replace the " +
+                "value implied by the triple."
+            ),
+        ),
         "quiet": attr.bool(
             doc = "If stdout and stderr should not be printed to the terminal.",
             default = True,
@@ -376,6 +394,13 @@ CARGO_BAZEL_REPIN=1 CARGO_BAZEL_REPIN_ONLY=crate_index bazel sync --only=crate_i
                 "generate the value for this field. If unset, the defaults defined there will be used."
             ),
         ),
+        "strict_package_metadata": attr.bool(
+            doc = (
+                "If true, malformed `[package.metadata.bazel]` tables in dependencies fail generation " +
+                "instead of being ignored with a warning."
+            ),
+            default = False,
+        ),
         "supported_platform_triples": attr.string_list(
             doc = "A set of all platform triples to consider when generating dependencies.",
             default = SUPPORTED_PLATFORM_TRIPLES,
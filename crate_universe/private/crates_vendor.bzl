@@ -289,6 +289,8 @@ def _write_config_file(ctx):
             generate_build_scripts = ctx.attr.generate_build_scripts,
             generate_target_compatible_with = ctx.attr.generate_target_compatible_with,
             supported_platform_triples = ctx.attr.supported_platform_triples,
+            platform_cfgs = ctx.attr.platform_cfgs,
+            strict_package_metadata = ctx.attr.strict_package_metadata,
             repository_name = ctx.attr.repository_name,
             output_pkg = _get_output_package(ctx),
             workspace_name = workspace_name,
@@ -314,6 +316,9 @@ def generate_config_file(
         output_pkg,
         workspace_name,
         render_config,
+        platform_cfgs = {},
+        custom_targets = {},
+        strict_package_metadata = False,
         repository_ctx = None):
     """Writes the rendering config to cargo-bazel-config.json.
 
@@ -330,6 +335,10 @@ def generate_config_file(
         output_pkg: The path to the package containing the build files.
         workspace_name (str): The name of the workspace.
         render_config: The render config to use.
+        platform_cfgs (dict, optional): Additional `cfg` options enabled for each platform triple.
+        custom_targets (dict, optional): Custom targets as returned by `collect_custom_targets`.
+        strict_package_metadata (bool, optional): Whether malformed `[package.metadata.bazel]` tables fail
+            generation instead of being ignored with a warning.
         repository_ctx (repository_ctx, optional): A repository context object
             used for enabling certain functionality.
 
@@ -404,6 +413,9 @@ def generate_config_file(
         cargo_config = None,
         render_config = render_config,
         supported_platform_triples = supported_platform_triples,
+        platform_cfgs = platform_cfgs,
+        custom_targets = custom_targets,
+        strict_package_metadata = strict_package_metadata,
         repository_name = repository_name or ctx.label.name,
         repository_ctx = repository_ctx,
     )
@@ -591,6 +603,15 @@ CRATES_VENDOR_ATTRS = {
     "packages": attr.string_dict(
         doc = "A set of crates (packages) specifications to depend on. See [crate.spec](#crate.spec).",
     ),
+    "platform_cfgs": attr.string_list_dict(
+        doc = (
+            "A mapping of platform triples to additional `cfg` options which are enabled for them. E.g. flags " +
+            "passed with `--cfg` in `RUSTFLAGS` (`tokio_unstable`), `target_feature = \"crt-static\"` or " +
+            "`panic = \"abort\"`. Dependencies under `[target.'cfg(..)'.dependencies]` which are gated on these " +
+            "options are otherwise never enabled. Options for keys with a single value such as `panic` replace the " +
+            "value implied by the triple."
+        ),
+    ),
     "render_config": attr.string(
         doc = (
             "The configuration flags to use for rendering. Use `//crate_universe:defs.bzl\\%render_config` to " +
@@ -606,6 +627,13 @@ CRATES_VENDOR_ATTRS = {
             "generate the value for this field. If unset, the defaults defined there will be used."
         ),
     ),
+    "strict_package_metadata": attr.bool(
+        doc = (
+            "If true, malformed `[package.metadata.bazel]` tables in dependencies fail generation " +
+            "instead of being ignored with a warning."
+        ),
+        default = False,
+    ),
     "supported_platform_triples": attr.string_list(
         doc = "A set of all platform triples to consider when generating dependencies.",
         default = SUPPORTED_PLATFORM_TRIPLES,
@@ -678,6 +706,10 @@ directory next to where the target is defined. To run it, simply call:
 bazel run //3rdparty:crates_vendor
 ```
 
+Note that `custom_targets` are not supported by `crates_vendor` as their target specifications cannot be
+read while the rule is analyzed. Use `crates_repository` or the `crate` module extension for platforms
+unknown to `rustc`.
+
 <a id="#crates_vendor_repinning_updating_dependencies"></a>
 
 ### Repinning / Updating Dependencies
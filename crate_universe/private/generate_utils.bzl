@@ -205,6 +205,38 @@ def collect_crate_annotations(annotations, repository_name):
             crate_annotations.update({id: data})
     return crate_annotations
 
+def collect_custom_targets(ctx, custom_targets):
+    """Read the target specifications of custom targets.
+
+    Args:
+        ctx (repository_ctx or module_ctx): The context object used to read the specifications.
+        custom_targets (dict): A mapping of target specification JSON files to the label of a
+            `config_setting` matching the target.
+
+    Returns:
+        dict: A mapping of custom target names to a struct of their `spec` and `platform` label.
+    """
+    targets = {}
+    for spec, platform in custom_targets.items():
+        path = ctx.path(spec)
+        if not path.basename.endswith(".json"):
+            fail("Custom target specifications must be `.json` files: {}".format(spec))
+
+        # Relative labels would resolve against the generated repository once rendered.
+        if not platform.startswith("@"):
+            fail("The `config_setting` of custom target {} must be an absolute label including the repository (e.g. `@//{}`): {}".format(
+                spec,
+                platform.lstrip("/"),
+                platform,
+            ))
+
+        # Like `rustc --target`, custom targets are named after their specification file.
+        targets[path.basename[:-len(".json")]] = struct(
+            spec = json.decode(ctx.read(path)),
+            platform = platform,
+        )
+    return targets
+
 def _read_cargo_config(repository_ctx):
     if repository_ctx.attr.cargo_config:
         config = repository_ctx.path(repository_ctx.attr.cargo_config)
@@ -249,6 +281,9 @@ def compile_config(
         render_config,
         supported_platform_triples,
         repository_name,
+        platform_cfgs = {},
+        custom_targets = {},
+        strict_package_metadata = False,
         repository_ctx = None):
     """Create a config file for generating crate targets
 
@@ -264,6 +299,10 @@ def compile_config(
         render_config (dict): The deserialized dict of the `render_config` function.
         supported_platform_triples (list): A list of platform triples
         repository_name (str): The name of the repository being generated
+        platform_cfgs (dict, optional): Additional `cfg` options enabled for each platform triple.
+        custom_targets (dict, optional): Custom targets as returned by `collect_custom_targets`.
+        strict_package_metadata (bool, optional): Whether malformed `[package.metadata.bazel]` tables fail
+            generation instead of being ignored with a warning.
         repository_ctx (repository_ctx, optional): A repository context object used for enabling
             certain functionality.
 
@@ -295,6 +334,17 @@ def compile_config(
         print("DEPRECATED: 'generate_target_compatible_with' has been moved to 'render_config'")
         render_config.update({"generate_target_compatible_with": False})
 
+    # Custom targets are always supported and use their own platform labels.
+    if custom_targets:
+        supported_platform_triples = supported_platform_triples + [
+            name
+            for name in custom_targets
+            if name not in supported_platform_triples
+        ]
+        platform_labels = dict(render_config.get("platform_labels", {}))
+        platform_labels.update({name: target.platform for name, target in custom_targets.items()})
+        render_config.update({"platform_labels": platform_labels})
+
     config = struct(
         generate_binaries = generate_binaries,
         generate_build_scripts = generate_build_scripts,
@@ -305,6 +355,9 @@ def compile_config(
             repository_name = repository_name,
         ),
         supported_platform_triples = supported_platform_triples,
+        platform_cfgs = platform_cfgs,
+        custom_targets = {name: target.spec for name, target in custom_targets.items()},
+        strict_package_metadata = strict_package_metadata,
     )
 
     return config
@@ -327,6 +380,9 @@ def generate_config(repository_ctx):
         cargo_config = _read_cargo_config(repository_ctx),
         render_config = _get_render_config(repository_ctx),
         supported_platform_triples = repository_ctx.attr.supported_platform_triples,
+        platform_cfgs = repository_ctx.attr.platform_cfgs,
+        custom_targets = collect_custom_targets(repository_ctx, repository_ctx.attr.custom_targets),
+        strict_package_metadata = repository_ctx.attr.strict_package_metadata,
         repository_name = repository_ctx.name,
         repository_ctx = repository_ctx,
     )
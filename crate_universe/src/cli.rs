@@ -1,9 +1,14 @@
 //! Command line interface entry points and utilities
 
 mod generate;
+mod members;
+mod merge_lockfile;
+mod outdated;
 mod query;
 mod render;
+mod schema;
 mod splice;
+mod validate;
 mod vendor;
 
 use clap::Parser;
@@ -17,16 +22,26 @@ use tracing_subscriber::FmtSubscriber;
 pub use tracing::Level as LogLevel;
 
 pub use self::generate::GenerateOptions;
+pub use self::members::MembersOptions;
+pub use self::merge_lockfile::MergeLockfileOptions;
+pub use self::outdated::{OutdatedFormat, OutdatedOptions};
 pub use self::query::QueryOptions;
 pub use self::render::RenderOptions;
+pub use self::schema::{SchemaKind, SchemaOptions};
 pub use self::splice::SpliceOptions;
+pub use self::validate::ValidateOptions;
 pub use self::vendor::VendorOptions;
 
 // Entrypoints
 pub use generate::generate;
+pub use members::members;
+pub use merge_lockfile::merge_lockfile;
+pub use outdated::outdated;
 pub use query::query;
 pub use render::render;
+pub use schema::schema;
 pub use splice::splice;
+pub use validate::validate;
 pub use vendor::vendor;
 
 #[derive(Parser, Debug)]
@@ -50,6 +65,21 @@ pub enum Options {
 
     /// Render a BUILD file for a single crate.
     Render(RenderOptions),
+
+    /// Create or update BUILD files for the members of a Cargo workspace.
+    Members(MembersOptions),
+
+    /// Export a JSON Schema for the config file or crate annotations.
+    Schema(SchemaOptions),
+
+    /// Check a config file and the annotations found in Cargo metadata.
+    Validate(ValidateOptions),
+
+    /// Three-way merge `cargo-bazel-lock.json` files, e.g. as a git merge driver.
+    MergeLockfile(MergeLockfileOptions),
+
+    /// Report the upgrades available for each crate using the locally cached registry indexes.
+    Outdated(OutdatedOptions),
 }
 
 // Convenience wrappers to avoid dependencies in the binary
@@ -59,7 +89,18 @@ pub fn parse_args() -> Options {
     Options::parse()
 }
 
-const EXPECTED_LOGGER_NAMES: [&str; 5] = ["Generate", "Splice", "Query", "Vendor", "Render"];
+const EXPECTED_LOGGER_NAMES: [&str; 10] = [
+    "Generate",
+    "Splice",
+    "Query",
+    "Vendor",
+    "Render",
+    "Members",
+    "Schema",
+    "Validate",
+    "MergeLockfile",
+    "Outdated",
+];
 
 /// A wrapper for the tracing-subscriber default [FormatEvent]
 /// that prepends the name of the active CLI option.
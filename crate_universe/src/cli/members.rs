@@ -0,0 +1,49 @@
+//! The cli entrypoint for the `members` subcommand
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::config::Config;
+use crate::context::Context;
+use crate::rendering::{write_outputs, Renderer};
+
+/// Command line options for the `members` subcommand
+#[derive(Parser, Debug)]
+#[clap(about = "Command line options for the `members` subcommand", version)]
+pub struct MembersOptions {
+    /// The config file with information about the Bazel and Cargo workspace
+    #[clap(long)]
+    pub config: PathBuf,
+
+    /// The lockfile path for reproducible Cargo->Bazel renderings
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    /// The root of the Bazel workspace containing the Cargo workspace members
+    #[clap(long)]
+    pub workspace_dir: PathBuf,
+
+    /// If true, outputs will be printed instead of written to disk.
+    #[clap(long)]
+    pub dry_run: bool,
+}
+
+/// Create or update the BUILD files of Cargo workspace members
+pub fn members(opt: MembersOptions) -> Result<()> {
+    // Load the config
+    let config = Config::try_from_path(&opt.config)?;
+
+    let context = Context::try_from_path(&opt.lockfile)
+        .with_context(|| format!("Failed to load lockfile {}", opt.lockfile.display()))?;
+
+    let outputs = Renderer::new(
+        Arc::new(config.rendering),
+        Arc::new(config.supported_platform_triples),
+    )
+    .render_members(&context, &opt.workspace_dir)?;
+
+    write_outputs(outputs, opt.dry_run)
+}
@@ -0,0 +1,65 @@
+//! The cli entrypoint for the `merge-lockfile` subcommand
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::context::Context;
+use crate::lockfile::{merge_lockfiles, write_lockfile};
+
+/// Command line options for the `merge-lockfile` subcommand
+///
+/// The arguments match the ones git passes to a
+/// [merge driver](https://git-scm.com/docs/gitattributes#_defining_a_custom_merge_driver):
+///
+/// ```text
+/// [merge "cargo-bazel-lock"]
+///     name = cargo-bazel lockfile merge driver
+///     driver = cargo-bazel merge-lockfile %O %A %B
+/// ```
+#[derive(Parser, Debug)]
+#[clap(
+    about = "Command line options for the `merge-lockfile` subcommand",
+    version
+)]
+pub struct MergeLockfileOptions {
+    /// The lockfile of the common ancestor (`%O`).
+    pub base: PathBuf,
+
+    /// The lockfile of the current branch (`%A`). The merged lockfile is written
+    /// here unless `--output` is given.
+    pub ours: PathBuf,
+
+    /// The lockfile of the branch being merged (`%B`).
+    pub theirs: PathBuf,
+
+    /// An alternate path to write the merged lockfile to.
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+}
+
+/// Three-way merge `cargo-bazel` lockfiles
+pub fn merge_lockfile(opt: MergeLockfileOptions) -> Result<()> {
+    let base = load_lockfile(&opt.base)?;
+    let ours = load_lockfile(&opt.ours)?;
+    let theirs = load_lockfile(&opt.theirs)?;
+
+    let merged = merge_lockfiles(&base, &ours, &theirs)?;
+
+    write_lockfile(merged, opt.output.as_ref().unwrap_or(&opt.ours), false)
+}
+
+fn load_lockfile(path: &Path) -> Result<Context> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read lockfile: {}", path.display()))?;
+
+    // Git provides an empty file when there is no common ancestor.
+    if content.trim().is_empty() {
+        return Ok(Context::default());
+    }
+
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse lockfile: {}", path.display()))
+}
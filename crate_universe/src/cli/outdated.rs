@@ -0,0 +1,414 @@
+//! The cli entrypoint for the `outdated` subcommand
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context as AnyhowContext, Result};
+use clap::{Parser, ValueEnum};
+use semver::{Comparator, Op, Version, VersionReq};
+use serde::Serialize;
+
+use crate::config::CrateId;
+use crate::context::{Context, CrateContext};
+use crate::metadata::Cargo;
+use crate::splicing::cargo_config::CargoConfig;
+use crate::splicing::CrateIndexLookup;
+
+/// The formats the `outdated` report can be written in.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum OutdatedFormat {
+    /// A human readable table.
+    Table,
+
+    /// A JSON array with an entry per crate.
+    Json,
+}
+
+/// Command line options for the `outdated` subcommand
+#[derive(Parser, Debug)]
+#[clap(about = "Command line options for the `outdated` subcommand", version)]
+pub struct OutdatedOptions {
+    /// The lockfile path for reproducible Cargo->Bazel renderings
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    /// The Cargo lockfile the `cargo-bazel` lockfile was generated from
+    #[clap(long)]
+    pub cargo_lockfile: PathBuf,
+
+    /// The path to a Cargo config file, used to resolve registry replacements
+    #[clap(long)]
+    pub cargo_config: Option<PathBuf>,
+
+    /// The path to a Cargo binary, used to locate the registry index caches
+    #[clap(long, env = "CARGO")]
+    pub cargo: PathBuf,
+
+    /// The path to a rustc binary for use with Cargo
+    #[clap(long, env = "RUSTC")]
+    pub rustc: PathBuf,
+
+    /// The format of the report
+    #[clap(long, value_enum, default_value = "table")]
+    pub format: OutdatedFormat,
+
+    /// The path to write the report to. If unset, it's printed to stdout.
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+}
+
+/// The upgrades available for a single crate.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct OutdatedCrate {
+    /// The name of the crate
+    name: String,
+
+    /// The version in the lockfile
+    version: Version,
+
+    /// The highest version which is semver compatible with `version`.
+    latest_compatible: Option<Version>,
+
+    /// The highest version which isn't a pre-release.
+    latest: Option<Version>,
+
+    /// Whether or not a workspace member depends on the crate directly.
+    direct: bool,
+
+    /// The workspace members depending on the crate, directly or transitively.
+    workspace_members: BTreeSet<String>,
+}
+
+/// Report the upgrades available for the crates in a lockfile. Versions are looked
+/// up in the registry indexes cached by Cargo, so no network access is needed.
+pub fn outdated(opt: OutdatedOptions) -> Result<()> {
+    let context = Context::try_from_path(&opt.lockfile)
+        .with_context(|| format!("Failed to load lockfile {}", opt.lockfile.display()))?;
+
+    let cargo_lockfile = cargo_lock::Lockfile::load(&opt.cargo_lockfile).with_context(|| {
+        format!(
+            "Failed to load Cargo lockfile {}",
+            opt.cargo_lockfile.display()
+        )
+    })?;
+
+    let cargo_config = opt
+        .cargo_config
+        .as_deref()
+        .map(CargoConfig::try_from_path)
+        .transpose()?;
+
+    let cargo = Cargo::new(opt.cargo, opt.rustc);
+    let hash_kind = if cargo.uses_stable_registry_hash()? {
+        crates_index::HashKind::Stable
+    } else {
+        crates_index::HashKind::Legacy
+    };
+    let sparse_crates_io = cargo.use_sparse_registries_for_crates_io()?;
+
+    // Lookup the published versions of each registry crate in the lockfile
+    let mut indexes = BTreeMap::<String, Option<CrateIndexLookup>>::new();
+    let mut available = BTreeMap::new();
+    for pkg in &cargo_lockfile.packages {
+        let source = match &pkg.source {
+            Some(source) if source.is_remote_registry() => source,
+            _ => continue,
+        };
+        let id = CrateId::new(pkg.name.as_str().to_owned(), pkg.version.clone());
+        if !context.crates.contains_key(&id) {
+            continue;
+        }
+
+        let url = source.url().to_string();
+        let index = indexes.entry(url).or_insert_with_key(|url| {
+            let index_url = match &cargo_config {
+                Some(config) => config.resolve_replacement_url(url).ok()?,
+                None => url,
+            };
+            CrateIndexLookup::new(source.kind(), index_url, sparse_crates_io, &hash_kind, true)
+                .map_err(|e| tracing::warn!("Skipping crates from {}: {:?}", url, e))
+                .ok()
+        });
+
+        if let Some(index) = index {
+            match index.versions(&id.name) {
+                Ok(versions) => {
+                    available.insert(id, versions);
+                }
+                Err(e) => tracing::warn!("Unable to determine the versions of {}: {:?}", id, e),
+            }
+        }
+    }
+
+    let report = outdated_crates(&context, &available);
+    let content = match opt.format {
+        OutdatedFormat::Table => render_table(&report),
+        OutdatedFormat::Json => serde_json::to_string_pretty(&report)? + "\n",
+    };
+
+    match opt.output {
+        Some(path) => fs::write(&path, content)
+            .with_context(|| format!("Failed to write report to {}", path.display())),
+        None => {
+            print!("{content}");
+            Ok(())
+        }
+    }
+}
+
+/// Collect the upgrades of every crate which isn't a workspace member.
+fn outdated_crates(
+    context: &Context,
+    available: &BTreeMap<CrateId, Vec<Version>>,
+) -> Vec<OutdatedCrate> {
+    let members = constraining_members(context);
+
+    context
+        .crates
+        .keys()
+        .filter(|id| !context.workspace_members.contains_key(id))
+        .map(|id| {
+            let versions = available.get(id).map(Vec::as_slice).unwrap_or_default();
+            let compatible = compatible_req(&id.version);
+
+            OutdatedCrate {
+                name: id.name.clone(),
+                version: id.version.clone(),
+                latest_compatible: versions
+                    .iter()
+                    .filter(|v| compatible.matches(v))
+                    .max()
+                    .cloned(),
+                latest: versions.iter().filter(|v| v.pre.is_empty()).max().cloned(),
+                direct: context.direct_deps.contains(id) || context.direct_dev_deps.contains(id),
+                workspace_members: members.get(id).cloned().unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+/// The requirement Cargo would use for a dependency on `version`.
+fn compatible_req(version: &Version) -> VersionReq {
+    VersionReq {
+        comparators: vec![Comparator {
+            op: Op::Caret,
+            major: version.major,
+            minor: Some(version.minor),
+            patch: Some(version.patch),
+            pre: version.pre.clone(),
+        }],
+    }
+}
+
+/// Map each crate to the names of the workspace members depending on it. Dev dependencies
+/// are only followed for the members themselves as Cargo doesn't build them for dependencies.
+fn constraining_members(context: &Context) -> BTreeMap<&CrateId, BTreeSet<String>> {
+    let mut constraints = BTreeMap::<&CrateId, BTreeSet<String>>::new();
+
+    for member in context.workspace_members.keys() {
+        let Some(member) = context.crates.get(member) else {
+            continue;
+        };
+
+        let mut seen = BTreeSet::new();
+        let mut queue = dependencies(member, true);
+        while let Some(id) = queue.pop() {
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            if let Some((id, krate)) = context.crates.get_key_value(&id) {
+                constraints
+                    .entry(id)
+                    .or_default()
+                    .insert(member.name.clone());
+                queue.extend(dependencies(krate, false));
+            }
+        }
+    }
+
+    constraints
+}
+
+fn dependencies(krate: &CrateContext, include_dev: bool) -> Vec<CrateId> {
+    let attrs = &krate.common_attrs;
+    let mut deps = vec![&attrs.deps, &attrs.proc_macro_deps];
+    if include_dev {
+        deps.extend([&attrs.deps_dev, &attrs.proc_macro_deps_dev]);
+    }
+    let mut artifact_deps = vec![&attrs.artifact_deps];
+    if let Some(build) = &krate.build_script_attrs {
+        deps.extend([&build.deps, &build.proc_macro_deps, &build.link_deps]);
+        artifact_deps.push(&build.artifact_deps);
+    }
+
+    deps.into_iter()
+        .flat_map(|deps| deps.values())
+        .map(|dep| dep.id)
+        .chain(
+            artifact_deps
+                .into_iter()
+                .flat_map(|deps| deps.values())
+                .map(|dep| dep.id),
+        )
+        .collect()
+}
+
+fn render_table(report: &[OutdatedCrate]) -> String {
+    let display = |version: &Option<Version>| {
+        version
+            .as_ref()
+            .map_or_else(|| "-".to_owned(), Version::to_string)
+    };
+
+    let mut rows = vec![[
+        "Name".to_owned(),
+        "Version".to_owned(),
+        "Compatible".to_owned(),
+        "Latest".to_owned(),
+        "Kind".to_owned(),
+        "Workspace members".to_owned(),
+    ]];
+    rows.extend(report.iter().map(|krate| {
+        [
+            krate.name.clone(),
+            krate.version.to_string(),
+            display(&krate.latest_compatible),
+            display(&krate.latest),
+            if krate.direct { "direct" } else { "transitive" }.to_owned(),
+            Vec::from_iter(krate.workspace_members.iter().cloned()).join(", "),
+        ]
+    }));
+
+    let mut widths = [0; 6];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    rows.iter()
+        .map(|row| {
+            let line = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:width$}"))
+                .collect::<Vec<_>>()
+                .join("  ");
+            line.trim_end().to_owned() + "\n"
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::context::CrateDependency;
+
+    fn mock_crate(name: &str, version: &str, deps: &[&CrateId]) -> (CrateId, CrateContext) {
+        let mut krate: CrateContext = serde_json::from_value(serde_json::json!({
+            "name": name,
+            "version": version,
+            "license_ids": [],
+        }))
+        .unwrap();
+        for id in deps {
+            krate.common_attrs.deps.insert(
+                CrateDependency {
+                    id: (*id).clone(),
+                    target: None,
+                    alias: None,
+                    local_path: None,
+                },
+                None,
+            );
+        }
+        (CrateId::new(name.to_owned(), krate.version.clone()), krate)
+    }
+
+    fn versions(versions: &[&str]) -> Vec<Version> {
+        versions
+            .iter()
+            .map(|v| Version::parse(v).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn report_outdated_crates() {
+        let (leaf_id, leaf) = mock_crate("leaf", "0.3.1", &[]);
+        let (lib_id, lib) = mock_crate("lib", "1.2.0", &[&leaf_id]);
+        let (app_id, app) = mock_crate("app", "0.1.0", &[&lib_id]);
+        let (tool_id, mut tool) = mock_crate("tool", "0.1.0", &[]);
+        tool.common_attrs.deps_dev.insert(
+            CrateDependency {
+                id: leaf_id.clone(),
+                target: None,
+                alias: None,
+                local_path: None,
+            },
+            None,
+        );
+
+        let context = Context {
+            crates: BTreeMap::from([
+                (leaf_id.clone(), leaf),
+                (lib_id.clone(), lib),
+                (app_id.clone(), app),
+                (tool_id.clone(), tool),
+            ]),
+            workspace_members: BTreeMap::from([
+                (app_id, "app".to_owned()),
+                (tool_id, "tool".to_owned()),
+            ]),
+            direct_deps: BTreeSet::from([lib_id.clone()]),
+            direct_dev_deps: BTreeSet::from([leaf_id.clone()]),
+            ..Context::default()
+        };
+        let available = BTreeMap::from([(
+            lib_id,
+            versions(&["1.0.0", "1.2.0", "1.4.2", "2.0.0", "2.1.0-rc.1"]),
+        )]);
+
+        assert_eq!(
+            outdated_crates(&context, &available),
+            vec![
+                OutdatedCrate {
+                    name: "leaf".to_owned(),
+                    version: Version::new(0, 3, 1),
+                    latest_compatible: None,
+                    latest: None,
+                    direct: true,
+                    workspace_members: BTreeSet::from(["app".to_owned(), "tool".to_owned()]),
+                },
+                OutdatedCrate {
+                    name: "lib".to_owned(),
+                    version: Version::new(1, 2, 0),
+                    latest_compatible: Some(Version::new(1, 4, 2)),
+                    latest: Some(Version::new(2, 0, 0)),
+                    direct: true,
+                    workspace_members: BTreeSet::from(["app".to_owned()]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn compatible_versions() {
+        let matching = |version: &str, available: &[&str]| {
+            let req = compatible_req(&Version::parse(version).unwrap());
+            versions(available)
+                .into_iter()
+                .filter(|v| req.matches(v))
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(matching("0.3.1", &["0.3.0", "0.3.9", "0.4.0"]), ["0.3.9"]);
+        assert_eq!(matching("0.0.1", &["0.0.1", "0.0.2", "0.1.0"]), ["0.0.1"]);
+        assert_eq!(
+            matching("1.0.0-beta.1", &["1.0.0-beta.2", "1.0.0", "1.5.0", "2.0.0"]),
+            ["1.0.0-beta.2", "1.0.0", "1.5.0"]
+        );
+    }
+}
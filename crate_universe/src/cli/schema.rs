@@ -0,0 +1,84 @@
+//! The cli entrypoint for the `schema` subcommand
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+
+use crate::config::{root_schema, AnnotationsProvidedByPackage, Config, CrateAnnotations};
+
+/// The inputs a JSON Schema can be exported for.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum SchemaKind {
+    /// The config file passed to `cargo-bazel` via `--config`.
+    Config,
+
+    /// A single crate annotation.
+    CrateAnnotations,
+
+    /// The `[package.metadata.bazel]` table of a Cargo.toml.
+    PackageMetadata,
+}
+
+/// Command line options for the `schema` subcommand
+#[derive(Parser, Debug)]
+#[clap(about = "Command line options for the `schema` subcommand", version)]
+pub struct SchemaOptions {
+    /// The input to produce a schema for
+    #[clap(long, value_enum, default_value = "config")]
+    pub kind: SchemaKind,
+
+    /// The path to write the schema to. If unset, it's printed to stdout.
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+}
+
+/// Export a JSON Schema describing one of the inputs of `cargo-bazel`
+pub fn schema(opt: SchemaOptions) -> Result<()> {
+    let schema = match opt.kind {
+        SchemaKind::Config => root_schema::<Config>("Config"),
+        SchemaKind::CrateAnnotations => root_schema::<CrateAnnotations>("CrateAnnotations"),
+        SchemaKind::PackageMetadata => {
+            root_schema::<AnnotationsProvidedByPackage>("AnnotationsProvidedByPackage")
+        }
+    };
+    let content = serde_json::to_string_pretty(&schema)? + "\n";
+
+    match opt.output {
+        Some(path) => fs::write(&path, content)
+            .with_context(|| format!("Failed to write schema to {}", path.display())),
+        None => {
+            print!("{content}");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn schema_output() {
+        let (_temp_dir, dir) = crate::test::test_tempdir("schema_output");
+
+        for (kind, title) in [
+            (SchemaKind::Config, "Config"),
+            (SchemaKind::CrateAnnotations, "CrateAnnotations"),
+            (SchemaKind::PackageMetadata, "AnnotationsProvidedByPackage"),
+        ] {
+            let output = dir.join(format!("{title}.json"));
+            schema(SchemaOptions {
+                kind,
+                output: Some(output.clone()),
+            })
+            .unwrap();
+
+            let schema: serde_json::Value =
+                serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+            assert_eq!(schema["title"], title);
+            assert!(schema["properties"].is_object(), "{title}");
+        }
+    }
+}
@@ -111,8 +111,10 @@ pub fn splice(opt: SpliceOptions) -> Result<()> {
         .context("Failed to generate cargo metadata")?;
 
     let config = Config::try_from_path(&opt.config).context("Failed to parse config")?;
-    let resolver = CargoResolver::new(&cargo_metadata);
-    let resolver_data = resolver.execute(&config.supported_platform_triples);
+    let resolver = CargoResolver::new(&cargo_metadata)
+        .with_platform_cfgs(config.platform_cfgs.clone())
+        .with_custom_targets(config.custom_targets.clone());
+    let resolver_data = resolver.execute(&config.supported_platform_triples)?;
 
     // Write the registry url info to the manifest now that a lockfile has been generated
     WorkspaceMetadata::write_registry_urls_and_feature_map(
@@ -0,0 +1,116 @@
+//! The cli entrypoint for the `validate` subcommand
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+
+use crate::config::{AnnotationsProvidedByPackage, Config};
+use crate::metadata::find_workspace_annotations;
+
+/// Command line options for the `validate` subcommand
+#[derive(Parser, Debug)]
+#[clap(about = "Command line options for the `validate` subcommand", version)]
+pub struct ValidateOptions {
+    /// The config file with information about the Bazel and Cargo workspace
+    #[clap(long)]
+    pub config: Option<PathBuf>,
+
+    /// The path to a Cargo metadata `json` file whose annotations should be checked.
+    #[clap(long)]
+    pub metadata: Option<PathBuf>,
+
+    /// Treat malformed `[package.metadata.bazel]` tables as errors instead of warnings.
+    #[clap(long)]
+    pub strict: bool,
+}
+
+/// Check the config and Cargo metadata annotations used by `cargo-bazel`
+pub fn validate(opt: ValidateOptions) -> Result<()> {
+    if let Some(path) = &opt.config {
+        Config::try_from_path(path)
+            .with_context(|| format!("Invalid config file: {}", path.display()))?;
+    }
+
+    if let Some(path) = &opt.metadata {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to load Cargo Metadata: {}", path.display()))?;
+        let metadata: cargo_metadata::Metadata =
+            serde_json::from_str(&content).context("Unable to deserialize Cargo metadata")?;
+
+        find_workspace_annotations(&metadata)?;
+
+        let mut packages: Vec<_> = metadata.packages.iter().collect();
+        packages.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+
+        let errors: Vec<String> = packages
+            .into_iter()
+            .flat_map(|pkg| {
+                AnnotationsProvidedByPackage::validate(&pkg.metadata)
+                    .into_iter()
+                    .map(move |err| format!("{} {}: {}", pkg.name, pkg.version, err))
+            })
+            .collect();
+
+        if opt.strict && !errors.is_empty() {
+            bail!(
+                "Malformed `[package.metadata.bazel]` tables were found:\n{}",
+                errors.join("\n")
+            );
+        }
+        for err in errors {
+            tracing::warn!("Ignoring malformed package metadata: {}", err);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn validate_metadata() {
+        let (_temp_dir, dir) = crate::test::test_tempdir("validate_metadata");
+        let mut metadata = crate::test::metadata::has_package_metadata();
+        for package in &mut metadata.packages {
+            if package.name == "has_package_metadata" {
+                package.metadata["bazel"]["rustc_env"]["BROKEN"] = serde_json::json!(1);
+            }
+        }
+        let metadata_path = dir.join("metadata.json");
+        fs::write(&metadata_path, serde_json::to_string(&metadata).unwrap()).unwrap();
+
+        let options = |strict| ValidateOptions {
+            config: None,
+            metadata: Some(metadata_path.clone()),
+            strict,
+        };
+
+        validate(options(false)).unwrap();
+
+        let err = validate(options(true)).unwrap_err().to_string();
+        assert!(
+            err.contains("has_package_metadata 0.0.0: `package.metadata.bazel.rustc_env.BROKEN`"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn validate_config() {
+        let (_temp_dir, dir) = crate::test::test_tempdir("validate_config");
+        let config_path = dir.join("config.json");
+        fs::write(&config_path, r#"{ "generate_binaries": "yes" }"#).unwrap();
+
+        let err = validate(ValidateOptions {
+            config: Some(config_path.clone()),
+            metadata: None,
+            strict: false,
+        })
+        .unwrap_err()
+        .to_string();
+        assert!(err.starts_with("Invalid config file"), "{err}");
+    }
+}
@@ -1,5 +1,7 @@
 //! A module for configuration information
 
+mod schema;
+
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Formatter;
@@ -9,7 +11,7 @@ use std::path::Path;
 use std::str::FromStr;
 use std::{fmt, fs};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use cargo_lock::package::GitReference;
 use cargo_metadata::Package;
 use semver::VersionReq;
@@ -19,7 +21,9 @@ use serde::{Deserialize, Serialize, Serializer};
 
 use crate::select::{Select, Selectable};
 use crate::utils::starlark::Label;
-use crate::utils::target_triple::TargetTriple;
+use crate::utils::target_triple::{TargetSpec, TargetTriple};
+
+pub(crate) use self::schema::*;
 
 /// Representations of different kinds of crate vendoring into workspaces.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -94,6 +98,11 @@ pub(crate) struct RenderConfig {
     #[serde(default = "default_platforms_template")]
     pub(crate) platforms_template: String,
 
+    /// Labels to use for the platform constraints of specific target triples in
+    /// place of `platforms_template`. E.g. for custom targets.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) platform_labels: BTreeMap<TargetTriple, String>,
+
     /// The command to use for regenerating generated files.
     pub(crate) regen_command: String,
 
@@ -126,6 +135,7 @@ impl Default for RenderConfig {
             generate_cargo_toml_env_vars: default_generate_cargo_toml_env_vars(),
             generate_target_compatible_with: default_generate_target_compatible_with(),
             platforms_template: default_platforms_template(),
+            platform_labels: BTreeMap::new(),
             regen_command: String::default(),
             vendor_mode: Option::default(),
             generate_rules_license_metadata: default_generate_rules_license_metadata(),
@@ -240,9 +250,26 @@ pub(crate) struct CrateAnnotations {
     /// Which subset of the crate's bins should get produced as `rust_binary` targets.
     pub(crate) gen_binaries: Option<GenBinaries>,
 
+    /// Which subset of the crate's integration tests should get produced as `rust_test` targets.
+    pub(crate) gen_tests: Option<GenBinaries>,
+
+    /// Which subset of the crate's examples should get produced as `rust_binary` targets.
+    pub(crate) gen_examples: Option<GenBinaries>,
+
+    /// Which subset of the crate's benches should get produced as `rust_test` targets.
+    pub(crate) gen_benches: Option<GenBinaries>,
+
     /// Determines whether or not Cargo build scripts should be generated for the current package
     pub(crate) gen_build_script: Option<bool>,
 
+    /// Determines whether or not a `rust_shared_library` should be generated for a package
+    /// whose library has the `cdylib` crate type.
+    pub(crate) gen_shared_library: Option<bool>,
+
+    /// Determines whether or not a `rust_static_library` should be generated for a package
+    /// whose library has the `staticlib` crate type.
+    pub(crate) gen_static_library: Option<bool>,
+
     /// Additional data to pass to
     /// [deps](https://bazelbuild.github.io/rules_rust/defs.html#rust_library-deps) attribute.
     pub(crate) deps: Option<Select<BTreeSet<Label>>>,
@@ -276,6 +303,7 @@ pub(crate) struct CrateAnnotations {
     pub(crate) compile_data_glob_excludes: Option<BTreeSet<String>>,
 
     /// If true, disables pipelining for library targets generated for this crate.
+    #[serde(default)]
     pub(crate) disable_pipelining: bool,
 
     /// Additional data to pass to  the target's
@@ -408,7 +436,12 @@ impl Add for CrateAnnotations {
         #[rustfmt::skip]
         let output = CrateAnnotations {
             gen_binaries: self.gen_binaries.or(rhs.gen_binaries),
+            gen_tests: self.gen_tests.or(rhs.gen_tests),
+            gen_examples: self.gen_examples.or(rhs.gen_examples),
+            gen_benches: self.gen_benches.or(rhs.gen_benches),
             gen_build_script: self.gen_build_script.or(rhs.gen_build_script),
+            gen_shared_library: self.gen_shared_library.or(rhs.gen_shared_library),
+            gen_static_library: self.gen_static_library.or(rhs.gen_static_library),
             deps: select_merge(self.deps, rhs.deps),
             proc_macro_deps: select_merge(self.proc_macro_deps, rhs.proc_macro_deps),
             crate_features: select_merge(self.crate_features, rhs.crate_features),
@@ -464,6 +497,7 @@ impl Sum for CrateAnnotations {
 /// data = ["font.woff2"]
 /// extra_aliased_targets = { ... }
 /// gen_build_script = false
+/// gen_shared_library = true
 /// ```
 ///
 /// These are considered default values which apply if the Bazel workspace does
@@ -472,6 +506,8 @@ impl Sum for CrateAnnotations {
 #[derive(Debug, Deserialize)]
 pub(crate) struct AnnotationsProvidedByPackage {
     pub(crate) gen_build_script: Option<bool>,
+    pub(crate) gen_shared_library: Option<bool>,
+    pub(crate) gen_static_library: Option<bool>,
     pub(crate) data: Option<Select<BTreeSet<Label>>>,
     pub(crate) data_glob: Option<BTreeSet<String>>,
     pub(crate) deps: Option<Select<BTreeSet<Label>>>,
@@ -488,14 +524,187 @@ pub(crate) struct AnnotationsProvidedByPackage {
     pub(crate) extra_aliased_targets: Option<BTreeMap<String, String>>,
 }
 
+impl AnnotationsProvidedByPackage {
+    /// The keys supported in `[package.metadata.bazel]`.
+    pub(crate) fn fields() -> &'static [&'static str] {
+        struct_fields::<Self>()
+    }
+
+    /// Check a package's `[package.metadata.bazel]` table, returning a message
+    /// for every malformed or unknown entry.
+    pub(crate) fn validate(pkg_metadata: &serde_json::Value) -> Vec<String> {
+        let table = match pkg_metadata.get("bazel") {
+            Some(serde_json::Value::Object(table)) => table,
+            Some(other) => {
+                return vec![format!(
+                    "`package.metadata.bazel`: expected a table, found `{other}`"
+                )]
+            }
+            None => return Vec::new(),
+        };
+
+        table
+            .iter()
+            .filter_map(|(key, value)| {
+                if !Self::fields().contains(&key.as_str()) {
+                    return Some(format!("`package.metadata.bazel.{key}`: unknown field"));
+                }
+
+                // Deserialize each entry on its own so the error can be attributed to it.
+                let entry = serde_json::Value::Object(serde_json::Map::from_iter([(
+                    key.clone(),
+                    value.clone(),
+                )]));
+                let deserialize = |entry: &serde_json::Value| {
+                    Self::deserialize(entry)
+                        .map(|_| ())
+                        .map_err(|e| e.to_string())
+                };
+                let err = deserialize(&entry).err()?;
+
+                let path = error_path(&entry, &err, deserialize);
+                Some(format!("`package.metadata.bazel{path}`: {err}"))
+            })
+            .collect()
+    }
+}
+
+/// The names of the fields of a struct, as serde passes them to
+/// [serde::Deserializer::deserialize_struct].
+fn struct_fields<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> serde::Deserializer<'de> for FieldNames<'_> {
+        type Error = serde::de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(serde::de::Error::custom("expected a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _: &'static str,
+            fields: &'static [&'static str],
+            _: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(serde::de::Error::custom(
+                "only the field names are collected",
+            ))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+            ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+/// A step into a [serde_json::Value].
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl PathSegment {
+    fn get_mut<'a>(&self, value: &'a mut serde_json::Value) -> &'a mut serde_json::Value {
+        match self {
+            Self::Key(key) => &mut value[key.as_str()],
+            Self::Index(index) => &mut value[*index],
+        }
+    }
+}
+
+/// Errors from deserializing a [serde_json::Value] don't say where in the value they
+/// occurred. Narrow it down by removing entries until deserializing gives a different
+/// result, returning the path of the offending value.
+fn error_path<F>(root: &serde_json::Value, err: &str, deserialize: F) -> String
+where
+    F: Fn(&serde_json::Value) -> Result<(), String>,
+{
+    let mut path: Vec<PathSegment> = Vec::new();
+    let mut node = root;
+
+    'descend: loop {
+        let children: Vec<PathSegment> = match node {
+            serde_json::Value::Object(map) => map.keys().cloned().map(PathSegment::Key).collect(),
+            serde_json::Value::Array(items) => (0..items.len()).map(PathSegment::Index).collect(),
+            _ => break,
+        };
+
+        for child in children {
+            let mut candidate = root.clone();
+            let parent = path
+                .iter()
+                .fold(&mut candidate, |value, segment| segment.get_mut(value));
+            match (parent, &child) {
+                (serde_json::Value::Object(map), PathSegment::Key(key)) => {
+                    map.remove(key);
+                }
+                (serde_json::Value::Array(items), PathSegment::Index(index)) => {
+                    items.remove(*index);
+                }
+                _ => unreachable!("children are collected from the parent"),
+            }
+
+            if deserialize(&candidate).err().as_deref() != Some(err) {
+                node = match &child {
+                    PathSegment::Key(key) => &node[key.as_str()],
+                    PathSegment::Index(index) => &node[*index],
+                };
+                path.push(child);
+                continue 'descend;
+            }
+        }
+
+        break;
+    }
+
+    path.iter()
+        .map(|segment| match segment {
+            PathSegment::Key(key) => format!(".{key}"),
+            PathSegment::Index(index) => format!("[{index}]"),
+        })
+        .collect()
+}
+
 impl CrateAnnotations {
+    /// Use the `[package.metadata.bazel]` table of a package for annotations the workspace
+    /// didn't set. Problems with the table are logged, or returned as an error if `strict`.
     pub(crate) fn apply_defaults_from_package_metadata(
         &mut self,
-        pkg_metadata: &serde_json::Value,
-    ) {
+        package: &Package,
+        strict: bool,
+    ) -> Result<()> {
+        let pkg_metadata = &package.metadata;
+        let problems = AnnotationsProvidedByPackage::validate(pkg_metadata);
+        if strict && !problems.is_empty() {
+            bail!(
+                "Malformed `[package.metadata.bazel]` table in {} {}:\n  - {}",
+                package.name,
+                package.version,
+                problems.join("\n  - ")
+            );
+        }
+        for problem in problems {
+            tracing::warn!(
+                "Ignoring malformed package metadata of {} {}: {}",
+                package.name,
+                package.version,
+                problem
+            );
+        }
+
         #[deny(unused_variables)]
         let AnnotationsProvidedByPackage {
             gen_build_script,
+            gen_shared_library,
+            gen_static_library,
             data,
             data_glob,
             deps,
@@ -517,7 +726,7 @@ impl CrateAnnotations {
             // don't want a library to be impossible to import into Bazel for
             // having old or broken annotations. The Bazel workspace can specify
             // its own correct annotations.
-            Err(_) => return,
+            Err(_) => return Ok(()),
         };
 
         fn default<T>(workspace_value: &mut Option<T>, default_value: Option<T>) {
@@ -528,6 +737,8 @@ impl CrateAnnotations {
 
         default(&mut self.gen_build_script, gen_build_script);
         default(&mut self.gen_build_script, gen_build_script);
+        default(&mut self.gen_shared_library, gen_shared_library);
+        default(&mut self.gen_static_library, gen_static_library);
         default(&mut self.data, data);
         default(&mut self.data_glob, data_glob);
         default(&mut self.deps, deps);
@@ -548,6 +759,8 @@ impl CrateAnnotations {
             additive_build_file_content,
         );
         default(&mut self.extra_aliased_targets, extra_aliased_targets);
+
+        Ok(())
     }
 }
 
@@ -636,6 +849,16 @@ pub(crate) enum GenBinaries {
     Some(BTreeSet<String>),
 }
 
+impl GenBinaries {
+    /// Whether or not a target with the given name should be generated.
+    pub(crate) fn includes(&self, name: &str) -> bool {
+        match self {
+            GenBinaries::All => true,
+            GenBinaries::Some(set) => set.contains(name),
+        }
+    }
+}
+
 impl Default for GenBinaries {
     fn default() -> Self {
         GenBinaries::Some(BTreeSet::new())
@@ -669,7 +892,7 @@ impl<'de> Visitor<'de> for GenBinariesVisitor {
     type Value = GenBinaries;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("boolean, or array of bin names")
+        formatter.write_str("boolean, or array of target names")
     }
 
     fn visit_bool<E>(self, gen_binaries: bool) -> Result<Self::Value, E> {
@@ -688,6 +911,97 @@ impl<'de> Visitor<'de> for GenBinariesVisitor {
     }
 }
 
+/// Additional `cfg` options which are considered enabled for a platform beyond the ones
+/// implied by its target triple. E.g. custom flags passed with `--cfg` in `RUSTFLAGS`
+/// (`tokio_unstable`), `target_feature = "crt-static"` or `panic = "abort"`.
+///
+/// Options for keys which hold a single value for a target (`panic`, `target_os`, etc.)
+/// replace the value implied by the triple instead of being enabled alongside it.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(try_from = "BTreeSet<String>", into = "BTreeSet<String>")]
+pub(crate) struct PlatformCfgs {
+    cfgs: BTreeSet<String>,
+    expressions: Vec<cfg_expr::Expression>,
+}
+
+impl PlatformCfgs {
+    /// Determine whether or not the given predicate is one of the enabled options.
+    pub(crate) fn contains(&self, predicate: &cfg_expr::Predicate) -> bool {
+        self.expressions
+            .iter()
+            .any(|expression| expression.predicates().next().as_ref() == Some(predicate))
+    }
+
+    /// Determine whether or not any option is configured for the key of the given target predicate.
+    fn configures(&self, target_predicate: &cfg_expr::TargetPredicate) -> bool {
+        self.expressions.iter().any(|expression| {
+            matches!(
+                expression.predicates().next(),
+                Some(cfg_expr::Predicate::Target(tp))
+                    if std::mem::discriminant(&tp) == std::mem::discriminant(target_predicate)
+            )
+        })
+    }
+
+    /// Evaluate a predicate for a platform described by `target_info` with the given options enabled.
+    pub(crate) fn eval(
+        cfgs: Option<&Self>,
+        target_info: &cfg_expr::targets::TargetInfo,
+        predicate: &cfg_expr::Predicate,
+    ) -> bool {
+        use cfg_expr::expr::TargetMatcher;
+        use cfg_expr::TargetPredicate;
+
+        let cfgs = match cfgs {
+            Some(cfgs) => cfgs,
+            None => {
+                return match predicate {
+                    cfg_expr::Predicate::Target(tp) => target_info.matches(tp),
+                    _ => false,
+                }
+            }
+        };
+
+        match predicate {
+            // A target may belong to multiple families and support multiple atomic widths.
+            cfg_expr::Predicate::Target(
+                tp @ (TargetPredicate::Family(_) | TargetPredicate::HasAtomic(_)),
+            ) => target_info.matches(tp) || cfgs.contains(predicate),
+            cfg_expr::Predicate::Target(tp) if cfgs.configures(tp) => cfgs.contains(predicate),
+            cfg_expr::Predicate::Target(tp) => target_info.matches(tp),
+            _ => cfgs.contains(predicate),
+        }
+    }
+}
+
+impl TryFrom<BTreeSet<String>> for PlatformCfgs {
+    type Error = anyhow::Error;
+
+    fn try_from(cfgs: BTreeSet<String>) -> Result<Self> {
+        let expressions = cfgs
+            .iter()
+            .map(|cfg| {
+                if cfg.contains('(') {
+                    anyhow::bail!(
+                        "Platform cfgs must be a single option such as `tokio_unstable` or `feature = \"foo\"`, found: `{}`",
+                        cfg
+                    );
+                }
+                cfg_expr::Expression::parse(cfg)
+                    .with_context(|| format!("Failed to parse platform cfg: `{cfg}`"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { cfgs, expressions })
+    }
+}
+
+impl From<PlatformCfgs> for BTreeSet<String> {
+    fn from(value: PlatformCfgs) -> Self {
+        value.cfgs
+    }
+}
+
 /// Workspace specific settings to control how targets are generated
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 #[serde(deny_unknown_fields)]
@@ -711,6 +1025,21 @@ pub(crate) struct Config {
     /// A set of platform triples to use in generated select statements
     #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
     pub(crate) supported_platform_triples: BTreeSet<TargetTriple>,
+
+    /// Additional `cfg` options enabled for each platform when evaluating the
+    /// `[target.'cfg(..)'.dependencies]` of crates.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) platform_cfgs: BTreeMap<TargetTriple, PlatformCfgs>,
+
+    /// Custom targets defined by a target specification JSON, keyed by the name
+    /// used for them in `supported_platform_triples`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) custom_targets: BTreeMap<TargetTriple, TargetSpec>,
+
+    /// Whether malformed `[package.metadata.bazel]` tables fail generation instead
+    /// of being ignored with a warning.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) strict_package_metadata: bool,
 }
 
 impl Config {
@@ -998,4 +1327,65 @@ mod test {
             "//custom/platform:{triple}"
         );
     }
+
+    #[test]
+    fn platform_cfgs_serde() {
+        let cfgs: PlatformCfgs = serde_json::from_value(serde_json::json!([
+            "tokio_unstable",
+            "target_feature = \"crt-static\"",
+        ]))
+        .unwrap();
+
+        assert!(cfgs.contains(&cfg_expr::Predicate::Flag("tokio_unstable")));
+        assert!(cfgs.contains(&cfg_expr::Predicate::TargetFeature("crt-static")));
+        assert!(!cfgs.contains(&cfg_expr::Predicate::Flag("crt-static")));
+        assert_eq!(
+            serde_json::to_value(&cfgs).unwrap(),
+            serde_json::json!(["target_feature = \"crt-static\"", "tokio_unstable"])
+        );
+
+        let err = serde_json::from_value::<PlatformCfgs>(serde_json::json!(["not(unix)"]))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("must be a single option"), "{err}");
+    }
+
+    #[test]
+    fn package_metadata_fields() {
+        let fields = AnnotationsProvidedByPackage::fields();
+
+        assert_eq!(fields.len(), 17);
+        assert!(fields.contains(&"gen_build_script"));
+        assert!(fields.contains(&"extra_aliased_targets"));
+        assert!(!fields.contains(&"gen_binaries"));
+    }
+
+    #[test]
+    fn validate_package_metadata() {
+        let pkg_metadata = serde_json::json!({
+            "bazel": {
+                "compile_data_glob": ["**/*.txt", 2],
+                "data_glob": ["**/*.txt"],
+                "gen_build_script": "yes",
+                "rustc_env": { "BAR": ["bar"], "FOO": "foo" },
+                "rustc_flag": ["--cfg=foo"],
+            },
+        });
+
+        assert_eq!(
+            AnnotationsProvidedByPackage::validate(&pkg_metadata),
+            vec![
+                "`package.metadata.bazel.compile_data_glob[1]`: invalid type: integer `2`, expected a string".to_owned(),
+                "`package.metadata.bazel.gen_build_script`: invalid type: string \"yes\", expected a boolean".to_owned(),
+                "`package.metadata.bazel.rustc_env.BAR`: data did not match any variant of untagged enum Either".to_owned(),
+                "`package.metadata.bazel.rustc_flag`: unknown field".to_owned(),
+            ]
+        );
+
+        assert!(AnnotationsProvidedByPackage::validate(&serde_json::Value::Null).is_empty());
+        assert_eq!(
+            AnnotationsProvidedByPackage::validate(&serde_json::json!({ "bazel": true })),
+            vec!["`package.metadata.bazel`: expected a table, found `true`".to_owned()]
+        );
+    }
 }
@@ -0,0 +1,449 @@
+//! [JSON Schema](https://json-schema.org/) descriptions of configuration types
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::{json, Map, Value};
+
+use super::*;
+
+/// The dialect of JSON Schema used for exported schemas.
+const SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Types which can describe the JSON they deserialize from.
+pub(crate) trait JsonSchema {
+    fn json_schema() -> Value;
+}
+
+/// Produce a standalone schema document for `T`.
+pub(crate) fn root_schema<T: JsonSchema>(title: &str) -> Value {
+    let mut schema = T::json_schema();
+    if let Some(map) = schema.as_object_mut() {
+        map.insert("$schema".to_owned(), json!(SCHEMA_DIALECT));
+        map.insert("title".to_owned(), json!(title));
+    }
+    schema
+}
+
+/// The schema of `T` annotated with a description.
+fn property<T: JsonSchema>(description: &str) -> Value {
+    let mut schema = T::json_schema();
+    if let Some(map) = schema.as_object_mut() {
+        map.insert("description".to_owned(), json!(description));
+    }
+    schema
+}
+
+/// The schema of `T` annotated with a description and the value used when it's omitted.
+fn property_with_default<T: JsonSchema>(description: &str, default: Value) -> Value {
+    let mut schema = property::<T>(description);
+    if let Some(map) = schema.as_object_mut() {
+        map.insert("default".to_owned(), default);
+    }
+    schema
+}
+
+/// The schema of a struct with the given fields.
+fn object(properties: Vec<(&str, Value)>, required: &[&str], deny_unknown_fields: bool) -> Value {
+    let properties: Map<String, Value> = properties
+        .into_iter()
+        .map(|(name, schema)| (name.to_owned(), schema))
+        .collect();
+
+    let mut schema = json!({
+        "type": "object",
+        "properties": properties,
+    });
+    let map = schema.as_object_mut().unwrap();
+    if !required.is_empty() {
+        map.insert("required".to_owned(), json!(required));
+    }
+    if deny_unknown_fields {
+        map.insert("additionalProperties".to_owned(), json!(false));
+    }
+    schema
+}
+
+impl JsonSchema for bool {
+    fn json_schema() -> Value {
+        json!({ "type": "boolean" })
+    }
+}
+
+impl JsonSchema for i32 {
+    fn json_schema() -> Value {
+        json!({ "type": "integer" })
+    }
+}
+
+impl JsonSchema for String {
+    fn json_schema() -> Value {
+        json!({ "type": "string" })
+    }
+}
+
+impl JsonSchema for Label {
+    fn json_schema() -> Value {
+        json!({
+            "type": "string",
+            "description": "A Bazel label.",
+        })
+    }
+}
+
+impl JsonSchema for TargetTriple {
+    fn json_schema() -> Value {
+        json!({ "type": "string" })
+    }
+}
+
+impl JsonSchema for toml::Value {
+    fn json_schema() -> Value {
+        json!({})
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Option<T> {
+    fn json_schema() -> Value {
+        json!({ "anyOf": [T::json_schema(), { "type": "null" }] })
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Vec<T> {
+    fn json_schema() -> Value {
+        json!({
+            "type": "array",
+            "items": T::json_schema(),
+        })
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for BTreeSet<T> {
+    fn json_schema() -> Value {
+        json!({
+            "type": "array",
+            "items": T::json_schema(),
+            "uniqueItems": true,
+        })
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for BTreeMap<String, T> {
+    fn json_schema() -> Value {
+        json!({
+            "type": "object",
+            "additionalProperties": T::json_schema(),
+        })
+    }
+}
+
+impl<T> JsonSchema for Select<T>
+where
+    T: Selectable,
+    T::CommonType: JsonSchema,
+    T::SelectsType: JsonSchema,
+{
+    fn json_schema() -> Value {
+        json!({
+            "anyOf": [
+                T::CommonType::json_schema(),
+                {
+                    "type": "object",
+                    "properties": {
+                        "common": T::CommonType::json_schema(),
+                        "selects": {
+                            "type": "object",
+                            "description": "Values keyed by the configuration they apply to.",
+                            "additionalProperties": T::SelectsType::json_schema(),
+                        },
+                    },
+                    "required": ["selects"],
+                },
+            ],
+        })
+    }
+}
+
+impl JsonSchema for GenBinaries {
+    fn json_schema() -> Value {
+        json!({
+            "anyOf": [
+                { "type": "boolean" },
+                BTreeSet::<String>::json_schema(),
+            ],
+        })
+    }
+}
+
+impl JsonSchema for AliasRule {
+    fn json_schema() -> Value {
+        json!({
+            "anyOf": [
+                { "enum": ["alias", "dbg", "fastbuild", "opt"] },
+                object(
+                    vec![
+                        ("bzl", String::json_schema()),
+                        ("rule", String::json_schema()),
+                    ],
+                    &["bzl", "rule"],
+                    false,
+                ),
+            ],
+        })
+    }
+}
+
+impl JsonSchema for VendorMode {
+    fn json_schema() -> Value {
+        json!({ "enum": ["local", "remote"] })
+    }
+}
+
+impl JsonSchema for PlatformCfgs {
+    fn json_schema() -> Value {
+        BTreeSet::<String>::json_schema()
+    }
+}
+
+impl JsonSchema for TargetSpec {
+    fn json_schema() -> Value {
+        // Target specifications have many more fields than the ones used to evaluate
+        // `cfg` expressions, all of which are ignored.
+        let mut schema = object(
+            vec![
+                ("llvm-target", String::json_schema()),
+                ("arch", String::json_schema()),
+                (
+                    "target-pointer-width",
+                    json!({ "type": ["integer", "string"] }),
+                ),
+            ],
+            &["llvm-target", "arch", "target-pointer-width"],
+            false,
+        );
+        schema["description"] = json!("A `rustc` target specification.");
+        schema
+    }
+}
+
+impl JsonSchema for RenderConfig {
+    fn json_schema() -> Value {
+        #[rustfmt::skip]
+        let properties = vec![
+            ("repository_name", property::<String>("The name of the repository being rendered.")),
+            ("build_file_template", property_with_default::<String>("The pattern to use for BUILD file names.", json!(default_build_file_template()))),
+            ("crate_label_template", property_with_default::<String>("The pattern to use for a crate target.", json!(default_crate_label_template()))),
+            ("crate_alias_template", property_with_default::<String>("The pattern to use for a crate alias.", json!(default_crate_alias_template()))),
+            ("crates_module_template", property_with_default::<String>("The pattern to use for the `defs.bzl` and `BUILD.bazel` file names used for the crates module.", json!(default_crates_module_template()))),
+            ("crate_repository_template", property_with_default::<String>("The pattern used for a crate's repository name.", json!(default_crate_repository_template()))),
+            ("default_alias_rule", property_with_default::<AliasRule>("Default alias rule to use for packages.", json!(AliasRule::default()))),
+            ("default_package_name", property::<Option<String>>("The default of the `package_name` parameter to use for the module macros like `all_crate_deps`.")),
+            ("generate_target_compatible_with", property_with_default::<bool>("Whether to generate `target_compatible_with` annotations on the generated BUILD files.", json!(default_generate_target_compatible_with()))),
+            ("platforms_template", property_with_default::<String>("The pattern to use for platform constraints.", json!(default_platforms_template()))),
+            ("platform_labels", property::<BTreeMap<String, String>>("Labels to use for the platform constraints of specific target triples in place of `platforms_template`.")),
+            ("regen_command", property::<String>("The command to use for regenerating generated files.")),
+            ("vendor_mode", property::<Option<VendorMode>>("An optional configuration for rendering content to be rendered into repositories.")),
+            ("generate_rules_license_metadata", property_with_default::<bool>("Whether to generate package metadata.", json!(default_generate_rules_license_metadata()))),
+            ("generate_cargo_toml_env_vars", property::<bool>("Whether to generate cargo_toml_env_vars targets.")),
+        ];
+
+        object(
+            properties,
+            &[
+                "repository_name",
+                "regen_command",
+                "generate_cargo_toml_env_vars",
+            ],
+            true,
+        )
+    }
+}
+
+impl JsonSchema for CrateAnnotations {
+    fn json_schema() -> Value {
+        #[rustfmt::skip]
+        let properties = vec![
+            ("gen_binaries", property::<Option<GenBinaries>>("Which subset of the crate's bins should get produced as `rust_binary` targets.")),
+            ("gen_tests", property::<Option<GenBinaries>>("Which subset of the crate's integration tests should get produced as `rust_test` targets.")),
+            ("gen_examples", property::<Option<GenBinaries>>("Which subset of the crate's examples should get produced as `rust_binary` targets.")),
+            ("gen_benches", property::<Option<GenBinaries>>("Which subset of the crate's benches should get produced as `rust_test` targets.")),
+            ("gen_build_script", property::<Option<bool>>("Whether or not Cargo build scripts should be generated for the package.")),
+            ("gen_shared_library", property::<Option<bool>>("Whether or not a `rust_shared_library` should be generated for a `cdylib` library.")),
+            ("gen_static_library", property::<Option<bool>>("Whether or not a `rust_static_library` should be generated for a `staticlib` library.")),
+            ("deps", property::<Option<Select<BTreeSet<Label>>>>("Additional `deps` for the crate's targets.")),
+            ("proc_macro_deps", property::<Option<Select<BTreeSet<Label>>>>("Additional `proc_macro_deps` for the crate's targets.")),
+            ("crate_features", property::<Option<Select<BTreeSet<String>>>>("Additional `crate_features` for the crate's targets.")),
+            ("data", property::<Option<Select<BTreeSet<Label>>>>("Additional `data` for the crate's targets.")),
+            ("data_glob", property::<Option<BTreeSet<String>>>("Glob patterns to add to the `data` of the crate's targets.")),
+            ("compile_data", property::<Option<Select<BTreeSet<Label>>>>("Additional `compile_data` for the crate's targets.")),
+            ("compile_data_glob", property::<Option<BTreeSet<String>>>("Glob patterns to add to the `compile_data` of the crate's targets.")),
+            ("compile_data_glob_excludes", property::<Option<BTreeSet<String>>>("Glob patterns to exclude from the `compile_data` of the crate's targets.")),
+            ("disable_pipelining", property_with_default::<bool>("If true, disables pipelining for library targets generated for this crate.", json!(false))),
+            ("rustc_env", property::<Option<Select<BTreeMap<String, String>>>>("Additional `rustc_env` for the crate's targets.")),
+            ("rustc_env_files", property::<Option<Select<BTreeSet<String>>>>("Additional `rustc_env_files` for the crate's targets.")),
+            ("rustc_flags", property::<Option<Select<Vec<String>>>>("Additional `rustc_flags` for the crate's targets.")),
+            ("build_script_deps", property::<Option<Select<BTreeSet<Label>>>>("Additional `deps` for the crate's build script.")),
+            ("build_script_link_deps", property::<Option<Select<BTreeSet<Label>>>>("Additional `link_deps` for the crate's build script.")),
+            ("build_script_proc_macro_deps", property::<Option<Select<BTreeSet<Label>>>>("Additional `proc_macro_deps` for the crate's build script.")),
+            ("build_script_compile_data", property::<Option<Select<BTreeSet<Label>>>>("Additional `compile_data` for the crate's build script.")),
+            ("build_script_data", property::<Option<Select<BTreeSet<Label>>>>("Additional `data` for the crate's build script.")),
+            ("build_script_tools", property::<Option<Select<BTreeSet<Label>>>>("Additional `tools` for the crate's build script.")),
+            ("build_script_data_glob", property::<Option<BTreeSet<String>>>("Glob patterns to add to the `data` of the crate's build script.")),
+            ("build_script_env", property::<Option<Select<BTreeMap<String, String>>>>("Additional `build_script_env` for the crate's build script.")),
+            ("build_script_rustc_env", property::<Option<Select<BTreeMap<String, String>>>>("Additional `rustc_env` for the crate's build script.")),
+            ("build_script_toolchains", property::<Option<BTreeSet<Label>>>("Additional `toolchains` for the crate's build script.")),
+            ("build_script_use_default_shell_env", property::<Option<i32>>("The `use_default_shell_env` attribute of the crate's build script.")),
+            ("build_script_rundir", property::<Option<Select<String>>>("Directory to run the crate's build script in.")),
+            ("additive_build_file_content", property::<Option<String>>("Extra contents to write to the bottom of the generated BUILD file.")),
+            ("shallow_since", property::<Option<String>>("The `shallow_since` attribute of a git sourced crate's repository.")),
+            ("patch_args", property::<Option<Vec<String>>>("The `patch_args` attribute of the crate's repository.")),
+            ("patch_tool", property::<Option<String>>("The `patch_tool` attribute of the crate's repository.")),
+            ("patches", property::<Option<BTreeSet<String>>>("The `patches` attribute of the crate's repository.")),
+            ("extra_aliased_targets", property::<Option<BTreeMap<String, String>>>("Extra targets that should be aliased during rendering.")),
+            ("alias_rule", property::<Option<AliasRule>>("Transition rule to use instead of `native.alias()`.")),
+            ("override_targets", property::<Option<BTreeMap<String, Label>>>("The targets to use instead of the generated ones.")),
+        ];
+
+        object(properties, &[], false)
+    }
+}
+
+impl JsonSchema for AnnotationsProvidedByPackage {
+    fn json_schema() -> Value {
+        // These share their meaning with the annotations of the same name.
+        let annotations = CrateAnnotations::json_schema();
+        let properties = Self::fields()
+            .iter()
+            .map(|field| (*field, annotations["properties"][field].clone()))
+            .collect();
+
+        object(properties, &[], false)
+    }
+}
+
+impl JsonSchema for Config {
+    fn json_schema() -> Value {
+        let mut annotations = BTreeMap::<String, CrateAnnotations>::json_schema();
+        annotations["description"] = json!(
+            "Additional settings to apply to generated crates, keyed by `{name} {version requirement}`."
+        );
+        let mut platform_cfgs = BTreeMap::<String, PlatformCfgs>::json_schema();
+        platform_cfgs["description"] = json!(
+            "Additional `cfg` options enabled for each platform triple, e.g. `tokio_unstable` or `target_feature = \"crt-static\"`."
+        );
+
+        #[rustfmt::skip]
+        let properties = vec![
+            ("generate_binaries", property::<bool>("Whether to generate `rust_binary` targets for all bins by default.")),
+            ("generate_build_scripts", property::<bool>("Whether or not to generate Cargo build scripts by default.")),
+            ("annotations", annotations),
+            ("rendering", property::<RenderConfig>("Settings used to determine various render info.")),
+            ("cargo_config", property::<Option<toml::Value>>("The contents of a Cargo configuration file.")),
+            ("supported_platform_triples", property::<BTreeSet<TargetTriple>>("A set of platform triples to use in generated select statements.")),
+            ("platform_cfgs", platform_cfgs),
+            ("custom_targets", property::<BTreeMap<String, TargetSpec>>("Custom targets defined by a target specification JSON, keyed by the name used for them in `supported_platform_triples`.")),
+            ("strict_package_metadata", property::<bool>("Whether malformed `[package.metadata.bazel]` tables fail generation instead of being ignored with a warning.")),
+        ];
+
+        object(
+            properties,
+            &["generate_binaries", "generate_build_scripts", "rendering"],
+            true,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn property_names(schema: &Value) -> BTreeSet<String> {
+        schema["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    fn field_names<T: Serialize>(value: &T) -> BTreeSet<String> {
+        serde_json::to_value(value)
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn crate_annotations_schema_matches_fields() {
+        assert_eq!(
+            property_names(&CrateAnnotations::json_schema()),
+            field_names(&CrateAnnotations::default()),
+        );
+    }
+
+    #[test]
+    fn config_schema_matches_fields() {
+        let config = Config {
+            annotations: BTreeMap::from([(
+                CrateNameAndVersionReq::new("mock-crate".to_owned(), "*".parse().unwrap()),
+                CrateAnnotations::default(),
+            )]),
+            supported_platform_triples: BTreeSet::from([TargetTriple::from_bazel(
+                "x86_64-unknown-linux-gnu".to_owned(),
+            )]),
+            platform_cfgs: BTreeMap::from([(
+                TargetTriple::from_bazel("x86_64-unknown-linux-gnu".to_owned()),
+                PlatformCfgs::default(),
+            )]),
+            custom_targets: BTreeMap::from([(
+                TargetTriple::from_bazel("x86_64-custom-none".to_owned()),
+                serde_json::from_value(json!({
+                    "llvm-target": "x86_64-unknown-none",
+                    "arch": "x86_64",
+                    "target-pointer-width": 64,
+                }))
+                .unwrap(),
+            )]),
+            rendering: RenderConfig {
+                platform_labels: BTreeMap::from([(
+                    TargetTriple::from_bazel("x86_64-custom-none".to_owned()),
+                    "//platforms:x86_64-custom-none".to_owned(),
+                )]),
+                ..RenderConfig::default()
+            },
+            strict_package_metadata: true,
+            ..Config::default()
+        };
+
+        let schema = Config::json_schema();
+        assert_eq!(property_names(&schema), field_names(&config));
+        assert_eq!(
+            property_names(&schema["properties"]["rendering"]),
+            field_names(&config.rendering),
+        );
+    }
+
+    #[test]
+    fn package_metadata_schema_is_subset_of_annotations() {
+        let schema = AnnotationsProvidedByPackage::json_schema();
+        let annotations = CrateAnnotations::json_schema();
+
+        for field in AnnotationsProvidedByPackage::fields() {
+            assert_eq!(
+                schema["properties"][field], annotations["properties"][field],
+                "{field}"
+            );
+        }
+    }
+
+    #[test]
+    fn root_schema_has_dialect() {
+        let schema = root_schema::<Config>("Config");
+
+        assert_eq!(schema["$schema"], json!(SCHEMA_DIALECT));
+        assert_eq!(schema["title"], json!("Config"));
+        assert_eq!(schema["additionalProperties"], json!(false));
+    }
+}
@@ -103,6 +103,8 @@ impl Context {
         let conditions = resolve_cfg_platforms(
             crates.values(),
             &annotations.config.supported_platform_triples,
+            &annotations.config.platform_cfgs,
+            &annotations.config.custom_targets,
         )?;
 
         // Generate a list of all workspace members
@@ -313,7 +315,8 @@ mod test {
         let mut metadata = crate::test::metadata::common();
 
         let resolved = CargoResolver::new(&metadata)
-            .execute([TargetTriple::from_bazel("x86_64-unknown-linux-gnu".into())]);
+            .execute([TargetTriple::from_bazel("x86_64-unknown-linux-gnu".into())])
+            .unwrap();
 
         metadata.workspace_metadata = serde_json::json!({
             "cargo-bazel": WorkspaceMetadata {
@@ -337,9 +340,11 @@ mod test {
     fn mock_context_aliases() -> Context {
         let mut metadata = crate::test::metadata::alias();
 
-        let resolver_metadata = CargoResolver::new(&metadata).execute([TargetTriple::from_bazel(
-            "x86_64-unknown-linux-gnu".to_owned(),
-        )]);
+        let resolver_metadata = CargoResolver::new(&metadata)
+            .execute([TargetTriple::from_bazel(
+                "x86_64-unknown-linux-gnu".to_owned(),
+            )])
+            .unwrap();
 
         metadata.workspace_metadata = serde_json::json!({
             "cargo-bazel": WorkspaceMetadata {
@@ -363,9 +368,11 @@ mod test {
     fn mock_context_workspace_build_scripts_deps() -> Context {
         let mut metadata = crate::test::metadata::workspace_build_scripts_deps();
 
-        let resolver_metadata = CargoResolver::new(&metadata).execute([TargetTriple::from_bazel(
-            "x86_64-unknown-linux-gnu".to_owned(),
-        )]);
+        let resolver_metadata = CargoResolver::new(&metadata)
+            .execute([TargetTriple::from_bazel(
+                "x86_64-unknown-linux-gnu".to_owned(),
+            )])
+            .unwrap();
 
         metadata.workspace_metadata = serde_json::json!({
             "cargo-bazel": WorkspaceMetadata {
@@ -10,7 +10,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::config::{AliasRule, CrateId, GenBinaries};
 use crate::metadata::{
-    CrateAnnotation, Dependency, MetadataAnnotation, PairedExtras, SourceAnnotation,
+    ArtifactKind, ArtifactPlatform, CrateAnnotation, Dependency, MetadataAnnotation, PairedExtras,
+    SourceAnnotation,
 };
 use crate::select::Select;
 use crate::utils::sanitize_module_name;
@@ -36,6 +37,28 @@ pub struct CrateDependency {
     pub(crate) local_path: Option<Utf8PathBuf>,
 }
 
+/// A dependency on a binary or library artifact of another crate. See
+/// [artifact dependencies](https://doc.rust-lang.org/nightly/cargo/reference/unstable.html#artifact-dependencies).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ArtifactCrateDependency {
+    /// The [CrateId] of the dependency
+    pub id: CrateId,
+
+    /// The name of the dependency's target which produces the artifact, e.g. `foo__bin`.
+    pub target: String,
+
+    /// The environment variables which Cargo sets to the path of the artifact.
+    pub env: BTreeSet<String>,
+
+    /// The platform the artifact is built for, if set on the dependency.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) platform: Option<ArtifactPlatform>,
+
+    /// Local path of this dependency if provided.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) local_path: Option<Utf8PathBuf>,
+}
+
 #[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone)]
 #[serde(default)]
 pub(crate) struct TargetAttributes {
@@ -55,6 +78,16 @@ pub(crate) struct TargetAttributes {
     pub(crate) srcs: Glob,
 }
 
+/// Attributes of test and bench targets.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone)]
+pub(crate) struct TestAttributes {
+    #[serde(flatten)]
+    pub(crate) target: TargetAttributes,
+
+    /// Whether or not the target uses the libtest harness (the `harness` key in Cargo.toml).
+    pub(crate) harness: bool,
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone)]
 pub(crate) enum Rule {
     /// `rust_library`
@@ -68,17 +101,36 @@ pub(crate) enum Rule {
 
     /// `cargo_build_script`
     BuildScript(TargetAttributes),
+
+    /// `rust_shared_library`
+    SharedLibrary(TargetAttributes),
+
+    /// `rust_static_library`
+    StaticLibrary(TargetAttributes),
+
+    /// `rust_test`
+    Test(TestAttributes),
+
+    /// `rust_binary` of an example
+    Example(TargetAttributes),
+
+    /// `rust_test` of a bench
+    Bench(TestAttributes),
 }
 
 impl Rule {
     /// The keys that can be used in override_targets to override these Rule sources.
     /// These intentionally match the accepted `Target.kind`s returned by cargo-metadata.
-    pub(crate) fn override_target_key(&self) -> &'static str {
+    /// Tests, examples and benches are never depended on and so can't be overridden.
+    pub(crate) fn override_target_key(&self) -> Option<&'static str> {
         match self {
-            Self::Library(..) => "lib",
-            Self::ProcMacro(..) => "proc-macro",
-            Self::Binary(..) => "bin",
-            Self::BuildScript(..) => "custom-build",
+            Self::Library(..) => Some("lib"),
+            Self::ProcMacro(..) => Some("proc-macro"),
+            Self::Binary(..) => Some("bin"),
+            Self::BuildScript(..) => Some("custom-build"),
+            Self::SharedLibrary(..) => Some("cdylib"),
+            Self::StaticLibrary(..) => Some("staticlib"),
+            Self::Test(..) | Self::Example(..) | Self::Bench(..) => None,
         }
     }
 
@@ -87,7 +139,11 @@ impl Rule {
             Self::Library(attrs)
             | Self::ProcMacro(attrs)
             | Self::Binary(attrs)
-            | Self::BuildScript(attrs) => &attrs.crate_name,
+            | Self::BuildScript(attrs)
+            | Self::SharedLibrary(attrs)
+            | Self::StaticLibrary(attrs)
+            | Self::Example(attrs) => &attrs.crate_name,
+            Self::Test(attrs) | Self::Bench(attrs) => &attrs.target.crate_name,
         }
     }
 }
@@ -141,6 +197,9 @@ pub(crate) struct CommonAttributes {
     #[serde(skip_serializing_if = "Select::is_empty")]
     pub(crate) proc_macro_deps_dev: Select<BTreeSet<CrateDependency>>,
 
+    #[serde(skip_serializing_if = "Select::is_empty")]
+    pub(crate) artifact_deps: Select<BTreeSet<ArtifactCrateDependency>>,
+
     #[serde(skip_serializing_if = "Select::is_empty")]
     pub(crate) rustc_env: Select<BTreeMap<String, String>>,
 
@@ -190,6 +249,7 @@ impl Default for CommonAttributes {
             proc_macro_deps: Default::default(),
             extra_proc_macro_deps: Default::default(),
             proc_macro_deps_dev: Default::default(),
+            artifact_deps: Default::default(),
             rustc_env: Default::default(),
             rustc_env_files: Default::default(),
             rustc_flags: Default::default(),
@@ -248,6 +308,9 @@ pub(crate) struct BuildScriptAttributes {
     #[serde(skip_serializing_if = "Select::is_empty")]
     pub(crate) extra_link_deps: Select<BTreeSet<Label>>,
 
+    #[serde(skip_serializing_if = "Select::is_empty")]
+    pub(crate) artifact_deps: Select<BTreeSet<ArtifactCrateDependency>>,
+
     #[serde(skip_serializing_if = "Select::is_empty")]
     pub(crate) build_script_env: Select<BTreeMap<String, String>>,
 
@@ -297,6 +360,7 @@ impl Default for BuildScriptAttributes {
             extra_deps: Default::default(),
             link_deps: Default::default(),
             extra_link_deps: Default::default(),
+            artifact_deps: Default::default(),
             build_script_env: Default::default(),
             rundir: Default::default(),
             extra_proc_macro_deps: Default::default(),
@@ -550,6 +614,81 @@ impl CrateContext {
             })
         };
 
+        let new_artifact_deps = |dep: &Dependency| {
+            let Some(artifact) = &dep.artifact else {
+                return Vec::new();
+            };
+            let pkg = &metadata.packages[&metadata.package_map[&dep.id]];
+            if metadata.workspace_members.contains(&pkg.id) {
+                return Vec::new();
+            }
+
+            let dep_name = sanitize_module_name(dep.alias.as_deref().unwrap_or(&pkg.name));
+            pkg.targets
+                .iter()
+                .flat_map(|target| {
+                    artifact
+                        .kinds
+                        .iter()
+                        .filter(|kind| kind.matches(target))
+                        .map(move |kind| (kind, target))
+                })
+                .map(|(kind, target)| {
+                    let (bazel_target, env_kind) = match kind {
+                        ArtifactKind::Bins | ArtifactKind::Bin(_) => {
+                            (format!("{}__bin", target.name), "BIN")
+                        }
+                        ArtifactKind::Cdylib => (
+                            format!("{}__cdylib", sanitize_module_name(&target.name)),
+                            "CDYLIB",
+                        ),
+                        ArtifactKind::Staticlib => (
+                            format!("{}__staticlib", sanitize_module_name(&target.name)),
+                            "STATICLIB",
+                        ),
+                    };
+
+                    // Cargo sets `CARGO_<KIND>_FILE_<DEP>_<NAME>` for every artifact and
+                    // `CARGO_<KIND>_FILE_<DEP>` for libraries and binaries named after the dependency.
+                    let env_prefix = format!("CARGO_{}_FILE_{}", env_kind, dep_name.to_uppercase());
+                    let mut env = BTreeSet::from([format!("{}_{}", env_prefix, target.name)]);
+                    if env_kind != "BIN" || sanitize_module_name(&target.name) == dep_name {
+                        env.insert(env_prefix);
+                    }
+
+                    ArtifactCrateDependency {
+                        id: dep.id.clone(),
+                        target: bazel_target,
+                        env,
+                        platform: artifact.target.clone(),
+                        local_path: match source_annotations.get(&pkg.id) {
+                            Some(SourceAnnotation::Path { path }) => Some(path.clone()),
+                            _ => None,
+                        },
+                    }
+                })
+                .collect()
+        };
+
+        fn map_artifact_deps<'a>(
+            deps: impl IntoIterator<Item = &'a Dependency> + 'a,
+            new_artifact_deps: impl Fn(&'a Dependency) -> Vec<ArtifactCrateDependency> + 'a,
+        ) -> impl Iterator<Item = (ArtifactCrateDependency, Option<String>)> + 'a {
+            deps.into_iter().flat_map(move |dep| {
+                new_artifact_deps(dep).into_iter().flat_map(|adep| {
+                    dep.platforms
+                        .is_empty()
+                        .then_some((adep.clone(), None))
+                        .into_iter()
+                        .chain(
+                            dep.platforms
+                                .iter()
+                                .map(move |p| (adep.clone(), Some(p.to_string()))),
+                        )
+                })
+            })
+        }
+
         fn map_deps<'a>(
             deps: impl IntoIterator<Item = &'a Dependency> + 'a,
             new_crate_dep: impl Fn(&'a Dependency) -> Option<CrateDependency> + 'a,
@@ -578,12 +717,19 @@ impl CrateContext {
         let proc_macro_deps_dev = resolve_items(&annotations, |item| {
             map_deps(&item.proc_macro_deps_dev, new_crate_dep)
         });
+        let artifact_deps = resolve_items(annotations, |item| {
+            map_artifact_deps(&item.artifact_deps, new_artifact_deps)
+        });
         let crate_features = {
-            let mut intersection = annotations.values().map(|v| v.features.clone()).next().unwrap_or_default();
+            let mut intersection = annotations
+                .values()
+                .map(|v| v.features.clone())
+                .next()
+                .unwrap_or_default();
 
-            annotations.values().for_each(|annotation| {
-                intersection.retain(|v| annotation.features.contains(v))
-            });
+            annotations
+                .values()
+                .for_each(|annotation| intersection.retain(|v| annotation.features.contains(v)));
 
             let mut select = Select::from_value(intersection);
             for (triple, annotation) in annotations {
@@ -604,6 +750,7 @@ impl CrateContext {
             edition: package.edition.as_str().to_string(),
             proc_macro_deps,
             proc_macro_deps_dev,
+            artifact_deps,
             version: package.version.to_string(),
             ..Default::default()
         };
@@ -614,6 +761,12 @@ impl CrateContext {
         let include_build_scripts =
             Self::crate_includes_build_script(package_extra, include_build_scripts);
 
+        // Artifacts used by artifact dependencies of other crates are always generated.
+        let artifacts: BTreeSet<&ArtifactKind> = annotations
+            .values()
+            .flat_map(|annotation| annotation.artifacts.iter())
+            .collect();
+
         let gen_none = GenBinaries::Some(BTreeSet::new());
         let gen_binaries = package_extra
             .and_then(|(_, settings)| settings.crate_extra.gen_binaries.as_ref())
@@ -622,15 +775,69 @@ impl CrateContext {
             } else {
                 &gen_none
             });
+        let gen_binaries = &match gen_binaries {
+            GenBinaries::Some(_) if artifacts.contains(&ArtifactKind::Bins) => GenBinaries::All,
+            GenBinaries::Some(names) => GenBinaries::Some(
+                names
+                    .iter()
+                    .cloned()
+                    .chain(artifacts.iter().filter_map(|kind| match kind {
+                        ArtifactKind::Bin(name) => Some(name.clone()),
+                        _ => None,
+                    }))
+                    .collect(),
+            ),
+            GenBinaries::All => GenBinaries::All,
+        };
+
+        let gen_tests = package_extra
+            .and_then(|(_, settings)| settings.crate_extra.gen_tests.as_ref())
+            .unwrap_or(&gen_none);
+        let gen_examples = package_extra
+            .and_then(|(_, settings)| settings.crate_extra.gen_examples.as_ref())
+            .unwrap_or(&gen_none);
+        let gen_benches = package_extra
+            .and_then(|(_, settings)| settings.crate_extra.gen_benches.as_ref())
+            .unwrap_or(&gen_none);
+        let gen_shared_library = package_extra
+            .and_then(|(_, settings)| settings.crate_extra.gen_shared_library)
+            .unwrap_or(false)
+            || artifacts.contains(&ArtifactKind::Cdylib);
+        let gen_static_library = package_extra
+            .and_then(|(_, settings)| settings.crate_extra.gen_static_library)
+            .unwrap_or(false)
+            || artifacts.contains(&ArtifactKind::Staticlib);
 
         // Iterate over each target and produce a Bazel target for all supported "kinds"
-        let targets = Self::collect_targets(
+        let mut targets = Self::collect_targets(
             package,
             gen_binaries,
+            gen_tests,
+            gen_examples,
+            gen_benches,
             include_build_scripts,
+            gen_shared_library,
+            gen_static_library,
             sources_are_present,
         )?;
 
+        let unresolved_dev_deps = Self::unresolved_dev_dependencies(
+            package,
+            &targets,
+            metadata.workspace_members.contains(&package.id),
+        );
+        if !unresolved_dev_deps.is_empty() {
+            tracing::warn!(
+                "Skipping the tests, examples and benches of {} as Cargo only resolves \
+                dev-dependencies for workspace members and they would be missing: {}",
+                crate_id,
+                unresolved_dev_deps.iter().join(", "),
+            );
+            targets.retain(|rule| {
+                !matches!(rule, Rule::Test(..) | Rule::Example(..) | Rule::Bench(..))
+            });
+        }
+
         // Parse the library crate name from the set of included targets
         let library_target_name = {
             let lib_targets: Vec<&TargetAttributes> = targets
@@ -683,11 +890,15 @@ impl CrateContext {
             let build_proc_macro_deps = resolve_items(&annotations, |item| {
                 map_deps(&item.build_proc_macro_deps, new_crate_dep)
             });
+            let build_artifact_deps = resolve_items(annotations, |item| {
+                map_artifact_deps(&item.build_artifact_deps, new_artifact_deps)
+            });
 
             Some(BuildScriptAttributes {
                 deps: build_deps,
                 link_deps: build_link_deps,
                 proc_macro_deps: build_proc_macro_deps,
+                artifact_deps: build_artifact_deps,
                 links: package.links.clone(),
                 ..Default::default()
             })
@@ -1064,11 +1275,66 @@ impl CrateContext {
             .unwrap_or(default_generate_build_script)
     }
 
+    /// Determine which of a package's test and bench targets do not use the libtest harness.
+    ///
+    /// This information is not part of `cargo metadata` output, so the package's manifest is
+    /// parsed instead. Targets are assumed to use the harness if the manifest cannot be read.
+    fn targets_without_harness(package: &Package) -> BTreeSet<String> {
+        let manifest = match std::fs::read(&package.manifest_path)
+            .ok()
+            .and_then(|content| cargo_toml::Manifest::from_slice(&content).ok())
+        {
+            Some(manifest) => manifest,
+            None => return BTreeSet::new(),
+        };
+
+        manifest
+            .test
+            .iter()
+            .chain(manifest.bench.iter())
+            .filter(|product| !product.harness)
+            .filter_map(|product| product.name.clone())
+            .collect()
+    }
+
+    /// The dev-dependencies of a package which its generated tests, examples and benches
+    /// are missing. Cargo only resolves dev-dependencies for workspace members, though
+    /// ones which are also regular dependencies of the package are available.
+    fn unresolved_dev_dependencies(
+        package: &Package,
+        targets: &BTreeSet<Rule>,
+        is_workspace_member: bool,
+    ) -> BTreeSet<String> {
+        let has_dev_targets = targets
+            .iter()
+            .any(|rule| matches!(rule, Rule::Test(..) | Rule::Example(..) | Rule::Bench(..)));
+        if is_workspace_member || !has_dev_targets {
+            return BTreeSet::new();
+        }
+
+        let (dev_deps, deps): (Vec<_>, Vec<_>) = package
+            .dependencies
+            .iter()
+            .partition(|dep| dep.kind == cargo_metadata::DependencyKind::Development);
+
+        dev_deps
+            .into_iter()
+            .filter(|dev_dep| !deps.iter().any(|dep| dep.name == dev_dep.name))
+            .map(|dep| dep.name.clone())
+            .collect()
+    }
+
     /// Collect all Bazel targets that should be generated for a particular Package
+    #[allow(clippy::too_many_arguments)]
     fn collect_targets(
         package: &Package,
         gen_binaries: &GenBinaries,
+        gen_tests: &GenBinaries,
+        gen_examples: &GenBinaries,
+        gen_benches: &GenBinaries,
         include_build_scripts: bool,
+        gen_shared_library: bool,
+        gen_static_library: bool,
         sources_are_present: bool,
     ) -> anyhow::Result<BTreeSet<Rule>> {
         let package_root = package
@@ -1077,6 +1343,14 @@ impl CrateContext {
             .parent()
             .expect("Every manifest should have a parent directory");
 
+        let targets_without_harness =
+            if gen_tests == &GenBinaries::default() && gen_benches == &GenBinaries::default() {
+                BTreeSet::new()
+            } else {
+                Self::targets_without_harness(package)
+            };
+        let targets_without_harness = &targets_without_harness;
+
         package
             .targets
             .iter()
@@ -1116,7 +1390,7 @@ impl CrateContext {
                     }
 
                     // Check to see if the dependencies is a library target
-                    if matches!(kind, cargo_metadata::TargetKind::Lib | cargo_metadata::TargetKind::RLib) {
+                    if matches!(kind, cargo_metadata::TargetKind::Lib | cargo_metadata::TargetKind::RLib | cargo_metadata::TargetKind::DyLib) {
                         return Some(Ok(Rule::Library(TargetAttributes {
                             crate_name,
                             crate_root,
@@ -1124,12 +1398,26 @@ impl CrateContext {
                         })));
                     }
 
+                    // Shared and static libraries are only generated when requested by an annotation
+                    if gen_shared_library && matches!(kind, cargo_metadata::TargetKind::CDyLib) {
+                        return Some(Ok(Rule::SharedLibrary(TargetAttributes {
+                            crate_name,
+                            crate_root,
+                            srcs: Glob::new_rust_srcs(!sources_are_present),
+                        })));
+                    }
+
+                    if gen_static_library && matches!(kind, cargo_metadata::TargetKind::StaticLib) {
+                        return Some(Ok(Rule::StaticLibrary(TargetAttributes {
+                            crate_name,
+                            crate_root,
+                            srcs: Glob::new_rust_srcs(!sources_are_present),
+                        })));
+                    }
+
                     // Check if the target kind is binary and is one of the ones included in gen_binaries
                     if matches!(kind, cargo_metadata::TargetKind::Bin)
-                        && match gen_binaries {
-                            GenBinaries::All => true,
-                            GenBinaries::Some(set) => set.contains(&target.name),
-                        }
+                        && gen_binaries.includes(&target.name)
                     {
                         return Some(Ok(Rule::Binary(TargetAttributes {
                             crate_name: target.name.clone(),
@@ -1138,6 +1426,45 @@ impl CrateContext {
                         })));
                     }
 
+                    // Tests, examples and benches are only generated when requested by an annotation
+                    if matches!(kind, cargo_metadata::TargetKind::Test)
+                        && gen_tests.includes(&target.name)
+                    {
+                        return Some(Ok(Rule::Test(TestAttributes {
+                            target: TargetAttributes {
+                                crate_name: target.name.clone(),
+                                crate_root,
+                                srcs: Glob::new_rust_srcs(!sources_are_present),
+                            },
+                            harness: !targets_without_harness.contains(&target.name),
+                        })));
+                    }
+
+                    // Examples may also be libraries which are not supported.
+                    if matches!(kind, cargo_metadata::TargetKind::Example)
+                        && target.crate_types.contains(&cargo_metadata::CrateType::Bin)
+                        && gen_examples.includes(&target.name)
+                    {
+                        return Some(Ok(Rule::Example(TargetAttributes {
+                            crate_name: target.name.clone(),
+                            crate_root,
+                            srcs: Glob::new_rust_srcs(!sources_are_present),
+                        })));
+                    }
+
+                    if matches!(kind, cargo_metadata::TargetKind::Bench)
+                        && gen_benches.includes(&target.name)
+                    {
+                        return Some(Ok(Rule::Bench(TestAttributes {
+                            target: TargetAttributes {
+                                crate_name: target.name.clone(),
+                                crate_root,
+                                srcs: Glob::new_rust_srcs(!sources_are_present),
+                            },
+                            harness: !targets_without_harness.contains(&target.name),
+                        })));
+                    }
+
                     None
                 })
             })
@@ -1153,7 +1480,7 @@ mod test {
     use semver::Version;
 
     use crate::config::CrateAnnotations;
-    use crate::metadata::{Annotations, CrateAnnotation};
+    use crate::metadata::{Annotations, ArtifactDependency, CrateAnnotation};
 
     fn common_annotations() -> Annotations {
         Annotations::new(
@@ -1438,6 +1765,325 @@ mod test {
         );
     }
 
+    #[test]
+    fn context_cdylib_crate_type_with_gen_shared_library() {
+        let annotations = crate_type_annotations();
+
+        let id = CrateId::new("sysinfo".into(), Version::new(0, 22, 5));
+
+        let mut pairred_extras = BTreeMap::new();
+        pairred_extras.insert(
+            id.clone(),
+            PairedExtras {
+                crate_extra: CrateAnnotations {
+                    gen_shared_library: Some(true),
+                    ..CrateAnnotations::default()
+                },
+            },
+        );
+
+        let include_binaries = false;
+        let include_build_scripts = false;
+        let are_sources_present = false;
+        let context = CrateContext::new(
+            annotations
+                .metadata
+                .packages
+                .values()
+                .find(|pkg| CrateId::from(*pkg) == id)
+                .unwrap(),
+            annotations
+                .metadata
+                .workspace_metadata
+                .resolver_metadata
+                .get(&id)
+                .unwrap(),
+            &annotations.metadata,
+            &annotations.lockfile.crates,
+            &pairred_extras,
+            include_binaries,
+            include_build_scripts,
+            are_sources_present,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(context.name, "sysinfo");
+        assert_eq!(context.library_target_name, Some("sysinfo".to_owned()));
+        assert_eq!(
+            context.targets,
+            BTreeSet::from([
+                Rule::Library(TargetAttributes {
+                    crate_name: "sysinfo".to_owned(),
+                    crate_root: Some("src/lib.rs".to_owned()),
+                    srcs: Glob::new_rust_srcs(!are_sources_present),
+                }),
+                Rule::SharedLibrary(TargetAttributes {
+                    crate_name: "sysinfo".to_owned(),
+                    crate_root: Some("src/lib.rs".to_owned()),
+                    srcs: Glob::new_rust_srcs(!are_sources_present),
+                }),
+            ]),
+        );
+    }
+
+    #[test]
+    fn context_with_artifact_deps() {
+        let annotations = crate_type_annotations();
+
+        let id = CrateId::new("rayon".into(), Version::new(1, 5, 1));
+        let sysinfo_id = CrateId::new("sysinfo".into(), Version::new(0, 22, 5));
+
+        let artifact_dep = |target| Dependency {
+            id: sysinfo_id.clone(),
+            target_name: Some("sysinfo".to_owned()),
+            alias: None,
+            features: BTreeSet::new(),
+            optional: false,
+            platforms: BTreeSet::new(),
+            artifact: Some(ArtifactDependency {
+                kinds: BTreeSet::from([ArtifactKind::Cdylib]),
+                lib: false,
+                target,
+            }),
+        };
+        let triple = TargetTriple::from_bazel("x86_64-unknown-linux-gnu".to_owned());
+        let crate_annotations = BTreeMap::from([(
+            triple.clone(),
+            CrateAnnotation {
+                artifact_deps: BTreeSet::from([artifact_dep(None)]),
+                build_artifact_deps: BTreeSet::from([artifact_dep(Some(
+                    ArtifactPlatform::Triple("wasm32-unknown-unknown".to_owned()),
+                ))]),
+                ..CrateAnnotation::default()
+            },
+        )]);
+
+        let include_binaries = false;
+        let include_build_scripts = true;
+        let are_sources_present = false;
+        let context = CrateContext::new(
+            annotations
+                .metadata
+                .packages
+                .values()
+                .find(|pkg| CrateId::from(*pkg) == id)
+                .unwrap(),
+            &crate_annotations,
+            &annotations.metadata,
+            &annotations.lockfile.crates,
+            &annotations.pairred_extras,
+            include_binaries,
+            include_build_scripts,
+            are_sources_present,
+            false,
+        )
+        .unwrap();
+
+        let expected = |platform| ArtifactCrateDependency {
+            id: sysinfo_id.clone(),
+            target: "sysinfo__cdylib".to_owned(),
+            env: BTreeSet::from([
+                "CARGO_CDYLIB_FILE_SYSINFO".to_owned(),
+                "CARGO_CDYLIB_FILE_SYSINFO_sysinfo".to_owned(),
+            ]),
+            platform,
+            local_path: None,
+        };
+        assert_eq!(
+            context.common_attrs.artifact_deps.items(),
+            vec![(Some(triple.to_bazel()), expected(None))],
+        );
+        assert_eq!(
+            context.build_script_attrs.unwrap().artifact_deps.items(),
+            vec![(
+                Some(triple.to_bazel()),
+                expected(Some(ArtifactPlatform::Triple(
+                    "wasm32-unknown-unknown".to_owned()
+                ))),
+            )],
+        );
+        // Artifact dependencies are not library dependencies.
+        assert!(context
+            .common_attrs
+            .deps
+            .values()
+            .iter()
+            .all(|dep| dep.id != sysinfo_id));
+    }
+
+    #[test]
+    fn context_with_requested_artifacts() {
+        let annotations = crate_type_annotations();
+
+        let id = CrateId::new("sysinfo".into(), Version::new(0, 22, 5));
+
+        let crate_annotations = BTreeMap::from([(
+            TargetTriple::from_bazel("x86_64-unknown-linux-gnu".to_owned()),
+            CrateAnnotation {
+                artifacts: BTreeSet::from([ArtifactKind::Cdylib]),
+                ..CrateAnnotation::default()
+            },
+        )]);
+
+        let include_binaries = false;
+        let include_build_scripts = false;
+        let are_sources_present = false;
+        let context = CrateContext::new(
+            annotations
+                .metadata
+                .packages
+                .values()
+                .find(|pkg| CrateId::from(*pkg) == id)
+                .unwrap(),
+            &crate_annotations,
+            &annotations.metadata,
+            &annotations.lockfile.crates,
+            &annotations.pairred_extras,
+            include_binaries,
+            include_build_scripts,
+            are_sources_present,
+            false,
+        )
+        .unwrap();
+
+        // The shared library is generated without `gen_shared_library` as it's used as an artifact.
+        assert!(context
+            .targets
+            .contains(&Rule::SharedLibrary(TargetAttributes {
+                crate_name: "sysinfo".to_owned(),
+                crate_root: Some("src/lib.rs".to_owned()),
+                srcs: Glob::new_rust_srcs(!are_sources_present),
+            })));
+    }
+
+    #[test]
+    fn targets_without_harness() {
+        let (_temp_dir, dir) = crate::test::test_tempdir("targets_without_harness");
+        let manifest_path = dir.join("Cargo.toml");
+        std::fs::write(
+            &manifest_path,
+            indoc::indoc! {r#"
+                [package]
+                name = "mock-crate"
+                version = "0.1.0"
+
+                [[test]]
+                name = "integration"
+
+                [[test]]
+                name = "trybuild"
+                harness = false
+
+                [[bench]]
+                name = "speed"
+                harness = false
+            "#},
+        )
+        .unwrap();
+
+        let annotations = common_annotations();
+        let mut package = annotations
+            .metadata
+            .packages
+            .values()
+            .next()
+            .unwrap()
+            .clone();
+        package.manifest_path = camino::Utf8PathBuf::from_path_buf(manifest_path).unwrap();
+
+        assert_eq!(
+            CrateContext::targets_without_harness(&package),
+            BTreeSet::from(["speed".to_owned(), "trybuild".to_owned()]),
+        );
+    }
+
+    #[test]
+    fn unresolved_dev_dependencies() {
+        let annotations = common_annotations();
+        let mut package = annotations
+            .metadata
+            .packages
+            .values()
+            .next()
+            .unwrap()
+            .clone();
+        package.dependencies = serde_json::from_value(serde_json::json!([
+            {
+                "name": "proptest",
+                "req": "^1.0",
+                "kind": "dev",
+                "optional": false,
+                "uses_default_features": true,
+                "features": [],
+                "target": null,
+                "rename": null,
+                "registry": null,
+                "source": null,
+            },
+            {
+                "name": "serde",
+                "req": "^1.0",
+                "kind": "dev",
+                "optional": false,
+                "uses_default_features": true,
+                "features": ["derive"],
+                "target": null,
+                "rename": null,
+                "registry": null,
+                "source": null,
+            },
+            {
+                "name": "serde",
+                "req": "^1.0",
+                "kind": null,
+                "optional": false,
+                "uses_default_features": true,
+                "features": [],
+                "target": null,
+                "rename": null,
+                "registry": null,
+                "source": null,
+            },
+        ]))
+        .unwrap();
+
+        let library = Rule::Library(TargetAttributes {
+            crate_name: "mock_crate".to_owned(),
+            crate_root: None,
+            srcs: Glob::default(),
+        });
+        let test = Rule::Test(TestAttributes {
+            target: TargetAttributes {
+                crate_name: "integration".to_owned(),
+                crate_root: None,
+                srcs: Glob::default(),
+            },
+            harness: true,
+        });
+
+        assert_eq!(
+            CrateContext::unresolved_dev_dependencies(
+                &package,
+                &BTreeSet::from([library.clone(), test.clone()]),
+                false
+            ),
+            BTreeSet::from(["proptest".to_owned()]),
+        );
+        assert!(CrateContext::unresolved_dev_dependencies(
+            &package,
+            &BTreeSet::from([library.clone()]),
+            false
+        )
+        .is_empty());
+        assert!(CrateContext::unresolved_dev_dependencies(
+            &package,
+            &BTreeSet::from([library, test]),
+            true
+        )
+        .is_empty());
+    }
+
     fn package_context_test(
         set_package: fn(package: &mut Package),
         check_context: fn(context: CrateContext),
@@ -1,11 +1,13 @@
+use std::borrow::Cow;
 use std::collections::{BTreeMap, BTreeSet};
 
 use anyhow::{anyhow, Context, Result};
-use cfg_expr::targets::{get_builtin_target_by_triple, TargetInfo};
+use cfg_expr::targets::TargetInfo;
 use cfg_expr::{Expression, Predicate};
 
+use crate::config::PlatformCfgs;
 use crate::context::CrateContext;
-use crate::utils::target_triple::TargetTriple;
+use crate::utils::target_triple::{TargetSpec, TargetTriple};
 
 /// Walk through all dependencies in a [CrateContext] list for all configuration specific
 /// dependencies to produce a mapping of configurations/Cargo target_triples to compatible
@@ -13,6 +15,8 @@ use crate::utils::target_triple::TargetTriple;
 pub(crate) fn resolve_cfg_platforms<'a>(
     crates: impl IntoIterator<Item = &'a CrateContext>,
     supported_platform_triples: &BTreeSet<TargetTriple>,
+    platform_cfgs: &BTreeMap<TargetTriple, PlatformCfgs>,
+    custom_targets: &BTreeMap<TargetTriple, TargetSpec>,
 ) -> Result<BTreeMap<String, BTreeSet<TargetTriple>>> {
     // Collect all unique configurations from all dependencies into a single set
     let configurations: BTreeSet<String> = crates
@@ -25,11 +29,13 @@ pub(crate) fn resolve_cfg_platforms<'a>(
             configurations.extend(attr.deps_dev.configurations());
             configurations.extend(attr.proc_macro_deps.configurations());
             configurations.extend(attr.proc_macro_deps_dev.configurations());
+            configurations.extend(attr.artifact_deps.configurations());
 
             // Chain the build dependencies if some are defined
             if let Some(attr) = &ctx.build_script_attrs {
                 configurations.extend(attr.deps.configurations());
                 configurations.extend(attr.proc_macro_deps.configurations());
+                configurations.extend(attr.artifact_deps.configurations());
             }
 
             if let Some(feature_maps) = &ctx.feature_dep_maps {
@@ -52,19 +58,28 @@ pub(crate) fn resolve_cfg_platforms<'a>(
         })
         .collect();
 
+    for triple in platform_cfgs.keys() {
+        if !supported_platform_triples.contains(triple) {
+            tracing::warn!(
+                "Ignoring `platform_cfgs` of {} as it is not one of the `supported_platform_triples`",
+                triple
+            );
+        }
+    }
+
     // Generate target information for each triple string
     let target_infos = supported_platform_triples
         .iter()
         .map(
-            |target_triple| match get_builtin_target_by_triple(&target_triple.to_cargo()) {
+            |target_triple| match target_triple.target_info(custom_targets) {
                 Some(info) => Ok((target_triple, info)),
                 None => Err(anyhow!(
-                    "Invalid platform triple in supported platforms: {}",
+                    "Invalid platform triple in supported platforms: {}. Targets unknown to rustc must be defined in `custom_targets`.",
                     target_triple
                 )),
             },
         )
-        .collect::<Result<BTreeMap<&TargetTriple, &'static TargetInfo>>>()?;
+        .collect::<Result<BTreeMap<&TargetTriple, Cow<'static, TargetInfo>>>>()?;
 
     // `cfg-expr` does not understand configurations that are simply platform triples
     // (`x86_64-unknown-linux-gnu` vs `cfg(target = "x86_64-unknown-linux-gnu")`). So
@@ -92,14 +107,15 @@ pub(crate) fn resolve_cfg_platforms<'a>(
 
             let triples = target_infos
                 .iter()
-                .filter(|(_, target_info)| {
+                .filter(|(triple, target_info)| {
+                    // Options which are not implied by the triple itself (custom `--cfg`
+                    // flags, target features, etc.) are only enabled when configured.
+                    let cfgs = platform_cfgs.get(**triple);
                     expression.eval(|p| match p {
-                        Predicate::Target(tp) => tp.matches(**target_info),
-                        Predicate::KeyValue { key, val } => {
-                            *key == "target" && val == &target_info.triple.as_str()
+                        Predicate::KeyValue { key, val } if *key == "target" => {
+                            val == &target_info.triple.as_str()
                         }
-                        // For now there is no other kind of matching
-                        _ => false,
+                        _ => PlatformCfgs::eval(cfgs, target_info, p),
                     })
                 })
                 .map(|(triple, _)| (*triple).clone())
@@ -180,8 +196,13 @@ mod test {
             feature_dep_maps: None,
         };
 
-        let configurations =
-            resolve_cfg_platforms(vec![&context], &supported_platform_triples()).unwrap();
+        let configurations = resolve_cfg_platforms(
+            vec![&context],
+            &supported_platform_triples(),
+            &BTreeMap::new(),
+            &BTreeMap::new(),
+        )
+        .unwrap();
 
         assert_eq!(
             configurations,
@@ -263,8 +284,13 @@ mod test {
         data.into_iter().for_each(|(configuration, expectation)| {
             let context = mock_resolve_context(configuration.clone());
 
-            let configurations =
-                resolve_cfg_platforms(vec![&context], &supported_platform_triples()).unwrap();
+            let configurations = resolve_cfg_platforms(
+                vec![&context],
+                &supported_platform_triples(),
+                &BTreeMap::new(),
+                &BTreeMap::new(),
+            )
+            .unwrap();
 
             assert_eq!(
                 configurations,
@@ -330,8 +356,13 @@ mod test {
             feature_dep_maps: None,
         };
 
-        let configurations =
-            resolve_cfg_platforms(vec![&context], &supported_platform_triples()).unwrap();
+        let configurations = resolve_cfg_platforms(
+            vec![&context],
+            &supported_platform_triples(),
+            &BTreeMap::new(),
+            &BTreeMap::new(),
+        )
+        .unwrap();
 
         assert_eq!(
             configurations,
@@ -399,8 +430,13 @@ mod test {
             feature_dep_maps: None,
         };
 
-        let configurations =
-            resolve_cfg_platforms(vec![&context], &supported_platform_triples()).unwrap();
+        let configurations = resolve_cfg_platforms(
+            vec![&context],
+            &supported_platform_triples(),
+            &BTreeMap::new(),
+            &BTreeMap::new(),
+        )
+        .unwrap();
 
         assert_eq!(
             configurations,
@@ -424,4 +460,137 @@ mod test {
             ])
         );
     }
+
+    #[test]
+    fn resolve_platform_cfgs() {
+        let data = BTreeMap::from([
+            (
+                "cfg(tokio_unstable)".to_owned(),
+                BTreeSet::from([TargetTriple::from_bazel(
+                    "x86_64-unknown-linux-gnu".to_owned(),
+                )]),
+            ),
+            (
+                r#"cfg(all(target_os = "macos", target_feature = "crt-static"))"#.to_owned(),
+                BTreeSet::from([TargetTriple::from_bazel("aarch64-apple-darwin".to_owned())]),
+            ),
+            (
+                r#"cfg(not(panic = "abort"))"#.to_owned(),
+                BTreeSet::from([
+                    TargetTriple::from_bazel("aarch64-apple-darwin".to_owned()),
+                    TargetTriple::from_bazel("i686-apple-darwin".to_owned()),
+                ]),
+            ),
+            // Configured values replace the ones of the triple.
+            (
+                r#"cfg(panic = "unwind")"#.to_owned(),
+                BTreeSet::from([
+                    TargetTriple::from_bazel("aarch64-apple-darwin".to_owned()),
+                    TargetTriple::from_bazel("i686-apple-darwin".to_owned()),
+                ]),
+            ),
+            // Unless a target may have multiple values for the key.
+            (
+                r#"cfg(target_family = "wasm")"#.to_owned(),
+                BTreeSet::from([TargetTriple::from_bazel(
+                    "x86_64-unknown-linux-gnu".to_owned(),
+                )]),
+            ),
+            ("cfg(unix)".to_owned(), supported_platform_triples()),
+        ]);
+
+        let platform_cfgs = BTreeMap::from([
+            (
+                TargetTriple::from_bazel("aarch64-apple-darwin".to_owned()),
+                serde_json::from_value(serde_json::json!(["target_feature = \"crt-static\""]))
+                    .unwrap(),
+            ),
+            (
+                TargetTriple::from_bazel("x86_64-unknown-linux-gnu".to_owned()),
+                serde_json::from_value(serde_json::json!([
+                    "tokio_unstable",
+                    "panic = \"abort\"",
+                    "target_family = \"wasm\"",
+                ]))
+                .unwrap(),
+            ),
+        ]);
+
+        data.into_iter().for_each(|(configuration, expectation)| {
+            let context = mock_resolve_context(configuration.clone());
+
+            let configurations = resolve_cfg_platforms(
+                vec![&context],
+                &supported_platform_triples(),
+                &platform_cfgs,
+                &BTreeMap::new(),
+            )
+            .unwrap();
+
+            assert_eq!(
+                configurations[&configuration], expectation,
+                "{configuration}"
+            );
+        })
+    }
+
+    #[test]
+    fn resolve_custom_targets() {
+        let custom = TargetTriple::from_bazel("thumbv7em-custom-none".to_owned());
+        let custom_targets = BTreeMap::from([(
+            custom.clone(),
+            serde_json::from_value(serde_json::json!({
+                "llvm-target": "thumbv7em-none-eabihf",
+                "arch": "arm",
+                "abi": "eabihf",
+                "target-pointer-width": "32",
+                "panic-strategy": "abort",
+            }))
+            .unwrap(),
+        )]);
+        let mut supported_platform_triples = supported_platform_triples();
+        supported_platform_triples.insert(custom.clone());
+
+        let data = BTreeMap::from([
+            (
+                r#"cfg(all(target_arch = "arm", target_os = "none"))"#.to_owned(),
+                BTreeSet::from([custom.clone()]),
+            ),
+            (
+                r#"cfg(all(target_arch = "arm", panic = "abort"))"#.to_owned(),
+                BTreeSet::from([custom.clone()]),
+            ),
+            (
+                "thumbv7em-custom-none".to_owned(),
+                BTreeSet::from([custom.clone()]),
+            ),
+        ]);
+
+        data.into_iter().for_each(|(configuration, expectation)| {
+            let context = mock_resolve_context(configuration.clone());
+
+            let configurations = resolve_cfg_platforms(
+                vec![&context],
+                &supported_platform_triples,
+                &BTreeMap::new(),
+                &custom_targets,
+            )
+            .unwrap();
+
+            assert_eq!(
+                configurations[&configuration], expectation,
+                "{configuration}"
+            );
+        });
+
+        // Without the custom target definition the triple is unknown.
+        let context = mock_resolve_context("cfg(unix)".to_owned());
+        assert!(resolve_cfg_platforms(
+            vec![&context],
+            &supported_platform_triples,
+            &BTreeMap::new(),
+            &BTreeMap::new(),
+        )
+        .is_err());
+    }
 }
@@ -1,5 +1,7 @@
 //! Utility module for interacting with the cargo-bazel lockfile.
 
+mod merge;
+
 use std::collections::BTreeMap;
 use std::ffi::OsStr;
 use std::fs;
@@ -16,6 +18,8 @@ use crate::context::Context;
 use crate::metadata::Cargo;
 use crate::splicing::{SplicingManifest, SplicingMetadata};
 
+pub(crate) use self::merge::*;
+
 pub(crate) fn lock_context(
     mut context: Context,
     config: &Config,
@@ -292,7 +296,7 @@ mod test {
         );
 
         assert_eq!(
-            Digest("17a426b0cb5c1eff5114997a35562bc8560afd365ec80ae31b3dcc449ee1fa60".to_owned()),
+            Digest("7d6aab2114347a26d1e913c2ee3f0a9ee94b1a88d253f92156d9e4967c605837".to_owned()),
             digest,
         );
     }
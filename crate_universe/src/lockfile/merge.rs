@@ -0,0 +1,331 @@
+//! Three-way merging of lockfiles, e.g. for use as a git merge driver.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+use anyhow::{bail, Result};
+use serde_json::{Map, Value};
+
+use crate::config::CrateId;
+use crate::context::{Context, CrateContext};
+
+/// Combine the changes `ours` and `theirs` made to their common ancestor `base`.
+///
+/// The checksum of the merged lockfile is cleared as the inputs it was computed
+/// from are not available here. Changes which can't be combined are reported as
+/// an error listing every conflict.
+pub(crate) fn merge_lockfiles(base: &Context, ours: &Context, theirs: &Context) -> Result<Context> {
+    let mut conflicts = Vec::new();
+
+    let crates = merge_maps(
+        &base.crates,
+        &ours.crates,
+        &theirs.crates,
+        merge_crate,
+        &mut conflicts,
+    );
+    conflicts.extend(version_conflicts(base, ours, theirs, &crates));
+
+    let workspace_members = merge_maps(
+        &base.workspace_members,
+        &ours.workspace_members,
+        &theirs.workspace_members,
+        |_, _, _, _| Err("the workspace path".to_owned()),
+        &mut conflicts,
+    );
+
+    let conditions = merge_maps(
+        &base.conditions,
+        &ours.conditions,
+        &theirs.conditions,
+        |_, base, ours, theirs| Ok(merge_sets(base.unwrap_or(&BTreeSet::new()), ours, theirs)),
+        &mut conflicts,
+    );
+
+    if !conflicts.is_empty() {
+        bail!(
+            "Unable to merge lockfiles, both sides made conflicting changes:\n  - {}\n\
+            Resolve the conflict by re-pinning the dependencies (e.g. `CARGO_BAZEL_REPIN=1`).",
+            conflicts.join("\n  - ")
+        );
+    }
+
+    Ok(Context {
+        checksum: None,
+        crates,
+        binary_crates: merge_sets(
+            &base.binary_crates,
+            &ours.binary_crates,
+            &theirs.binary_crates,
+        ),
+        workspace_members,
+        conditions,
+        direct_deps: merge_sets(&base.direct_deps, &ours.direct_deps, &theirs.direct_deps),
+        direct_dev_deps: merge_sets(
+            &base.direct_dev_deps,
+            &ours.direct_dev_deps,
+            &theirs.direct_dev_deps,
+        ),
+        unused_patches: merge_sets(
+            &base.unused_patches,
+            &ours.unused_patches,
+            &theirs.unused_patches,
+        ),
+    })
+}
+
+/// Keep the items which both sides have, and the ones either side added.
+fn merge_sets<T: Ord + Clone>(
+    base: &BTreeSet<T>,
+    ours: &BTreeSet<T>,
+    theirs: &BTreeSet<T>,
+) -> BTreeSet<T> {
+    ours.union(theirs)
+        .filter(|item| (ours.contains(item) && theirs.contains(item)) || !base.contains(item))
+        .cloned()
+        .collect()
+}
+
+/// Merge maps key by key. Entries which were changed differently on both sides are
+/// passed to `merge_value`, which describes what conflicted when they can't be merged.
+fn merge_maps<K, V, F>(
+    base: &BTreeMap<K, V>,
+    ours: &BTreeMap<K, V>,
+    theirs: &BTreeMap<K, V>,
+    merge_value: F,
+    conflicts: &mut Vec<String>,
+) -> BTreeMap<K, V>
+where
+    K: Ord + Clone + Display,
+    V: PartialEq + Clone,
+    F: Fn(&K, Option<&V>, &V, &V) -> Result<V, String>,
+{
+    let keys: BTreeSet<&K> = base
+        .keys()
+        .chain(ours.keys())
+        .chain(theirs.keys())
+        .collect();
+
+    keys.into_iter()
+        .filter_map(|key| {
+            let (base, ours, theirs) = (base.get(key), ours.get(key), theirs.get(key));
+
+            let value = if ours == theirs || base == theirs {
+                ours.cloned()
+            } else if base == ours {
+                theirs.cloned()
+            } else {
+                match (ours, theirs) {
+                    (Some(ours), Some(theirs)) => match merge_value(key, base, ours, theirs) {
+                        Ok(value) => Some(value),
+                        Err(what) => {
+                            conflicts.push(format!("`{key}` changed {what} on both sides"));
+                            None
+                        }
+                    },
+                    _ => {
+                        conflicts.push(format!(
+                            "`{key}` was removed on one side and changed on the other"
+                        ));
+                        None
+                    }
+                }
+            };
+
+            value.map(|value| (key.clone(), value))
+        })
+        .collect()
+}
+
+/// Merge the fields of a crate which both sides changed. Fields are compared as
+/// a whole, so changes to the same field only merge if they're identical.
+fn merge_crate(
+    _: &CrateId,
+    base: Option<&CrateContext>,
+    ours: &CrateContext,
+    theirs: &CrateContext,
+) -> Result<CrateContext, String> {
+    let to_value = |krate: &CrateContext| serde_json::to_value(krate).unwrap();
+
+    let merged = merge_values(
+        base.map(to_value).as_ref(),
+        Some(&to_value(ours)),
+        Some(&to_value(theirs)),
+        "",
+    )?;
+
+    serde_json::from_value(merged.unwrap_or_default()).map_err(|e| format!("the crate ({e})"))
+}
+
+fn merge_values(
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+    path: &str,
+) -> Result<Option<Value>, String> {
+    if ours == theirs || base == theirs {
+        return Ok(ours.cloned());
+    }
+    if base == ours {
+        return Ok(theirs.cloned());
+    }
+
+    match (ours, theirs) {
+        (Some(Value::Object(ours)), Some(Value::Object(theirs))) => {
+            let base = base.and_then(Value::as_object);
+            let keys: BTreeSet<&String> = ours.keys().chain(theirs.keys()).collect();
+
+            let mut merged = Map::new();
+            for key in keys {
+                let value = merge_values(
+                    base.and_then(|base| base.get(key)),
+                    ours.get(key),
+                    theirs.get(key),
+                    &format!("{path}.{key}"),
+                )?;
+                if let Some(value) = value {
+                    merged.insert(key.clone(), value);
+                }
+            }
+
+            Ok(Some(Value::Object(merged)))
+        }
+        _ => Err(format!("`{}`", path.trim_start_matches('.'))),
+    }
+}
+
+/// Both sides changing the versions of the same crate to different ones would leave
+/// versions in the merged lockfile which neither side resolved.
+fn version_conflicts(
+    base: &Context,
+    ours: &Context,
+    theirs: &Context,
+    merged: &BTreeMap<CrateId, CrateContext>,
+) -> Vec<String> {
+    let (base, ours, theirs, merged) = (
+        crate_versions(&base.crates),
+        crate_versions(&ours.crates),
+        crate_versions(&theirs.crates),
+        crate_versions(merged),
+    );
+
+    let empty = BTreeSet::new();
+    let names: BTreeSet<&str> = ours.keys().chain(theirs.keys()).copied().collect();
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let get = |versions: &BTreeMap<&str, BTreeSet<String>>| {
+                versions.get(name).unwrap_or(&empty).clone()
+            };
+            let (base, ours, theirs, merged) =
+                (get(&base), get(&ours), get(&theirs), get(&merged));
+
+            if ours == base || theirs == base || merged == ours || merged == theirs {
+                return None;
+            }
+
+            let list = |versions: BTreeSet<String>| Vec::from_iter(versions).join(", ");
+            Some(format!(
+                "`{}` was changed to different versions on both sides: ours has [{}] and theirs has [{}]",
+                name,
+                list(ours),
+                list(theirs),
+            ))
+        })
+        .collect()
+}
+
+fn crate_versions(crates: &BTreeMap<CrateId, CrateContext>) -> BTreeMap<&str, BTreeSet<String>> {
+    let mut versions = BTreeMap::<&str, BTreeSet<String>>::new();
+    for id in crates.keys() {
+        versions
+            .entry(id.name.as_str())
+            .or_default()
+            .insert(id.version.to_string());
+    }
+    versions
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mock_crate(name: &str, version: &str) -> (CrateId, CrateContext) {
+        let krate: CrateContext = serde_json::from_value(serde_json::json!({
+            "name": name,
+            "version": version,
+            "license_ids": [],
+        }))
+        .unwrap();
+        (CrateId::new(name.to_owned(), krate.version.clone()), krate)
+    }
+
+    fn mock_context(crates: &[(&str, &str)]) -> Context {
+        let crates: BTreeMap<_, _> = crates
+            .iter()
+            .map(|(name, version)| mock_crate(name, version))
+            .collect();
+        Context {
+            direct_deps: crates.keys().cloned().collect(),
+            crates,
+            ..Context::default()
+        }
+    }
+
+    #[test]
+    fn merge_independent_changes() {
+        let base = mock_context(&[("a", "1.0.0"), ("b", "1.0.0")]);
+        let ours = mock_context(&[("a", "1.0.0"), ("b", "1.0.0"), ("c", "1.0.0")]);
+        let theirs = mock_context(&[("a", "1.1.0"), ("d", "1.0.0")]);
+
+        let merged = merge_lockfiles(&base, &ours, &theirs).unwrap();
+
+        assert_eq!(
+            merged,
+            mock_context(&[("a", "1.1.0"), ("c", "1.0.0"), ("d", "1.0.0")])
+        );
+        assert!(merged.checksum.is_none());
+    }
+
+    #[test]
+    fn merge_crate_fields() {
+        let base = mock_context(&[("a", "1.0.0")]);
+        let (id, _) = mock_crate("a", "1.0.0");
+
+        let mut ours = base.clone();
+        ours.crates.get_mut(&id).unwrap().license = Some("MIT".to_owned());
+        let mut theirs = base.clone();
+        theirs.crates.get_mut(&id).unwrap().disable_pipelining = true;
+
+        let merged = merge_lockfiles(&base, &ours, &theirs).unwrap();
+        let krate = &merged.crates[&id];
+        assert_eq!(krate.license.as_deref(), Some("MIT"));
+        assert!(krate.disable_pipelining);
+
+        theirs.crates.get_mut(&id).unwrap().license = Some("Apache-2.0".to_owned());
+        let err = merge_lockfiles(&base, &ours, &theirs)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("`a 1.0.0` changed `license` on both sides"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn merge_conflicting_versions() {
+        let base = mock_context(&[("a", "1.0.0"), ("b", "1.0.0")]);
+        let ours = mock_context(&[("a", "1.1.0"), ("b", "1.0.0")]);
+        let theirs = mock_context(&[("a", "1.2.0"), ("b", "2.0.0")]);
+
+        let err = merge_lockfiles(&base, &ours, &theirs)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("`a` was changed to different versions on both sides: ours has [1.1.0] and theirs has [1.2.0]"),
+            "{err}"
+        );
+        assert!(!err.contains("`b`"), "{err}");
+    }
+}
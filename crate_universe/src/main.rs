@@ -38,5 +38,25 @@ fn main() -> cli::Result<()> {
             cli::init_logging("Render", level);
             cli::render(opt)
         }
+        cli::Options::Members(opt) => {
+            cli::init_logging("Members", level);
+            cli::members(opt)
+        }
+        cli::Options::Schema(opt) => {
+            cli::init_logging("Schema", level);
+            cli::schema(opt)
+        }
+        cli::Options::Validate(opt) => {
+            cli::init_logging("Validate", level);
+            cli::validate(opt)
+        }
+        cli::Options::MergeLockfile(opt) => {
+            cli::init_logging("MergeLockfile", level);
+            cli::merge_lockfile(opt)
+        }
+        cli::Options::Outdated(opt) => {
+            cli::init_logging("Outdated", level);
+            cli::outdated(opt)
+        }
     }
 }
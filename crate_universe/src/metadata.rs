@@ -1,5 +1,6 @@
 //! Tools for gathering various kinds of metadata (Cargo.lock, Cargo metadata, Crate Index info).
 
+mod artifact_dependency;
 mod cargo_bin;
 mod cargo_resolver;
 mod metadata_annotation;
@@ -15,6 +16,7 @@ use cargo_lock::Lockfile as CargoLockfile;
 use cargo_metadata::Metadata as CargoMetadata;
 use tracing::debug;
 
+pub(crate) use self::artifact_dependency::*;
 pub(crate) use self::cargo_bin::*;
 pub(crate) use self::cargo_resolver::*;
 pub(crate) use self::metadata_annotation::*;
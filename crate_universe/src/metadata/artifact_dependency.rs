@@ -0,0 +1,450 @@
+//! Support for Cargo's [artifact dependencies](https://doc.rust-lang.org/nightly/cargo/reference/unstable.html#artifact-dependencies).
+//!
+//! `cargo metadata` does not report which dependencies are artifact dependencies so they
+//! are instead read from the `Cargo.toml` files of the packages which declare them.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Display};
+use std::fs;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use camino::Utf8Path;
+use cargo_metadata::{DependencyKind, Package, Target, TargetKind};
+use cargo_platform::Platform;
+use cargo_toml::{Dependency as TomlDependency, Manifest};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// A kind of artifact requested from a dependency (the `artifact` key).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub(crate) enum ArtifactKind {
+    /// `bin`: every binary of the dependency.
+    Bins,
+    /// `bin:<name>`: a single binary of the dependency.
+    Bin(String),
+    /// `cdylib`
+    Cdylib,
+    /// `staticlib`
+    Staticlib,
+}
+
+impl ArtifactKind {
+    /// Whether or not the given target of a dependency provides this kind of artifact.
+    pub(crate) fn matches(&self, target: &Target) -> bool {
+        match self {
+            Self::Bins => target.kind.contains(&TargetKind::Bin),
+            Self::Bin(name) => target.kind.contains(&TargetKind::Bin) && &target.name == name,
+            Self::Cdylib => target.kind.contains(&TargetKind::CDyLib),
+            Self::Staticlib => target.kind.contains(&TargetKind::StaticLib),
+        }
+    }
+}
+
+impl FromStr for ArtifactKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "bin" => Ok(Self::Bins),
+            "cdylib" => Ok(Self::Cdylib),
+            "staticlib" => Ok(Self::Staticlib),
+            _ => match s.strip_prefix("bin:") {
+                Some(name) if !name.is_empty() => Ok(Self::Bin(name.to_owned())),
+                _ => bail!("Unsupported artifact kind `{}`", s),
+            },
+        }
+    }
+}
+
+impl Display for ArtifactKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bins => f.write_str("bin"),
+            Self::Bin(name) => write!(f, "bin:{name}"),
+            Self::Cdylib => f.write_str("cdylib"),
+            Self::Staticlib => f.write_str("staticlib"),
+        }
+    }
+}
+
+impl TryFrom<String> for ArtifactKind {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<ArtifactKind> for String {
+    fn from(value: ArtifactKind) -> Self {
+        value.to_string()
+    }
+}
+
+/// The platform an artifact is built for (the `target` key).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub(crate) enum ArtifactPlatform {
+    /// `target = "target"`: the platform being built for, even for build dependencies.
+    Target,
+    /// An explicit target triple.
+    Triple(String),
+}
+
+impl From<String> for ArtifactPlatform {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "target" => Self::Target,
+            _ => Self::Triple(value),
+        }
+    }
+}
+
+impl From<ArtifactPlatform> for String {
+    fn from(value: ArtifactPlatform) -> Self {
+        match value {
+            ArtifactPlatform::Target => "target".to_owned(),
+            ArtifactPlatform::Triple(triple) => triple,
+        }
+    }
+}
+
+/// The artifact settings of a single dependency.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub(crate) struct ArtifactDependency {
+    /// The kinds of artifacts requested.
+    pub(crate) kinds: BTreeSet<ArtifactKind>,
+
+    /// Whether or not the library of the dependency is also used (the `lib` key).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) lib: bool,
+
+    /// The platform to build the artifacts for. Artifacts are built for the same platform
+    /// as the dependency would be if this is unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) target: Option<ArtifactPlatform>,
+}
+
+impl ArtifactDependency {
+    fn from_detail(unstable: &BTreeMap<String, toml::Value>) -> Result<Option<Self>> {
+        let kinds = match unstable.get("artifact") {
+            None => return Ok(None),
+            Some(toml::Value::String(kind)) => BTreeSet::from([kind.parse()?]),
+            Some(toml::Value::Array(kinds)) => kinds
+                .iter()
+                .map(|kind| {
+                    kind.as_str()
+                        .ok_or_else(|| anyhow!("Expected a string, found `{}`", kind))?
+                        .parse()
+                })
+                .collect::<Result<_>>()?,
+            Some(value) => bail!("Expected a string or an array, found `{}`", value),
+        };
+
+        let lib = match unstable.get("lib") {
+            None => false,
+            Some(toml::Value::Boolean(lib)) => *lib,
+            Some(value) => bail!("Expected `lib` to be a boolean, found `{}`", value),
+        };
+
+        let target = match unstable.get("target") {
+            None => None,
+            Some(toml::Value::String(target)) => Some(ArtifactPlatform::from(target.clone())),
+            Some(value) => bail!("Expected `target` to be a string, found `{}`", value),
+        };
+
+        Ok(Some(Self { kinds, lib, target }))
+    }
+}
+
+/// The artifact dependencies declared in a single `Cargo.toml` file.
+#[derive(Debug, Default)]
+pub(crate) struct ManifestArtifacts {
+    /// Artifact dependencies keyed by their kind, platform and name (or rename) in the manifest.
+    artifacts: HashMap<(DependencyKind, Option<String>, String), ArtifactDependency>,
+}
+
+impl ManifestArtifacts {
+    /// Read the artifact dependencies of a package. Packages from registries can not have
+    /// artifact dependencies and manifests which can't be read are treated as having none.
+    pub(crate) fn load(package: &Package, workspace_manifest: Option<&Manifest>) -> Self {
+        if package.source.as_ref().is_some_and(|source| {
+            source.repr.starts_with("registry+") || source.repr.starts_with("sparse+")
+        }) {
+            return Self::default();
+        }
+
+        let Some(manifest) = read_manifest(&package.manifest_path) else {
+            return Self::default();
+        };
+
+        Self::new(&manifest, workspace_manifest).unwrap_or_else(|err| {
+            warn!(
+                "Ignoring artifact dependencies of {}: {:?}",
+                package.id, err
+            );
+            Self::default()
+        })
+    }
+
+    pub(crate) fn new(manifest: &Manifest, workspace_manifest: Option<&Manifest>) -> Result<Self> {
+        let workspace_deps = workspace_manifest
+            .or(Some(manifest))
+            .and_then(|manifest| manifest.workspace.as_ref())
+            .map(|workspace| &workspace.dependencies);
+
+        let mut artifacts = HashMap::new();
+        let tables = [(
+            None,
+            &manifest.dependencies,
+            &manifest.dev_dependencies,
+            &manifest.build_dependencies,
+        )]
+        .into_iter()
+        .chain(manifest.target.iter().map(|(platform, target)| {
+            (
+                Some(platform),
+                &target.dependencies,
+                &target.dev_dependencies,
+                &target.build_dependencies,
+            )
+        }));
+
+        for (platform, normal, dev, build) in tables {
+            for (kind, deps) in [
+                (DependencyKind::Normal, normal),
+                (DependencyKind::Development, dev),
+                (DependencyKind::Build, build),
+            ] {
+                for (name, dep) in deps {
+                    let detail = match dep {
+                        TomlDependency::Inherited(_) => {
+                            match workspace_deps.and_then(|deps| deps.get(name)) {
+                                Some(TomlDependency::Detailed(detail)) => detail,
+                                _ => continue,
+                            }
+                        }
+                        TomlDependency::Detailed(detail) => detail,
+                        TomlDependency::Simple(_) => continue,
+                    };
+
+                    let artifact = ArtifactDependency::from_detail(&detail.unstable)
+                        .with_context(|| format!("Invalid artifact dependency `{}`", name))?;
+                    if let Some(artifact) = artifact {
+                        let platform = platform
+                            .map(|platform| normalize_platform(platform))
+                            .transpose()?;
+                        artifacts.insert((kind, platform, name.clone()), artifact);
+                    }
+                }
+            }
+        }
+
+        Ok(Self { artifacts })
+    }
+
+    /// Locate the artifact settings of a dependency reported by `cargo metadata`.
+    pub(crate) fn get(&self, dep: &cargo_metadata::Dependency) -> Option<&ArtifactDependency> {
+        if self.artifacts.is_empty() {
+            return None;
+        }
+
+        let platform = dep.target.as_ref().map(ToString::to_string);
+        let name = dep.rename.as_ref().unwrap_or(&dep.name);
+        self.artifacts.get(&(dep.kind, platform, name.clone()))
+    }
+}
+
+/// Read the `[workspace]` manifest of a Cargo workspace, if it can be read.
+pub(crate) fn read_workspace_manifest(workspace_root: &Utf8Path) -> Option<Manifest> {
+    read_manifest(&workspace_root.join("Cargo.toml"))
+}
+
+fn read_manifest(path: &Utf8Path) -> Option<Manifest> {
+    let content = fs::read_to_string(path).ok()?;
+    Manifest::from_str(&content).ok()
+}
+
+/// Render a `[target.<platform>]` key the same way `cargo metadata` renders dependency targets.
+fn normalize_platform(platform: &str) -> Result<String> {
+    Platform::from_str(platform)
+        .map(|platform| platform.to_string())
+        .with_context(|| format!("Invalid target platform `{}`", platform))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dependency(
+        kind: DependencyKind,
+        name: &str,
+        target: Option<&str>,
+    ) -> cargo_metadata::Dependency {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "source": null,
+            "req": "*",
+            "kind": match kind {
+                DependencyKind::Normal => None,
+                DependencyKind::Development => Some("dev"),
+                DependencyKind::Build => Some("build"),
+                _ => unreachable!(),
+            },
+            "optional": false,
+            "uses_default_features": true,
+            "features": [],
+            "target": target,
+            "rename": null,
+            "registry": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn parse_artifact_kinds() {
+        assert_eq!(ArtifactKind::from_str("bin").unwrap(), ArtifactKind::Bins);
+        assert_eq!(
+            ArtifactKind::from_str("bin:firmware").unwrap(),
+            ArtifactKind::Bin("firmware".to_owned())
+        );
+        assert_eq!(
+            ArtifactKind::from_str("cdylib").unwrap(),
+            ArtifactKind::Cdylib
+        );
+        assert_eq!(
+            ArtifactKind::from_str("staticlib").unwrap(),
+            ArtifactKind::Staticlib
+        );
+        assert!(ArtifactKind::from_str("bin:").is_err());
+        assert!(ArtifactKind::from_str("dylib").is_err());
+    }
+
+    #[test]
+    fn parse_manifest_artifacts() {
+        let manifest = Manifest::from_str(
+            r#"
+            [package]
+            name = "artifacts"
+            version = "0.1.0"
+
+            [dependencies]
+            firmware = { path = "firmware", artifact = "bin:firmware", target = "thumbv7em-none-eabihf" }
+            plugin = { path = "plugin", artifact = ["cdylib", "staticlib"], lib = true }
+            plain = { path = "plain" }
+
+            [build-dependencies]
+            tool = { path = "tool", artifact = "bin" }
+            embedded = { path = "embedded", artifact = "bin", target = "target" }
+
+            [target.'cfg(unix)'.dependencies]
+            helper = { path = "helper", artifact = "bin" }
+            "#,
+        )
+        .unwrap();
+
+        let artifacts = ManifestArtifacts::new(&manifest, None).unwrap();
+
+        assert_eq!(
+            artifacts.get(&dependency(DependencyKind::Normal, "firmware", None)),
+            Some(&ArtifactDependency {
+                kinds: BTreeSet::from([ArtifactKind::Bin("firmware".to_owned())]),
+                lib: false,
+                target: Some(ArtifactPlatform::Triple("thumbv7em-none-eabihf".to_owned())),
+            })
+        );
+        assert_eq!(
+            artifacts.get(&dependency(DependencyKind::Normal, "plugin", None)),
+            Some(&ArtifactDependency {
+                kinds: BTreeSet::from([ArtifactKind::Cdylib, ArtifactKind::Staticlib]),
+                lib: true,
+                target: None,
+            })
+        );
+        assert_eq!(
+            artifacts.get(&dependency(DependencyKind::Build, "tool", None)),
+            Some(&ArtifactDependency {
+                kinds: BTreeSet::from([ArtifactKind::Bins]),
+                lib: false,
+                target: None,
+            })
+        );
+        assert_eq!(
+            artifacts
+                .get(&dependency(DependencyKind::Build, "embedded", None))
+                .and_then(|artifact| artifact.target.clone()),
+            Some(ArtifactPlatform::Target)
+        );
+        assert!(artifacts
+            .get(&dependency(
+                DependencyKind::Normal,
+                "helper",
+                Some("cfg(unix)")
+            ))
+            .is_some());
+        assert!(artifacts
+            .get(&dependency(DependencyKind::Normal, "helper", None))
+            .is_none());
+        assert!(artifacts
+            .get(&dependency(DependencyKind::Normal, "plain", None))
+            .is_none());
+        assert!(artifacts
+            .get(&dependency(DependencyKind::Normal, "tool", None))
+            .is_none());
+    }
+
+    #[test]
+    fn parse_inherited_manifest_artifacts() {
+        let workspace = Manifest::from_str(
+            r#"
+            [workspace]
+            members = ["member"]
+
+            [workspace.dependencies]
+            tool = { path = "tool", artifact = "bin" }
+            "#,
+        )
+        .unwrap();
+        let manifest = Manifest::from_str(
+            r#"
+            [package]
+            name = "member"
+            version = "0.1.0"
+
+            [build-dependencies]
+            tool = { workspace = true }
+            "#,
+        )
+        .unwrap();
+
+        let artifacts = ManifestArtifacts::new(&manifest, Some(&workspace)).unwrap();
+
+        assert_eq!(
+            artifacts.get(&dependency(DependencyKind::Build, "tool", None)),
+            Some(&ArtifactDependency {
+                kinds: BTreeSet::from([ArtifactKind::Bins]),
+                lib: false,
+                target: None,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_manifest_artifacts() {
+        let manifest = Manifest::from_str(
+            r#"
+            [package]
+            name = "artifacts"
+            version = "0.1.0"
+
+            [dependencies]
+            tool = { path = "tool", artifact = "rlib" }
+            "#,
+        )
+        .unwrap();
+
+        assert!(ManifestArtifacts::new(&manifest, None).is_err());
+    }
+}
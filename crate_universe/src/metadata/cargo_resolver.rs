@@ -4,14 +4,16 @@ use std::borrow::Borrow;
 use std::cell::RefCell;
 use std::collections::{BTreeMap, BTreeSet};
 
+use anyhow::{anyhow, Result};
 use cargo_metadata::{Package, TargetKind};
 use cargo_platform::Platform;
-use cfg_expr::expr::TargetMatcher;
-use cfg_expr::Predicate;
 use itertools::Itertools;
 
-use crate::config::CrateId;
-use crate::utils::target_triple::TargetTriple;
+use crate::config::{CrateId, PlatformCfgs};
+use crate::metadata::{
+    read_workspace_manifest, ArtifactDependency, ArtifactKind, ManifestArtifacts,
+};
+use crate::utils::target_triple::{TargetSpec, TargetTriple};
 
 /// A list platform triples that support host tools
 ///
@@ -61,6 +63,7 @@ struct ResolveDep<'a> {
     use_default_featues: bool,
     target: Option<&'a Platform>,
     kind: DependencyKind,
+    artifact: Option<ArtifactDependency>,
 }
 
 struct PackageWithDeps<'a> {
@@ -97,6 +100,10 @@ struct DepBorrow<'a> {
     platforms: BTreeSet<&'a Platform>,
     features: BTreeSet<&'a str>,
     aliases_optional: BTreeSet<(Option<&'a str>, bool)>,
+    /// Whether or not the library of the dependency is used. This is only
+    /// false for dependencies which are exclusively artifact dependencies.
+    lib: bool,
+    artifacts: BTreeSet<&'a ArtifactDependency>,
 }
 
 #[derive(Debug, Default)]
@@ -115,6 +122,8 @@ struct ResolvedPackageBorrow<'a> {
 pub struct CargoResolver<'a> {
     workspace_members: BTreeSet<&'a cargo_metadata::PackageId>,
     dependency_resolve: BTreeMap<&'a cargo_metadata::PackageId, PackageWithDeps<'a>>,
+    platform_cfgs: BTreeMap<TargetTriple, PlatformCfgs>,
+    custom_targets: BTreeMap<TargetTriple, TargetSpec>,
 }
 
 #[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Debug)]
@@ -148,6 +157,11 @@ pub(crate) struct Dependency {
 
     /// A set of platfoms that this dependency is for.
     pub(crate) platforms: BTreeSet<Platform>,
+
+    /// The artifacts of the dependency used by the current package if this is an
+    /// [artifact dependency](https://doc.rust-lang.org/nightly/cargo/reference/unstable.html#artifact-dependencies).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) artifact: Option<ArtifactDependency>,
 }
 
 #[derive(Debug, Default, PartialEq, Eq, Clone, serde::Deserialize, serde::Serialize)]
@@ -168,6 +182,13 @@ pub struct CrateAnnotation {
     pub build_proc_macro_deps: BTreeSet<Dependency>,
     #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
     pub build_link_deps: BTreeSet<Dependency>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub artifact_deps: BTreeSet<Dependency>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub build_artifact_deps: BTreeSet<Dependency>,
+    /// Artifacts of this crate which are used by artifact dependencies of its dependents.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub artifacts: BTreeSet<ArtifactKind>,
 }
 
 impl<'a> CargoResolver<'a> {
@@ -185,6 +206,10 @@ impl<'a> CargoResolver<'a> {
         let workspace_members_pkg_id: BTreeSet<_> = metadata.workspace_members.iter().collect();
         let mut workspace_members = BTreeSet::new();
 
+        // Artifact dependencies are not reported by `cargo metadata` and are instead
+        // read from the manifests which declare them.
+        let workspace_manifest = read_workspace_manifest(&metadata.workspace_root);
+
         // Now we resolve!
         let dependency_resolve: BTreeMap<_, _> = metadata
             .packages
@@ -195,6 +220,8 @@ impl<'a> CargoResolver<'a> {
                     workspace_members.insert(&package.id);
                 }
 
+                let artifacts = ManifestArtifacts::load(package, workspace_manifest.as_ref());
+
                 (
                     &package.id,
                     PackageWithDeps {
@@ -238,6 +265,7 @@ impl<'a> CargoResolver<'a> {
                                     features: &dep.features,
                                     optional: dep.optional,
                                     use_default_featues: dep.uses_default_features,
+                                    artifact: artifacts.get(dep).cloned(),
                                 })
                             })
                             .collect(),
@@ -298,13 +326,33 @@ impl<'a> CargoResolver<'a> {
         Self {
             dependency_resolve,
             workspace_members,
+            platform_cfgs: BTreeMap::new(),
+            custom_targets: BTreeMap::new(),
         }
     }
 
+    /// Set additional `cfg` options which are enabled for each platform.
+    pub fn with_platform_cfgs(
+        mut self,
+        platform_cfgs: BTreeMap<TargetTriple, PlatformCfgs>,
+    ) -> Self {
+        self.platform_cfgs = platform_cfgs;
+        self
+    }
+
+    /// Set the custom targets which may be used in place of `rustc`'s builtin targets.
+    pub fn with_custom_targets(
+        mut self,
+        custom_targets: BTreeMap<TargetTriple, TargetSpec>,
+    ) -> Self {
+        self.custom_targets = custom_targets;
+        self
+    }
+
     pub fn execute(
         &self,
         target_triples: impl IntoIterator<Item = impl Borrow<TargetTriple>>,
-    ) -> BTreeMap<CrateId, BTreeMap<TargetTriple, CrateAnnotation>> {
+    ) -> Result<BTreeMap<CrateId, BTreeMap<TargetTriple, CrateAnnotation>>> {
         let mut data = BTreeMap::default();
 
         let target_triples: Vec<_> = target_triples
@@ -324,11 +372,11 @@ impl<'a> CargoResolver<'a> {
         // We only want to spawn processes for unique cargo platforms
         for host in &host_triples {
             for target in &target_triples {
-                self.resolve(host, target, &mut data);
+                self.resolve(host, target, &mut data)?;
             }
         }
 
-        data
+        Ok(data)
     }
 
     fn resolve(
@@ -336,10 +384,19 @@ impl<'a> CargoResolver<'a> {
         host: &TargetTriple,
         target: &TargetTriple,
         data: &mut BTreeMap<CrateId, BTreeMap<TargetTriple, CrateAnnotation>>,
-    ) {
-        let host_flags = cfg_expr::targets::get_builtin_target_by_triple(&host.to_cargo()).unwrap();
-        let target_flags =
-            cfg_expr::targets::get_builtin_target_by_triple(&target.to_cargo()).unwrap();
+    ) -> Result<()> {
+        let target_info = |triple: &TargetTriple| {
+            triple.target_info(&self.custom_targets).ok_or_else(|| {
+                anyhow!(
+                    "Invalid platform triple in supported platforms: {}. Targets unknown to rustc must be defined in `custom_targets`.",
+                    triple
+                )
+            })
+        };
+        let host_flags = target_info(host)?;
+        let target_flags = target_info(target)?;
+        let host_cfgs = self.platform_cfgs.get(host);
+        let target_cfgs = self.platform_cfgs.get(target);
 
         let mut resolved = ResolvedPackageMap::new();
 
@@ -416,7 +473,12 @@ impl<'a> CargoResolver<'a> {
                 .collect::<BTreeSet<_>>();
 
             let activated_deps = deps.iter().filter_map(|dep| {
-                let dep_location = if location == target
+                let dep_location = if dep.artifact.as_ref().is_some_and(|a| a.target.is_some()) {
+                    // Artifacts with an explicit `target` are built for the target platform,
+                    // even when they are build dependencies. Artifacts for a specific triple
+                    // are approximated by the target platform as well.
+                    target
+                } else if location == target
                     && (matches!(dep.kind, DependencyKind::Build)
                         || self.dependency_resolve[&dep.id].is_proc_macro)
                 {
@@ -426,19 +488,16 @@ impl<'a> CargoResolver<'a> {
                 };
 
                 if let Some(cfg_expr) = dep.target {
-                    let location_flags = if dep_location == host {
-                        host_flags
+                    let (location_flags, location_cfgs) = if dep_location == host {
+                        (host_flags.as_ref(), host_cfgs)
                     } else {
-                        target_flags
+                        (target_flags.as_ref(), target_cfgs)
                     };
 
                     if !match cfg_expr {
                         Platform::Cfg(cfg) => cfg_expr::Expression::parse(&cfg.to_string())
                             .unwrap()
-                            .eval(|pred| match pred {
-                                Predicate::Target(tp) => location_flags.matches(tp),
-                                _ => false,
-                            }),
+                            .eval(|pred| PlatformCfgs::eval(location_cfgs, location_flags, pred)),
                         Platform::Name(name) => {
                             location_flags.triple.as_str().eq_ignore_ascii_case(name)
                         }
@@ -482,6 +541,14 @@ impl<'a> CargoResolver<'a> {
 
                 resolved_dep.platforms.extend(dep.target);
 
+                match &dep.artifact {
+                    Some(artifact) => {
+                        resolved_dep.lib |= artifact.lib;
+                        resolved_dep.artifacts.insert(artifact);
+                    }
+                    None => resolved_dep.lib = true,
+                }
+
                 resolved_dep
                     .aliases_optional
                     .insert((dep.is_alias.then_some(dep.name), dep.optional));
@@ -519,6 +586,8 @@ impl<'a> CargoResolver<'a> {
             }
         }
 
+        let mut requested_artifacts = Vec::new();
+
         for ((id, location), package) in &resolved {
             let package = package.borrow();
             let pkg = self.dependency_resolve[id].package;
@@ -553,8 +622,13 @@ impl<'a> CargoResolver<'a> {
                 });
             }
 
-            for ((dep_id, _, kind), dep) in &package.deps {
+            for ((dep_id, dep_location, kind), dep) in &package.deps {
                 let dep_pkg = &self.dependency_resolve[dep_id];
+
+                for artifact in &dep.artifacts {
+                    requested_artifacts.push((dep_pkg.package, *dep_location, &artifact.kinds));
+                }
+
                 for (alias, optional) in &dep.aliases_optional {
                     let dependency = Dependency {
                         features: dep.features.iter().map(|f| f.to_string()).collect(),
@@ -563,8 +637,28 @@ impl<'a> CargoResolver<'a> {
                         target_name: dep_pkg.library_target_name.map(|t| t.to_string()),
                         optional: *optional,
                         platforms: dep.platforms.iter().copied().cloned().collect(),
+                        artifact: None,
                     };
 
+                    for artifact in &dep.artifacts {
+                        let artifact_dependency = Dependency {
+                            artifact: Some((*artifact).clone()),
+                            ..dependency.clone()
+                        };
+                        match kind {
+                            DependencyKind::Normal => &mut annotation.artifact_deps,
+                            DependencyKind::Build => &mut annotation.build_artifact_deps,
+                            // Dev dependencies are only tracked for workspace members
+                            // which have no generated targets.
+                            DependencyKind::Development => continue,
+                        }
+                        .insert(artifact_dependency);
+                    }
+
+                    if !dep.lib {
+                        continue;
+                    }
+
                     if *kind == DependencyKind::Normal
                         && !dep_pkg.is_proc_macro
                         && dep_pkg.package.links.is_some()
@@ -584,5 +678,16 @@ impl<'a> CargoResolver<'a> {
                 }
             }
         }
+
+        for (package, location, kinds) in requested_artifacts {
+            data.entry(CrateId::from(package))
+                .or_default()
+                .entry(location.clone())
+                .or_default()
+                .artifacts
+                .extend(kinds.iter().cloned());
+        }
+
+        Ok(())
     }
 }
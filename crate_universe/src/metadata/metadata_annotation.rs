@@ -9,7 +9,7 @@ use cargo_metadata::{Node, Package, PackageId};
 use hex::ToHex;
 use serde::{Deserialize, Serialize};
 
-use crate::config::{Commitish, Config, CrateAnnotations, CrateId};
+use crate::config::{Commitish, Config, CrateAnnotations, CrateId, CrateNameAndVersionReq};
 use crate::metadata::CargoResolver;
 use crate::splicing::{SourceInfo, WorkspaceMetadata};
 
@@ -36,14 +36,17 @@ pub(crate) struct MetadataAnnotation {
 }
 
 impl MetadataAnnotation {
-    pub(crate) fn new(metadata: CargoMetadata, config: &Config) -> MetadataAnnotation {
-        // UNWRAP: The workspace metadata should be written by a controlled process. This should not return a result
-        let workspace_metadata =
-            find_workspace_metadata(&metadata).unwrap_or_else(|| WorkspaceMetadata {
+    pub(crate) fn new(metadata: CargoMetadata, config: &Config) -> Result<MetadataAnnotation> {
+        let workspace_metadata = match find_workspace_metadata(&metadata) {
+            Some(workspace_metadata) => workspace_metadata,
+            None => WorkspaceMetadata {
                 resolver_metadata: CargoResolver::new(&metadata)
-                    .execute(&config.supported_platform_triples),
+                    .with_platform_cfgs(config.platform_cfgs.clone())
+                    .with_custom_targets(config.custom_targets.clone())
+                    .execute(&config.supported_platform_triples)?,
                 ..Default::default()
-            });
+            },
+        };
 
         let resolve = metadata
             .resolve
@@ -68,7 +71,7 @@ impl MetadataAnnotation {
             .map(|pkg| (pkg.id.clone(), pkg))
             .collect();
 
-        MetadataAnnotation {
+        Ok(MetadataAnnotation {
             package_map: packages
                 .values()
                 .map(|pkg| (pkg.into(), pkg.id.clone()))
@@ -77,7 +80,7 @@ impl MetadataAnnotation {
             workspace_members,
             workspace_root: PathBuf::from(metadata.workspace_root.as_std_path()),
             workspace_metadata,
-        }
+        })
     }
 }
 
@@ -428,10 +431,14 @@ impl Annotations {
             nonhermetic_root_bazel_workspace_dir,
         )?;
 
+        // Collect annotations defined in the root Cargo.toml
+        let workspace_annotations = find_workspace_annotations(&cargo_metadata)?;
+
         // Annotate the cargo metadata
-        let metadata_annotation = MetadataAnnotation::new(cargo_metadata, &config);
+        let metadata_annotation = MetadataAnnotation::new(cargo_metadata, &config)?;
 
         let mut unused_extra_annotations = config.annotations.clone();
+        let mut unused_workspace_annotations = workspace_annotations.clone();
 
         // Ensure each override matches a particular package
         let pairred_extras = metadata_annotation
@@ -449,21 +456,36 @@ impl Annotations {
                         // Filter out the annotation
                         extra
                     })
+                    // Annotations from the Bazel workspace come first so their
+                    // values take precedence over those from the Cargo workspace.
+                    .chain(
+                        workspace_annotations
+                            .iter()
+                            .filter(|(id, _)| id.matches(pkg))
+                            .map(|(id, extra)| {
+                                unused_workspace_annotations.remove(id);
+                                extra
+                            }),
+                    )
                     .cloned()
                     .sum();
 
-                crate_extra.apply_defaults_from_package_metadata(&pkg.metadata);
+                if let Err(err) = crate_extra
+                    .apply_defaults_from_package_metadata(pkg, config.strict_package_metadata)
+                {
+                    return Some(Err(err));
+                }
 
                 if crate_extra == CrateAnnotations::default() {
                     None
                 } else {
-                    Some((
+                    Some(Ok((
                         CrateId::new(pkg.name.clone(), pkg.version.clone()),
                         PairedExtras { crate_extra },
-                    ))
+                    )))
                 }
             })
-            .collect();
+            .collect::<Result<_>>()?;
 
         // Alert on any unused annotations
         if !unused_extra_annotations.is_empty() {
@@ -472,6 +494,12 @@ impl Annotations {
                 unused_extra_annotations.keys()
             );
         }
+        if !unused_workspace_annotations.is_empty() {
+            bail!(
+                "Unused annotations were provided in `[workspace.metadata.bazel.annotations]`. Please remove them: {:?}",
+                unused_workspace_annotations.keys()
+            );
+        }
 
         // Annotate metadata
         Ok(Annotations {
@@ -491,6 +519,28 @@ fn find_workspace_metadata(cargo_metadata: &CargoMetadata) -> Option<WorkspaceMe
     Some(serde_json::from_value(value.to_owned()).unwrap())
 }
 
+/// Parse annotations from the `[workspace.metadata.bazel.annotations]` table of
+/// the root Cargo.toml. Each key is a crate name and version requirement.
+///
+/// ```toml
+/// [workspace.metadata.bazel.annotations."openssl-sys 0.9"]
+/// gen_build_script = false
+/// rustc_flags = ["--cfg=ossl111"]
+/// ```
+pub(crate) fn find_workspace_annotations(
+    cargo_metadata: &CargoMetadata,
+) -> Result<BTreeMap<CrateNameAndVersionReq, CrateAnnotations>> {
+    match cargo_metadata
+        .workspace_metadata
+        .get("bazel")
+        .and_then(|bazel| bazel.get("annotations"))
+    {
+        Some(value) => serde_json::from_value(value.to_owned())
+            .context("Failed to parse `[workspace.metadata.bazel.annotations]`"),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
 /// Determines whether or not a package is a workspace member. This follows
 /// the Cargo definition of a workspace member with one exception where
 /// "extra workspace members" are *not* treated as workspace members
@@ -523,7 +573,6 @@ fn cargo_meta_pkg_to_locked_pkg<'a>(
 mod test {
     use super::*;
 
-    use crate::config::CrateNameAndVersionReq;
     use crate::select::Select;
     use crate::test::*;
     use crate::utils::target_triple::TargetTriple;
@@ -546,7 +595,8 @@ mod test {
                 )]),
                 ..Default::default()
             },
-        );
+        )
+        .unwrap();
         let log_crates: BTreeMap<_, _> = annotations
             .workspace_metadata
             .resolver_metadata
@@ -570,7 +620,28 @@ mod test {
 
     #[test]
     fn annotate_metadata_with_build_scripts() {
-        MetadataAnnotation::new(test::metadata::build_scripts(), &Config::default());
+        MetadataAnnotation::new(test::metadata::build_scripts(), &Config::default()).unwrap();
+    }
+
+    #[test]
+    fn annotate_metadata_with_unknown_triple() {
+        let err = MetadataAnnotation::new(
+            test::metadata::alias(),
+            &Config {
+                supported_platform_triples: BTreeSet::from_iter([
+                    TargetTriple::from_bazel("x86_64-unknown-linux-gnu".into()),
+                    TargetTriple::from_bazel("x86_64-unknown-made-up".into()),
+                ]),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+
+        assert!(
+            err.to_string()
+                .contains("Targets unknown to rustc must be defined in `custom_targets`."),
+            "{err}"
+        );
     }
 
     #[test]
@@ -739,4 +810,138 @@ mod test {
         };
         assert_eq!(*extras, expected);
     }
+
+    #[test]
+    fn strict_package_metadata() {
+        let mut metadata = test::metadata::has_package_metadata();
+        for package in &mut metadata.packages {
+            if package.name == "has_package_metadata" {
+                package.metadata["bazel"]["rustc_env"]["BROKEN"] = serde_json::json!(1);
+            }
+        }
+
+        let annotations = |strict_package_metadata| {
+            Annotations::new(
+                metadata.clone(),
+                &None,
+                test::lockfile::has_package_metadata(),
+                Config {
+                    strict_package_metadata,
+                    ..Config::default()
+                },
+                Utf8Path::new("/tmp/bazelworkspace"),
+            )
+        };
+
+        // The malformed table is ignored by default.
+        let crate_id = CrateId::new(
+            "has_package_metadata".to_owned(),
+            semver::Version::new(0, 0, 0),
+        );
+        assert!(!annotations(false)
+            .unwrap()
+            .pairred_extras
+            .contains_key(&crate_id));
+
+        let err = format!("{:?}", annotations(true).unwrap_err());
+        assert!(
+            err.contains(
+                "Malformed `[package.metadata.bazel]` table in has_package_metadata 0.0.0"
+            ),
+            "{err}"
+        );
+        assert!(
+            err.contains("`package.metadata.bazel.rustc_env.BROKEN`"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn annotations_from_workspace_metadata() {
+        let crate_id = CrateId::new(
+            "has_package_metadata".to_owned(),
+            semver::Version::new(0, 0, 0),
+        );
+
+        let mut config = Config::default();
+        config.annotations.insert(
+            CrateNameAndVersionReq::new("has_package_metadata".to_owned(), "*".parse().unwrap()),
+            CrateAnnotations {
+                gen_build_script: Some(true),
+                rustc_env: Some(Select::from_value(BTreeMap::from([(
+                    "BAR".to_owned(),
+                    "bar is set".to_owned(),
+                )]))),
+                ..CrateAnnotations::default()
+            },
+        );
+
+        // Equivalent to `[workspace.metadata.bazel.annotations."has_package_metadata 0.0.0"]`
+        let mut metadata = test::metadata::has_package_metadata();
+        metadata.workspace_metadata = serde_json::json!({
+            "bazel": {
+                "annotations": {
+                    "has_package_metadata 0.0.0": {
+                        "gen_build_script": false,
+                        "rustc_env": {
+                            "BAZ": "baz is set",
+                        },
+                        "rustc_flags": ["--cfg=baz"],
+                    },
+                },
+            },
+        });
+
+        let combined_annotations = Annotations::new(
+            metadata,
+            &None,
+            test::lockfile::has_package_metadata(),
+            config,
+            Utf8Path::new("/tmp/bazelworkspace"),
+        )
+        .unwrap();
+
+        let extras = &combined_annotations.pairred_extras[&crate_id].crate_extra;
+        let expected = CrateAnnotations {
+            // Values from the Bazel workspace take precedence.
+            gen_build_script: Some(true),
+            // Collections from both workspaces are merged.
+            rustc_env: Some(Select::from_value(BTreeMap::from([
+                ("BAR".to_owned(), "bar is set".to_owned()),
+                ("BAZ".to_owned(), "baz is set".to_owned()),
+            ]))),
+            rustc_flags: Some(Select::from_value(vec!["--cfg=baz".to_owned()])),
+            // This comes from has_package_metadata's [package.metadata.bazel].
+            additive_build_file_content: Some("genrule(**kwargs)\n".to_owned()),
+            ..CrateAnnotations::default()
+        };
+        assert_eq!(*extras, expected);
+    }
+
+    #[test]
+    fn detect_unused_workspace_metadata_annotation() {
+        let mut metadata = test::metadata::no_deps();
+        metadata.workspace_metadata = serde_json::json!({
+            "bazel": {
+                "annotations": {
+                    "mock-crate 0.1.0": {
+                        "gen_build_script": false,
+                    },
+                },
+            },
+        });
+
+        let result = Annotations::new(
+            metadata,
+            &None,
+            test::lockfile::no_deps(),
+            Config::default(),
+            Utf8Path::new("/tmp/bazelworkspace"),
+        );
+        assert!(result.is_err());
+
+        let result_str = format!("{result:?}");
+        assert!(result_str.contains("`[workspace.metadata.bazel.annotations]`"));
+        assert!(result_str.contains("mock-crate"));
+    }
 }
@@ -1,10 +1,11 @@
 //! Tools for rendering and writing BUILD and other Starlark files
 
+mod members;
 mod template_engine;
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 
@@ -12,16 +13,20 @@ use anyhow::{bail, Context as AnyhowContext, Result};
 use itertools::Itertools;
 
 use crate::config::{AliasRule, RenderConfig, VendorMode};
-use crate::context::crate_context::{CrateContext, CrateDependency, Rule};
+use crate::context::crate_context::{
+    ArtifactCrateDependency, CrateContext, CrateDependency, Rule, TestAttributes,
+};
 use crate::context::{Context, TargetAttributes};
-use crate::metadata::SourceAnnotation;
+use crate::metadata::{ArtifactPlatform, SourceAnnotation};
+use crate::rendering::members::MembersRenderer;
 use crate::rendering::template_engine::TemplateEngine;
 use crate::select::Select;
 use crate::splicing::default_splicing_package_crate_id;
 use crate::utils::starlark::{
-    self, Alias, CargoBuildScript, CargoTomlEnvVars, CommonAttrs, Data, ExportsFiles, Filegroup,
-    Glob, Label, Load, Package, RustBinary, RustLibrary, RustProcMacro, SelectDict, SelectList,
-    SelectScalar, SelectSet, Starlark, TargetCompatibleWith,
+    self, Alias, CargoArtifact, CargoBuildScript, CargoTomlEnvVars, CommonAttrs, Data,
+    ExportsFiles, Filegroup, Glob, Label, Load, Package, RustBinary, RustLibrary,
+    RustNativeLibrary, RustProcMacro, RustTest, SelectDict, SelectList, SelectScalar, SelectSet,
+    Starlark, TargetCompatibleWith,
 };
 use crate::utils::target_triple::TargetTriple;
 use crate::utils::{self, sanitize_repository_name};
@@ -101,10 +106,7 @@ impl Renderer {
                         target_triples
                             .iter()
                             .map(|target_triple| {
-                                render_platform_constraint_label(
-                                    &self.config.platforms_template,
-                                    target_triple,
-                                )
+                                render_platform_constraint_label(&self.config, target_triple)
                             })
                             .collect(),
                     )
@@ -116,16 +118,24 @@ impl Renderer {
                 .map(|target_triple| {
                     (
                         target_triple.clone(),
-                        render_platform_constraint_label(
-                            &self.config.platforms_template,
-                            target_triple,
-                        ),
+                        render_platform_constraint_label(&self.config, target_triple),
                     )
                 })
                 .collect(),
         }
     }
 
+    /// Render BUILD files for the members of a Cargo workspace located in `workspace_dir`,
+    /// updating any which already exist.
+    pub(crate) fn render_members(
+        &self,
+        context: &Context,
+        workspace_dir: &Path,
+    ) -> Result<BTreeMap<PathBuf, String>> {
+        let platforms = self.render_platform_labels(Arc::new(context.conditions.clone()));
+        MembersRenderer::new(&self.config, context, &platforms).render(workspace_dir)
+    }
+
     fn render_crates_module(
         &self,
         engine: &TemplateEngine,
@@ -349,6 +359,53 @@ impl Renderer {
             starlark.extend(binaries.into_iter().map(Starlark::Alias));
         }
 
+        // An `alias` for each shared and static library of remote crates.
+        let native_library_crates: Vec<&CrateContext> = context
+            .crates
+            .values()
+            .filter(|krate| krate.repository.is_some())
+            .filter(|krate| {
+                krate
+                    .targets
+                    .iter()
+                    .any(|rule| matches!(rule, Rule::SharedLibrary(..) | Rule::StaticLibrary(..)))
+            })
+            .collect();
+        let mut native_libraries = Vec::new();
+        for krate in &native_library_crates {
+            let is_duplicate = 1 < native_library_crates
+                .iter()
+                .filter(|check| check.name == krate.name)
+                .count();
+            for rule in &krate.targets {
+                let suffix = match rule {
+                    Rule::SharedLibrary(..) => "cdylib",
+                    Rule::StaticLibrary(..) => "staticlib",
+                    _ => continue,
+                };
+                native_libraries.push(Alias {
+                    rule: AliasRule::default().rule(),
+                    // If duplicates exist, include version to disambiguate them.
+                    name: if is_duplicate {
+                        format!("{}-{}__{}", krate.name, krate.version, suffix)
+                    } else {
+                        format!("{}__{}", krate.name, suffix)
+                    },
+                    actual: self.crate_label(
+                        &krate.name,
+                        &krate.version.to_string(),
+                        &format!("{}__{}", rule.crate_name(), suffix),
+                    ),
+                    tags: BTreeSet::from(["manual".to_owned()]),
+                });
+            }
+        }
+        if !native_libraries.is_empty() {
+            let comment = "# Shared and static libraries".to_owned();
+            starlark.push(Starlark::Verbatim(comment));
+            starlark.extend(native_libraries.into_iter().map(Starlark::Alias));
+        }
+
         let starlark = starlark::serialize(&starlark)?;
         Ok(starlark)
     }
@@ -407,6 +464,8 @@ impl Renderer {
             }
         }
 
+        let cargo_artifacts = self.make_artifact_deps(&mut krate);
+
         let mut starlark = Vec::new();
 
         // Banner comment for top of the file.
@@ -488,11 +547,25 @@ impl Renderer {
             }));
         }
 
+        if !cargo_artifacts.is_empty() {
+            load("@rules_rust//cargo:defs.bzl", "cargo_artifact");
+            starlark.extend(cargo_artifacts.into_iter().map(Starlark::CargoArtifact));
+        }
+
         for rule in &krate.targets {
-            if let Some(override_target) = krate.override_targets.get(rule.override_target_key()) {
+            let override_target = rule
+                .override_target_key()
+                .and_then(|key| krate.override_targets.get(key));
+            if let Some(override_target) = override_target {
                 starlark.push(Starlark::Alias(Alias {
                     rule: AliasRule::default().rule(),
-                    name: rule.crate_name().to_owned(),
+                    name: match rule {
+                        Rule::SharedLibrary(target) => format!("{}__cdylib", target.crate_name),
+                        Rule::StaticLibrary(target) => {
+                            format!("{}__staticlib", target.crate_name)
+                        }
+                        _ => rule.crate_name().to_owned(),
+                    },
                     actual: override_target.clone(),
                     tags: BTreeSet::from(["manual".to_owned()]),
                 }));
@@ -526,6 +599,33 @@ impl Renderer {
                         let rust_binary = self.make_rust_binary(platforms, &krate, target)?;
                         starlark.push(Starlark::RustBinary(rust_binary));
                     }
+                    Rule::SharedLibrary(target) => {
+                        load("@rules_rust//rust:defs.bzl", "rust_shared_library");
+                        let rust_shared_library =
+                            self.make_rust_native_library(platforms, &krate, target, "cdylib")?;
+                        starlark.push(Starlark::RustSharedLibrary(rust_shared_library));
+                    }
+                    Rule::StaticLibrary(target) => {
+                        load("@rules_rust//rust:defs.bzl", "rust_static_library");
+                        let rust_static_library =
+                            self.make_rust_native_library(platforms, &krate, target, "staticlib")?;
+                        starlark.push(Starlark::RustStaticLibrary(rust_static_library));
+                    }
+                    Rule::Test(test) => {
+                        load("@rules_rust//rust:defs.bzl", "rust_test");
+                        let rust_test = self.make_rust_test(platforms, &krate, test, "test")?;
+                        starlark.push(Starlark::RustTest(rust_test));
+                    }
+                    Rule::Example(target) => {
+                        load("@rules_rust//rust:defs.bzl", "rust_binary");
+                        let rust_binary = self.make_rust_example(platforms, &krate, target)?;
+                        starlark.push(Starlark::RustBinary(rust_binary));
+                    }
+                    Rule::Bench(test) => {
+                        load("@rules_rust//rust:defs.bzl", "rust_test");
+                        let rust_test = self.make_rust_test(platforms, &krate, test, "bench")?;
+                        starlark.push(Starlark::RustTest(rust_test));
+                    }
                 }
             }
         }
@@ -546,6 +646,70 @@ impl Renderer {
         Ok(starlark)
     }
 
+    /// Provide the artifacts of a crate's artifact dependencies as data and set the environment
+    /// variables Cargo would set to their paths. Artifacts which are built for a specific platform
+    /// are transitioned by the returned `cargo_artifact` targets.
+    fn make_artifact_deps(&self, krate: &mut CrateContext) -> Vec<CargoArtifact> {
+        let mut cargo_artifacts = BTreeMap::new();
+        let mut artifact_label = |dep: &ArtifactCrateDependency| {
+            let label = match &dep.local_path {
+                Some(path) => Label::from_str(&format!("//{}:{}", path, dep.target)).unwrap(),
+                None => self.crate_label(&dep.id.name, &dep.id.version.to_string(), &dep.target),
+            };
+
+            match &dep.platform {
+                Some(ArtifactPlatform::Triple(triple)) => {
+                    let name = format!(
+                        "{}-{}__{}__{}",
+                        dep.id.name, dep.id.version, dep.target, triple
+                    );
+                    cargo_artifacts
+                        .entry(name.clone())
+                        .or_insert_with(|| CargoArtifact {
+                            name: name.clone(),
+                            actual: label,
+                            platform: self.config.platforms_template.replace("{triple}", triple),
+                            tags: BTreeSet::from(["manual".to_owned()]),
+                        });
+                    Label::Relative { target: name }
+                }
+                _ => label,
+            }
+        };
+
+        let common_attrs = &mut krate.common_attrs;
+        for (configuration, dep) in common_attrs.artifact_deps.items() {
+            let label = artifact_label(&dep);
+            for env in &dep.env {
+                common_attrs.rustc_env.insert(
+                    (env.clone(), format!("$(execpath {label})")),
+                    configuration.clone(),
+                );
+            }
+            common_attrs.compile_data.insert(label, configuration);
+        }
+
+        if let Some(attrs) = &mut krate.build_script_attrs {
+            for (configuration, dep) in attrs.artifact_deps.items() {
+                let label = artifact_label(&dep);
+                for env in &dep.env {
+                    attrs.build_script_env.insert(
+                        (env.clone(), format!("$(execpath {label})")),
+                        configuration.clone(),
+                    );
+                }
+
+                // Artifacts of build dependencies are built for the host unless a `target` is set.
+                match dep.platform {
+                    Some(_) => attrs.data.insert(label, configuration),
+                    None => attrs.tools.insert(label, configuration),
+                }
+            }
+        }
+
+        cargo_artifacts.into_values().collect()
+    }
+
     fn make_cargo_build_script(
         &self,
         platforms: &Platforms,
@@ -807,6 +971,159 @@ impl Renderer {
         })
     }
 
+    /// Create a `rust_shared_library` (`cdylib`) or `rust_static_library` (`staticlib`)
+    /// of the crate's library.
+    fn make_rust_native_library(
+        &self,
+        platforms: &Platforms,
+        krate: &CrateContext,
+        target: &TargetAttributes,
+        kind: &str,
+    ) -> Result<RustNativeLibrary> {
+        Ok(RustNativeLibrary {
+            name: format!("{}__{}", target.crate_name, kind),
+            crate_name: target.crate_name.clone(),
+            deps: SelectSet::new(
+                self.make_deps(
+                    krate.common_attrs.deps.clone(),
+                    krate.common_attrs.extra_deps.clone(),
+                ),
+                &krate.common_attrs.build_targets,
+                platforms,
+            ),
+            proc_macro_deps: SelectSet::new(
+                self.make_deps(
+                    krate.common_attrs.proc_macro_deps.clone(),
+                    krate.common_attrs.extra_proc_macro_deps.clone(),
+                ),
+                &krate.common_attrs.build_targets,
+                platforms,
+            ),
+            aliases: SelectDict::new(
+                self.make_aliases(krate, false, false),
+                &krate.common_attrs.build_targets,
+                platforms,
+            ),
+            common: self.make_common_attrs(platforms, krate, target)?,
+        })
+    }
+
+    fn make_rust_test(
+        &self,
+        platforms: &Platforms,
+        krate: &CrateContext,
+        test: &TestAttributes,
+        kind: &str,
+    ) -> Result<RustTest> {
+        let (deps, proc_macro_deps) = self.make_dev_deps(krate);
+        Ok(RustTest {
+            name: format!("{}__{}", test.target.crate_name, kind),
+            deps: SelectSet::new(deps, &krate.common_attrs.build_targets, platforms),
+            proc_macro_deps: SelectSet::new(
+                proc_macro_deps,
+                &krate.common_attrs.build_targets,
+                platforms,
+            ),
+            aliases: SelectDict::new(
+                self.make_aliases(krate, false, true),
+                &krate.common_attrs.build_targets,
+                platforms,
+            ),
+            common: self.make_dev_common_attrs(platforms, krate, &test.target)?,
+            use_libtest_harness: (!test.harness).then_some(false),
+        })
+    }
+
+    fn make_rust_example(
+        &self,
+        platforms: &Platforms,
+        krate: &CrateContext,
+        target: &TargetAttributes,
+    ) -> Result<RustBinary> {
+        let (deps, proc_macro_deps) = self.make_dev_deps(krate);
+        Ok(RustBinary {
+            name: format!("{}__example", target.crate_name),
+            deps: SelectSet::new(deps, &krate.common_attrs.build_targets, platforms),
+            proc_macro_deps: SelectSet::new(
+                proc_macro_deps,
+                &krate.common_attrs.build_targets,
+                platforms,
+            ),
+            aliases: SelectDict::new(
+                self.make_aliases(krate, false, true),
+                &krate.common_attrs.build_targets,
+                platforms,
+            ),
+            common: self.make_dev_common_attrs(platforms, krate, target)?,
+        })
+    }
+
+    /// The `deps` and `proc_macro_deps` of tests, examples and benches which, unlike other
+    /// targets, include the crate's own library. Dev-dependencies are included as well, but
+    /// Cargo only resolves them for workspace members.
+    fn make_dev_deps(
+        &self,
+        krate: &CrateContext,
+    ) -> (Select<BTreeSet<Label>>, Select<BTreeSet<Label>>) {
+        let mut deps = Select::merge(
+            self.make_deps(
+                krate.common_attrs.deps.clone(),
+                krate.common_attrs.extra_deps.clone(),
+            ),
+            self.make_deps(krate.common_attrs.deps_dev.clone(), Select::default()),
+        );
+        let mut proc_macro_deps = Select::merge(
+            self.make_deps(
+                krate.common_attrs.proc_macro_deps.clone(),
+                krate.common_attrs.extra_proc_macro_deps.clone(),
+            ),
+            self.make_deps(
+                krate.common_attrs.proc_macro_deps_dev.clone(),
+                Select::default(),
+            ),
+        );
+
+        if let Some(library_target_name) = &krate.library_target_name {
+            let label = Label::from_str(&format!(":{library_target_name}")).unwrap();
+            if krate
+                .targets
+                .iter()
+                .any(|rule| matches!(rule, Rule::ProcMacro(..)))
+            {
+                proc_macro_deps.insert(label, None);
+            } else {
+                deps.insert(label, None);
+            }
+        }
+
+        (deps, proc_macro_deps)
+    }
+
+    /// Common attributes of tests, examples and benches which may read any file of the package
+    /// at runtime.
+    fn make_dev_common_attrs(
+        &self,
+        platforms: &Platforms,
+        krate: &CrateContext,
+        target: &TargetAttributes,
+    ) -> Result<CommonAttrs> {
+        let mut common = self.make_common_attrs(platforms, krate, target)?;
+        common.data = make_data(
+            &krate.common_attrs.build_targets,
+            platforms,
+            krate
+                .common_attrs
+                .data_glob
+                .iter()
+                .cloned()
+                .chain(["**".to_owned()])
+                .collect(),
+            Default::default(),
+            krate.common_attrs.data.clone(),
+        );
+        Ok(common)
+    }
+
     fn make_common_attrs(
         &self,
         platforms: &Platforms,
@@ -1044,8 +1361,13 @@ pub(crate) fn render_module_label(template: &str, name: &str) -> Result<Label> {
 }
 
 /// Render the Bazel label of a platform triple
-fn render_platform_constraint_label(template: &str, target_triple: &TargetTriple) -> String {
-    template.replace("{triple}", &target_triple.to_bazel())
+fn render_platform_constraint_label(config: &RenderConfig, target_triple: &TargetTriple) -> String {
+    match config.platform_labels.get(target_triple) {
+        Some(label) => label.clone(),
+        None => config
+            .platforms_template
+            .replace("{triple}", &target_triple.to_bazel()),
+    }
 }
 
 fn render_build_file_template(template: &str, name: &str, version: &str) -> Result<Label> {
@@ -1233,6 +1555,37 @@ mod test {
         assert!(build_file_content.contains("disable_pipelining = True"));
     }
 
+    #[test]
+    fn render_custom_platform_labels() {
+        let custom = TargetTriple::from_bazel("thumbv7em-custom-none".to_owned());
+        let linux = TargetTriple::from_bazel("x86_64-unknown-linux-gnu".to_owned());
+
+        let renderer = Renderer::new(
+            Arc::new(RenderConfig {
+                platform_labels: BTreeMap::from([(
+                    custom.clone(),
+                    "//platforms:thumbv7em-custom-none".to_owned(),
+                )]),
+                ..mock_render_config(None).as_ref().clone()
+            }),
+            Arc::new(BTreeSet::from([custom.clone(), linux.clone()])),
+        );
+        let platforms = renderer.render_platform_labels(Arc::new(BTreeMap::from([(
+            r#"cfg(target_arch = "arm")"#.to_owned(),
+            BTreeSet::from([custom.clone()]),
+        )])));
+
+        assert_eq!(
+            platforms.label_matcher[r#"cfg(target_arch = "arm")"#],
+            BTreeSet::from(["//platforms:thumbv7em-custom-none".to_owned()])
+        );
+        assert_eq!(
+            platforms.targets[&custom],
+            "//platforms:thumbv7em-custom-none"
+        );
+        assert!(!platforms.targets.contains_key(&linux));
+    }
+
     #[test]
     fn render_cargo_build_script() {
         let mut context = Context::default();
@@ -1380,6 +1733,106 @@ mod test {
         assert!(build_file_content.contains("name = \"_bs\""));
     }
 
+    #[test]
+    fn render_artifact_deps() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        let artifact_id = CrateId::new("firmware".to_owned(), VERSION_ZERO_ONE_ZERO);
+
+        let artifact_dep = |platform| ArtifactCrateDependency {
+            id: artifact_id.clone(),
+            target: "firmware__bin".to_owned(),
+            env: BTreeSet::from(["CARGO_BIN_FILE_FIRMWARE".to_owned()]),
+            platform,
+            local_path: None,
+        };
+
+        let mut common_attrs = CommonAttributes::default();
+        common_attrs.artifact_deps.insert(
+            artifact_dep(Some(ArtifactPlatform::Triple(
+                "thumbv7em-none-eabihf".to_owned(),
+            ))),
+            None,
+        );
+        let mut build_script_attrs = BuildScriptAttributes::default();
+        build_script_attrs
+            .artifact_deps
+            .insert(artifact_dep(None), None);
+
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                name: crate_id.name,
+                version: crate_id.version,
+                package_url: None,
+                repository: None,
+                targets: BTreeSet::from([
+                    Rule::Library(mock_target_attributes()),
+                    Rule::BuildScript(TargetAttributes {
+                        crate_name: "build_script_build".to_owned(),
+                        crate_root: Some("build.rs".to_owned()),
+                        ..TargetAttributes::default()
+                    }),
+                ]),
+                library_target_name: None,
+                common_attrs,
+                build_script_attrs: Some(build_script_attrs),
+                license: None,
+                license_ids: BTreeSet::default(),
+                license_file: None,
+                additive_build_file_content: None,
+                disable_pipelining: false,
+                extra_aliased_targets: BTreeMap::default(),
+                alias_rule: None,
+                override_targets: BTreeMap::default(),
+                crate_features: BTreeMap::default(),
+                feature_dep_maps: None,
+            },
+        );
+
+        let renderer = Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+        let output = renderer.render(&context, None).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        let transitioned = indoc! {r#"
+            cargo_artifact(
+                name = "firmware-0.1.0__firmware__bin__thumbv7em-none-eabihf",
+                actual = "@test_rendering__firmware-0.1.0//:firmware__bin",
+                platform = "@rules_rust//rust/platform:thumbv7em-none-eabihf",
+                tags = ["manual"],
+            )
+        "#};
+        assert!(
+            build_file_content.contains(transitioned),
+            "```\n{}```\n",
+            build_file_content
+        );
+        assert!(
+            build_file_content.contains(
+                "\"CARGO_BIN_FILE_FIRMWARE\": \"$(execpath :firmware-0.1.0__firmware__bin__thumbv7em-none-eabihf)\""
+            ),
+            "```\n{}```\n",
+            build_file_content
+        );
+        assert!(
+            build_file_content.contains(
+                "\"CARGO_BIN_FILE_FIRMWARE\": \"$(execpath @test_rendering__firmware-0.1.0//:firmware__bin)\""
+            ),
+            "```\n{}```\n",
+            build_file_content
+        );
+        assert!(
+            build_file_content.contains(
+                "tools = [\n        \"@test_rendering__firmware-0.1.0//:firmware__bin\","
+            ),
+            "```\n{}```\n",
+            build_file_content
+        );
+    }
+
     #[test]
     fn render_proc_macro() {
         let mut context = Context::default();
@@ -1460,6 +1913,135 @@ mod test {
         assert!(build_file_content.contains("\"crate-name=mock_crate\""));
     }
 
+    #[test]
+    fn render_shared_and_static_library() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                name: crate_id.name,
+                version: crate_id.version,
+                package_url: None,
+                repository: Some(SourceAnnotation::Http {
+                    url: "https://crates.io/api/v1/crates/mock_crate/0.1.0/download".to_owned(),
+                    sha256: None,
+                    patch_args: None,
+                    patch_tool: None,
+                    patches: None,
+                }),
+                targets: BTreeSet::from([
+                    Rule::Library(mock_target_attributes()),
+                    Rule::SharedLibrary(mock_target_attributes()),
+                    Rule::StaticLibrary(mock_target_attributes()),
+                ]),
+                library_target_name: None,
+                common_attrs: CommonAttributes::default(),
+                build_script_attrs: None,
+                license: None,
+                license_ids: BTreeSet::default(),
+                license_file: None,
+                additive_build_file_content: None,
+                disable_pipelining: false,
+                extra_aliased_targets: BTreeMap::default(),
+                alias_rule: None,
+                override_targets: BTreeMap::default(),
+                crate_features: BTreeMap::default(),
+                feature_dep_maps: None,
+            },
+        );
+
+        let renderer = Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+        let output = renderer.render(&context, None).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        assert!(build_file_content.contains("rust_library("));
+        assert!(build_file_content.contains("rust_shared_library("));
+        assert!(build_file_content.contains("name = \"mock_crate__cdylib\""));
+        assert!(build_file_content.contains("rust_static_library("));
+        assert!(build_file_content.contains("name = \"mock_crate__staticlib\""));
+        assert!(build_file_content.contains("crate_name = \"mock_crate\""));
+
+        let module_build_file_content = output.get(&PathBuf::from("BUILD.bazel")).unwrap();
+
+        assert!(module_build_file_content.contains("name = \"mock_crate__cdylib\""));
+        assert!(module_build_file_content.contains("name = \"mock_crate__staticlib\""));
+    }
+
+    #[test]
+    fn render_tests_examples_and_benches() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                name: crate_id.name,
+                version: crate_id.version,
+                package_url: None,
+                repository: None,
+                targets: BTreeSet::from([
+                    Rule::Library(mock_target_attributes()),
+                    Rule::Test(TestAttributes {
+                        target: TargetAttributes {
+                            crate_name: "integration".to_owned(),
+                            crate_root: Some("tests/integration.rs".to_owned()),
+                            ..TargetAttributes::default()
+                        },
+                        harness: true,
+                    }),
+                    Rule::Example(TargetAttributes {
+                        crate_name: "demo".to_owned(),
+                        crate_root: Some("examples/demo.rs".to_owned()),
+                        ..TargetAttributes::default()
+                    }),
+                    Rule::Bench(TestAttributes {
+                        target: TargetAttributes {
+                            crate_name: "speed".to_owned(),
+                            crate_root: Some("benches/speed.rs".to_owned()),
+                            ..TargetAttributes::default()
+                        },
+                        harness: false,
+                    }),
+                ]),
+                library_target_name: Some("mock_crate".to_owned()),
+                common_attrs: CommonAttributes::default(),
+                build_script_attrs: None,
+                license: None,
+                license_ids: BTreeSet::default(),
+                license_file: None,
+                additive_build_file_content: None,
+                disable_pipelining: false,
+                extra_aliased_targets: BTreeMap::default(),
+                alias_rule: None,
+                override_targets: BTreeMap::default(),
+                crate_features: BTreeMap::default(),
+                feature_dep_maps: None,
+            },
+        );
+
+        let renderer = Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+        let output = renderer.render(&context, None).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        assert!(build_file_content.contains("rust_test("));
+        assert!(build_file_content.contains("name = \"integration__test\""));
+        assert!(build_file_content.contains("name = \"demo__example\""));
+        assert!(build_file_content.contains("name = \"speed__bench\""));
+        assert!(build_file_content.contains("\":mock_crate\""));
+        assert_eq!(
+            build_file_content
+                .matches("use_libtest_harness = False")
+                .count(),
+            1
+        );
+    }
+
     #[test]
     fn render_additive_build_contents() {
         let mut context = Context::default();
@@ -0,0 +1,1108 @@
+//! Rendering of BUILD files for the members of a Cargo workspace.
+//!
+//! Unlike the BUILD files of third party crates, these files are owned by users. Existing files
+//! are updated in place so that only attributes derived from `Cargo.toml` change while anything
+//! marked with a `# keep` comment is left untouched.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context as AnyhowContext, Result};
+use cargo_toml::{DepsSet, Manifest, OptionalFile, Product};
+use serde::Serialize;
+use serde_starlark::FunctionCall;
+
+use crate::config::{CrateId, RenderConfig};
+use crate::context::crate_context::{CrateContext, CrateDependency, Rule};
+use crate::context::Context;
+use crate::rendering::{render_module_label, Platforms};
+use crate::select::Select;
+use crate::utils::sanitize_module_name;
+use crate::utils::starlark::{
+    Argument, BuildFile, Call, Glob, Label, Repository, SelectDict, SelectSet, Statement,
+};
+
+/// The rules which may be generated for workspace members and the files which define them.
+const RULES: [(&str, &str); 5] = [
+    ("cargo_build_script", "@rules_rust//cargo:defs.bzl"),
+    ("rust_binary", "@rules_rust//rust:defs.bzl"),
+    ("rust_library", "@rules_rust//rust:defs.bzl"),
+    ("rust_proc_macro", "@rules_rust//rust:defs.bzl"),
+    ("rust_test", "@rules_rust//rust:defs.bzl"),
+];
+
+/// Attributes derived from `Cargo.toml` which are updated every time BUILD files are rendered.
+/// Other attributes are only written when a target is created.
+const GENERATED_ATTRIBUTES: [&str; 10] = [
+    "aliases",
+    "crate",
+    "crate_features",
+    "crate_root",
+    "deps",
+    "edition",
+    "proc_macro_deps",
+    "srcs",
+    "use_libtest_harness",
+    "version",
+];
+
+const BUILD_FILE_HEADER: &str = "\
+# Targets in this file are generated from `Cargo.toml` by `cargo-bazel members`.
+# Generated attributes are updated every time the command runs. Add a `# keep`
+# comment to an attribute or the line above a target to maintain it by hand.
+";
+
+/// The name of the build script target of a workspace member.
+const BUILD_SCRIPT_TARGET: &str = "build_script_build";
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Srcs {
+    Glob(Glob),
+    List(BTreeSet<String>),
+}
+
+/// A target generated for a workspace member.
+#[derive(Debug, Serialize)]
+struct MemberTarget {
+    #[serde(skip)]
+    rule: &'static str,
+    name: String,
+    #[serde(skip_serializing_if = "SelectDict::is_empty")]
+    aliases: SelectDict<Label, String>,
+    #[serde(rename = "crate", skip_serializing_if = "Option::is_none")]
+    krate: Option<Label>,
+    #[serde(skip_serializing_if = "SelectSet::is_empty")]
+    crate_features: SelectSet<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    crate_root: Option<String>,
+    #[serde(skip_serializing_if = "SelectSet::is_empty")]
+    deps: SelectSet<Label>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    edition: Option<String>,
+    #[serde(skip_serializing_if = "SelectSet::is_empty")]
+    proc_macro_deps: SelectSet<Label>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    srcs: Option<Srcs>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    use_libtest_harness: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    visibility: BTreeSet<String>,
+}
+
+impl MemberTarget {
+    fn to_call(&self) -> Result<Call> {
+        let text = serde_starlark::to_string(&FunctionCall::new(self.rule, self))?;
+        Ok(Call::parse(&text).expect("Generated targets are always function calls"))
+    }
+}
+
+/// The dependencies of a target.
+#[derive(Debug, Default, Clone)]
+struct Deps {
+    deps: Select<BTreeSet<Label>>,
+    proc_macro_deps: Select<BTreeSet<Label>>,
+    aliases: Select<BTreeMap<Label, String>>,
+}
+
+impl Deps {
+    fn merge(lhs: Self, rhs: Self) -> Self {
+        Self {
+            deps: Select::merge(lhs.deps, rhs.deps),
+            proc_macro_deps: Select::merge(lhs.proc_macro_deps, rhs.proc_macro_deps),
+            aliases: Select::merge(lhs.aliases, rhs.aliases),
+        }
+    }
+
+    /// Add a dependency on a target in the same package.
+    fn with_local_target(mut self, name: &str, proc_macro: bool) -> Self {
+        let label = Label::from_str(&format!(":{name}")).unwrap();
+        if proc_macro {
+            self.proc_macro_deps.insert(label, None);
+        } else {
+            self.deps.insert(label, None);
+        }
+        self
+    }
+}
+
+/// The library target of a workspace member.
+struct MemberLibrary {
+    label: Label,
+    proc_macro: bool,
+}
+
+pub(crate) struct MembersRenderer<'a> {
+    config: &'a RenderConfig,
+    context: &'a Context,
+    platforms: &'a Platforms,
+
+    /// The libraries of workspace members by package name.
+    libraries: BTreeMap<&'a str, MemberLibrary>,
+}
+
+impl<'a> MembersRenderer<'a> {
+    pub(crate) fn new(
+        config: &'a RenderConfig,
+        context: &'a Context,
+        platforms: &'a Platforms,
+    ) -> Self {
+        let libraries = context
+            .workspace_members
+            .iter()
+            .filter_map(|(id, path)| {
+                let krate = &context.crates[id];
+                let name = krate.library_target_name.as_ref()?;
+                Some((
+                    id.name.as_str(),
+                    MemberLibrary {
+                        label: Label::from_str(&format!("//{path}:{name}")).ok()?,
+                        proc_macro: is_proc_macro(krate),
+                    },
+                ))
+            })
+            .collect();
+
+        Self {
+            config,
+            context,
+            platforms,
+            libraries,
+        }
+    }
+
+    /// Render the BUILD files of all workspace members which need to be created or updated.
+    pub(crate) fn render(&self, workspace_dir: &Path) -> Result<BTreeMap<PathBuf, String>> {
+        let mut outputs = BTreeMap::new();
+        for (id, path) in &self.context.workspace_members {
+            let package_dir = workspace_dir.join(path);
+            let targets = self.make_targets(id, &package_dir).with_context(|| {
+                format!("Failed to generate targets for workspace member {}", id)
+            })?;
+
+            let build_file = ["BUILD.bazel", "BUILD"]
+                .iter()
+                .map(|name| package_dir.join(name))
+                .find(|path| path.exists())
+                .unwrap_or_else(|| package_dir.join("BUILD.bazel"));
+            let existing = match build_file.exists() {
+                true => Some(fs::read_to_string(&build_file).with_context(|| {
+                    format!("Failed to read BUILD file {}", build_file.display())
+                })?),
+                false => None,
+            };
+
+            let content = update_build_file(existing.as_deref(), &targets)
+                .with_context(|| format!("Failed to update BUILD file {}", build_file.display()))?;
+            if existing.as_ref() != Some(&content) {
+                outputs.insert(build_file, content);
+            }
+        }
+        Ok(outputs)
+    }
+
+    fn make_targets(&self, id: &CrateId, package_dir: &Path) -> Result<Vec<MemberTarget>> {
+        let krate = &self.context.crates[id];
+        let manifest_path = package_dir.join("Cargo.toml");
+        let manifest = Manifest::from_path(&manifest_path)
+            .with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+
+        let features: BTreeSet<String> = krate
+            .common_attrs
+            .crate_features
+            .values()
+            .into_iter()
+            .collect();
+        let is_enabled = |product: &Product| {
+            let enabled = product
+                .required_features
+                .iter()
+                .all(|feature| features.contains(feature));
+            if !enabled {
+                tracing::debug!(
+                    "Skipping target {:?} of {} with disabled required features",
+                    product.name,
+                    id
+                );
+            }
+            enabled
+        };
+
+        let member_deps = |deps| self.make_member_deps(&id.name, &manifest, &features, deps);
+
+        let normal = Deps::merge(
+            self.make_crate_deps(
+                &krate.common_attrs.deps,
+                &krate.common_attrs.proc_macro_deps,
+            ),
+            member_deps(platform_deps(&manifest, &manifest.dependencies, |t| {
+                &t.dependencies
+            })),
+        );
+        let dev = Deps::merge(
+            self.make_crate_deps(
+                &krate.common_attrs.deps_dev,
+                &krate.common_attrs.proc_macro_deps_dev,
+            ),
+            member_deps(platform_deps(&manifest, &manifest.dev_dependencies, |t| {
+                &t.dev_dependencies
+            })),
+        );
+        let build = {
+            let crate_deps = match &krate.build_script_attrs {
+                Some(attrs) => self.make_crate_deps(&attrs.deps, &attrs.proc_macro_deps),
+                None => Deps::default(),
+            };
+            Deps::merge(
+                crate_deps,
+                member_deps(platform_deps(
+                    &manifest,
+                    &manifest.build_dependencies,
+                    |t| &t.build_dependencies,
+                )),
+            )
+        };
+
+        let build_script = match &manifest.package().build {
+            Some(OptionalFile::Path(path)) => Some(normalize_path(&path.to_string_lossy())),
+            _ => None,
+        };
+        let normal = match build_script {
+            Some(_) => normal.with_local_target(BUILD_SCRIPT_TARGET, false),
+            None => normal,
+        };
+
+        let mut targets = Vec::new();
+        let mut names = BTreeSet::new();
+
+        if let Some(path) = &build_script {
+            names.insert(BUILD_SCRIPT_TARGET.to_owned());
+            targets.push(MemberTarget {
+                crate_root: Some(path.clone()),
+                srcs: Some(Srcs::List(BTreeSet::from([path.clone()]))),
+                ..self.make_target(krate, "cargo_build_script", BUILD_SCRIPT_TARGET, build)
+            });
+        }
+
+        let library = manifest
+            .lib
+            .as_ref()
+            .zip(krate.library_target_name.as_ref())
+            .and_then(|(lib, name)| Some((lib, name, normalize_path(lib.path.as_ref()?))));
+        if let Some((lib, name, crate_root)) = &library {
+            names.insert(name.to_string());
+            let rule = match lib.proc_macro {
+                true => "rust_proc_macro",
+                false => "rust_library",
+            };
+            targets.push(MemberTarget {
+                crate_root: Some(crate_root.clone()),
+                srcs: Some(make_srcs(crate_root)),
+                visibility: BTreeSet::from(["//visibility:public".to_owned()]),
+                ..self.make_target(krate, rule, name, normal.clone())
+            });
+        }
+        let with_library = |deps: Deps| match &library {
+            Some((lib, name, _)) => deps.with_local_target(name, lib.proc_macro),
+            None => deps,
+        };
+
+        for bin in manifest.bin.iter().filter(|bin| is_enabled(bin)) {
+            let (Some(name), Some(path)) = (&bin.name, &bin.path) else {
+                continue;
+            };
+            let crate_root = normalize_path(path);
+            targets.push(MemberTarget {
+                crate_root: Some(crate_root.clone()),
+                srcs: Some(make_srcs(&crate_root)),
+                ..self.make_target(
+                    krate,
+                    "rust_binary",
+                    &unique_name(&mut names, name, "bin"),
+                    with_library(normal.clone()),
+                )
+            });
+        }
+
+        // Unit tests inherit the sources, dependencies and settings of the library.
+        if let Some((_, library_name, _)) = library.as_ref().filter(|(lib, ..)| lib.test) {
+            let name = unique_name(&mut names, library_name, "test");
+            targets.push(MemberTarget {
+                krate: Some(Label::from_str(&format!(":{library_name}")).unwrap()),
+                crate_features: SelectSet::new(
+                    Select::default(),
+                    &krate.common_attrs.build_targets,
+                    self.platforms,
+                ),
+                edition: None,
+                version: None,
+                ..self.make_target(krate, "rust_test", &name, dev.clone())
+            });
+        }
+
+        for test in manifest.test.iter().filter(|test| is_enabled(test)) {
+            let (Some(name), Some(path)) = (&test.name, &test.path) else {
+                continue;
+            };
+            let crate_root = normalize_path(path);
+            targets.push(MemberTarget {
+                crate_root: Some(crate_root.clone()),
+                srcs: Some(make_srcs(&crate_root)),
+                use_libtest_harness: (!test.harness).then_some(false),
+                ..self.make_target(
+                    krate,
+                    "rust_test",
+                    &unique_name(&mut names, name, "test"),
+                    with_library(Deps::merge(normal.clone(), dev.clone())),
+                )
+            });
+        }
+
+        Ok(targets)
+    }
+
+    fn make_target(
+        &self,
+        krate: &CrateContext,
+        rule: &'static str,
+        name: &str,
+        deps: Deps,
+    ) -> MemberTarget {
+        let build_targets = &krate.common_attrs.build_targets;
+        MemberTarget {
+            rule,
+            name: name.to_owned(),
+            aliases: SelectDict::new(deps.aliases, build_targets, self.platforms),
+            krate: None,
+            crate_features: SelectSet::new(
+                krate.common_attrs.crate_features.clone(),
+                build_targets,
+                self.platforms,
+            ),
+            crate_root: None,
+            deps: SelectSet::new(deps.deps, build_targets, self.platforms),
+            edition: Some(krate.common_attrs.edition.clone()),
+            proc_macro_deps: SelectSet::new(deps.proc_macro_deps, build_targets, self.platforms),
+            srcs: None,
+            use_libtest_harness: None,
+            version: Some(krate.common_attrs.version.clone()),
+            visibility: BTreeSet::new(),
+        }
+    }
+
+    /// Dependencies on third party crates, resolved through the aliases of the crates repository.
+    fn make_crate_deps(
+        &self,
+        deps: &Select<BTreeSet<CrateDependency>>,
+        proc_macro_deps: &Select<BTreeSet<CrateDependency>>,
+    ) -> Deps {
+        let mut aliases = Select::default();
+        for (configuration, dep) in deps.items().into_iter().chain(proc_macro_deps.items()) {
+            if let (Some(alias), Some(label)) = (&dep.alias, self.crate_label(&dep)) {
+                aliases.insert((label, alias.clone()), configuration);
+            }
+        }
+
+        Deps {
+            deps: deps.clone().filter_map(|dep| self.crate_label(&dep)),
+            proc_macro_deps: proc_macro_deps
+                .clone()
+                .filter_map(|dep| self.crate_label(&dep)),
+            aliases,
+        }
+    }
+
+    /// Dependencies on other workspace members. These are not tracked by the [Context] and are
+    /// read from the member's `Cargo.toml` instead.
+    fn make_member_deps(
+        &self,
+        package_name: &str,
+        manifest: &Manifest,
+        features: &BTreeSet<String>,
+        deps: Vec<(Option<String>, &DepsSet)>,
+    ) -> Deps {
+        let mut result = Deps::default();
+        for (configuration, deps) in deps {
+            for (key, dep) in deps {
+                let name = dep.package().unwrap_or(key);
+                let Some(library) = self.libraries.get(name) else {
+                    continue;
+                };
+                if name == package_name
+                    || (dep.optional() && !is_optional_dep_enabled(key, manifest, features))
+                {
+                    continue;
+                }
+
+                if library.proc_macro {
+                    result
+                        .proc_macro_deps
+                        .insert(library.label.clone(), configuration.clone());
+                } else {
+                    result
+                        .deps
+                        .insert(library.label.clone(), configuration.clone());
+                }
+                if key != name {
+                    result.aliases.insert(
+                        (library.label.clone(), sanitize_module_name(key)),
+                        configuration.clone(),
+                    );
+                }
+            }
+        }
+        result
+    }
+
+    /// The label of a library dependency. Dependencies on crates are resolved through the aliases
+    /// rendered for workspace members in the crates repository.
+    fn crate_label(&self, dep: &CrateDependency) -> Option<Label> {
+        let target = dep.target.as_deref()?;
+        if let Some(path) = &dep.local_path {
+            return Label::from_str(&format!("//{}:{}", path, target)).ok();
+        }
+
+        let name = dep
+            .alias
+            .as_ref()
+            .filter(|alias| **alias != dep.id.name)
+            .unwrap_or(&dep.id.name);
+        let label = render_module_label(
+            &self.config.crates_module_template,
+            &format!("{}-{}", name, dep.id.version),
+        )
+        .ok()?;
+
+        // Unless vendored, the aliases are in a separate repository.
+        Some(match label {
+            Label::Absolute {
+                repository: Repository::Local,
+                package,
+                target,
+            } if self.config.vendor_mode.is_none() => Label::Absolute {
+                repository: Repository::Explicit(self.config.repository_name.clone()),
+                package,
+                target,
+            },
+            label => label,
+        })
+    }
+}
+
+/// A table of dependencies along with the platform specific tables of the same kind.
+fn platform_deps<'m>(
+    manifest: &'m Manifest,
+    deps: &'m DepsSet,
+    select: fn(&cargo_toml::Target) -> &DepsSet,
+) -> Vec<(Option<String>, &'m DepsSet)> {
+    std::iter::once((None, deps))
+        .chain(
+            manifest
+                .target
+                .iter()
+                .map(|(cfg, target)| (Some(cfg.clone()), select(target))),
+        )
+        .collect()
+}
+
+fn is_proc_macro(krate: &CrateContext) -> bool {
+    krate
+        .targets
+        .iter()
+        .any(|rule| matches!(rule, Rule::ProcMacro(..)))
+}
+
+/// Whether an optional dependency is enabled by the features of a package.
+fn is_optional_dep_enabled(name: &str, manifest: &Manifest, enabled: &BTreeSet<String>) -> bool {
+    enabled.contains(name)
+        || enabled
+            .iter()
+            .filter_map(|feature| manifest.features.get(feature))
+            .flatten()
+            .any(|value| {
+                value.strip_prefix("dep:") == Some(name)
+                    || value.split_once('/').map(|(dep, _)| dep) == Some(name)
+            })
+}
+
+fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    path.strip_prefix("./").unwrap_or(&path).to_owned()
+}
+
+/// The sources of a target are all Rust files in the top level directory of its crate root.
+fn make_srcs(crate_root: &str) -> Srcs {
+    let include = match crate_root.split_once('/') {
+        Some((dir, _)) => format!("{dir}/**/*.rs"),
+        None => "**/*.rs".to_owned(),
+    };
+    Srcs::Glob(Glob {
+        allow_empty: false,
+        include: BTreeSet::from([include]),
+        exclude: BTreeSet::new(),
+    })
+}
+
+/// Avoid collisions between target names by adding a suffix to later targets.
+fn unique_name(names: &mut BTreeSet<String>, name: &str, suffix: &str) -> String {
+    let name = match names.contains(name) {
+        true => format!("{name}_{suffix}"),
+        false => name.to_owned(),
+    };
+    names.insert(name.clone());
+    name
+}
+
+/// Merge generated targets into the content of a BUILD file.
+fn update_build_file(existing: Option<&str>, targets: &[MemberTarget]) -> Result<String> {
+    let mut build_file = BuildFile::parse(existing.unwrap_or_default());
+
+    let mut rules = BTreeSet::new();
+    for target in targets {
+        let generated = target.to_call()?;
+        let existing = build_file.statements.iter_mut().find(|statement| {
+            statement
+                .call()
+                .is_some_and(|call| call.name() == Some(target.name.as_str()))
+        });
+
+        let Some(statement) = existing else {
+            rules.insert(target.rule);
+            build_file.statements.push(Statement {
+                leading: "\n".to_owned(),
+                text: generated.render(),
+            });
+            continue;
+        };
+
+        let call = statement.call().unwrap();
+        if statement.is_kept() || call.is_kept() {
+            continue;
+        }
+        if !RULES.iter().any(|(rule, _)| *rule == call.function) {
+            tracing::warn!(
+                "Not updating target `{}` which is a `{}` instead of a `{}`",
+                target.name,
+                call.function,
+                target.rule
+            );
+            continue;
+        }
+
+        rules.insert(target.rule);
+        let merged = merge_call(&call, &generated);
+        if merged != call {
+            statement.text = merged.render();
+        }
+    }
+
+    for statement in &build_file.statements {
+        let Some(call) = statement.call() else {
+            continue;
+        };
+        let is_stale = RULES.iter().any(|(rule, _)| *rule == call.function)
+            && !statement.is_kept()
+            && !call.is_kept()
+            && !targets
+                .iter()
+                .any(|target| call.name() == Some(target.name.as_str()));
+        if is_stale {
+            tracing::warn!(
+                "Target `{}` is not generated from Cargo.toml. Remove it or mark it with `# keep`",
+                call.name().unwrap_or_default()
+            );
+        }
+    }
+
+    let mut loads: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for (rule, bzl) in RULES {
+        if rules.contains(rule) {
+            loads.entry(bzl).or_default().insert(rule);
+        }
+    }
+    for (bzl, symbols) in loads {
+        add_load(&mut build_file, bzl, &symbols);
+    }
+
+    if existing.is_none() {
+        if let Some(first) = build_file.statements.first_mut() {
+            first.leading = BUILD_FILE_HEADER.to_owned();
+        }
+    }
+
+    Ok(build_file.render())
+}
+
+/// Update the generated attributes of an existing target.
+fn merge_call(existing: &Call, generated: &Call) -> Call {
+    let is_generated = |arg: &Argument| {
+        arg.name
+            .as_deref()
+            .is_some_and(|name| GENERATED_ATTRIBUTES.contains(&name))
+    };
+
+    let mut merged = existing.clone();
+    merged.function = generated.function.clone();
+    merged.arguments = existing
+        .arguments
+        .iter()
+        .filter_map(|arg| {
+            if !is_generated(arg) || arg.is_kept() {
+                return Some(arg.clone());
+            }
+            let name = arg.name.as_deref().unwrap();
+            generated.argument(name).map(|generated| Argument {
+                value: generated.value.clone(),
+                ..arg.clone()
+            })
+        })
+        .collect();
+
+    for arg in generated.arguments.iter().filter(|arg| is_generated(arg)) {
+        let name = arg.name.as_deref().unwrap();
+        if merged.argument(name).is_none() {
+            merged.arguments.push(arg.clone());
+        }
+    }
+
+    merged
+}
+
+/// Ensure a BUILD file loads the given symbols from a `.bzl` file.
+fn add_load(build_file: &mut BuildFile, bzl: &str, symbols: &BTreeSet<&str>) {
+    let is_load =
+        |statement: &Statement| statement.call().is_some_and(|call| call.function == "load");
+
+    for statement in build_file.statements.iter_mut() {
+        let Some(mut call) = statement.call().filter(|call| call.function == "load") else {
+            continue;
+        };
+        if call.arguments.first().and_then(Argument::string_value) != Some(bzl) {
+            continue;
+        }
+
+        let loaded: BTreeSet<String> = call
+            .arguments
+            .iter()
+            .filter_map(|arg| match &arg.name {
+                Some(name) => Some(name.clone()),
+                None => arg.string_value().map(str::to_owned),
+            })
+            .collect();
+        let missing: Vec<&str> = symbols
+            .iter()
+            .filter(|symbol| !loaded.contains(**symbol))
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            for symbol in missing {
+                call.arguments
+                    .push(Argument::new(None, &format!("\"{}\"", symbol)));
+            }
+            statement.text = call.render();
+        }
+        return;
+    }
+
+    let mut arguments = vec![Argument::new(None, &format!("\"{}\"", bzl))];
+    arguments.extend(
+        symbols
+            .iter()
+            .map(|symbol| Argument::new(None, &format!("\"{}\"", symbol))),
+    );
+    let call = Call {
+        multiline: false,
+        ..Call::new("load", arguments)
+    };
+    let mut load = Statement {
+        leading: String::new(),
+        text: call.render(),
+    };
+
+    // Insert the load after existing ones or at the top of the file, below any header comments.
+    match build_file.statements.iter().rposition(is_load) {
+        Some(index) => build_file.statements.insert(index + 1, load),
+        None => {
+            match build_file.statements.first_mut() {
+                Some(first) => {
+                    load.leading = std::mem::replace(&mut first.leading, "\n".to_owned());
+                }
+                None => {
+                    load.leading = std::mem::take(&mut build_file.trailing);
+                }
+            }
+            build_file.statements.insert(0, load);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use indoc::indoc;
+    use semver::Version;
+
+    use crate::context::crate_context::TargetAttributes;
+    use crate::context::CommonAttributes;
+    use crate::rendering::Renderer;
+    use crate::test;
+
+    fn mock_crate(name: &str, library: bool, deps: &[&CrateId]) -> CrateContext {
+        let mut common_attrs = CommonAttributes {
+            edition: "2021".to_owned(),
+            version: "0.1.0".to_owned(),
+            ..CommonAttributes::default()
+        };
+        for dep in deps {
+            common_attrs.deps.insert(
+                CrateDependency {
+                    id: (*dep).clone(),
+                    target: Some(dep.name.clone()),
+                    alias: None,
+                    local_path: None,
+                },
+                None,
+            );
+        }
+
+        CrateContext {
+            name: name.to_owned(),
+            version: Version::new(0, 1, 0),
+            package_url: None,
+            repository: None,
+            targets: match library {
+                true => BTreeSet::from([Rule::Library(TargetAttributes {
+                    crate_name: name.to_owned(),
+                    crate_root: Some("src/lib.rs".to_owned()),
+                    ..TargetAttributes::default()
+                })]),
+                false => BTreeSet::new(),
+            },
+            library_target_name: library.then(|| name.to_owned()),
+            common_attrs,
+            build_script_attrs: None,
+            license: None,
+            license_ids: BTreeSet::default(),
+            license_file: None,
+            additive_build_file_content: None,
+            disable_pipelining: false,
+            extra_aliased_targets: BTreeMap::default(),
+            alias_rule: None,
+            override_targets: BTreeMap::default(),
+            crate_features: BTreeMap::default(),
+            feature_dep_maps: None,
+        }
+    }
+
+    /// A workspace with an `app` member depending on a `util` member and the `anyhow` crate.
+    fn mock_workspace() -> (Option<tempfile::TempDir>, PathBuf, Context) {
+        let (temp_dir, workspace_dir) = test::test_tempdir("members");
+        let files = [
+            ("Cargo.toml", "[workspace]\nmembers = [\"app\", \"util\"]\n"),
+            (
+                "app/Cargo.toml",
+                indoc! {r#"
+                    [package]
+                    name = "app"
+                    version = "0.1.0"
+                    edition = "2021"
+
+                    [dependencies]
+                    anyhow = "1.0"
+                    util = { path = "../util" }
+
+                    [[test]]
+                    name = "integration"
+                    harness = false
+                "#},
+            ),
+            ("app/build.rs", "fn main() {}\n"),
+            ("app/src/lib.rs", ""),
+            ("app/src/main.rs", "fn main() {}\n"),
+            ("app/tests/integration.rs", "fn main() {}\n"),
+            (
+                "util/Cargo.toml",
+                "[package]\nname = \"util\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+            ),
+            ("util/src/lib.rs", ""),
+        ];
+        for (path, content) in files {
+            let path = workspace_dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let anyhow = CrateId::new("anyhow".to_owned(), Version::new(1, 0, 80));
+        let app = CrateId::new("app".to_owned(), Version::new(0, 1, 0));
+        let util = CrateId::new("util".to_owned(), Version::new(0, 1, 0));
+
+        let mut context = Context::default();
+        context
+            .crates
+            .insert(app.clone(), mock_crate("app", true, &[&anyhow]));
+        context
+            .crates
+            .insert(util.clone(), mock_crate("util", true, &[]));
+        context.workspace_members.insert(app, "app".to_owned());
+        context.workspace_members.insert(util, "util".to_owned());
+
+        (temp_dir, workspace_dir, context)
+    }
+
+    fn render(context: &Context, workspace_dir: &Path) -> BTreeMap<PathBuf, String> {
+        let config = RenderConfig {
+            repository_name: "crates".to_owned(),
+            ..RenderConfig::default()
+        };
+        Renderer::new(Arc::new(config), Arc::new(BTreeSet::new()))
+            .render_members(context, workspace_dir)
+            .unwrap()
+    }
+
+    #[test]
+    fn render_new_build_files() {
+        let (_temp_dir, workspace_dir, context) = mock_workspace();
+
+        let outputs = render(&context, &workspace_dir);
+
+        assert_eq!(
+            outputs.keys().collect::<Vec<_>>(),
+            [
+                &workspace_dir.join("app/BUILD.bazel"),
+                &workspace_dir.join("util/BUILD.bazel")
+            ]
+        );
+        assert_eq!(
+            outputs[&workspace_dir.join("app/BUILD.bazel")],
+            indoc! {r#"
+                # Targets in this file are generated from `Cargo.toml` by `cargo-bazel members`.
+                # Generated attributes are updated every time the command runs. Add a `# keep`
+                # comment to an attribute or the line above a target to maintain it by hand.
+                load("@rules_rust//cargo:defs.bzl", "cargo_build_script")
+                load("@rules_rust//rust:defs.bzl", "rust_binary", "rust_library", "rust_test")
+
+                cargo_build_script(
+                    name = "build_script_build",
+                    crate_root = "build.rs",
+                    edition = "2021",
+                    srcs = ["build.rs"],
+                    version = "0.1.0",
+                )
+
+                rust_library(
+                    name = "app",
+                    crate_root = "src/lib.rs",
+                    deps = [
+                        ":build_script_build",
+                        "@crates//:anyhow-1.0.80",
+                        "//util:util",
+                    ],
+                    edition = "2021",
+                    srcs = glob(
+                        allow_empty = False,
+                        include = ["src/**/*.rs"],
+                    ),
+                    version = "0.1.0",
+                    visibility = ["//visibility:public"],
+                )
+
+                rust_binary(
+                    name = "app_bin",
+                    crate_root = "src/main.rs",
+                    deps = [
+                        ":app",
+                        ":build_script_build",
+                        "@crates//:anyhow-1.0.80",
+                        "//util:util",
+                    ],
+                    edition = "2021",
+                    srcs = glob(
+                        allow_empty = False,
+                        include = ["src/**/*.rs"],
+                    ),
+                    version = "0.1.0",
+                )
+
+                rust_test(
+                    name = "app_test",
+                    crate = ":app",
+                )
+
+                rust_test(
+                    name = "integration",
+                    crate_root = "tests/integration.rs",
+                    deps = [
+                        ":app",
+                        ":build_script_build",
+                        "@crates//:anyhow-1.0.80",
+                        "//util:util",
+                    ],
+                    edition = "2021",
+                    srcs = glob(
+                        allow_empty = False,
+                        include = ["tests/**/*.rs"],
+                    ),
+                    use_libtest_harness = False,
+                    version = "0.1.0",
+                )
+            "#}
+        );
+    }
+
+    #[test]
+    fn update_existing_build_files() {
+        let (_temp_dir, workspace_dir, context) = mock_workspace();
+        fs::write(
+            workspace_dir.join("util/BUILD.bazel"),
+            indoc! {r#"
+                load("@rules_rust//rust:defs.bzl", "rust_library")
+                load("//tools:defs.bzl", "custom_rule")
+
+                rust_library(
+                    name = "util",
+                    srcs = ["src/lib.rs"],  # keep
+                    edition = "2018",
+                    rustc_flags = ["-Dwarnings"],
+                    deps = [":stale"],
+                )
+
+                # keep
+                rust_test(
+                    name = "util_test",
+                    crate = ":util",
+                    tags = ["manual"],
+                )
+
+                rust_binary(
+                    name = "tool",
+                    srcs = ["tool.rs"],
+                )
+
+                custom_rule(name = "extra")
+            "#},
+        )
+        .unwrap();
+
+        let outputs = render(&context, &workspace_dir);
+
+        assert_eq!(
+            outputs[&workspace_dir.join("util/BUILD.bazel")],
+            indoc! {r#"
+                load("@rules_rust//rust:defs.bzl", "rust_library")
+                load("//tools:defs.bzl", "custom_rule")
+
+                rust_library(
+                    name = "util",
+                    srcs = ["src/lib.rs"],  # keep
+                    edition = "2021",
+                    rustc_flags = ["-Dwarnings"],
+                    crate_root = "src/lib.rs",
+                    version = "0.1.0",
+                )
+
+                # keep
+                rust_test(
+                    name = "util_test",
+                    crate = ":util",
+                    tags = ["manual"],
+                )
+
+                rust_binary(
+                    name = "tool",
+                    srcs = ["tool.rs"],
+                )
+
+                custom_rule(name = "extra")
+            "#}
+        );
+
+        // Rendering again does not change the updated file.
+        fs::write(
+            workspace_dir.join("util/BUILD.bazel"),
+            &outputs[&workspace_dir.join("util/BUILD.bazel")],
+        )
+        .unwrap();
+        let outputs = render(&context, &workspace_dir);
+        assert!(!outputs.contains_key(&workspace_dir.join("util/BUILD.bazel")));
+    }
+
+    #[test]
+    fn add_missing_loads() {
+        let mut build_file = BuildFile::parse(indoc! {r#"
+            # A header
+
+            load("@rules_rust//rust:defs.bzl", "rust_library")
+
+            rust_library(name = "a")
+        "#});
+
+        add_load(
+            &mut build_file,
+            "@rules_rust//rust:defs.bzl",
+            &BTreeSet::from(["rust_library", "rust_test"]),
+        );
+        add_load(
+            &mut build_file,
+            "@rules_rust//cargo:defs.bzl",
+            &BTreeSet::from(["cargo_build_script"]),
+        );
+
+        assert_eq!(
+            build_file.render(),
+            indoc! {r#"
+                # A header
+
+                load("@rules_rust//rust:defs.bzl", "rust_library", "rust_test")
+                load("@rules_rust//cargo:defs.bzl", "cargo_build_script")
+
+                rust_library(name = "a")
+            "#}
+        );
+
+        let mut build_file = BuildFile::parse("# A header\n\nexports_files([\"a\"])\n");
+        add_load(
+            &mut build_file,
+            "@rules_rust//rust:defs.bzl",
+            &BTreeSet::from(["rust_library"]),
+        );
+        assert_eq!(
+            build_file.render(),
+            "# A header\n\nload(\"@rules_rust//rust:defs.bzl\", \"rust_library\")\n\nexports_files([\"a\"])\n"
+        );
+    }
+
+    #[test]
+    fn optional_dep_enabled() {
+        let manifest = Manifest::from_str(indoc! {r#"
+            [package]
+            name = "a"
+            version = "0.1.0"
+
+            [dependencies]
+            b = { version = "1", optional = true }
+            c = { version = "1", optional = true }
+            d = { version = "1", optional = true }
+
+            [features]
+            default = ["dep:b", "c/std", "d?/std"]
+        "#})
+        .unwrap();
+        let features = BTreeSet::from(["default".to_owned()]);
+
+        assert!(is_optional_dep_enabled("b", &manifest, &features));
+        assert!(is_optional_dep_enabled("c", &manifest, &features));
+        assert!(!is_optional_dep_enabled("d", &manifest, &features));
+        assert!(is_optional_dep_enabled(
+            "d",
+            &manifest,
+            &BTreeSet::from(["d".to_owned()])
+        ));
+    }
+}
@@ -17,12 +17,11 @@ use serde::{Deserialize, Serialize};
 
 use crate::config::CrateId;
 use crate::metadata::{Cargo, CargoUpdateRequest, CrateAnnotation, LockGenerator};
-use crate::utils;
 use crate::utils::starlark::Label;
 use crate::utils::target_triple::TargetTriple;
 
 use self::cargo_config::CargoConfig;
-use self::crate_index_lookup::CrateIndexLookup;
+pub(crate) use self::crate_index_lookup::CrateIndexLookup;
 pub(crate) use self::splicer::*;
 
 type DirectPackageManifest = BTreeMap<String, cargo_toml::DependencyDetail>;
@@ -308,55 +307,13 @@ impl WorkspaceMetadata {
                 } else {
                     &url
                 };
-                let index = if cargo.use_sparse_registries_for_crates_io()?
-                    && index_url == utils::CRATES_IO_INDEX_URL
-                {
-                    CrateIndexLookup::Http(crates_index::SparseIndex::from_url_with_hash_kind(
-                        "sparse+https://index.crates.io/",
-                        &crate_index_hash_kind,
-                    )?)
-                } else if index_url.starts_with("sparse+") {
-                    CrateIndexLookup::Http(crates_index::SparseIndex::from_url_with_hash_kind(
-                        index_url,
-                        &crate_index_hash_kind,
-                    )?)
-                } else {
-                    match source_kind {
-                        SourceKind::Registry => {
-                            let index = {
-                                // Load the index for the current url
-                                let index = crates_index::GitIndex::from_url_with_hash_kind(
-                                    index_url,
-                                    &crate_index_hash_kind,
-                                )
-                                .with_context(|| {
-                                    format!("Failed to load index for url: {index_url}")
-                                })?;
-
-                                // Ensure each index has a valid index config
-                                index.index_config().with_context(|| {
-                                    format!("`config.json` not found in index: {index_url}")
-                                })?;
-
-                                index
-                            };
-                            CrateIndexLookup::Git(index)
-                        }
-                        SourceKind::SparseRegistry => CrateIndexLookup::Http(
-                            crates_index::SparseIndex::from_url_with_hash_kind(
-                                format!("sparse+{}", index_url).as_str(),
-                                &crate_index_hash_kind,
-                            )?,
-                        ),
-                        unknown => {
-                            return Err(anyhow!(
-                                "'{:?}' crate index type is not supported (caused by '{}')",
-                                &unknown,
-                                url
-                            ));
-                        }
-                    }
-                };
+                let index = CrateIndexLookup::new(
+                    &source_kind,
+                    index_url,
+                    cargo.use_sparse_registries_for_crates_io()?,
+                    &crate_index_hash_kind,
+                    false,
+                )?;
                 Ok((url, index))
             })
             .collect::<Result<BTreeMap<String, _>>>()
@@ -1,6 +1,8 @@
 use crate::splicing::SourceInfo;
-use anyhow::{Context, Result};
-use crates_index::IndexConfig;
+use crate::utils;
+use anyhow::{anyhow, Context, Result};
+use cargo_lock::package::SourceKind;
+use crates_index::{HashKind, IndexConfig};
 use hex::ToHex;
 
 pub(crate) enum CrateIndexLookup {
@@ -9,6 +11,87 @@ pub(crate) enum CrateIndexLookup {
 }
 
 impl CrateIndexLookup {
+    /// Open the index of a registry from the local Cargo home.
+    ///
+    /// Git indexes which have not been cloned yet are only fetched when `offline` is false.
+    /// Sparse indexes never touch the network, lookups are served from their on-disk cache.
+    pub(crate) fn new(
+        source_kind: &SourceKind,
+        index_url: &str,
+        sparse_crates_io: bool,
+        hash_kind: &HashKind,
+        offline: bool,
+    ) -> Result<Self> {
+        if sparse_crates_io && index_url == utils::CRATES_IO_INDEX_URL {
+            return Ok(Self::Http(
+                crates_index::SparseIndex::from_url_with_hash_kind(
+                    "sparse+https://index.crates.io/",
+                    hash_kind,
+                )?,
+            ));
+        }
+        if index_url.starts_with("sparse+") {
+            return Ok(Self::Http(
+                crates_index::SparseIndex::from_url_with_hash_kind(index_url, hash_kind)?,
+            ));
+        }
+
+        match source_kind {
+            SourceKind::Registry => {
+                // Load the index for the current url
+                let index = if offline {
+                    crates_index::GitIndex::try_from_url_with_hash_kind(index_url, hash_kind)
+                        .with_context(|| format!("Failed to load index for url: {index_url}"))?
+                        .ok_or_else(|| {
+                            anyhow!("No local clone of the index for url: {index_url}")
+                        })?
+                } else {
+                    crates_index::GitIndex::from_url_with_hash_kind(index_url, hash_kind)
+                        .with_context(|| format!("Failed to load index for url: {index_url}"))?
+                };
+
+                // Ensure each index has a valid index config
+                index
+                    .index_config()
+                    .with_context(|| format!("`config.json` not found in index: {index_url}"))?;
+
+                Ok(Self::Git(index))
+            }
+            SourceKind::SparseRegistry => Ok(Self::Http(
+                crates_index::SparseIndex::from_url_with_hash_kind(
+                    format!("sparse+{}", index_url).as_str(),
+                    hash_kind,
+                )?,
+            )),
+            unknown => Err(anyhow!(
+                "'{:?}' crate index type is not supported (caused by '{}')",
+                &unknown,
+                index_url
+            )),
+        }
+    }
+
+    /// All versions of a crate which haven't been yanked, in ascending order.
+    pub(crate) fn versions(&self, name: &str) -> Result<Vec<semver::Version>> {
+        let crate_ = match self {
+            Self::Http(index) => index
+                .crate_from_cache(name)
+                .with_context(|| format!("Failed to get crate from cache: {:?}", index))?,
+            Self::Git(index) => index
+                .crate_(name)
+                .ok_or_else(|| anyhow!("Crate `{}` not found in index: {}", name, index.url()))?,
+        };
+
+        let mut versions = crate_
+            .versions()
+            .iter()
+            .filter(|v| !v.is_yanked())
+            .filter_map(|v| semver::Version::parse(v.version()).ok())
+            .collect::<Vec<_>>();
+        versions.sort();
+        Ok(versions)
+    }
+
     pub(crate) fn get_source_info(&self, pkg: &cargo_lock::Package) -> Result<Option<SourceInfo>> {
         let index_config = self
             .index_config()
@@ -125,6 +208,27 @@ mod test {
         }
     }
 
+    #[test]
+    fn sparse_index_versions() {
+        let runfiles = runfiles::Runfiles::create().unwrap();
+        let cargo_home = runfiles::rlocation!(
+            runfiles,
+            "rules_rust/crate_universe/test_data/crate_indexes/lazy_static/cargo_home"
+        )
+        .unwrap();
+
+        let index = CrateIndexLookup::Http(
+            crates_index::SparseIndex::with_path(cargo_home, "sparse+https://index.crates.io/")
+                .unwrap(),
+        );
+        let versions = index.versions("lazy_static").unwrap();
+
+        assert_eq!(versions.len(), 37);
+        assert_eq!(versions.first(), Some(&Version::new(0, 1, 0)));
+        assert_eq!(versions.last(), Some(&Version::new(1, 4, 0)));
+        assert!(index.versions("not_in_the_cache").is_err());
+    }
+
     struct EnvVarResetter {
         key: OsString,
         value: Option<OsString>,
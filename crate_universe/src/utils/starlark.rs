@@ -1,5 +1,6 @@
 //! A module for representations of starlark constructs
 
+mod build_file;
 mod glob;
 mod label;
 mod select;
@@ -15,6 +16,7 @@ use std::collections::BTreeSet as Set;
 use serde::{Serialize, Serializer};
 use serde_starlark::{Error as StarlarkError, FunctionCall};
 
+pub(crate) use build_file::*;
 pub(crate) use glob::*;
 pub(crate) use label::*;
 pub(crate) use select::*;
@@ -36,12 +38,19 @@ pub(crate) enum Starlark {
     Alias(Alias),
     CargoBuildScript(CargoBuildScript),
     CargoTomlEnvVars(CargoTomlEnvVars),
+    CargoArtifact(CargoArtifact),
     #[serde(serialize_with = "serialize::rust_proc_macro")]
     RustProcMacro(RustProcMacro),
     #[serde(serialize_with = "serialize::rust_library")]
     RustLibrary(RustLibrary),
     #[serde(serialize_with = "serialize::rust_binary")]
     RustBinary(RustBinary),
+    #[serde(serialize_with = "serialize::rust_shared_library")]
+    RustSharedLibrary(RustNativeLibrary),
+    #[serde(serialize_with = "serialize::rust_static_library")]
+    RustStaticLibrary(RustNativeLibrary),
+    #[serde(serialize_with = "serialize::rust_test")]
+    RustTest(RustTest),
 
     #[serde(skip_serializing)]
     Verbatim(String),
@@ -148,6 +157,16 @@ pub(crate) struct CargoTomlEnvVars {
     pub(crate) src: String,
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename = "cargo_artifact")]
+pub(crate) struct CargoArtifact {
+    pub(crate) name: String,
+    pub(crate) actual: Label,
+    pub(crate) platform: String,
+    #[serde(skip_serializing_if = "Set::is_empty")]
+    pub(crate) tags: Set<String>,
+}
+
 #[derive(Serialize)]
 pub(crate) struct RustProcMacro {
     pub(crate) name: String,
@@ -189,6 +208,36 @@ pub(crate) struct RustBinary {
     pub(crate) common: CommonAttrs,
 }
 
+/// The attributes of a `rust_shared_library` or `rust_static_library`.
+#[derive(Serialize)]
+pub(crate) struct RustNativeLibrary {
+    pub(crate) name: String,
+    pub(crate) crate_name: String,
+    #[serde(skip_serializing_if = "SelectSet::is_empty")]
+    pub(crate) deps: SelectSet<Label>,
+    #[serde(skip_serializing_if = "SelectSet::is_empty")]
+    pub(crate) proc_macro_deps: SelectSet<Label>,
+    #[serde(skip_serializing_if = "SelectDict::is_empty")]
+    pub(crate) aliases: SelectDict<Label, String>,
+    #[serde(flatten)]
+    pub(crate) common: CommonAttrs,
+}
+
+#[derive(Serialize)]
+pub(crate) struct RustTest {
+    pub(crate) name: String,
+    #[serde(skip_serializing_if = "SelectSet::is_empty")]
+    pub(crate) deps: SelectSet<Label>,
+    #[serde(skip_serializing_if = "SelectSet::is_empty")]
+    pub(crate) proc_macro_deps: SelectSet<Label>,
+    #[serde(skip_serializing_if = "SelectDict::is_empty")]
+    pub(crate) aliases: SelectDict<Label, String>,
+    #[serde(flatten)]
+    pub(crate) common: CommonAttrs,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) use_libtest_harness: Option<bool>,
+}
+
 #[derive(Serialize)]
 pub(crate) struct CommonAttrs {
     #[serde(skip_serializing_if = "Data::is_empty")]
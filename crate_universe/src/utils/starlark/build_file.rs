@@ -0,0 +1,625 @@
+//! A minimal parser for updating existing BUILD files in place.
+//!
+//! Only the structure needed to merge generated targets into user maintained files is
+//! understood: top level statements, function calls and their arguments. Everything else,
+//! including comments and the formatting of argument values, is preserved verbatim.
+
+/// Tracks whether a position in Starlark source is nested in brackets or a string.
+#[derive(Debug, Default)]
+struct Scanner {
+    depth: usize,
+    quote: Option<(char, bool)>,
+    escaped: bool,
+}
+
+impl Scanner {
+    /// Whether the scanner is outside of any brackets and strings.
+    fn at_top_level(&self) -> bool {
+        self.depth == 0 && self.quote.is_none()
+    }
+
+    /// Advance the scanner by one character of `text` at `index`. Returns the number of
+    /// bytes consumed and whether a comment starts at `index`.
+    fn advance(&mut self, text: &str, index: usize) -> (usize, bool) {
+        let rest = &text[index..];
+        let c = rest.chars().next().expect("index is in bounds");
+        let len = c.len_utf8();
+
+        if let Some((quote, triple)) = self.quote {
+            if self.escaped {
+                self.escaped = false;
+            } else if c == '\\' {
+                self.escaped = true;
+            } else if c == quote {
+                if !triple {
+                    self.quote = None;
+                } else if rest.starts_with(&quote.to_string().repeat(3)) {
+                    self.quote = None;
+                    return (3, false);
+                }
+            } else if c == '\n' && !triple {
+                // Unterminated strings end with the line.
+                self.quote = None;
+            }
+            return (len, false);
+        }
+
+        match c {
+            '#' => return (len, true),
+            '"' | '\'' => {
+                let triple = rest.starts_with(&c.to_string().repeat(3));
+                self.quote = Some((c, triple));
+                if triple {
+                    return (3, false);
+                }
+            }
+            '(' | '[' | '{' => self.depth += 1,
+            ')' | ']' | '}' => self.depth = self.depth.saturating_sub(1),
+            _ => {}
+        }
+        (len, false)
+    }
+}
+
+/// The end of the line starting at or after `index`, excluding the newline.
+fn line_end(text: &str, index: usize) -> usize {
+    text[index..]
+        .find('\n')
+        .map(|offset| index + offset)
+        .unwrap_or(text.len())
+}
+
+/// Whether a comment marks the code it is attached to as maintained by hand.
+pub(crate) fn is_keep_comment(comment: &str) -> bool {
+    let comment = comment.trim_start_matches('#').trim_start();
+    match comment.strip_prefix("keep") {
+        Some(rest) => !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_'),
+        None => false,
+    }
+}
+
+/// Find the comments in a piece of Starlark source which are not part of a string.
+fn comments(text: &str) -> Vec<&str> {
+    let mut scanner = Scanner::default();
+    let mut comments = Vec::new();
+    let mut index = 0;
+    while index < text.len() {
+        let (len, comment) = scanner.advance(text, index);
+        if comment {
+            let end = line_end(text, index);
+            comments.push(&text[index..end]);
+            index = end;
+        } else {
+            index += len;
+        }
+    }
+    comments
+}
+
+/// A top level statement of a BUILD file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Statement {
+    /// Comments and blank lines preceding the statement.
+    pub(crate) leading: String,
+
+    /// The source of the statement, including a comment trailing its last line.
+    pub(crate) text: String,
+}
+
+impl Statement {
+    /// Parse the statement as a function call, e.g. a rule or a `load` statement.
+    pub(crate) fn call(&self) -> Option<Call> {
+        Call::parse(&self.text)
+    }
+
+    /// Whether the statement is marked with a `# keep` comment on the line above it.
+    pub(crate) fn is_kept(&self) -> bool {
+        self.leading
+            .lines()
+            .map(str::trim)
+            .rev()
+            .take_while(|line| line.starts_with('#'))
+            .any(is_keep_comment)
+    }
+}
+
+/// The statements of a BUILD file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct BuildFile {
+    pub(crate) statements: Vec<Statement>,
+
+    /// Comments and blank lines following the last statement.
+    pub(crate) trailing: String,
+}
+
+impl BuildFile {
+    pub(crate) fn parse(text: &str) -> Self {
+        let mut statements = Vec::new();
+        let mut leading = String::new();
+        let mut current: Option<String> = None;
+        let mut scanner = Scanner::default();
+
+        for line in text.split_inclusive('\n') {
+            let statement = match current.as_mut() {
+                Some(statement) => statement,
+                None => {
+                    let trimmed = line.trim();
+                    if trimmed.is_empty() || trimmed.starts_with('#') {
+                        leading.push_str(line);
+                        continue;
+                    }
+                    current.insert(String::new())
+                }
+            };
+
+            let mut index = 0;
+            while index < line.len() {
+                let (len, comment) = scanner.advance(line, index);
+                index = if comment {
+                    line_end(line, index)
+                } else {
+                    index + len
+                };
+            }
+            statement.push_str(line);
+
+            if scanner.at_top_level() {
+                statements.push(Statement {
+                    leading: std::mem::take(&mut leading),
+                    text: current.take().unwrap(),
+                });
+            }
+        }
+
+        // An unterminated statement is preserved as is.
+        if let Some(text) = current {
+            statements.push(Statement {
+                leading: std::mem::take(&mut leading),
+                text,
+            });
+        }
+
+        Self {
+            statements,
+            trailing: leading,
+        }
+    }
+
+    /// Render the BUILD file, ensuring it ends with a single newline.
+    pub(crate) fn render(&self) -> String {
+        let mut content = String::new();
+        for statement in &self.statements {
+            content.push_str(&statement.leading);
+            content.push_str(&statement.text);
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+        }
+        content.push_str(&self.trailing);
+        format!("{}\n", content.trim_end())
+    }
+}
+
+/// An argument of a function call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Argument {
+    /// Comment lines preceding the argument.
+    pub(crate) comments: Vec<String>,
+
+    /// The name of a keyword argument.
+    pub(crate) name: Option<String>,
+
+    /// The source of the argument's value.
+    pub(crate) value: String,
+
+    /// A comment on the line the argument ends on.
+    pub(crate) comment: Option<String>,
+}
+
+impl Argument {
+    pub(crate) fn new(name: Option<&str>, value: &str) -> Self {
+        Self {
+            comments: Vec::new(),
+            name: name.map(str::to_owned),
+            value: value.to_owned(),
+            comment: None,
+        }
+    }
+
+    /// Whether the argument is marked with a `# keep` comment.
+    pub(crate) fn is_kept(&self) -> bool {
+        self.comments.iter().any(|c| is_keep_comment(c))
+            || self.comment.as_deref().is_some_and(is_keep_comment)
+            || comments(self.value.lines().next().unwrap_or_default())
+                .into_iter()
+                .any(is_keep_comment)
+    }
+
+    /// The value of the argument if it is a plain string literal.
+    pub(crate) fn string_value(&self) -> Option<&str> {
+        let value = self.value.trim();
+        ['"', '\''].iter().find_map(|quote| {
+            value
+                .strip_prefix(*quote)?
+                .strip_suffix(*quote)
+                .filter(|inner| !inner.contains(*quote))
+        })
+    }
+
+    fn from_source(comments: Vec<String>, source: &str, comment: Option<String>) -> Self {
+        let source = source.trim();
+        let name = source.split_once('=').and_then(|(name, rest)| {
+            let name = name.trim();
+            let is_identifier = !name.is_empty()
+                && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+                && !rest.starts_with('=');
+            is_identifier.then(|| name.to_owned())
+        });
+        let value = match &name {
+            Some(_) => source.split_once('=').unwrap().1.trim(),
+            None => source,
+        };
+        Self {
+            comments,
+            name,
+            value: value.to_owned(),
+            comment,
+        }
+    }
+}
+
+/// A function call statement such as a rule or macro invocation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Call {
+    /// The name of the called function.
+    pub(crate) function: String,
+
+    /// A comment on the line of the opening parenthesis.
+    pub(crate) comment: Option<String>,
+
+    pub(crate) arguments: Vec<Argument>,
+
+    /// Comment lines following the last argument.
+    pub(crate) closing_comments: Vec<String>,
+
+    /// Whether the call spans multiple lines.
+    pub(crate) multiline: bool,
+
+    /// Anything following the closing parenthesis on the same line.
+    pub(crate) suffix: String,
+}
+
+impl Call {
+    pub(crate) fn new(function: &str, arguments: Vec<Argument>) -> Self {
+        Self {
+            function: function.to_owned(),
+            comment: None,
+            arguments,
+            closing_comments: Vec::new(),
+            multiline: true,
+            suffix: String::new(),
+        }
+    }
+
+    /// Parse a statement of the form `function(arguments...)`.
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let text = text.trim_end();
+        let (function, rest) = text.split_once('(')?;
+        let function = function.trim();
+        if function.is_empty()
+            || !function
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+        {
+            return None;
+        }
+
+        let open = text.len() - rest.len();
+        let mut scanner = Scanner {
+            depth: 1,
+            ..Scanner::default()
+        };
+
+        let mut call = Self {
+            function: function.to_owned(),
+            comment: None,
+            arguments: Vec::new(),
+            closing_comments: Vec::new(),
+            multiline: false,
+            suffix: String::new(),
+        };
+
+        let mut pending_comments = Vec::new();
+        let mut argument_start = open;
+        // The index of the last argument if it ended on the current line.
+        let mut ended_on_line: Option<usize> = None;
+        let mut index = open;
+        let close = loop {
+            if index >= text.len() {
+                return None;
+            }
+            let c = text[index..].chars().next().unwrap();
+            if scanner.depth == 1 && scanner.quote.is_none() {
+                match c {
+                    ')' => break index,
+                    ',' => {
+                        let source = &text[argument_start..index];
+                        call.arguments.push(Argument::from_source(
+                            std::mem::take(&mut pending_comments),
+                            source,
+                            None,
+                        ));
+                        ended_on_line = Some(call.arguments.len() - 1);
+                        argument_start = index + 1;
+                        index += 1;
+                        continue;
+                    }
+                    '\n' => {
+                        call.multiline = true;
+                        ended_on_line = None;
+                    }
+                    _ => {}
+                }
+            }
+
+            let (len, comment) = scanner.advance(text, index);
+            if !comment {
+                index += len;
+                continue;
+            }
+
+            let end = line_end(text, index);
+            if scanner.depth > 1 {
+                // Comments nested in an argument's value are part of the value.
+                index = end;
+                continue;
+            }
+
+            let comment = text[index..end].trim_end().to_owned();
+            let pending = text[argument_start..index].trim();
+            if !pending.is_empty() {
+                // A comment following the last argument which has no trailing comma.
+                call.arguments.push(Argument::from_source(
+                    std::mem::take(&mut pending_comments),
+                    pending,
+                    Some(comment),
+                ));
+                argument_start = end;
+            } else if let Some(last) = ended_on_line {
+                call.arguments[last].comment = Some(comment);
+            } else if !call.multiline && call.arguments.is_empty() {
+                call.comment = Some(comment);
+            } else {
+                pending_comments.push(comment);
+            }
+            argument_start = argument_start.max(end);
+            index = end;
+        };
+
+        let source = text[argument_start..close].trim();
+        if !source.is_empty() {
+            call.arguments.push(Argument::from_source(
+                std::mem::take(&mut pending_comments),
+                source,
+                None,
+            ));
+        }
+        call.closing_comments = pending_comments;
+        call.suffix = text[close + 1..].to_owned();
+
+        Some(call)
+    }
+
+    /// The value of a keyword argument.
+    pub(crate) fn argument(&self, name: &str) -> Option<&Argument> {
+        self.arguments
+            .iter()
+            .find(|arg| arg.name.as_deref() == Some(name))
+    }
+
+    /// The `name` of a rule.
+    pub(crate) fn name(&self) -> Option<&str> {
+        self.argument("name").and_then(Argument::string_value)
+    }
+
+    /// Whether the call is marked with a `# keep` comment on its opening line.
+    pub(crate) fn is_kept(&self) -> bool {
+        self.comment.as_deref().is_some_and(is_keep_comment)
+    }
+
+    pub(crate) fn render(&self) -> String {
+        let multiline = self.multiline
+            || self.comment.is_some()
+            || !self.closing_comments.is_empty()
+            || self
+                .arguments
+                .iter()
+                .any(|arg| !arg.comments.is_empty() || arg.comment.is_some())
+            || self.arguments.iter().any(|arg| arg.value.contains('\n'));
+
+        let mut text = format!("{}(", self.function);
+        if !multiline {
+            let arguments: Vec<String> = self.arguments.iter().map(render_argument).collect();
+            text.push_str(&arguments.join(", "));
+        } else {
+            if let Some(comment) = &self.comment {
+                text.push_str("  ");
+                text.push_str(comment);
+            }
+            text.push('\n');
+            for arg in &self.arguments {
+                for comment in &arg.comments {
+                    text.push_str(&format!("    {}\n", comment));
+                }
+                text.push_str(&format!("    {},", render_argument(arg)));
+                if let Some(comment) = &arg.comment {
+                    text.push_str("  ");
+                    text.push_str(comment);
+                }
+                text.push('\n');
+            }
+            for comment in &self.closing_comments {
+                text.push_str(&format!("    {}\n", comment));
+            }
+        }
+        text.push(')');
+        text.push_str(&self.suffix);
+        text.push('\n');
+        text
+    }
+}
+
+fn render_argument(arg: &Argument) -> String {
+    match &arg.name {
+        Some(name) => format!("{} = {}", name, arg.value),
+        None => arg.value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BUILD_FILE: &str = r##"# A header comment
+
+load("@rules_rust//rust:defs.bzl", "rust_library")
+
+# The library
+rust_library(
+    name = "lib",  # a comment
+    srcs = [
+        "lib.rs",  # not a keep comment for srcs
+        "#.rs",
+    ],
+    # keep
+    deps = [":dep"],
+    tags = ["a", "b"]
+)
+
+exports_files(["a.txt"])  # trailing
+# The end
+"##;
+
+    #[test]
+    fn parse_statements() {
+        let build_file = BuildFile::parse(BUILD_FILE);
+
+        assert_eq!(build_file.statements.len(), 3);
+        assert_eq!(build_file.statements[0].leading, "# A header comment\n\n");
+        assert_eq!(
+            build_file.statements[1].leading,
+            "\n# The library\n".to_owned()
+        );
+        assert_eq!(
+            build_file.statements[2].text,
+            "exports_files([\"a.txt\"])  # trailing\n"
+        );
+        assert_eq!(build_file.trailing, "# The end\n");
+        assert_eq!(build_file.render(), BUILD_FILE);
+    }
+
+    #[test]
+    fn parse_call() {
+        let build_file = BuildFile::parse(BUILD_FILE);
+        let call = build_file.statements[1].call().unwrap();
+
+        assert_eq!(call.function, "rust_library");
+        assert_eq!(call.name(), Some("lib"));
+        assert!(call.multiline);
+
+        let names: Vec<_> = call
+            .arguments
+            .iter()
+            .map(|arg| arg.name.as_deref().unwrap())
+            .collect();
+        assert_eq!(names, ["name", "srcs", "deps", "tags"]);
+
+        assert_eq!(call.arguments[0].comment.as_deref(), Some("# a comment"));
+        assert_eq!(
+            call.arguments[1].value,
+            "[\n        \"lib.rs\",  # not a keep comment for srcs\n        \"#.rs\",\n    ]"
+        );
+        assert!(!call.arguments[1].is_kept());
+        assert_eq!(call.arguments[2].comments, ["# keep"]);
+        assert!(call.arguments[2].is_kept());
+        assert_eq!(call.arguments[3].value, "[\"a\", \"b\"]");
+
+        let load = build_file.statements[0].call().unwrap();
+        assert!(!load.multiline);
+        let values: Vec<_> = load
+            .arguments
+            .iter()
+            .map(|arg| arg.string_value().unwrap())
+            .collect();
+        assert_eq!(values, ["@rules_rust//rust:defs.bzl", "rust_library"]);
+        assert_eq!(
+            load.render(),
+            "load(\"@rules_rust//rust:defs.bzl\", \"rust_library\")\n"
+        );
+    }
+
+    #[test]
+    fn render_call() {
+        let build_file = BuildFile::parse(BUILD_FILE);
+        let call = build_file.statements[1].call().unwrap();
+
+        assert_eq!(
+            call.render(),
+            r##"rust_library(
+    name = "lib",  # a comment
+    srcs = [
+        "lib.rs",  # not a keep comment for srcs
+        "#.rs",
+    ],
+    # keep
+    deps = [":dep"],
+    tags = ["a", "b"],
+)
+"##
+        );
+    }
+
+    #[test]
+    fn keep_markers() {
+        let build_file = BuildFile::parse(
+            r##"
+# keep
+rust_library(name = "a")
+
+rust_library(  # keep: maintained by hand
+    name = "b",
+    deps = [  # keep
+        ":c",
+    ],
+)
+
+rust_library(
+    name = "c",  # keeper
+)
+"##,
+        );
+
+        assert!(build_file.statements[0].is_kept());
+
+        let call = build_file.statements[1].call().unwrap();
+        assert!(!build_file.statements[1].is_kept());
+        assert!(call.is_kept());
+        assert!(call.argument("deps").unwrap().is_kept());
+
+        let call = build_file.statements[2].call().unwrap();
+        assert!(!call.is_kept());
+        assert!(!call.argument("name").unwrap().is_kept());
+    }
+
+    #[test]
+    fn non_call_statements() {
+        let build_file = BuildFile::parse("X = {\n    \"a\": 1,\n}\n\nfoo(x = X)\n");
+
+        assert_eq!(build_file.statements.len(), 2);
+        assert_eq!(build_file.statements[0].call(), None);
+        assert_eq!(
+            build_file.statements[1].call().unwrap().argument("x"),
+            Some(&Argument::new(Some("x"), "X"))
+        );
+    }
+}
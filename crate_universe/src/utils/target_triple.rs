@@ -1,8 +1,13 @@
 use std::{
-    borrow::Borrow,
+    borrow::{Borrow, Cow},
+    collections::BTreeMap,
     fmt::{Display, Formatter, Result},
 };
 
+use cfg_expr::targets::{
+    get_builtin_target_by_triple, Abi, Arch, Endian, Env, Families, Family, HasAtomic, HasAtomics,
+    Os, Panic, TargetInfo, Triple, Vendor,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -26,6 +31,21 @@ impl TargetTriple {
         // targets, etc.
         self.0.replace("nixos", "linux")
     }
+
+    /// Get the information used to evaluate `cfg` expressions for this target, either
+    /// from the custom targets or from the targets built into `rustc`.
+    pub(crate) fn target_info(
+        &self,
+        custom_targets: &BTreeMap<TargetTriple, TargetSpec>,
+    ) -> Option<Cow<'static, TargetInfo>> {
+        match custom_targets.get(self) {
+            Some(spec) => Some(Cow::Owned(TargetInfo {
+                triple: Triple::new(self.to_cargo()),
+                ..spec.info.clone()
+            })),
+            None => get_builtin_target_by_triple(&self.to_cargo()).map(Cow::Borrowed),
+        }
+    }
 }
 
 impl Borrow<String> for TargetTriple {
@@ -44,3 +64,227 @@ impl Display for TargetTriple {
         }
     }
 }
+
+/// A custom target defined by a `rustc` [target specification][spec] JSON file.
+/// Only the values needed to evaluate `cfg` expressions are retained.
+///
+/// [spec]: https://doc.rust-lang.org/rustc/targets/custom.html
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "TargetSpecJson", into = "TargetSpecJson")]
+pub(crate) struct TargetSpec {
+    json: TargetSpecJson,
+    info: TargetInfo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct TargetSpecJson {
+    llvm_target: String,
+    arch: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    os: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    env: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    abi: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    vendor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target_family: Option<TargetFamily>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target_endian: Option<String>,
+    target_pointer_width: PointerWidth,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_atomic_width: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min_atomic_width: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    panic_strategy: Option<String>,
+}
+
+/// Older target specifications provide the pointer width as a string.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum PointerWidth {
+    Integer(u8),
+    String(String),
+}
+
+/// Target specifications may provide a single family as a string instead of a list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum TargetFamily {
+    List(Vec<String>),
+    String(String),
+}
+
+impl TryFrom<TargetSpecJson> for TargetSpec {
+    type Error = anyhow::Error;
+
+    fn try_from(json: TargetSpecJson) -> anyhow::Result<Self> {
+        let pointer_width = match &json.target_pointer_width {
+            PointerWidth::Integer(width) => *width,
+            PointerWidth::String(width) => width
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid `target-pointer-width`: {}", width))?,
+        };
+
+        let endian = match json.target_endian.as_deref() {
+            Some(endian) => endian
+                .parse::<Endian>()
+                .map_err(|_| anyhow::anyhow!("Invalid `target-endian`: {}", endian))?,
+            None => Endian::little,
+        };
+
+        // `rustc` enables atomics for every width between the min and max, which
+        // defaults to the width of a pointer.
+        let max_atomic_width = json.max_atomic_width.unwrap_or(pointer_width.into());
+        let min_atomic_width = json.min_atomic_width.unwrap_or(8);
+        let atomic_widths = min_atomic_width..=max_atomic_width;
+        let has_atomics = [8, 16, 32, 64, 128]
+            .into_iter()
+            .filter(|width| atomic_widths.contains(width))
+            .map(HasAtomic::IntegerSize)
+            .chain(
+                atomic_widths
+                    .contains(&pointer_width.into())
+                    .then_some(HasAtomic::Pointer),
+            );
+
+        // Empty values and an `os` of `none` are equivalent to the value being unset.
+        let non_empty = |value: &Option<String>| value.clone().filter(|v| !v.is_empty());
+
+        let info = TargetInfo {
+            triple: Triple::new(json.llvm_target.clone()),
+            os: non_empty(&json.os).filter(|os| os != "none").map(Os::new),
+            abi: non_empty(&json.abi).map(Abi::new),
+            arch: Arch::new(json.arch.clone()),
+            env: non_empty(&json.env).map(Env::new),
+            vendor: Some(Vendor::new(
+                non_empty(&json.vendor).unwrap_or_else(|| "unknown".to_owned()),
+            )),
+            families: Families::new(
+                match &json.target_family {
+                    Some(TargetFamily::List(families)) => families.clone(),
+                    Some(TargetFamily::String(family)) => vec![family.clone()],
+                    None => Vec::new(),
+                }
+                .into_iter()
+                .map(Family::new),
+            ),
+            pointer_width,
+            endian,
+            has_atomics: HasAtomics::new(has_atomics),
+            panic: Panic::new(
+                non_empty(&json.panic_strategy).unwrap_or_else(|| "unwind".to_owned()),
+            ),
+        };
+
+        Ok(Self { json, info })
+    }
+}
+
+impl From<TargetSpec> for TargetSpecJson {
+    fn from(value: TargetSpec) -> Self {
+        value.json
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cfg_expr::Expression;
+
+    use super::*;
+
+    #[test]
+    fn custom_target_info() {
+        let spec: TargetSpec = serde_json::from_value(serde_json::json!({
+            "llvm-target": "thumbv7em-none-eabihf",
+            "arch": "arm",
+            "abi": "eabihf",
+            "data-layout": "e-m:e-p:32:32-Fi8-i64:64-v128:64:128-a:0:32-n32-S64",
+            "target-pointer-width": "32",
+            "max-atomic-width": 32,
+            "panic-strategy": "abort",
+            "linker-flavor": "gnu-lld",
+        }))
+        .unwrap();
+
+        let triple = TargetTriple::from_bazel("thumbv7em-custom-none".to_owned());
+        let custom_targets = BTreeMap::from([(triple.clone(), spec)]);
+        let info = triple.target_info(&custom_targets).unwrap();
+
+        assert_eq!(info.triple.as_str(), "thumbv7em-custom-none");
+        assert_eq!(info.pointer_width, 32);
+        assert!(info.os.is_none());
+
+        let eval = |cfg: &str| {
+            Expression::parse(cfg).unwrap().eval(|pred| match pred {
+                cfg_expr::Predicate::Target(tp) => {
+                    cfg_expr::expr::TargetMatcher::matches(info.as_ref(), tp)
+                }
+                _ => false,
+            })
+        };
+        assert!(eval(
+            r#"cfg(all(target_arch = "arm", target_abi = "eabihf"))"#
+        ));
+        assert!(eval(
+            r#"cfg(all(target_has_atomic = "32", target_has_atomic = "ptr"))"#
+        ));
+        assert!(!eval(r#"cfg(target_has_atomic = "64")"#));
+        assert!(eval(r#"cfg(panic = "abort")"#));
+        assert!(!eval("cfg(unix)"));
+
+        // Builtin targets are still available.
+        assert!(
+            TargetTriple::from_bazel("x86_64-unknown-linux-gnu".to_owned())
+                .target_info(&custom_targets)
+                .is_some()
+        );
+        assert!(TargetTriple::from_bazel("unknown-target".to_owned())
+            .target_info(&custom_targets)
+            .is_none());
+    }
+
+    #[test]
+    fn target_family_string_or_list() {
+        let families = |target_family: serde_json::Value| {
+            let spec: TargetSpec = serde_json::from_value(serde_json::json!({
+                "llvm-target": "x86_64-unknown-linux-gnu",
+                "arch": "x86_64",
+                "os": "linux",
+                "target-pointer-width": "64",
+                "target-family": target_family,
+            }))
+            .unwrap();
+
+            let triple = TargetTriple::from_bazel("x86_64-custom-linux".to_owned());
+            let custom_targets = BTreeMap::from([(triple.clone(), spec)]);
+            let info = triple.target_info(&custom_targets).unwrap();
+            info.families
+                .iter()
+                .map(|family| family.as_str().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(families(serde_json::json!("unix")), vec!["unix"]);
+        assert_eq!(
+            families(serde_json::json!(["unix", "wasm"])),
+            vec!["unix", "wasm"]
+        );
+    }
+
+    #[test]
+    fn invalid_target_spec() {
+        let err = serde_json::from_value::<TargetSpec>(serde_json::json!({
+            "llvm-target": "x86_64-unknown-none",
+            "arch": "x86_64",
+            "target-pointer-width": "64",
+            "target-endian": "middle",
+        }))
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("Invalid `target-endian`: middle"), "{err}");
+    }
+}
@@ -0,0 +1,513 @@
+//! A checker for links in a book rendered by `mdbook`.
+//!
+//! Internal links and anchors are verified against the rendered HTML files. External links
+//! are only checked when an allowlist of URL prefixes is provided and the network is never
+//! accessed. The checker can additionally extract the book's search index.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsStr;
+use std::fmt::Write as _;
+use std::path::{Component, Path, PathBuf};
+use std::{env, fs};
+
+/// Pages which are not checked as their links duplicate or differ from those of other pages.
+const SKIPPED_PAGES: [&str; 2] = ["print.html", "404.html"];
+
+struct Args {
+    pub book_dir: PathBuf,
+
+    pub report: Option<PathBuf>,
+
+    pub allowlist: Option<PathBuf>,
+
+    pub search_index: Option<PathBuf>,
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        let mut book_dir: Option<PathBuf> = None;
+        let mut report: Option<PathBuf> = None;
+        let mut allowlist: Option<PathBuf> = None;
+        let mut search_index: Option<PathBuf> = None;
+
+        for arg in env::args().skip(1) {
+            let (flag, val) = arg
+                .split_once('=')
+                .unwrap_or_else(|| panic!("Unexpected argument `{}`", arg));
+            match flag {
+                "--book_dir" => book_dir = Some(PathBuf::from(val)),
+                "--report" => report = Some(PathBuf::from(val)),
+                "--allowlist" => allowlist = Some(PathBuf::from(val)),
+                "--search_index" => search_index = Some(PathBuf::from(val)),
+                _ => panic!("Unexpected argument `{}`", arg),
+            }
+        }
+
+        Self {
+            book_dir: book_dir.expect("Missing required argument `--book_dir`"),
+            report,
+            allowlist,
+            search_index,
+        }
+    }
+}
+
+/// The links and anchors of a rendered page.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Page {
+    /// The values of all `id` and `name` attributes.
+    pub anchors: BTreeSet<String>,
+
+    /// The values of all `href` and `src` attributes.
+    pub links: Vec<String>,
+}
+
+/// Replace the character references mdbook emits in attribute values.
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Decode `%XX` escape sequences in a URL component.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let byte = std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if let Some(byte) = byte {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Extract anchors and links from the attributes of HTML tags.
+fn parse_page(html: &str) -> Page {
+    let mut page = Page::default();
+
+    // Only the attributes of tags are inspected. Text, comments and scripts are skipped.
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map(|end| &rest[end + 3..]).unwrap_or("");
+            continue;
+        }
+
+        let end = rest.find('>').unwrap_or(rest.len());
+        let tag = &rest[1..end];
+        rest = &rest[end..];
+
+        if tag.starts_with("script") && !tag.ends_with('/') {
+            rest = rest.find("</script").map(|end| &rest[end..]).unwrap_or("");
+        }
+
+        for (name, value) in parse_attributes(tag) {
+            match name.as_str() {
+                "id" | "name" if !tag.starts_with("meta") => {
+                    page.anchors.insert(value);
+                }
+                "href" | "src" => page.links.push(value),
+                _ => {}
+            }
+        }
+    }
+
+    page
+}
+
+/// Parse the attributes of a tag, e.g. `a href="x.html" class=link hidden`.
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut chars = tag
+        .trim_start_matches(|c: char| !c.is_whitespace())
+        .chars()
+        .peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace() || *c == '/').is_some() {}
+
+        let mut name = String::new();
+        while let Some(c) = chars.next_if(|c| *c != '=' && *c != '/' && !c.is_whitespace()) {
+            name.push(c);
+        }
+        if name.is_empty() {
+            break;
+        }
+
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        // Attributes without values, e.g. `hidden`, are skipped.
+        if chars.next_if_eq(&'=').is_none() {
+            continue;
+        }
+
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let value: String = match chars.next_if(|c| *c == '"' || *c == '\'') {
+            Some(quote) => chars.by_ref().take_while(|c| *c != quote).collect(),
+            None => chars.by_ref().take_while(|c| !c.is_whitespace()).collect(),
+        };
+
+        attributes.push((name.to_lowercase(), decode_entities(&value)));
+    }
+
+    attributes
+}
+
+/// Load the URL prefixes of an external link allowlist. Empty lines and `#` comments are ignored.
+fn parse_allowlist(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.split_once('#').map_or(line, |(line, _)| line).trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Normalize a path within the book without accessing the filesystem.
+///
+/// Returns `None` if the path leaves the book.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::ParentDir => {
+                if normalized.as_os_str().is_empty() {
+                    return None;
+                }
+                normalized.pop();
+            }
+            _ => {}
+        }
+    }
+    Some(normalized)
+}
+
+/// Check a single link of a page and return a description of the problem if it is broken.
+fn check_link(
+    page_path: &Path,
+    link: &str,
+    files: &BTreeSet<PathBuf>,
+    pages: &BTreeMap<PathBuf, Page>,
+    allowlist: Option<&[String]>,
+) -> Option<String> {
+    if link.starts_with("http://") || link.starts_with("https://") || link.starts_with("//") {
+        let allowlist = allowlist?;
+        if allowlist.iter().any(|prefix| link.starts_with(prefix)) {
+            return None;
+        }
+        return Some("external link is not in the allowlist".to_owned());
+    }
+
+    // Other schemes such as `mailto:` or `javascript:` are not checked.
+    if let Some((scheme, _)) = link.split_once(':') {
+        if !scheme.contains('/') {
+            return None;
+        }
+    }
+
+    let (path, fragment) = match link.split_once('#') {
+        Some((path, fragment)) => (path, Some(percent_decode(fragment))),
+        None => (link, None),
+    };
+    let path = path.split_once('?').map_or(path, |(path, _)| path);
+    let path = percent_decode(path);
+
+    let target = if path.is_empty() {
+        page_path.to_path_buf()
+    } else {
+        let joined = match path.strip_prefix('/') {
+            Some(path) => PathBuf::from(path),
+            None => page_path.parent().unwrap_or(Path::new("")).join(&path),
+        };
+        let target = match normalize(&joined) {
+            Some(target) => target,
+            None => return Some("target is outside of the book".to_owned()),
+        };
+        if files.contains(&target) {
+            target
+        } else if files.contains(&target.join("index.html")) {
+            target.join("index.html")
+        } else {
+            return Some("target does not exist".to_owned());
+        }
+    };
+
+    match (fragment, pages.get(&target)) {
+        (Some(fragment), Some(page)) if !fragment.is_empty() => {
+            if page.anchors.contains(&fragment) {
+                None
+            } else {
+                Some(format!("anchor `{}` does not exist", fragment))
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Recursively collect all files in a directory relative to the root of the book.
+fn collect_files(root: &Path, dir: &Path, files: &mut BTreeSet<PathBuf>) {
+    let entries = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("Failed to read directory: {}\n{:?}", dir.display(), e));
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_files(root, &path, files);
+        } else {
+            files.insert(path.strip_prefix(root).unwrap().to_path_buf());
+        }
+    }
+}
+
+/// Check all pages of a book and return a list of `(page, link, problem)`.
+fn check_book(
+    files: &BTreeSet<PathBuf>,
+    pages: &BTreeMap<PathBuf, Page>,
+    allowlist: Option<&[String]>,
+) -> Vec<(PathBuf, String, String)> {
+    let mut problems = Vec::new();
+    for (page_path, page) in pages.iter() {
+        if matches!(page_path.to_str(), Some(p) if SKIPPED_PAGES.contains(&p)) {
+            continue;
+        }
+
+        let mut checked = BTreeSet::new();
+        for link in page.links.iter() {
+            if !checked.insert(link) {
+                continue;
+            }
+            if let Some(problem) = check_link(page_path, link, files, pages, allowlist) {
+                problems.push((page_path.clone(), link.clone(), problem));
+            }
+        }
+    }
+    problems
+}
+
+/// Locate the search index of a rendered book and return its JSON content.
+///
+/// Depending on the version of mdbook, the index is written as `searchindex.json` or only as a
+/// script (`searchindex.js`, `searchindex-<hash>.js`) which assigns the index to `window.search`.
+fn extract_search_index(book_dir: &Path, files: &BTreeSet<PathBuf>) -> Result<String, String> {
+    let json = book_dir.join("searchindex.json");
+    if json.exists() {
+        return fs::read_to_string(&json)
+            .map_err(|e| format!("Failed to read {}: {:?}", json.display(), e));
+    }
+
+    let script = files
+        .iter()
+        .find(|file| {
+            file.parent() == Some(Path::new(""))
+                && matches!(file.to_str(), Some(f) if f.starts_with("searchindex") && f.ends_with(".js"))
+        })
+        .ok_or_else(|| {
+            "The book does not contain a search index. Is `output.html.search` disabled?".to_owned()
+        })?;
+
+    let content = fs::read_to_string(book_dir.join(script))
+        .map_err(|e| format!("Failed to read {}: {:?}", script.display(), e))?;
+    match (content.find('{'), content.rfind('}')) {
+        (Some(start), Some(end)) if start < end => Ok(content[start..=end].to_owned()),
+        _ => Err(format!(
+            "Failed to locate the search index in {}",
+            script.display()
+        )),
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut files = BTreeSet::new();
+    collect_files(&args.book_dir, &args.book_dir, &mut files);
+
+    if let Some(search_index) = &args.search_index {
+        let index = extract_search_index(&args.book_dir, &files).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        fs::write(search_index, index).unwrap_or_else(|e| {
+            panic!(
+                "Failed to write search index: {}\n{:?}",
+                search_index.display(),
+                e
+            )
+        });
+    }
+
+    let report = match &args.report {
+        Some(report) => report,
+        None => return,
+    };
+
+    let allowlist =
+        args.allowlist.as_ref().map(|path| {
+            parse_allowlist(&fs::read_to_string(path).unwrap_or_else(|e| {
+                panic!("Failed to read allowlist: {}\n{:?}", path.display(), e)
+            }))
+        });
+
+    let pages: BTreeMap<PathBuf, Page> = files
+        .iter()
+        .filter(|file| file.extension() == Some(OsStr::new("html")))
+        .map(|file| {
+            let html = fs::read_to_string(args.book_dir.join(file))
+                .unwrap_or_else(|e| panic!("Failed to read: {}\n{:?}", file.display(), e));
+            (file.clone(), parse_page(&html))
+        })
+        .collect();
+
+    let problems = check_book(&files, &pages, allowlist.as_deref());
+
+    let mut content = String::new();
+    for (page, link, problem) in problems.iter() {
+        writeln!(content, "{}: `{}`: {}", page.display(), link, problem).unwrap();
+    }
+    writeln!(
+        content,
+        "Found {} broken links in {} pages.",
+        problems.len(),
+        pages.len()
+    )
+    .unwrap();
+
+    fs::write(report, &content)
+        .unwrap_or_else(|e| panic!("Failed to write report: {}\n{:?}", report.display(), e));
+
+    if !problems.is_empty() {
+        eprint!("{}", content);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn book() -> (BTreeSet<PathBuf>, BTreeMap<PathBuf, Page>) {
+        let index = r##"<!DOCTYPE html>
+<html><head><meta name="description" content="x"><link rel="stylesheet" href="css/general.css">
+<script>var x = "<a href='missing.html'>";</script></head>
+<body><h1 id="intro"><a class="header" href="#intro">Intro</a></h1>
+<!-- <a href="commented.html"> -->
+<a href="guide/setup.html#install-%26-run">Setup</a>
+<a href="guide/">Guide</a>
+<a href="guide/setup.html#missing">Bad anchor</a>
+<a href=missing.html>Bad page</a>
+<a href="../outside.html">Outside</a>
+<a href="mailto:me@example.com">Mail</a>
+<a href="https://github.com/bazelbuild/rules_rust">Repo</a>
+<a href="https://example.com/">Example</a>
+</body></html>"##;
+        let setup =
+            r#"<h2 id="install-&amp;-run">Install</h2><a href="../index.html#intro">Back</a>"#;
+
+        let files: BTreeSet<PathBuf> = [
+            "index.html",
+            "css/general.css",
+            "guide/index.html",
+            "guide/setup.html",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        let pages = BTreeMap::from([
+            (PathBuf::from("index.html"), parse_page(index)),
+            (PathBuf::from("guide/index.html"), Page::default()),
+            (PathBuf::from("guide/setup.html"), parse_page(setup)),
+        ]);
+        (files, pages)
+    }
+
+    #[test]
+    fn parse_page_test() {
+        let (_, pages) = book();
+        let page = &pages[Path::new("index.html")];
+
+        assert_eq!(page.anchors, BTreeSet::from(["intro".to_owned()]));
+        assert_eq!(
+            page.links,
+            vec![
+                "css/general.css",
+                "#intro",
+                "guide/setup.html#install-%26-run",
+                "guide/",
+                "guide/setup.html#missing",
+                "missing.html",
+                "../outside.html",
+                "mailto:me@example.com",
+                "https://github.com/bazelbuild/rules_rust",
+                "https://example.com/",
+            ]
+        );
+    }
+
+    #[test]
+    fn check_book_test() {
+        let (files, pages) = book();
+
+        assert_eq!(
+            check_book(&files, &pages, None),
+            vec![
+                (
+                    PathBuf::from("index.html"),
+                    "guide/setup.html#missing".to_owned(),
+                    "anchor `missing` does not exist".to_owned(),
+                ),
+                (
+                    PathBuf::from("index.html"),
+                    "missing.html".to_owned(),
+                    "target does not exist".to_owned(),
+                ),
+                (
+                    PathBuf::from("index.html"),
+                    "../outside.html".to_owned(),
+                    "target is outside of the book".to_owned(),
+                ),
+            ]
+        );
+
+        let allowlist =
+            parse_allowlist("# Allowed sites\nhttps://github.com/bazelbuild/ # rules\n");
+        assert_eq!(allowlist, vec!["https://github.com/bazelbuild/"]);
+
+        let problems = check_book(&files, &pages, Some(&allowlist));
+        assert_eq!(problems.len(), 4);
+        assert_eq!(problems[3].1, "https://example.com/");
+        assert_eq!(problems[3].2, "external link is not in the allowlist");
+    }
+
+    #[test]
+    fn parse_attributes_test() {
+        assert_eq!(
+            parse_attributes(r#"a hidden class = link href='a b.html' id="x&amp;y"/"#),
+            vec![
+                ("class".to_owned(), "link".to_owned()),
+                ("href".to_owned(), "a b.html".to_owned()),
+                ("id".to_owned(), "x&y".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn percent_decode_test() {
+        assert_eq!(percent_decode("a%20b%2"), "a b%2");
+        assert_eq!(percent_decode("caf%C3%A9"), "café");
+    }
+}
@@ -50,10 +50,48 @@ def _mdbook_impl(ctx):
         toolchain = "@rules_rust_mdbook//:toolchain_type",
     )
 
+    # The search index is only extracted when the `search_index` output group is requested.
+    search_index = ctx.actions.declare_file("{}.searchindex.json".format(ctx.label.name))
+    search_index_args = ctx.actions.args()
+    search_index_args.add("--book_dir={}".format(output.path))
+    search_index_args.add("--search_index={}".format(search_index.path))
+    ctx.actions.run(
+        mnemonic = "MdBookSearchIndex",
+        executable = ctx.executable._link_checker,
+        outputs = [search_index],
+        arguments = [search_index_args],
+        inputs = [output],
+    )
+
+    output_groups = {"search_index": depset([search_index])}
+
+    if ctx.attr.check_links:
+        report = ctx.actions.declare_file("{}.link_check_report.txt".format(ctx.label.name))
+        check_inputs = [output]
+        check_args = ctx.actions.args()
+        check_args.add("--book_dir={}".format(output.path))
+        check_args.add("--report={}".format(report.path))
+        if ctx.file.external_links_allowlist:
+            check_args.add("--allowlist={}".format(ctx.file.external_links_allowlist.path))
+            check_inputs.append(ctx.file.external_links_allowlist)
+
+        ctx.actions.run(
+            mnemonic = "MdBookLinkCheck",
+            executable = ctx.executable._link_checker,
+            outputs = [report],
+            arguments = [check_args],
+            inputs = check_inputs,
+        )
+
+        output_groups["_validation"] = depset([report])
+    elif ctx.file.external_links_allowlist:
+        fail("`external_links_allowlist` requires `check_links = True` for {}".format(ctx.label))
+
     return [
         DefaultInfo(
             files = depset([output]),
         ),
+        OutputGroupInfo(**output_groups),
         MdBookInfo(
             srcs = depset(ctx.files.srcs),
             config = ctx.file.book,
@@ -63,13 +101,33 @@ def _mdbook_impl(ctx):
 
 mdbook = rule(
     implementation = _mdbook_impl,
-    doc = "Rules to create book from markdown files using `mdBook`.",
+    doc = """\
+Rules to create book from markdown files using `mdBook`.
+
+The book's search index can be built as a separate artifact by requesting the `search_index`
+output group, e.g. `bazel build //:book --output_groups=search_index`.
+""",
     attrs = {
         "book": attr.label(
             doc = "The `book.toml` file.",
             allow_single_file = ["book.toml"],
             mandatory = True,
         ),
+        "check_links": attr.bool(
+            doc = (
+                "Whether to verify internal links and anchors of the rendered book. Broken links " +
+                "fail the build with a report. External links are only checked if " +
+                "`external_links_allowlist` is set and are never fetched."
+            ),
+            default = False,
+        ),
+        "external_links_allowlist": attr.label(
+            doc = (
+                "A file of URL prefixes, one per line, which external links must start with. " +
+                "Empty lines and `#` comments are ignored. Requires `check_links`."
+            ),
+            allow_single_file = True,
+        ),
         "plugins": attr.label_list(
             doc = (
                 "Executables to inject into `PATH` for use in " +
@@ -82,6 +140,11 @@ mdbook = rule(
             doc = "All inputs to the book.",
             allow_files = True,
         ),
+        "_link_checker": attr.label(
+            cfg = "exec",
+            executable = True,
+            default = Label("//private:link_checker"),
+        ),
         "_process_wrapper": attr.label(
             cfg = "exec",
             executable = True,
@@ -137,6 +137,34 @@ Lastly, you must register the toolchain in your `WORKSPACE` file. For example:
 register_toolchains("//toolchains:prost_toolchain")
 ```
 
+#### <a name="pbjson">Generating canonical JSON with `pbjson`
+
+Setting the `pbjson_runtime` attribute of `rust_prost_toolchain` generates [canonical protobuf JSON][json]
+`serde` implementations for all messages and enums using [`pbjson-build`]. The runtime must provide the
+[`pbjson`] and [`serde`] crates. Types from other `rust_prost_library` targets are resolved through their
+crates so those targets must be generated with the same toolchain.
+
+```python
+rust_library_group(
+    name = "pbjson_runtime",
+    deps = [
+        "@crates_io//:pbjson",
+        "@crates_io//:serde",
+    ],
+)
+
+rust_prost_toolchain(
+    name = "prost_toolchain_impl",
+    pbjson_runtime = ":pbjson_runtime",
+    # ...
+)
+```
+
+[json]: https://protobuf.dev/programming-guides/proto3/#json
+[`pbjson`]: https://crates.io/crates/pbjson
+[`pbjson-build`]: https://crates.io/crates/pbjson-build
+[`serde`]: https://crates.io/crates/serde
+
 ---
 ---
 """
@@ -428,6 +428,7 @@ _NORMAL_DEPENDENCIES = {
         _REQUIRED_FEATURE: {
             _COMMON_CONDITION: {
                 "h2": Label("@rrprd//:h2-0.4.6"),
+                "pbjson-build": Label("@rrprd//:pbjson-build-0.7.0"),
                 "prost": Label("@rrprd//:prost-0.13.1"),
                 "prost-types": Label("@rrprd//:prost-types-0.13.1"),
                 "protoc-gen-prost": Label("@rrprd//:protoc-gen-prost-0.4.0"),
@@ -1101,6 +1102,16 @@ def crate_repositories():
         build_file = Label("//private/3rdparty/crates:BUILD.parking_lot_core-0.9.10.bazel"),
     )
 
+    maybe(
+        http_archive,
+        name = "rrprd__pbjson-build-0.7.0",
+        sha256 = "6eea3058763d6e656105d1403cb04e0a41b7bbac6362d413e7c33be0c32279c9",
+        type = "tar.gz",
+        urls = ["https://static.crates.io/crates/pbjson-build/0.7.0/download"],
+        strip_prefix = "pbjson-build-0.7.0",
+        build_file = Label("//private/3rdparty/crates:BUILD.pbjson-build-0.7.0.bazel"),
+    )
+
     maybe(
         http_archive,
         name = "rrprd__percent-encoding-2.3.1",
@@ -1683,6 +1694,7 @@ def crate_repositories():
 
     return [
         struct(repo = "rrprd__h2-0.4.6", is_dev_dep = False),
+        struct(repo = "rrprd__pbjson-build-0.7.0", is_dev_dep = False),
         struct(repo = "rrprd__prost-0.13.1", is_dev_dep = False),
         struct(repo = "rrprd__prost-types-0.13.1", is_dev_dep = False),
         struct(repo = "rrprd__protoc-gen-prost-0.4.0", is_dev_dep = False),
@@ -70,10 +70,22 @@ def _compile_proto(
 
     tonic_opts = []
     prost_opts = []
+    path_opts = []
     additional_srcs = []
+    file_descriptor_set = False
+    reflect = False
     for transform_info in transform_infos:
+        file_descriptor_set = file_descriptor_set or transform_info.file_descriptor_set
+        reflect = reflect or transform_info.reflect
         tonic_opts.extend(transform_info.tonic_opts)
         prost_opts.extend(transform_info.prost_opts)
+        for path, attributes in transform_info.type_attributes.items():
+            path_opts.extend(["--type_attribute={}={}".format(path, attribute) for attribute in attributes])
+        for path, attributes in transform_info.field_attributes.items():
+            path_opts.extend(["--field_attribute={}={}".format(path, attribute) for attribute in attributes])
+        path_opts.extend(["--bytes={}".format(path) for path in transform_info.bytes])
+        path_opts.extend(["--btree_map={}".format(path) for path in transform_info.btree_map])
+        path_opts.extend(["--boxed={}".format(path) for path in transform_info.boxed])
         additional_srcs.append(transform_info.srcs)
 
     all_additional_srcs = depset(transitive = additional_srcs)
@@ -93,6 +105,7 @@ def _compile_proto(
     additional_args.add("--descriptor_set={}".format(proto_info.direct_descriptor_set.path))
     additional_args.add("--additional_srcs={}".format(",".join([f.path for f in all_additional_srcs.to_list()])))
     additional_args.add_all(prost_toolchain.prost_opts + prost_opts, format_each = "--prost_opt=%s")
+    additional_args.add_all(path_opts)
 
     if prost_toolchain.tonic_plugin:
         tonic_plugin = prost_toolchain.tonic_plugin[DefaultInfo].files_to_run
@@ -103,13 +116,26 @@ def _compile_proto(
         additional_args.add_all(prost_toolchain.tonic_opts + tonic_opts, format_each = "--tonic_opt=%s")
         tools = depset([tonic_plugin.executable], transitive = [tools])
 
+    if prost_toolchain.pbjson_runtime:
+        additional_args.add("--is_pbjson")
+        additional_args.add_all(prost_toolchain.pbjson_opts, format_each = "--pbjson_opt=%s")
+    if file_descriptor_set:
+        additional_args.add("--is_file_descriptor_set")
+    if reflect:
+        additional_args.add("--is_reflect")
+
+    transitive_descriptor_sets = depset()
+    if prost_toolchain.pbjson_runtime or file_descriptor_set or reflect:
+        transitive_descriptor_sets = proto_info.transitive_descriptor_sets
+        additional_args.add_all(transitive_descriptor_sets, format_each = "--transitive_descriptor_set=%s")
+
     if rustfmt_toolchain:
         additional_args.add("--rustfmt={}".format(rustfmt_toolchain.rustfmt.path))
         tools = depset(transitive = [tools, rustfmt_toolchain.all_files])
 
     additional_inputs = depset(
         [deps_info_file, proto_info.direct_descriptor_set] + [dep[ProstProtoInfo].package_info for dep in deps],
-        transitive = [all_additional_srcs],
+        transitive = [all_additional_srcs, transitive_descriptor_sets],
     )
 
     proto_common.compile(
@@ -246,7 +272,7 @@ def _rust_prost_aspect_impl(target, ctx):
     rustfmt_toolchain = ctx.toolchains["@rules_rust//rust/rustfmt:toolchain_type"]
     prost_toolchain = ctx.toolchains[TOOLCHAIN_TYPE]
     rust_analyzer_deps = []
-    runtimes = [prost_toolchain.prost_runtime, prost_toolchain.tonic_runtime]
+    runtimes = [prost_toolchain.prost_runtime, prost_toolchain.tonic_runtime, prost_toolchain.pbjson_runtime]
     if not prost_toolchain.compile_well_known_types:
         runtimes.append(prost_toolchain.prost_types)
     for prost_runtime in runtimes:
@@ -470,6 +496,8 @@ def _rust_prost_toolchain_impl(ctx):
         proto_compiler = proto_toolchain.proto_compiler
 
     return [platform_common.ToolchainInfo(
+        pbjson_opts = ctx.attr.pbjson_opts,
+        pbjson_runtime = ctx.attr.pbjson_runtime,
         prost_opts = ctx.attr.prost_opts,
         prost_plugin = ctx.attr.prost_plugin,
         prost_plugin_flag = ctx.attr.prost_plugin_flag,
@@ -498,6 +526,23 @@ rust_prost_toolchain = rule(
             doc = "Whether to include transitive dependencies. If set to True, all transitive dependencies will directly accessible by the dependent crate.",
             default = False,
         ),
+        "pbjson_opts": attr.string_list(
+            doc = (
+                "Options for [`pbjson_build`](https://docs.rs/pbjson-build). One of `emit_fields`, " +
+                "`ignore_unknown_fields`, `preserve_proto_field_names`, `retain_enum_prefix` or " +
+                "`use_integers_for_enums`."
+            ),
+        ),
+        "pbjson_runtime": attr.label(
+            doc = (
+                "The [`pbjson`](https://crates.io/crates/pbjson) runtime crates to use. Must provide " +
+                "`pbjson` and `serde`. When set, canonical protobuf JSON `serde` implementations are " +
+                "generated for all messages and enums. If `compile_well_known_types` is False, " +
+                "well known types are taken from `pbjson_types` which must also be provided."
+            ),
+            providers = [[rust_common.crate_info], [rust_common.crate_group_info]],
+            aspects = [rust_analyzer_aspect],
+        ),
         "prost_opts": attr.string_list(
             doc = "Additional options to add to Prost.",
         ),
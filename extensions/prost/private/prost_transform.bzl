@@ -6,11 +6,18 @@ load("@rules_rust//rust:defs.bzl", "rust_common")
 ProstTransformInfo = provider(
     doc = "Info about transformations to apply to Prost generated source code.",
     fields = {
+        "boxed": "List[str]: Proto paths of message fields to wrap in a `Box`.",
+        "btree_map": "List[str]: Proto paths of map fields to generate as `BTreeMap`.",
+        "bytes": "List[str]: Proto paths of bytes fields to generate as `bytes::Bytes`.",
         "crate_name": "str: The name of crate generated by Prost.",
         "deps": "List[DepVariantInfo]: Additional dependencies to compile into the Prost target.",
+        "field_attributes": "Dict[str, List[str]]: Attributes to add to generated fields, keyed by proto path.",
+        "file_descriptor_set": "bool: Whether to expose the encoded descriptors of the protos as `FILE_DESCRIPTOR_SET`.",
         "prost_opts": "List[str]: Additional prost flags.",
+        "reflect": "bool: Whether to derive `prost_reflect::ReflectMessage` for all messages.",
         "srcs": "Depset[File]: Additional source files to include in generated Prost source code.",
         "tonic_opts": "List[str]: Additional tonic flags.",
+        "type_attributes": "Dict[str, List[str]]: Attributes to add to generated types, keyed by proto path.",
     },
 )
 
@@ -28,11 +35,18 @@ def _rust_prost_transform_impl(ctx):
     # consumers of the `proto_library` target this rule is expected to be passed
     # to.
     return [ProstTransformInfo(
+        boxed = ctx.attr.boxed,
+        btree_map = ctx.attr.btree_map,
+        bytes = ctx.attr.bytes,
         crate_name = ctx.attr.crate_name if ctx.attr.crate_name else None,
         deps = deps,
+        field_attributes = ctx.attr.field_attributes,
+        file_descriptor_set = ctx.attr.file_descriptor_set,
         prost_opts = ctx.attr.prost_opts,
+        reflect = ctx.attr.reflect,
         srcs = depset(ctx.files.srcs),
         tonic_opts = ctx.attr.tonic_opts,
+        type_attributes = ctx.attr.type_attributes,
     )]
 
 rust_prost_transform = rule(
@@ -70,9 +84,45 @@ rust_prost_library(
 The `rust_prost_library` will spawn an action on the `a_proto` target which consumes the
 `a_transform` rule to provide a means of granularly modifying a proto library for `ProstGenProto`
 actions with minimal impact to other consumers.
+
+Code generation can also be customized for specific proto paths. Paths follow the
+[`prost_build`](https://docs.rs/prost-build/latest/prost_build/struct.Config.html#method.btree_map)
+matching rules and any path which does not match a message, enum, field or oneof in the
+`proto_library` is reported as an error. E.g.
+```python
+rust_prost_transform(
+    name = "a_transform",
+    type_attributes = {
+        ".a.Request": [
+            "#[derive(serde::Serialize, serde::Deserialize)]",
+            "#[serde(rename_all = \"camelCase\")]",
+        ],
+    },
+    field_attributes = {
+        ".a.Request.payload": ["#[serde(default)]"],
+    },
+    bytes = [".a.Request.payload"],
+    btree_map = [".a"],
+    deps = [
+        "@crates//:serde",
+    ],
+)
+```
 """,
     implementation = _rust_prost_transform_impl,
     attrs = {
+        "boxed": attr.string_list(
+            doc = (
+                "Proto paths of message fields to wrap in a `Box`. Requires a `protoc-gen-prost` " +
+                "plugin which supports the `boxed` option."
+            ),
+        ),
+        "btree_map": attr.string_list(
+            doc = "Proto paths of `map` fields to generate as `BTreeMap` instead of `HashMap`.",
+        ),
+        "bytes": attr.string_list(
+            doc = "Proto paths of `bytes` fields to generate as `bytes::Bytes` instead of `Vec<u8>`.",
+        ),
         "crate_name": attr.string(
             doc = "The name of the crate generated by Prost. This is used to override the default name which is the name of the proto_library target.",
             mandatory = False,
@@ -81,9 +131,28 @@ actions with minimal impact to other consumers.
             doc = "Additional dependencies to add to the compiled crate.",
             providers = [[rust_common.crate_info], [rust_common.crate_group_info]],
         ),
+        "field_attributes": attr.string_list_dict(
+            doc = "A mapping of proto paths to attributes to add to the generated message fields or enum variants.",
+        ),
+        "file_descriptor_set": attr.bool(
+            doc = (
+                "Whether to expose an encoded `FileDescriptorSet` of the protos and everything they import " +
+                "as `pub const FILE_DESCRIPTOR_SET: &[u8]` in the generated crate. This can be used for " +
+                "gRPC server reflection."
+            ),
+            default = False,
+        ),
         "prost_opts": attr.string_list(
             doc = "Additional options to add to Prost.",
         ),
+        "reflect": attr.bool(
+            doc = (
+                "Whether to derive `prost_reflect::ReflectMessage` for all messages. Implies " +
+                "`file_descriptor_set`. The `prost-reflect` crate with the `derive` feature must be " +
+                "added to `deps`."
+            ),
+            default = False,
+        ),
         "srcs": attr.label_list(
             doc = "Additional source files to include in generated Prost source code.",
             allow_files = True,
@@ -91,5 +160,8 @@ actions with minimal impact to other consumers.
         "tonic_opts": attr.string_list(
             doc = "Additional options to add to Tonic.",
         ),
+        "type_attributes": attr.string_list_dict(
+            doc = "A mapping of proto paths to attributes to add to the generated messages, enums or oneofs.",
+        ),
     },
 )
@@ -94,7 +94,7 @@ impl Module {
 
         // If this is the last part (current module) then add the contents.
         if module_parts.len() == 1 {
-            current_module.contents = contents;
+            current_module.contents.push_str(&contents);
             return current_module;
         }
 
@@ -151,8 +151,13 @@ const ADDITIONAL_CONTENT_HEADER: &str =
 ///     }
 /// }
 /// ```
+///
+/// Any `pbjson` outputs are appended to the module of their package and an encoded
+/// `file_descriptor_set` is exposed as `FILE_DESCRIPTOR_SET` in the crate root.
 fn generate_lib_rs(
     prost_outputs: &BTreeSet<PathBuf>,
+    pbjson_outputs: &BTreeMap<String, String>,
+    file_descriptor_set: Option<&[u8]>,
     is_tonic: bool,
     direct_dep_crate_names: Vec<String>,
     additional_content: String,
@@ -163,6 +168,18 @@ fn generate_lib_rs(
     }
     contents.push("".to_string());
 
+    if let Some(bytes) = file_descriptor_set {
+        contents.push(
+            "/// An encoded `FileDescriptorSet` of the protos of this crate and their imports."
+                .to_string(),
+        );
+        contents.push(format!(
+            "pub const FILE_DESCRIPTOR_SET: &[u8] = {};",
+            byte_string_literal(bytes)
+        ));
+        contents.push("".to_string());
+    }
+
     let mut module_info = Module {
         name: "".to_string(),
         contents: contents.join("\n"),
@@ -195,6 +212,10 @@ fn generate_lib_rs(
         module_info.insert(module_name, contents);
     }
 
+    for (package, contents) in pbjson_outputs.iter() {
+        module_info.insert(snake_cased_package_name(package), contents.clone());
+    }
+
     let mut content = String::new();
     write_module(&mut content, &module_info, 0);
 
@@ -424,6 +445,773 @@ fn oneof_type_to_extern_paths(
     );
 }
 
+/// A prost code generation option scoped to a proto path.
+///
+/// Paths follow `prost_build` matching rules: a path with a leading `.` is fully
+/// qualified and matches itself and everything nested in it, a path without one
+/// matches by suffix, and `.` matches everything.
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum ProstPathOption {
+    /// Add an attribute to a generated message, enum or oneof type.
+    TypeAttribute { path: String, attribute: String },
+
+    /// Add an attribute to a generated message type.
+    MessageAttribute { path: String, attribute: String },
+
+    /// Add an attribute to a generated message field or enum variant.
+    FieldAttribute { path: String, attribute: String },
+
+    /// Generate `bytes::Bytes` instead of `Vec<u8>` for `bytes` fields.
+    Bytes(String),
+
+    /// Generate `BTreeMap` instead of `HashMap` for `map` fields.
+    BTreeMap(String),
+
+    /// Wrap message fields in a `Box`.
+    Boxed(String),
+}
+
+impl ProstPathOption {
+    /// Parse an option from a wrapper flag name and its value.
+    ///
+    /// Attribute options take `PATH=ATTRIBUTE` values while all others take a `PATH`.
+    fn parse(flag: &str, value: &str) -> Result<Option<ProstPathOption>, String> {
+        let split_attribute = || {
+            value
+                .split_once('=')
+                .map(|(path, attribute)| (path.to_string(), attribute.to_string()))
+                .ok_or_else(|| format!("Expected `{}=PATH=ATTRIBUTE`, got `{}`", flag, value))
+        };
+
+        let option = match flag {
+            "--type_attribute" => {
+                let (path, attribute) = split_attribute()?;
+                ProstPathOption::TypeAttribute { path, attribute }
+            }
+            "--field_attribute" => {
+                let (path, attribute) = split_attribute()?;
+                ProstPathOption::FieldAttribute { path, attribute }
+            }
+            "--bytes" => ProstPathOption::Bytes(value.to_string()),
+            "--btree_map" => ProstPathOption::BTreeMap(value.to_string()),
+            "--boxed" => ProstPathOption::Boxed(value.to_string()),
+            _ => return Ok(None),
+        };
+
+        if option.path().is_empty() {
+            return Err(format!("Expected a proto path for `{}`", flag));
+        }
+
+        Ok(Some(option))
+    }
+
+    /// The proto path the option applies to.
+    fn path(&self) -> &str {
+        match self {
+            ProstPathOption::TypeAttribute { path, .. }
+            | ProstPathOption::MessageAttribute { path, .. }
+            | ProstPathOption::FieldAttribute { path, .. }
+            | ProstPathOption::Bytes(path)
+            | ProstPathOption::BTreeMap(path)
+            | ProstPathOption::Boxed(path) => path,
+        }
+    }
+
+    /// Render the option as a `protoc-gen-prost` parameter.
+    ///
+    /// Commas delimit plugin parameters so they're escaped in attribute values.
+    fn to_prost_opt(&self) -> String {
+        let escape = |attribute: &str| attribute.replace(',', r"\,");
+
+        match self {
+            ProstPathOption::TypeAttribute { path, attribute } => {
+                format!("type_attribute={}={}", path, escape(attribute))
+            }
+            ProstPathOption::MessageAttribute { path, attribute } => {
+                format!("message_attribute={}={}", path, escape(attribute))
+            }
+            ProstPathOption::FieldAttribute { path, attribute } => {
+                format!("field_attribute={}={}", path, escape(attribute))
+            }
+            ProstPathOption::Bytes(path) => format!("bytes={}", path),
+            ProstPathOption::BTreeMap(path) => format!("btree_map={}", path),
+            ProstPathOption::Boxed(path) => format!("boxed={}", path),
+        }
+    }
+}
+
+/// Collect the fully qualified paths of every message, enum, enum value, field
+/// and oneof defined in a descriptor set.
+fn get_proto_paths(descriptor_set: &FileDescriptorSet) -> BTreeSet<ProtoPath> {
+    let mut proto_paths = BTreeSet::new();
+
+    for file in descriptor_set.file.iter() {
+        let proto_path = ProtoPath(file.package.clone().unwrap_or_default());
+
+        for message_type in file.message_type.iter() {
+            message_type_to_proto_paths(&mut proto_paths, &proto_path, message_type);
+        }
+
+        for enum_type in file.enum_type.iter() {
+            enum_type_to_proto_paths(&mut proto_paths, &proto_path, enum_type);
+        }
+    }
+
+    proto_paths
+}
+
+/// Add the proto paths defined by a message descriptor type.
+fn message_type_to_proto_paths(
+    proto_paths: &mut BTreeSet<ProtoPath>,
+    proto_path: &ProtoPath,
+    message_type: &DescriptorProto,
+) {
+    let proto_path = proto_path.join(message_type.name());
+
+    for field in message_type.field.iter() {
+        proto_paths.insert(proto_path.join(field.name()));
+    }
+
+    for oneof_type in message_type.oneof_decl.iter() {
+        proto_paths.insert(proto_path.join(oneof_type.name()));
+    }
+
+    for nested_type in message_type.nested_type.iter() {
+        message_type_to_proto_paths(proto_paths, &proto_path, nested_type);
+    }
+
+    for enum_type in message_type.enum_type.iter() {
+        enum_type_to_proto_paths(proto_paths, &proto_path, enum_type);
+    }
+
+    proto_paths.insert(proto_path);
+}
+
+/// Add the proto paths defined by an enum descriptor type.
+fn enum_type_to_proto_paths(
+    proto_paths: &mut BTreeSet<ProtoPath>,
+    proto_path: &ProtoPath,
+    enum_type: &EnumDescriptorProto,
+) {
+    let proto_path = proto_path.join(enum_type.name());
+
+    for value in enum_type.value.iter() {
+        proto_paths.insert(proto_path.join(value.name()));
+    }
+
+    proto_paths.insert(proto_path);
+}
+
+/// Whether a `prost_build` style path matches a fully qualified proto path.
+fn proto_path_matches(path: &str, proto_path: &ProtoPath) -> bool {
+    if path == "." {
+        return true;
+    }
+
+    match path.strip_prefix('.') {
+        Some(prefix) => {
+            proto_path.0 == prefix
+                || proto_path
+                    .0
+                    .strip_prefix(prefix)
+                    .is_some_and(|rest| rest.starts_with('.'))
+        }
+        None => {
+            proto_path.0 == path
+                || proto_path
+                    .0
+                    .strip_suffix(path)
+                    .is_some_and(|rest| rest.ends_with('.'))
+        }
+    }
+}
+
+/// Collect the files of a descriptor set along with every file they import.
+///
+/// Files are ordered so that each file follows its dependencies which allows the
+/// result to be decoded into a `prost_reflect::DescriptorPool`.
+fn get_file_descriptor_set(
+    descriptor_set: &FileDescriptorSet,
+    transitive_descriptor_sets: &[PathBuf],
+) -> Result<FileDescriptorSet, String> {
+    let mut files = BTreeMap::new();
+    for path in transitive_descriptor_sets {
+        let bytes = fs::read(path)
+            .map_err(|e| format!("Failed to read descriptor set: {}\n{:?}", path.display(), e))?;
+        let transitive_descriptors = FileDescriptorSet::decode(bytes.as_slice()).map_err(|e| {
+            format!(
+                "Failed to decode descriptor set: {}\n{:?}",
+                path.display(),
+                e
+            )
+        })?;
+        for file in transitive_descriptors.file {
+            files.insert(file.name().to_string(), file);
+        }
+    }
+    for file in descriptor_set.file.iter() {
+        files.insert(file.name().to_string(), file.clone());
+    }
+
+    let mut ordered = Vec::new();
+    let mut visited = BTreeSet::new();
+    for file in descriptor_set.file.iter() {
+        add_file_with_dependencies(file.name(), &files, &mut visited, &mut ordered)?;
+    }
+
+    Ok(FileDescriptorSet { file: ordered })
+}
+
+/// Add a file to `ordered` after all of its dependencies.
+fn add_file_with_dependencies(
+    name: &str,
+    files: &BTreeMap<String, FileDescriptorProto>,
+    visited: &mut BTreeSet<String>,
+    ordered: &mut Vec<FileDescriptorProto>,
+) -> Result<(), String> {
+    if !visited.insert(name.to_string()) {
+        return Ok(());
+    }
+
+    let file = files
+        .get(name)
+        .ok_or_else(|| format!("Failed to find the descriptor of imported file: {}", name))?;
+    for dependency in file.dependency.iter() {
+        add_file_with_dependencies(dependency, files, visited, ordered)?;
+    }
+
+    ordered.push(file.clone());
+    Ok(())
+}
+
+/// Render bytes as a Rust byte string literal.
+fn byte_string_literal(bytes: &[u8]) -> String {
+    let mut literal = String::from("b\"");
+    for byte in bytes {
+        literal.extend(std::ascii::escape_default(*byte).map(char::from));
+    }
+    literal.push('"');
+    literal
+}
+
+/// The full names of all messages in a descriptor set, excluding map entries.
+fn get_message_names(descriptor_set: &FileDescriptorSet) -> Vec<String> {
+    fn add_message_names(names: &mut Vec<String>, parent: &ProtoPath, message: &DescriptorProto) {
+        if message.options.as_ref().is_some_and(|o| o.map_entry()) {
+            return;
+        }
+
+        let name = parent.join(message.name());
+        for nested_type in message.nested_type.iter() {
+            add_message_names(names, &name, nested_type);
+        }
+        names.push(name.to_string());
+    }
+
+    let mut names = Vec::new();
+    for file in descriptor_set.file.iter() {
+        let package = ProtoPath(file.package().to_string());
+        for message_type in file.message_type.iter() {
+            add_message_names(&mut names, &package, message_type);
+        }
+    }
+
+    names.sort();
+    names
+}
+
+/// The message attributes which derive `prost_reflect::ReflectMessage` for every
+/// message using the crate's `FILE_DESCRIPTOR_SET`.
+///
+/// `prost_build` has no exact path matching, so each message uses whichever path
+/// matches no other message: its fully qualified path when nothing is nested in it,
+/// otherwise its name without a leading `.` when that is not a suffix of another message.
+fn get_reflect_options(descriptor_set: &FileDescriptorSet) -> Result<Vec<ProstPathOption>, String> {
+    let names = get_message_names(descriptor_set);
+
+    let mut options = Vec::new();
+    for name in names.iter() {
+        let nested_prefix = format!("{}.", name);
+        let suffix = format!(".{}", name);
+        let path = if !names.iter().any(|other| other.starts_with(&nested_prefix)) {
+            format!(".{}", name)
+        } else if !names.iter().any(|other| other.ends_with(&suffix)) {
+            name.clone()
+        } else {
+            return Err(format!(
+                "Unable to derive `prost_reflect::ReflectMessage` for `{}` without also matching messages nested in it or named `*{}`",
+                name, suffix
+            ));
+        };
+
+        options.push(ProstPathOption::MessageAttribute {
+            path: path.clone(),
+            attribute: "#[derive(::prost_reflect::ReflectMessage)]".to_string(),
+        });
+        options.push(ProstPathOption::MessageAttribute {
+            path,
+            attribute: format!(
+                "#[prost_reflect(file_descriptor_set_bytes = \"crate::FILE_DESCRIPTOR_SET\", message_name = \"{}\")]",
+                name
+            ),
+        });
+    }
+
+    Ok(options)
+}
+
+/// Ensure every option refers to a path defined in the descriptor set.
+///
+/// `protoc-gen-prost` silently ignores paths which don't match anything, so a
+/// misspelled path would otherwise go unnoticed.
+fn validate_path_options(
+    descriptor_set: &FileDescriptorSet,
+    path_options: &[ProstPathOption],
+) -> Result<(), String> {
+    let proto_paths = get_proto_paths(descriptor_set);
+
+    let unknown_paths = path_options
+        .iter()
+        .map(ProstPathOption::path)
+        .filter(|path| {
+            !proto_paths
+                .iter()
+                .any(|proto_path| proto_path_matches(path, proto_path))
+        })
+        .collect::<BTreeSet<_>>();
+
+    if unknown_paths.is_empty() {
+        return Ok(());
+    }
+
+    let proto_files = descriptor_set
+        .file
+        .iter()
+        .map(|file| format!("  {}", file.name()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Err(format!(
+        "The following proto paths do not match any message, enum, field or oneof:\n{}\nin proto files:\n{}",
+        unknown_paths
+            .into_iter()
+            .map(|path| format!("  {}", path))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        proto_files,
+    ))
+}
+
+/// The proto elements which generate each Rust item, grouped by the scope the
+/// items are defined in.
+#[derive(Debug, Default)]
+struct RustNames {
+    /// A description of each scope mapped to the items defined in it.
+    scopes: BTreeMap<String, BTreeMap<String, BTreeSet<ProtoPath>>>,
+
+    /// The proto file which defines each element.
+    files: BTreeMap<ProtoPath, String>,
+}
+
+impl RustNames {
+    /// Record that `proto_path` generates the Rust item `name` in `scope`.
+    fn insert(&mut self, scope: String, name: String, proto_path: ProtoPath, file: &str) {
+        self.files
+            .entry(proto_path.clone())
+            .or_insert_with(|| file.to_string());
+        self.scopes
+            .entry(scope)
+            .or_default()
+            .entry(name)
+            .or_default()
+            .insert(proto_path);
+    }
+
+    /// Describe every Rust item generated by more than one proto element.
+    fn collisions(&self) -> Vec<String> {
+        let mut collisions = Vec::new();
+
+        for (scope, items) in self.scopes.iter() {
+            for (name, proto_paths) in items.iter() {
+                if proto_paths.len() < 2 {
+                    continue;
+                }
+
+                let origins = proto_paths
+                    .iter()
+                    .map(|proto_path| format!("`{}` ({})", proto_path, self.files[proto_path]))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                collisions.push(format!("`{}` in {}: {}", name, scope, origins));
+            }
+        }
+
+        collisions
+    }
+}
+
+/// Describe the scope of items defined in a Rust module.
+fn module_scope(module: &str) -> String {
+    if module.is_empty() {
+        return "the crate root".to_string();
+    }
+
+    format!("module `{}`", module)
+}
+
+/// Join a name onto a Rust module path.
+fn join_module(module: &str, name: &str) -> String {
+    if module.is_empty() {
+        return name.to_string();
+    }
+
+    format!("{}::{}", module, name)
+}
+
+/// Record the Rust items generated for a file descriptor.
+fn file_to_rust_names(names: &mut RustNames, file: &FileDescriptorProto) {
+    let mut module = String::new();
+    let mut proto_path = ProtoPath::from("");
+
+    for part in file.package().split('.').filter(|part| !part.is_empty()) {
+        let name = escape_keyword(snake_cased_package_name(part));
+        proto_path = proto_path.join(part);
+        names.insert(
+            module_scope(&module),
+            name.clone(),
+            proto_path.clone(),
+            file.name(),
+        );
+        module = join_module(&module, &name);
+    }
+
+    for message_type in file.message_type.iter() {
+        message_type_to_rust_names(names, &module, &proto_path, message_type, file.name());
+    }
+
+    for enum_type in file.enum_type.iter() {
+        names.insert(
+            module_scope(&module),
+            enum_type.name().to_upper_camel_case(),
+            proto_path.join(enum_type.name()),
+            file.name(),
+        );
+    }
+}
+
+/// Record the Rust items generated for a message descriptor type.
+///
+/// A message generates a struct and, if it has nested types, enums or oneofs, a
+/// module of the same name in snake case. Oneofs generate both a struct field and
+/// an enum in that module.
+fn message_type_to_rust_names(
+    names: &mut RustNames,
+    module: &str,
+    proto_path: &ProtoPath,
+    message_type: &DescriptorProto,
+    file: &str,
+) {
+    let proto_path = proto_path.join(message_type.name());
+    let struct_name = message_type.name().to_upper_camel_case();
+    names.insert(
+        module_scope(module),
+        struct_name.clone(),
+        proto_path.clone(),
+        file,
+    );
+
+    // Map entries and synthetic oneofs of proto3 `optional` fields have no Rust items.
+    let nested_types = message_type
+        .nested_type
+        .iter()
+        .filter(|nested_type| !nested_type.options.as_ref().is_some_and(|o| o.map_entry()))
+        .collect::<Vec<_>>();
+    let oneof_indices = message_type
+        .field
+        .iter()
+        .filter(|field| !field.proto3_optional())
+        .filter_map(|field| field.oneof_index)
+        .collect::<BTreeSet<_>>();
+
+    let module_name = escape_keyword(message_type.name().to_snake_case());
+    let nested_module = join_module(module, &module_name);
+    if !nested_types.is_empty() || !message_type.enum_type.is_empty() || !oneof_indices.is_empty() {
+        names.insert(module_scope(module), module_name, proto_path.clone(), file);
+    }
+
+    let fields_scope = format!("the fields of `{}`", join_module(module, &struct_name));
+    for field in message_type.field.iter() {
+        let oneof_index = field.oneof_index.filter(|_| !field.proto3_optional());
+        match oneof_index.and_then(|index| message_type.oneof_decl.get(index as usize)) {
+            Some(oneof_type) => names.insert(
+                format!(
+                    "the variants of `{}`",
+                    join_module(&nested_module, &oneof_type.name().to_upper_camel_case())
+                ),
+                field.name().to_upper_camel_case(),
+                proto_path.join(field.name()),
+                file,
+            ),
+            None => names.insert(
+                fields_scope.clone(),
+                escape_keyword(field.name().to_snake_case()),
+                proto_path.join(field.name()),
+                file,
+            ),
+        }
+    }
+
+    for (index, oneof_type) in message_type.oneof_decl.iter().enumerate() {
+        if !oneof_indices.contains(&(index as i32)) {
+            continue;
+        }
+
+        let oneof_path = proto_path.join(oneof_type.name());
+        names.insert(
+            fields_scope.clone(),
+            escape_keyword(oneof_type.name().to_snake_case()),
+            oneof_path.clone(),
+            file,
+        );
+        names.insert(
+            module_scope(&nested_module),
+            oneof_type.name().to_upper_camel_case(),
+            oneof_path,
+            file,
+        );
+    }
+
+    for nested_type in nested_types {
+        message_type_to_rust_names(names, &nested_module, &proto_path, nested_type, file);
+    }
+
+    for enum_type in message_type.enum_type.iter() {
+        names.insert(
+            module_scope(&nested_module),
+            enum_type.name().to_upper_camel_case(),
+            proto_path.join(enum_type.name()),
+            file,
+        );
+    }
+}
+
+/// Ensure no two proto elements generate the same Rust item.
+///
+/// Such collisions would otherwise surface as rustc errors in the generated
+/// sources which are hard to trace back to the `.proto` files.
+fn validate_rust_names(descriptor_set: &FileDescriptorSet) -> Result<(), String> {
+    let mut names = RustNames::default();
+    for file in descriptor_set.file.iter() {
+        file_to_rust_names(&mut names, file);
+    }
+
+    let collisions = names.collisions();
+    if collisions.is_empty() {
+        return Ok(());
+    }
+
+    Err(format!(
+        "The following proto elements generate the same Rust items:\n{}",
+        collisions
+            .into_iter()
+            .map(|collision| format!("  {}", collision))
+            .collect::<Vec<_>>()
+            .join("\n")
+    ))
+}
+
+/// Map the fully qualified proto paths of all messages and enums in a descriptor
+/// set to the keys `get_extern_paths` writes for them in package info files.
+///
+/// Package info keys use lowercase module names for the parents of nested types
+/// (e.g. `foo.bar.Baz` for `foo.Bar.Baz`) which `pbjson_build` cannot resolve.
+fn get_extern_path_keys(descriptor_set: &FileDescriptorSet) -> BTreeMap<ProtoPath, ProtoPath> {
+    let mut keys = BTreeMap::new();
+
+    for file in descriptor_set.file.iter() {
+        let proto_path = ProtoPath(file.package.clone().unwrap_or_default());
+
+        for message_type in file.message_type.iter() {
+            message_type_to_extern_path_keys(&mut keys, &proto_path, &proto_path, message_type);
+        }
+
+        for enum_type in file.enum_type.iter() {
+            keys.insert(
+                proto_path.join(enum_type.name()),
+                proto_path.join(enum_type.name()),
+            );
+        }
+    }
+
+    keys
+}
+
+/// Add the package info keys for a message descriptor type and its nested types.
+fn message_type_to_extern_path_keys(
+    keys: &mut BTreeMap<ProtoPath, ProtoPath>,
+    key_path: &ProtoPath,
+    proto_path: &ProtoPath,
+    message_type: &DescriptorProto,
+) {
+    let name = message_type.name();
+    keys.insert(key_path.join(name), proto_path.join(name));
+
+    let key_path = key_path.join(&name.to_lowercase());
+    let proto_path = proto_path.join(name);
+
+    for nested_type in message_type.nested_type.iter() {
+        message_type_to_extern_path_keys(keys, &key_path, &proto_path, nested_type);
+    }
+
+    for enum_type in message_type.enum_type.iter() {
+        keys.insert(
+            key_path.join(enum_type.name()),
+            proto_path.join(enum_type.name()),
+        );
+    }
+}
+
+/// Compute the `pbjson_build` extern paths for the types provided by dependencies.
+fn get_pbjson_extern_paths(
+    descriptor_set: &FileDescriptorSet,
+    dep_extern_paths: &[(String, String)],
+) -> Vec<(String, String)> {
+    let keys = get_extern_path_keys(descriptor_set);
+
+    dep_extern_paths
+        .iter()
+        .map(|(proto_path, rust_path)| {
+            let proto_path = proto_path
+                .strip_prefix('.')
+                .and_then(|key| keys.get(&ProtoPath::from(key)))
+                .map(|proto_path| format!(".{}", proto_path))
+                .unwrap_or_else(|| proto_path.clone());
+
+            (proto_path, rust_path.clone())
+        })
+        .collect()
+}
+
+/// The fully qualified paths of the top level messages and enums of a descriptor set.
+fn get_pbjson_prefixes(descriptor_set: &FileDescriptorSet) -> Vec<String> {
+    let mut prefixes = Vec::new();
+
+    for file in descriptor_set.file.iter() {
+        let proto_path = ProtoPath(file.package.clone().unwrap_or_default());
+        let names = file
+            .message_type
+            .iter()
+            .map(|message_type| message_type.name())
+            .chain(file.enum_type.iter().map(|enum_type| enum_type.name()));
+
+        prefixes.extend(names.map(|name| format!(".{}", proto_path.join(name))));
+    }
+
+    prefixes
+}
+
+/// Generate `serde` implementations for the types of a descriptor set using `pbjson_build`.
+///
+/// Returns the generated source of each proto package.
+fn generate_pbjson(
+    out_dir: &Path,
+    descriptor_set: &FileDescriptorSet,
+    transitive_descriptor_sets: &[PathBuf],
+    dep_extern_paths: &[(String, String)],
+    compile_well_known_types: bool,
+    pbjson_opts: &[String],
+    path_options: &[ProstPathOption],
+) -> Result<BTreeMap<String, String>, String> {
+    let prefixes = get_pbjson_prefixes(descriptor_set);
+    if prefixes.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+
+    let mut builder = pbjson_build::Builder::new();
+    let mut transitive_descriptors = FileDescriptorSet::default();
+    for path in transitive_descriptor_sets {
+        let bytes = fs::read(path)
+            .map_err(|e| format!("Failed to read descriptor set: {}\n{:?}", path.display(), e))?;
+        builder.register_descriptors(&bytes).map_err(|e| {
+            format!(
+                "Failed to register descriptor set: {}\n{:?}",
+                path.display(),
+                e
+            )
+        })?;
+        transitive_descriptors
+            .merge(bytes.as_slice())
+            .map_err(|e| {
+                format!(
+                    "Failed to decode descriptor set: {}\n{:?}",
+                    path.display(),
+                    e
+                )
+            })?;
+    }
+
+    for (proto_path, rust_path) in
+        get_pbjson_extern_paths(&transitive_descriptors, dep_extern_paths)
+    {
+        builder.extern_path(proto_path, rust_path);
+    }
+    if !compile_well_known_types {
+        builder.extern_path(".google.protobuf", "::pbjson_types");
+    }
+
+    for opt in pbjson_opts {
+        match opt.as_str() {
+            "emit_fields" => builder.emit_fields(),
+            "ignore_unknown_fields" => builder.ignore_unknown_fields(),
+            "preserve_proto_field_names" => builder.preserve_proto_field_names(),
+            "retain_enum_prefix" => builder.retain_enum_prefix(),
+            "use_integers_for_enums" => builder.use_integers_for_enums(),
+            _ => return Err(format!("Unknown pbjson option: `{}`", opt)),
+        };
+    }
+
+    // `BTreeMap` fields generated by prost must be serialized as such.
+    builder.btree_map(path_options.iter().filter_map(|option| match option {
+        ProstPathOption::BTreeMap(path) => Some(path.clone()),
+        _ => None,
+    }));
+
+    if out_dir.exists() {
+        fs::remove_dir_all(out_dir)
+            .map_err(|e| format!("Failed to delete directory: {}\n{:?}", out_dir.display(), e))?;
+    }
+    fs::create_dir_all(out_dir)
+        .map_err(|e| format!("Failed to create directory: {}\n{:?}", out_dir.display(), e))?;
+
+    builder
+        .out_dir(out_dir)
+        .build(&prefixes)
+        .map_err(|e| format!("Failed to generate pbjson sources\n{:?}", e))?;
+
+    let mut outputs = BTreeMap::new();
+    for entry in fs::read_dir(out_dir).map_err(|e| format!("{:?}", e))? {
+        let path = entry.map_err(|e| format!("{:?}", e))?.path();
+        let Some(package) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".serde.rs"))
+        else {
+            continue;
+        };
+
+        // Match the module prost uses for files without a package.
+        let package = if package.is_empty() { "_" } else { package };
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read file: {}\n{:?}", path.display(), e))?;
+        outputs.insert(package.to_string(), contents);
+    }
+
+    fs::remove_dir_all(out_dir)
+        .map_err(|e| format!("Failed to delete directory: {}\n{:?}", out_dir.display(), e))?;
+
+    Ok(outputs)
+}
+
 /// The parsed command-line arguments.
 struct Args {
     /// The path to the protoc binary.
@@ -473,6 +1261,27 @@ struct Args {
 
     /// Extra arguments to pass to protoc.
     extra_args: Vec<String>,
+
+    /// Prost options scoped to proto paths.
+    path_options: Vec<ProstPathOption>,
+
+    /// Whether to generate `serde` implementations with pbjson.
+    is_pbjson: bool,
+
+    /// Whether to expose the encoded descriptors of the proto files as `FILE_DESCRIPTOR_SET`.
+    is_file_descriptor_set: bool,
+
+    /// Whether to derive `prost_reflect::ReflectMessage` for all messages.
+    is_reflect: bool,
+
+    /// Options for pbjson.
+    pbjson_opts: Vec<String>,
+
+    /// The descriptor sets of the proto files and all their dependencies.
+    transitive_descriptor_sets: Vec<PathBuf>,
+
+    /// The `extern_path` pairs of dependencies.
+    dep_extern_paths: Vec<(String, String)>,
 }
 
 impl Args {
@@ -494,6 +1303,14 @@ impl Args {
         let mut direct_dep_crate_names = Vec::new();
         let mut is_tonic = false;
         let mut compile_well_known_types = false;
+        let mut path_options = Vec::new();
+        let mut errors = Vec::new();
+        let mut is_pbjson = false;
+        let mut is_file_descriptor_set = false;
+        let mut is_reflect = false;
+        let mut pbjson_opts = Vec::new();
+        let mut transitive_descriptor_sets = Vec::new();
+        let mut dep_extern_paths = Vec::new();
 
         let mut extra_args = Vec::new();
 
@@ -522,6 +1339,21 @@ impl Args {
                 return;
             }
 
+            if arg == "--is_pbjson" {
+                is_pbjson = true;
+                return;
+            }
+
+            if arg == "--is_file_descriptor_set" {
+                is_file_descriptor_set = true;
+                return;
+            }
+
+            if arg == "--is_reflect" {
+                is_reflect = true;
+                return;
+            }
+
             if !arg.contains('=') {
                 extra_args.push(arg);
                 return;
@@ -554,6 +1386,10 @@ impl Args {
                             .lines()
                         {
                             tonic_or_prost_opts.push(format!("extern_path={}", flag.trim()));
+                            if let Some((proto_path, rust_path)) = flag.trim().split_once('=') {
+                                dep_extern_paths
+                                    .push((proto_path.to_string(), rust_path.to_string()));
+                            }
                         }
                     }
                 }
@@ -585,9 +1421,17 @@ impl Args {
                 ("--label", value) => {
                     label = Some(value.to_string());
                 }
-                (arg, value) => {
-                    extra_args.push(format!("{}={}", arg, value));
+                ("--pbjson_opt", value) => {
+                    pbjson_opts.push(value.to_string());
                 }
+                ("--transitive_descriptor_set", value) => {
+                    transitive_descriptor_sets.push(PathBuf::from(value));
+                }
+                (arg, value) => match ProstPathOption::parse(arg, value) {
+                    Ok(Some(option)) => path_options.push(option),
+                    Ok(None) => extra_args.push(format!("{}={}", arg, value)),
+                    Err(err) => errors.push(err),
+                },
             }
         };
 
@@ -607,6 +1451,10 @@ impl Args {
             }
         }
 
+        if !errors.is_empty() {
+            return Err(errors.join("\n"));
+        }
+
         for tonic_or_prost_opt in tonic_or_prost_opts {
             extra_args.push(format!("--prost_opt={}", tonic_or_prost_opt));
             if is_tonic {
@@ -667,6 +1515,14 @@ impl Args {
             label: label.unwrap(),
             extra_args,
             compile_well_known_types,
+            path_options,
+            is_pbjson,
+            // Reflection requires the descriptors of the messages.
+            is_file_descriptor_set: is_file_descriptor_set || is_reflect,
+            is_reflect,
+            pbjson_opts,
+            transitive_descriptor_sets,
+            dep_extern_paths,
         })
     }
 }
@@ -771,6 +1627,13 @@ fn main() {
         is_tonic,
         extra_args,
         compile_well_known_types,
+        path_options,
+        is_pbjson,
+        is_file_descriptor_set,
+        is_reflect,
+        pbjson_opts,
+        transitive_descriptor_sets,
+        dep_extern_paths,
     } = Args::parse().expect("Failed to parse args");
 
     let out_dir = get_and_create_output_dir(&out_dir, &label);
@@ -779,6 +1642,10 @@ fn main() {
     let package_name = get_package_name(&descriptor_set).unwrap_or_default();
     let expect_rs = expect_fs_file_to_be_generated(&descriptor_set);
     let has_services = has_services(&descriptor_set);
+    validate_rust_names(&descriptor_set)
+        .unwrap_or_else(|e| panic!("Conflicting proto names in {}\n{}", label, e));
+    validate_path_options(&descriptor_set, &path_options)
+        .unwrap_or_else(|e| panic!("Invalid prost options for {}\n{}", label, e));
     let additional_content = additional_srcs
         .into_iter()
         .map(|f| {
@@ -822,9 +1689,28 @@ fn main() {
         if is_tonic {
             args.push("--tonic_opt=compile_well_known_types".to_owned());
         }
+    } else if is_pbjson {
+        // Well known types must come from `pbjson_types` to be serializable.
+        args.push("--prost_opt=extern_path=.google.protobuf=::pbjson_types".to_owned());
+        if is_tonic {
+            args.push("--tonic_opt=extern_path=.google.protobuf=::pbjson_types".to_owned());
+        }
     }
 
     args.extend(extra_args);
+    args.extend(
+        path_options
+            .iter()
+            .map(|option| format!("--prost_opt={}", option.to_prost_opt())),
+    );
+    if is_reflect {
+        args.extend(
+            get_reflect_options(&descriptor_set)
+                .unwrap_or_else(|e| panic!("Invalid prost options for {}\n{}", label, e))
+                .iter()
+                .map(|option| format!("--prost_opt={}", option.to_prost_opt())),
+        );
+    }
     args.extend(
         proto_paths
             .iter()
@@ -936,6 +1822,34 @@ fn main() {
         }
     }
 
+    let pbjson_outputs = if is_pbjson {
+        let pbjson_dir = out_dir.parent().unwrap().join(format!(
+            "{}.pbjson",
+            out_dir.file_name().unwrap().to_string_lossy()
+        ));
+        generate_pbjson(
+            &pbjson_dir,
+            &descriptor_set,
+            &transitive_descriptor_sets,
+            &dep_extern_paths,
+            compile_well_known_types,
+            &pbjson_opts,
+            &path_options,
+        )
+        .unwrap_or_else(|e| panic!("Failed to generate pbjson sources for {}\n{}", label, e))
+    } else {
+        BTreeMap::new()
+    };
+
+    let file_descriptor_set = if is_file_descriptor_set {
+        let file_descriptor_set =
+            get_file_descriptor_set(&descriptor_set, &transitive_descriptor_sets)
+                .unwrap_or_else(|e| panic!("Failed to collect descriptors for {}\n{}", label, e));
+        Some(file_descriptor_set.encode_to_vec())
+    } else {
+        None
+    };
+
     let extern_paths = get_extern_paths(&descriptor_set, &crate_name, compile_well_known_types)
         .expect("Failed to compute proto package info");
 
@@ -944,6 +1858,8 @@ fn main() {
         &out_librs,
         generate_lib_rs(
             &rust_files,
+            &pbjson_outputs,
+            file_descriptor_set.as_deref(),
             is_tonic,
             direct_dep_crate_names,
             additional_content,
@@ -1005,7 +1921,10 @@ mod test {
 
     use super::*;
 
-    use prost_types::{FieldDescriptorProto, ServiceDescriptorProto};
+    use prost_types::{
+        field_descriptor_proto, EnumValueDescriptorProto, FieldDescriptorProto, MessageOptions,
+        ServiceDescriptorProto,
+    };
 
     #[test]
     fn oneof_type_to_extern_paths_test() {
@@ -1414,4 +2333,578 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn prost_path_option_parse_test() {
+        assert_eq!(
+            ProstPathOption::parse("--type_attribute", ".foo.Bar=#[derive(serde::Serialize)]"),
+            Ok(Some(ProstPathOption::TypeAttribute {
+                path: ".foo.Bar".to_string(),
+                attribute: "#[derive(serde::Serialize)]".to_string(),
+            }))
+        );
+        assert_eq!(
+            ProstPathOption::parse("--field_attribute", "Bar.baz=#[serde(default)]"),
+            Ok(Some(ProstPathOption::FieldAttribute {
+                path: "Bar.baz".to_string(),
+                attribute: "#[serde(default)]".to_string(),
+            }))
+        );
+        assert_eq!(
+            ProstPathOption::parse("--bytes", "."),
+            Ok(Some(ProstPathOption::Bytes(".".to_string())))
+        );
+        assert_eq!(
+            ProstPathOption::parse("--btree_map", ".foo"),
+            Ok(Some(ProstPathOption::BTreeMap(".foo".to_string())))
+        );
+        assert_eq!(
+            ProstPathOption::parse("--boxed", ".foo.Bar.baz"),
+            Ok(Some(ProstPathOption::Boxed(".foo.Bar.baz".to_string())))
+        );
+        assert_eq!(ProstPathOption::parse("--proto_path", "foo"), Ok(None));
+        assert!(ProstPathOption::parse("--type_attribute", ".foo.Bar").is_err());
+        assert!(ProstPathOption::parse("--bytes", "").is_err());
+    }
+
+    #[test]
+    fn prost_path_option_to_prost_opt_test() {
+        assert_eq!(
+            ProstPathOption::TypeAttribute {
+                path: ".foo.Bar".to_string(),
+                attribute: "#[derive(serde::Serialize, serde::Deserialize)]".to_string(),
+            }
+            .to_prost_opt(),
+            r"type_attribute=.foo.Bar=#[derive(serde::Serialize\, serde::Deserialize)]"
+        );
+        assert_eq!(
+            ProstPathOption::FieldAttribute {
+                path: "baz".to_string(),
+                attribute: r#"#[doc = "a\b"]"#.to_string(),
+            }
+            .to_prost_opt(),
+            r#"field_attribute=baz=#[doc = "a\b"]"#
+        );
+        assert_eq!(
+            ProstPathOption::Bytes(".".to_string()).to_prost_opt(),
+            "bytes=."
+        );
+        assert_eq!(
+            ProstPathOption::BTreeMap(".foo".to_string()).to_prost_opt(),
+            "btree_map=.foo"
+        );
+        assert_eq!(
+            ProstPathOption::Boxed(".foo.Bar.baz".to_string()).to_prost_opt(),
+            "boxed=.foo.Bar.baz"
+        );
+    }
+
+    #[test]
+    fn proto_path_matches_test() {
+        let proto_path = ProtoPath::from("foo.bar.Baz.qux");
+
+        for path in [
+            ".",
+            ".foo",
+            ".foo.bar",
+            ".foo.bar.Baz.qux",
+            "qux",
+            "Baz.qux",
+            "foo.bar.Baz.qux",
+        ] {
+            assert!(proto_path_matches(path, &proto_path), "{}", path);
+        }
+
+        for path in [".fo", ".bar", ".foo.bar.Baz.qux.quux", "ux", "Baz", ".qux"] {
+            assert!(!proto_path_matches(path, &proto_path), "{}", path);
+        }
+    }
+
+    #[test]
+    fn validate_path_options_test() {
+        let descriptor_set = FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("foo.proto".to_string()),
+                package: Some("foo".to_string()),
+                message_type: vec![DescriptorProto {
+                    name: Some("Bar".to_string()),
+                    field: vec![FieldDescriptorProto {
+                        name: Some("baz".to_string()),
+                        ..FieldDescriptorProto::default()
+                    }],
+                    oneof_decl: vec![OneofDescriptorProto {
+                        name: Some("choice".to_string()),
+                        ..OneofDescriptorProto::default()
+                    }],
+                    ..DescriptorProto::default()
+                }],
+                enum_type: vec![EnumDescriptorProto {
+                    name: Some("Qux".to_string()),
+                    value: vec![EnumValueDescriptorProto {
+                        name: Some("QUX_UNSPECIFIED".to_string()),
+                        ..EnumValueDescriptorProto::default()
+                    }],
+                    ..EnumDescriptorProto::default()
+                }],
+                ..FileDescriptorProto::default()
+            }],
+        };
+
+        assert_eq!(
+            get_proto_paths(&descriptor_set),
+            BTreeSet::from([
+                ProtoPath::from("foo.Bar"),
+                ProtoPath::from("foo.Bar.baz"),
+                ProtoPath::from("foo.Bar.choice"),
+                ProtoPath::from("foo.Qux"),
+                ProtoPath::from("foo.Qux.QUX_UNSPECIFIED"),
+            ])
+        );
+
+        let valid_options = [
+            ProstPathOption::TypeAttribute {
+                path: ".foo.Bar".to_string(),
+                attribute: "#[derive(serde::Serialize)]".to_string(),
+            },
+            ProstPathOption::FieldAttribute {
+                path: "QUX_UNSPECIFIED".to_string(),
+                attribute: "#[default]".to_string(),
+            },
+            ProstPathOption::Bytes(".".to_string()),
+            ProstPathOption::BTreeMap(".foo".to_string()),
+            ProstPathOption::Boxed("Bar.choice".to_string()),
+        ];
+        assert_eq!(
+            validate_path_options(&descriptor_set, &valid_options),
+            Ok(())
+        );
+
+        let invalid_options = [
+            ProstPathOption::TypeAttribute {
+                path: ".foo.Baz".to_string(),
+                attribute: "#[derive(serde::Serialize)]".to_string(),
+            },
+            ProstPathOption::Bytes(".foo.Bar.baz".to_string()),
+        ];
+        let err = validate_path_options(&descriptor_set, &invalid_options).unwrap_err();
+        assert!(err.contains(".foo.Baz"), "{}", err);
+        assert!(!err.contains(".foo.Bar.baz"), "{}", err);
+        assert!(err.contains("foo.proto"), "{}", err);
+    }
+
+    fn pbjson_descriptor_set() -> FileDescriptorSet {
+        FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("foo.proto".to_string()),
+                package: Some("foo".to_string()),
+                message_type: vec![DescriptorProto {
+                    name: Some("Bar".to_string()),
+                    field: vec![FieldDescriptorProto {
+                        name: Some("baz".to_string()),
+                        number: Some(1),
+                        label: Some(field_descriptor_proto::Label::Optional as i32),
+                        r#type: Some(field_descriptor_proto::Type::Message as i32),
+                        type_name: Some(".foo.Bar.Baz".to_string()),
+                        json_name: Some("baz".to_string()),
+                        ..FieldDescriptorProto::default()
+                    }],
+                    nested_type: vec![DescriptorProto {
+                        name: Some("Baz".to_string()),
+                        ..DescriptorProto::default()
+                    }],
+                    enum_type: vec![EnumDescriptorProto {
+                        name: Some("Kind".to_string()),
+                        ..EnumDescriptorProto::default()
+                    }],
+                    ..DescriptorProto::default()
+                }],
+                enum_type: vec![EnumDescriptorProto {
+                    name: Some("Qux".to_string()),
+                    value: vec![EnumValueDescriptorProto {
+                        name: Some("QUX_UNSPECIFIED".to_string()),
+                        number: Some(0),
+                        ..EnumValueDescriptorProto::default()
+                    }],
+                    ..EnumDescriptorProto::default()
+                }],
+                syntax: Some("proto3".to_string()),
+                ..FileDescriptorProto::default()
+            }],
+        }
+    }
+
+    #[test]
+    fn get_extern_path_keys_test() {
+        assert_eq!(
+            get_extern_path_keys(&pbjson_descriptor_set()),
+            BTreeMap::from([
+                (ProtoPath::from("foo.Bar"), ProtoPath::from("foo.Bar")),
+                (ProtoPath::from("foo.Qux"), ProtoPath::from("foo.Qux")),
+                (
+                    ProtoPath::from("foo.bar.Baz"),
+                    ProtoPath::from("foo.Bar.Baz")
+                ),
+                (
+                    ProtoPath::from("foo.bar.Kind"),
+                    ProtoPath::from("foo.Bar.Kind")
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn get_pbjson_extern_paths_test() {
+        let dep_extern_paths = [
+            (".foo.Bar".to_string(), "::foo_proto::foo::Bar".to_string()),
+            (
+                ".foo.bar.Baz".to_string(),
+                "::foo_proto::foo::bar::Baz".to_string(),
+            ),
+            (".other.Type".to_string(), "::other::Type".to_string()),
+        ];
+
+        assert_eq!(
+            get_pbjson_extern_paths(&pbjson_descriptor_set(), &dep_extern_paths),
+            vec![
+                (".foo.Bar".to_string(), "::foo_proto::foo::Bar".to_string()),
+                (
+                    ".foo.Bar.Baz".to_string(),
+                    "::foo_proto::foo::bar::Baz".to_string()
+                ),
+                (".other.Type".to_string(), "::other::Type".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn get_pbjson_prefixes_test() {
+        assert_eq!(
+            get_pbjson_prefixes(&pbjson_descriptor_set()),
+            vec![".foo.Bar".to_string(), ".foo.Qux".to_string()]
+        );
+        assert!(get_pbjson_prefixes(&FileDescriptorSet::default()).is_empty());
+    }
+
+    #[test]
+    fn generate_pbjson_test() {
+        let tmp_dir = env::temp_dir().join(format!("generate_pbjson_test-{}", process::id()));
+        fs::create_dir_all(&tmp_dir).unwrap();
+
+        let descriptor_set = pbjson_descriptor_set();
+        let descriptor_set_path = tmp_dir.join("foo.bin");
+        fs::write(&descriptor_set_path, descriptor_set.encode_to_vec()).unwrap();
+
+        let outputs = generate_pbjson(
+            &tmp_dir.join("pbjson"),
+            &descriptor_set,
+            &[descriptor_set_path],
+            &[],
+            true,
+            &["ignore_unknown_fields".to_string()],
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(outputs.keys().collect::<Vec<_>>(), vec!["foo"]);
+        assert!(outputs["foo"].contains("impl serde::Serialize for Bar"));
+        assert!(outputs["foo"].contains("impl serde::Serialize for bar::Baz"));
+        assert!(outputs["foo"].contains("impl serde::Serialize for Qux"));
+        assert!(!tmp_dir.join("pbjson").exists());
+
+        assert!(generate_pbjson(
+            &tmp_dir.join("pbjson"),
+            &descriptor_set,
+            &[tmp_dir.join("foo.bin")],
+            &[],
+            true,
+            &["unknown".to_string()],
+            &[],
+        )
+        .is_err());
+
+        fs::remove_dir_all(&tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn validate_rust_names_test() {
+        let file =
+            |name: &str, package: &str, message_type: Vec<DescriptorProto>| FileDescriptorProto {
+                name: Some(name.to_string()),
+                package: Some(package.to_string()),
+                message_type,
+                ..FileDescriptorProto::default()
+            };
+        let field = |name: &str, oneof_index: Option<i32>| FieldDescriptorProto {
+            name: Some(name.to_string()),
+            oneof_index,
+            ..FieldDescriptorProto::default()
+        };
+        let oneof = |name: &str| OneofDescriptorProto {
+            name: Some(name.to_string()),
+            ..OneofDescriptorProto::default()
+        };
+
+        // Distinct names, a package split across files, proto3 optional fields and map entries.
+        let descriptor_set = FileDescriptorSet {
+            file: vec![
+                file(
+                    "a.proto",
+                    "foo.bar",
+                    vec![DescriptorProto {
+                        name: Some("Baz".to_string()),
+                        field: vec![
+                            FieldDescriptorProto {
+                                proto3_optional: Some(true),
+                                ..field("qux", Some(0))
+                            },
+                            field("labels", None),
+                        ],
+                        oneof_decl: vec![oneof("_qux")],
+                        nested_type: vec![DescriptorProto {
+                            name: Some("LabelsEntry".to_string()),
+                            options: Some(MessageOptions {
+                                map_entry: Some(true),
+                                ..MessageOptions::default()
+                            }),
+                            ..DescriptorProto::default()
+                        }],
+                        ..DescriptorProto::default()
+                    }],
+                ),
+                file(
+                    "b.proto",
+                    "foo.bar",
+                    vec![DescriptorProto {
+                        name: Some("Qux".to_string()),
+                        ..DescriptorProto::default()
+                    }],
+                ),
+                // `Baz` has no nested module so `foo.bar.baz` does not collide with it.
+                file("c.proto", "foo.bar.baz", vec![]),
+            ],
+        };
+        assert_eq!(validate_rust_names(&descriptor_set), Ok(()));
+
+        // Packages which snake case to the same module.
+        let err = validate_rust_names(&FileDescriptorSet {
+            file: vec![
+                file("a.proto", "foo.BarBaz", vec![]),
+                file("b.proto", "foo.bar_baz", vec![]),
+            ],
+        })
+        .unwrap_err();
+        assert!(
+            err.contains(
+                "`bar_baz` in module `foo`: `foo.BarBaz` (a.proto), `foo.bar_baz` (b.proto)"
+            ),
+            "{}",
+            err
+        );
+
+        // A message's nested module and a package.
+        let err = validate_rust_names(&FileDescriptorSet {
+            file: vec![
+                file(
+                    "a.proto",
+                    "foo",
+                    vec![DescriptorProto {
+                        name: Some("Bar".to_string()),
+                        nested_type: vec![DescriptorProto {
+                            name: Some("Baz".to_string()),
+                            ..DescriptorProto::default()
+                        }],
+                        ..DescriptorProto::default()
+                    }],
+                ),
+                file("b.proto", "foo.bar", vec![]),
+            ],
+        })
+        .unwrap_err();
+        assert!(
+            err.contains("`bar` in module `foo`: `foo.Bar` (a.proto), `foo.bar` (b.proto)"),
+            "{}",
+            err
+        );
+
+        // Oneofs and fields.
+        let err = validate_rust_names(&FileDescriptorSet {
+            file: vec![file(
+                "a.proto",
+                "foo",
+                vec![DescriptorProto {
+                    name: Some("Bar".to_string()),
+                    field: vec![
+                        field("my_choice", None),
+                        field("a_value", Some(0)),
+                        field("AValue", Some(0)),
+                    ],
+                    oneof_decl: vec![oneof("MyChoice")],
+                    ..DescriptorProto::default()
+                }],
+            )],
+        })
+        .unwrap_err();
+        assert!(
+            err.contains("`my_choice` in the fields of `foo::Bar`: `foo.Bar.MyChoice` (a.proto), `foo.Bar.my_choice` (a.proto)"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("`AValue` in the variants of `foo::bar::MyChoice`: `foo.Bar.AValue` (a.proto), `foo.Bar.a_value` (a.proto)"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn get_file_descriptor_set_test() {
+        let file = |name: &str, dependency: &[&str]| FileDescriptorProto {
+            name: Some(name.to_string()),
+            dependency: dependency.iter().map(|d| d.to_string()).collect(),
+            ..FileDescriptorProto::default()
+        };
+
+        let tmp_dir =
+            env::temp_dir().join(format!("get_file_descriptor_set_test-{}", process::id()));
+        fs::create_dir_all(&tmp_dir).unwrap();
+
+        let transitive_descriptor_set = tmp_dir.join("transitive.bin");
+        fs::write(
+            &transitive_descriptor_set,
+            FileDescriptorSet {
+                file: vec![
+                    file("unused.proto", &[]),
+                    file("b.proto", &["c.proto"]),
+                    file("c.proto", &[]),
+                ],
+            }
+            .encode_to_vec(),
+        )
+        .unwrap();
+
+        let descriptor_set = FileDescriptorSet {
+            file: vec![file("a.proto", &["b.proto", "c.proto"])],
+        };
+        let file_descriptor_set = get_file_descriptor_set(
+            &descriptor_set,
+            std::slice::from_ref(&transitive_descriptor_set),
+        )
+        .unwrap();
+        assert_eq!(
+            file_descriptor_set
+                .file
+                .iter()
+                .map(|f| f.name())
+                .collect::<Vec<_>>(),
+            vec!["c.proto", "b.proto", "a.proto"]
+        );
+
+        let descriptor_set = FileDescriptorSet {
+            file: vec![file("a.proto", &["missing.proto"])],
+        };
+        assert!(get_file_descriptor_set(&descriptor_set, &[transitive_descriptor_set]).is_err());
+
+        fs::remove_dir_all(&tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn byte_string_literal_test() {
+        assert_eq!(byte_string_literal(b""), r#"b"""#);
+        assert_eq!(
+            byte_string_literal(b"a\n\"\\\x00\xff"),
+            r#"b"a\n\"\\\x00\xff""#
+        );
+    }
+
+    #[test]
+    fn get_reflect_options_test() {
+        let descriptor_set = FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("foo.proto".to_string()),
+                package: Some("foo".to_string()),
+                message_type: vec![DescriptorProto {
+                    name: Some("Bar".to_string()),
+                    nested_type: vec![
+                        DescriptorProto {
+                            name: Some("Baz".to_string()),
+                            ..DescriptorProto::default()
+                        },
+                        DescriptorProto {
+                            name: Some("LabelsEntry".to_string()),
+                            options: Some(MessageOptions {
+                                map_entry: Some(true),
+                                ..MessageOptions::default()
+                            }),
+                            ..DescriptorProto::default()
+                        },
+                    ],
+                    ..DescriptorProto::default()
+                }],
+                ..FileDescriptorProto::default()
+            }],
+        };
+
+        assert_eq!(
+            get_message_names(&descriptor_set),
+            vec!["foo.Bar".to_string(), "foo.Bar.Baz".to_string()]
+        );
+
+        let options = get_reflect_options(&descriptor_set)
+            .unwrap()
+            .iter()
+            .map(ProstPathOption::to_prost_opt)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            options,
+            vec![
+                "message_attribute=foo.Bar=#[derive(::prost_reflect::ReflectMessage)]".to_string(),
+                r#"message_attribute=foo.Bar=#[prost_reflect(file_descriptor_set_bytes = "crate::FILE_DESCRIPTOR_SET"\, message_name = "foo.Bar")]"#.to_string(),
+                "message_attribute=.foo.Bar.Baz=#[derive(::prost_reflect::ReflectMessage)]".to_string(),
+                r#"message_attribute=.foo.Bar.Baz=#[prost_reflect(file_descriptor_set_bytes = "crate::FILE_DESCRIPTOR_SET"\, message_name = "foo.Bar.Baz")]"#.to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn get_reflect_options_suffix_package_test() {
+        let file = |package: &str, message_type: DescriptorProto| FileDescriptorProto {
+            name: Some(format!("{}.proto", package)),
+            package: Some(package.to_string()),
+            message_type: vec![message_type],
+            ..FileDescriptorProto::default()
+        };
+        let message = |name: &str, nested_type: Vec<DescriptorProto>| DescriptorProto {
+            name: Some(name.to_string()),
+            nested_type,
+            ..DescriptorProto::default()
+        };
+
+        // `a.b.Msg` is a suffix of `z.a.b.Msg` so each needs a fully qualified path.
+        let descriptor_set = FileDescriptorSet {
+            file: vec![
+                file("a.b", message("Msg", Vec::new())),
+                file("z.a.b", message("Msg", Vec::new())),
+            ],
+        };
+        let paths = get_reflect_options(&descriptor_set)
+            .unwrap()
+            .iter()
+            .map(|option| option.path().to_string())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(
+            paths,
+            BTreeSet::from([".a.b.Msg".to_string(), ".z.a.b.Msg".to_string()])
+        );
+
+        // A fully qualified path would also match the nested message.
+        let descriptor_set = FileDescriptorSet {
+            file: vec![
+                file("a.b", message("Msg", vec![message("Nested", Vec::new())])),
+                file("z.a.b", message("Msg", Vec::new())),
+            ],
+        };
+        assert!(get_reflect_options(&descriptor_set)
+            .unwrap_err()
+            .contains("`a.b.Msg`"));
+    }
 }
@@ -0,0 +1,24 @@
+//! Tests the `FILE_DESCRIPTOR_SET` of generated crates.
+
+use prost::Message;
+use prost_types::FileDescriptorSet;
+
+#[test]
+fn test_file_descriptor_set() {
+    let file_descriptor_set = FileDescriptorSet::decode(service_proto::FILE_DESCRIPTOR_SET)
+        .expect("Failed to decode FILE_DESCRIPTOR_SET");
+
+    // Imports precede the files which import them.
+    let files = file_descriptor_set
+        .file
+        .iter()
+        .map(|file| file.name())
+        .collect::<Vec<_>>();
+    assert_eq!(files.last(), Some(&"service.proto"));
+    assert!(files.contains(&"imported.proto"));
+    assert!(files.contains(&"google/protobuf/timestamp.proto"));
+
+    let service = file_descriptor_set.file.last().unwrap();
+    assert_eq!(service.package(), "file_descriptor_set.service");
+    assert_eq!(service.message_type[0].name(), "Request");
+}
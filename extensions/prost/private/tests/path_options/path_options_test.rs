@@ -0,0 +1,32 @@
+//! Tests prost options scoped to proto paths.
+
+use std::collections::{BTreeMap, HashSet};
+
+use path_options_proto::path_options::{Key, Message};
+
+#[test]
+fn test_type_attributes() {
+    let keys = HashSet::from([
+        Key {
+            id: "a".to_string(),
+        },
+        Key {
+            id: "a".to_string(),
+        },
+    ]);
+
+    assert_eq!(keys.len(), 1);
+}
+
+#[test]
+fn test_bytes_and_btree_map() {
+    let message = Message {
+        name: "message".to_string(),
+        payload: b"payload".to_vec().into(),
+        labels: BTreeMap::from([("a".to_string(), "b".to_string())]),
+    };
+
+    // `Bytes::slice` is not available on `Vec<u8>`.
+    assert_eq!(message.payload.slice(0..3).as_ref(), b"pay");
+    assert_eq!(message.labels.keys().collect::<Vec<_>>(), vec!["a"]);
+}
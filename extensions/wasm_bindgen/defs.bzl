@@ -5,6 +5,7 @@ Bazel rules for generating wasm modules for Javascript using [wasm-bindgen][wb].
 ## Rules
 
 - [rust_wasm_bindgen](#rust_wasm_bindgen)
+- [rust_wasm_bindgen_test](#rust_wasm_bindgen_test)
 - [rust_wasm_bindgen_toolchain](#rust_wasm_bindgen_toolchain)
 
 ## Setup
@@ -27,6 +28,19 @@ should avoid calling `rust_wasm_bindgen_register_toolchains` and instead use the
 [rust_wasm_bindgen_toolchain](#rust_wasm_bindgen_toolchain) rule to define their own
 toolchains to register in the workspace.
 
+## Testing
+
+[rust_wasm_bindgen_test](#rust_wasm_bindgen_test) runs [wasm-bindgen tests][wbt] in one of
+the environments selected by its `mode` attribute:
+
+- `browser` (default): A headless browser driven by the toolchain's `webdriver`.
+- `node`: The `node` binary assigned to the toolchain's `nodejs` attribute.
+- `deno`: The `deno` binary assigned to the toolchain's `deno` attribute.
+
+The default toolchain only provides browsers, so Node.js and Deno tests require a custom
+[rust_wasm_bindgen_toolchain](#rust_wasm_bindgen_toolchain). Test results are reported
+per test case through `XML_OUTPUT_FILE` in every mode.
+
 ## Interfacing with Javascript rules
 
 While it's recommended for users to maintain their own , in the
@@ -39,6 +53,7 @@ for the `rules_js` submodule).
 
 
 [wb]: https://github.com/rustwasm/wasm-bindgen
+[wbt]: https://rustwasm.github.io/wasm-bindgen/wasm-bindgen-test/index.html
 """
 
 load(
@@ -197,27 +197,37 @@ An example of this rule in use can be seen at [@rules_rust//examples/wasm](../ex
 
 def _rust_wasm_bindgen_toolchain_impl(ctx):
     all_test_files = depset()
-    if ctx.attr.wasm_bindgen_test or ctx.attr.webdriver or ctx.attr.browser_type:
-        if not ctx.attr.wasm_bindgen_test:
-            fail("Not all webdriver attributes provided. Missing `wasm_bindgen_test` on `{}`".format(ctx.label))
+    if ctx.attr.webdriver or ctx.attr.browser_type:
         if not ctx.attr.webdriver:
             fail("Not all webdriver attributes provided. Missing `webdriver` on `{}`".format(ctx.label))
         if not ctx.attr.browser_type:
             fail("Not all webdriver attributes provided. Missing `browser_type` on `{}`".format(ctx.label))
 
+    test_tools = [
+        tool
+        for tool in [
+            ctx.attr.webdriver,
+            ctx.attr.webdriver_json,
+            ctx.attr.browser,
+            ctx.attr.nodejs,
+            ctx.attr.deno,
+        ]
+        if tool
+    ]
+
+    if ctx.attr.wasm_bindgen_test or test_tools:
+        if not ctx.attr.wasm_bindgen_test:
+            fail("Not all test attributes provided. Missing `wasm_bindgen_test` on `{}`".format(ctx.label))
+
         all_depsets = [
-            ctx.attr.webdriver[DefaultInfo].files,
-            ctx.attr.webdriver[DefaultInfo].default_runfiles.files,
             ctx.attr.wasm_bindgen_test_runner[DefaultInfo].files,
             ctx.attr.wasm_bindgen_test_runner[DefaultInfo].default_runfiles.files,
-            ctx.attr.webdriver_json[DefaultInfo].files,
-            ctx.attr.webdriver_json[DefaultInfo].default_runfiles.files,
         ]
 
-        if ctx.attr.browser:
+        for tool in test_tools:
             all_depsets.extend([
-                ctx.attr.browser[DefaultInfo].files,
-                ctx.attr.browser[DefaultInfo].default_runfiles.files,
+                tool[DefaultInfo].files,
+                tool[DefaultInfo].default_runfiles.files,
             ])
 
         all_test_files = depset(transitive = all_depsets)
@@ -231,6 +241,8 @@ def _rust_wasm_bindgen_toolchain_impl(ctx):
         webdriver_json = ctx.file.webdriver_json,
         browser_type = ctx.attr.browser_type,
         browser = ctx.executable.browser,
+        nodejs = ctx.executable.nodejs,
+        deno = ctx.executable.deno,
         all_test_files = all_test_files,
 
         # Deprecated
@@ -285,6 +297,18 @@ For additional information, see the [Bazel toolchains documentation][toolchains]
                 "safari",
             ],
         ),
+        "deno": attr.label(
+            doc = "The `deno` binary used by `rust_wasm_bindgen_test` targets with `mode = \"deno\"`.",
+            cfg = "exec",
+            executable = True,
+            allow_files = True,
+        ),
+        "nodejs": attr.label(
+            doc = "The `node` binary used by `rust_wasm_bindgen_test` targets with `mode = \"node\"`.",
+            cfg = "exec",
+            executable = True,
+            allow_files = True,
+        ),
         "wasm_bindgen_cli": attr.label(
             doc = "The label of a `wasm-bindgen-cli` executable.",
             executable = True,
@@ -61,8 +61,13 @@ def _rlocationpath(file, workspace_name):
 
 def _rust_wasm_bindgen_test_impl(ctx):
     wb_toolchain = ctx.toolchains[Label("//:toolchain_type")]
-    if not wb_toolchain.webdriver:
-        fail("The currently registered wasm_bindgen_toolchain does not have a webdriver assigned. Tests are unavailable without one.")
+    mode = ctx.attr.mode
+    if mode == "browser" and not wb_toolchain.webdriver:
+        fail("The currently registered wasm_bindgen_toolchain does not have a webdriver assigned. Browser tests are unavailable without one.")
+    if mode == "node" and not wb_toolchain.nodejs:
+        fail("The currently registered wasm_bindgen_toolchain does not have `nodejs` assigned. Node tests are unavailable without one.")
+    if mode == "deno" and not wb_toolchain.deno:
+        fail("The currently registered wasm_bindgen_toolchain does not have `deno` assigned. Deno tests are unavailable without one.")
 
     toolchain = find_toolchain(ctx)
 
@@ -164,17 +169,27 @@ def _rust_wasm_bindgen_test_impl(ctx):
         is_executable = True,
     )
 
-    if wb_toolchain.browser:
-        env["BROWSER"] = _rlocationpath(wb_toolchain.browser, ctx.workspace_name)
-
-    env["BROWSER_TYPE"] = wb_toolchain.browser_type
-    env["WEBDRIVER"] = _rlocationpath(wb_toolchain.webdriver, ctx.workspace_name)
-    env["WEBDRIVER_ARGS"] = " ".join(wb_toolchain.webdriver_args)
-    env["WEBDRIVER_JSON"] = _rlocationpath(wb_toolchain.webdriver_json, ctx.workspace_name)
+    env["WASM_BINDGEN_TEST_MODE"] = mode
     env["WASM_BINDGEN_TEST_RUNNER"] = _rlocationpath(wb_toolchain.wasm_bindgen_test_runner, ctx.workspace_name)
 
-    # Force the use of a browser for now as there is no node integration.
-    env["WASM_BINDGEN_USE_BROWSER"] = "1"
+    if mode == "browser":
+        if wb_toolchain.browser:
+            env["BROWSER"] = _rlocationpath(wb_toolchain.browser, ctx.workspace_name)
+
+        env["BROWSER_TYPE"] = wb_toolchain.browser_type
+        env["WEBDRIVER"] = _rlocationpath(wb_toolchain.webdriver, ctx.workspace_name)
+        env["WEBDRIVER_ARGS"] = " ".join(wb_toolchain.webdriver_args)
+        env["WEBDRIVER_JSON"] = _rlocationpath(wb_toolchain.webdriver_json, ctx.workspace_name)
+        env["WASM_BINDGEN_USE_BROWSER"] = "1"
+    elif mode == "node":
+        env["NODEJS"] = _rlocationpath(wb_toolchain.nodejs, ctx.workspace_name)
+
+        # Suites configured with `wasm_bindgen_test_configure!(run_in_browser)`
+        # are skipped rather than failing when run in node.
+        env["WASM_BINDGEN_TEST_ONLY_NODE"] = "1"
+    elif mode == "deno":
+        env["DENO"] = _rlocationpath(wb_toolchain.deno, ctx.workspace_name)
+        env["WASM_BINDGEN_USE_DENO"] = "1"
 
     providers = []
 
@@ -261,6 +276,19 @@ rust_wasm_bindgen_test = rule(
         "env_inherit": attr.string_list(
             doc = "Specifies additional environment variables to inherit from the external environment when the test is executed by bazel test.",
         ),
+        "mode": attr.string(
+            doc = """\
+            The environment in which tests are run.
+
+            - `browser`: Run tests in the toolchain's headless `browser` via its `webdriver`.
+            - `node`: Run tests with the toolchain's `nodejs` binary.
+            - `deno`: Run tests with the toolchain's `deno` binary.
+
+            Per-test results are written as JUnit XML to `XML_OUTPUT_FILE` in all modes.
+        """,
+            default = "browser",
+            values = ["browser", "node", "deno"],
+        ),
         "proc_macro_deps": attr.label_list(
             doc = """\
                 List of `rust_proc_macro` targets used to help build this library target.
@@ -3,9 +3,12 @@
 use std::collections::BTreeMap;
 use std::env;
 use std::fs;
+use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
-use std::process::{exit, Command};
+use std::process::{exit, Command, Stdio};
+use std::time::Instant;
 
+use libtest_report::{parse_pretty_output, render_junit_xml};
 use runfiles::{rlocation, Runfiles};
 
 fn write_webdriver_for_browser(
@@ -86,20 +89,12 @@ fn write_webdriver_for_browser(
     });
 }
 
-fn main() {
-    let runfiles = Runfiles::create().expect("Failed to locate runfiles");
-
-    let test_runner = rlocation!(
-        runfiles,
-        env::var("WASM_BINDGEN_TEST_RUNNER").expect("Failed to find TEST_WASM_BINARY env var")
-    )
-    .expect("Failed to locate test binary");
-    let test_bin = rlocation!(
-        runfiles,
-        env::var("TEST_WASM_BINARY").expect("Failed to find TEST_WASM_BINARY env var")
-    )
-    .expect("Failed to locate test binary");
-
+/// Configure the environment for running tests in a headless browser via webdriver.
+fn configure_browser(
+    runfiles: &Runfiles,
+    env: &mut BTreeMap<String, String>,
+    undeclared_test_outputs: &Path,
+) {
     let browser_type = env::var("BROWSER_TYPE").expect("Failed to find `BROWSER_TYPE` env var");
     let browser = env::var_os("BROWSER").map(|_| {
         rlocation!(runfiles, env::var("BROWSER").unwrap()).expect("Failed to locate browser")
@@ -117,22 +112,9 @@ fn main() {
     )
     .expect("Failed to locate webdriver");
 
-    // Update any existing environment variables.
-    let mut env = env::vars().collect::<BTreeMap<_, _>>();
-    env.insert("TMP".to_string(), env["TEST_TMPDIR"].clone());
-    env.insert("TEMP".to_string(), env["TEST_TMPDIR"].clone());
-    env.insert("TMPDIR".to_string(), env["TEST_TMPDIR"].clone());
-    env.insert("HOME".to_string(), env["TEST_TMPDIR"].clone());
-    env.insert("USERPROFILE".to_string(), env["TEST_TMPDIR"].clone());
-
     let webdriver_args =
         env::var("WEBDRIVER_ARGS").expect("Failed to find WEBDRIVER_ARGS env var.");
 
-    let undeclared_test_outputs = PathBuf::from(
-        env::var("TEST_UNDECLARED_OUTPUTS_DIR")
-            .expect("TEST_UNDECLARED_OUTPUTS_DIR should always be defined for tests."),
-    );
-
     let updated_webdriver_json = undeclared_test_outputs.join("webdriver.json");
     env.insert(
         "WASM_BINDGEN_WEBDRIVER_JSON".to_string(),
@@ -224,19 +206,162 @@ fn main() {
             panic!("Unexpected browser type: {}", browser_type)
         }
     }
+}
+
+/// Expose a javascript runtime to `wasm-bindgen-test-runner`.
+///
+/// The runner always spawns `node` or `deno` from `PATH` so the runtime from the
+/// toolchain is linked into a directory under the name the runner expects and
+/// that directory is put at the front of `PATH`.
+fn configure_js_runtime(
+    name: &str,
+    runtime: &Path,
+    env: &mut BTreeMap<String, String>,
+    test_tmpdir: &Path,
+) {
+    let bin_dir = test_tmpdir.join("wasm_bindgen_test_bin");
+    fs::create_dir_all(&bin_dir)
+        .unwrap_or_else(|e| panic!("Failed to create directory: {}\n{:?}", bin_dir.display(), e));
+
+    let link = bin_dir.join(format!("{}{}", name, env::consts::EXE_SUFFIX));
+    if link.exists() {
+        fs::remove_file(&link)
+            .unwrap_or_else(|e| panic!("Failed to remove: {}\n{:?}", link.display(), e));
+    }
+
+    #[cfg(unix)]
+    let result = std::os::unix::fs::symlink(runtime, &link);
+    #[cfg(not(unix))]
+    let result = fs::copy(runtime, &link).map(|_| ());
+
+    result.unwrap_or_else(|e| {
+        panic!(
+            "Failed to link {} runtime: {} -> {}\n{:?}",
+            name,
+            runtime.display(),
+            link.display(),
+            e
+        )
+    });
+
+    let mut paths = vec![bin_dir];
+    if let Some(path) = env.get("PATH") {
+        paths.extend(env::split_paths(path));
+    }
+    let path = env::join_paths(paths).expect("Failed to join PATH entries");
+    env.insert("PATH".to_string(), path.to_string_lossy().to_string());
+}
+
+fn main() {
+    let runfiles = Runfiles::create().expect("Failed to locate runfiles");
+
+    let test_runner = rlocation!(
+        runfiles,
+        env::var("WASM_BINDGEN_TEST_RUNNER").expect("Failed to find TEST_WASM_BINARY env var")
+    )
+    .expect("Failed to locate test binary");
+    let test_bin = rlocation!(
+        runfiles,
+        env::var("TEST_WASM_BINARY").expect("Failed to find TEST_WASM_BINARY env var")
+    )
+    .expect("Failed to locate test binary");
+
+    let test_mode = env::var("WASM_BINDGEN_TEST_MODE").unwrap_or_else(|_| "browser".to_string());
+
+    // Update any existing environment variables.
+    let mut env = env::vars().collect::<BTreeMap<_, _>>();
+    env.insert("TMP".to_string(), env["TEST_TMPDIR"].clone());
+    env.insert("TEMP".to_string(), env["TEST_TMPDIR"].clone());
+    env.insert("TMPDIR".to_string(), env["TEST_TMPDIR"].clone());
+    env.insert("HOME".to_string(), env["TEST_TMPDIR"].clone());
+    env.insert("USERPROFILE".to_string(), env["TEST_TMPDIR"].clone());
+
+    let test_tmpdir = PathBuf::from(env["TEST_TMPDIR"].clone());
+
+    let undeclared_test_outputs = PathBuf::from(
+        env::var("TEST_UNDECLARED_OUTPUTS_DIR")
+            .expect("TEST_UNDECLARED_OUTPUTS_DIR should always be defined for tests."),
+    );
+
+    match test_mode.as_str() {
+        "browser" => configure_browser(&runfiles, &mut env, &undeclared_test_outputs),
+        "node" => {
+            let nodejs = rlocation!(
+                runfiles,
+                env::var("NODEJS").expect("Failed to find NODEJS env var.")
+            )
+            .expect("Failed to locate nodejs");
+            configure_js_runtime("node", &nodejs, &mut env, &test_tmpdir);
+        }
+        "deno" => {
+            let deno = rlocation!(
+                runfiles,
+                env::var("DENO").expect("Failed to find DENO env var.")
+            )
+            .expect("Failed to locate deno");
+            configure_js_runtime("deno", &deno, &mut env, &test_tmpdir);
+        }
+        _ => {
+            panic!("Unexpected test mode: {}", test_mode)
+        }
+    }
 
-    // Run the test
+    // Run the test, forwarding stdout while retaining it for the test report.
+    let start = Instant::now();
     let mut command = Command::new(test_runner);
-    command.envs(env).arg(test_bin).args(env::args().skip(1));
-    let result = command
-        .status()
+    command
+        .envs(env)
+        .arg(test_bin)
+        .args(env::args().skip(1))
+        .stdout(Stdio::piped());
+    let mut child = command
+        .spawn()
         .unwrap_or_else(|_| panic!("Failed to spawn command: {:#?}", command));
 
+    let mut output = String::new();
+    {
+        let stdout = child.stdout.take().expect("Child stdout should be piped");
+        let mut reader = BufReader::new(stdout);
+        let mut console = std::io::stdout();
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => {
+                    console
+                        .write_all(&line)
+                        .and_then(|_| console.flush())
+                        .expect("Failed to forward test output");
+                    output.push_str(&String::from_utf8_lossy(&line));
+                }
+                Err(e) => panic!("Failed to read test output\n{:?}", e),
+            }
+        }
+    }
+
+    let result = child
+        .wait()
+        .unwrap_or_else(|e| panic!("Failed to wait on command: {:#?}\n{:?}", command, e));
+
+    // Only write a report when tests were observed so Bazel can otherwise
+    // generate its default report.
+    if let Some(xml_output_file) = env::var_os("XML_OUTPUT_FILE") {
+        let tests = parse_pretty_output(&output);
+        if !tests.is_empty() {
+            let suite = env::var("TEST_TARGET").unwrap_or_else(|_| "wasm_bindgen_test".to_string());
+            let xml = render_junit_xml(&suite, &tests, Some(start.elapsed().as_secs_f64()));
+            fs::write(&xml_output_file, xml).unwrap_or_else(|e| {
+                panic!(
+                    "Failed to write test report: {}\n{:?}",
+                    PathBuf::from(&xml_output_file).display(),
+                    e
+                )
+            });
+        }
+    }
+
     if !result.success() {
-        exit(
-            result
-                .code()
-                .expect("Completed processes will always have exit codes."),
-        )
+        exit(result.code().unwrap_or(1))
     }
 }
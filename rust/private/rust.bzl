@@ -321,6 +321,71 @@ def get_rust_test_flags(attr):
 
     return rust_flags
 
+def _create_libtest_launcher(ctx, default_info, test_binary):
+    """Replace the executable of a `rust_test` target with the libtest launcher.
+
+    Args:
+        ctx (ctx): The ctx object for the current target.
+        default_info (DefaultInfo): The `DefaultInfo` provider of the test binary.
+        test_binary (File): The test binary the launcher should run.
+
+    Returns:
+        tuple: A `DefaultInfo` provider for the launcher and the runfiles path of the test binary.
+    """
+    is_windows = ctx.executable._libtest_launcher.extension == "exe"
+    launcher = ctx.actions.declare_file("{}.libtest_launcher{}".format(
+        ctx.label.name,
+        ".exe" if is_windows else "",
+    ))
+    ctx.actions.symlink(
+        output = launcher,
+        target_file = ctx.executable._libtest_launcher,
+        is_executable = True,
+    )
+
+    runfiles = ctx.runfiles(files = [test_binary]).merge_all([
+        default_info.default_runfiles,
+        ctx.attr._libtest_launcher[DefaultInfo].default_runfiles,
+    ])
+
+    return DefaultInfo(
+        files = depset([launcher], transitive = [default_info.files]),
+        runfiles = runfiles,
+        executable = launcher,
+    ), _rlocationpath(ctx, test_binary)
+
+def _rlocationpath(ctx, file):
+    """Determine the runfiles path of a file.
+
+    Args:
+        ctx (ctx): The ctx object for the current target.
+        file (File): The file to locate.
+
+    Returns:
+        str: The path of `file` relative to the runfiles root.
+    """
+    if file.short_path.startswith("../"):
+        return file.short_path[len("../"):]
+    return "{}/{}".format(ctx.workspace_name, file.short_path)
+
+def _coverage_objects(ctx):
+    """Collect the instrumented Rust binaries and shared libraries among the `data` of a test.
+
+    Args:
+        ctx (ctx): The ctx object for the current target.
+
+    Returns:
+        list: The runfiles paths of objects coverage should be reported for in addition to the test binary.
+    """
+    objects = []
+    for data in getattr(ctx.attr, "data", []):
+        if rust_common.crate_info not in data or not ctx.coverage_instrumented(target = data):
+            continue
+        crate_info = data[rust_common.crate_info]
+        if crate_info.type in ("bin", "cdylib", "dylib"):
+            objects.append(_rlocationpath(ctx, crate_info.output))
+    return objects
+
 def _rust_test_impl(ctx):
     """The implementation of the `rust_test` rule.
 
@@ -521,6 +586,26 @@ def _rust_test_impl(ctx):
 
         env["RUST_LLVM_COV"] = llvm_cov_path
         env["RUST_LLVM_PROFDATA"] = llvm_profdata_path
+        if toolchain._coverage_ignore_filename_regex:
+            env["RUST_COVERAGE_IGNORE_FILENAME_REGEX"] = "|".join([
+                "({})".format(regex)
+                for regex in toolchain._coverage_ignore_filename_regex
+            ])
+        if toolchain._coverage_report_formats:
+            for report_format in toolchain._coverage_report_formats:
+                if report_format not in ["cobertura", "html", "json"]:
+                    fail("Unsupported coverage report format `{}`. Expected one of `cobertura`, `html`, `json`.".format(report_format))
+            env["RUST_COVERAGE_REPORT_FORMATS"] = ",".join(toolchain._coverage_report_formats)
+
+        # Always set so that `collect_coverage` does not search the runfiles when there are no objects.
+        env["RUST_COVERAGE_OBJECTS"] = " ".join(_coverage_objects(ctx))
+    if toolchain._experimental_use_libtest_launcher and ctx.attr.use_libtest_harness:
+        providers[0], test_binary_rlocationpath = _create_libtest_launcher(ctx, providers[0], output)
+        env["RUST_LIBTEST_LAUNCHER_TEST_BINARY"] = test_binary_rlocationpath
+        if coverage:
+            # The launcher is not instrumented so coverage should be reported for the test binary.
+            env["COVERAGE_BINARY"] = output.short_path
+
     components = "{}/{}".format(ctx.label.workspace_root, ctx.label.package).split("/")
     env["CARGO_MANIFEST_DIR"] = "/".join([c for c in components if c])
     providers.append(RunEnvironmentInfo(
@@ -894,8 +979,18 @@ _rust_test_attrs = {
             Whether to use `libtest`. For targets using this flag, individual tests can be run by using the
             [--test_arg](https://docs.bazel.build/versions/4.0.0/command-line-reference.html#flag--test_arg) flag.
             E.g. `bazel test //src:rust_test --test_arg=foo::test::test_fn`.
+
+            When `@rules_rust//rust/settings:experimental_use_libtest_launcher` is enabled, these tests
+            additionally support `--test_filter`, `shard_count`, and report individual test results
+            to Bazel as JUnit XML.
         """),
     ),
+    "_libtest_launcher": attr.label(
+        doc = "A launcher adding support for test sharding and JUnit reports to libtest harnesses.",
+        default = Label("//util/libtest_launcher"),
+        executable = True,
+        cfg = "target",
+    ),
     "_use_grep_includes": attr.bool(default = True),
 } | _coverage_attrs | _experimental_use_cc_common_link_attrs
 
@@ -1086,6 +1086,8 @@ def construct_arguments(
     if toolchain.llvm_cov and include_coverage:
         # https://doc.rust-lang.org/rustc/instrument-coverage.html
         rustc_flags.add("--codegen=instrument-coverage")
+        if toolchain._coverage_level != "line":
+            rustc_flags.add("-Zcoverage-options={}".format(toolchain._coverage_level))
 
     if toolchain._experimental_link_std_dylib:
         rustc_flags.add("--codegen=prefer-dynamic")
@@ -0,0 +1,420 @@
+//! A test runner for `rust_doc_test` targets.
+//!
+//! `rustdoc --test` builds and runs all doctests of a crate in a single call. This
+//! runner replays the rustdoc action recorded by `rustdoc_test_writer` from within
+//! the test's runfiles while adding support for `--test_filter`, test sharding, and
+//! reporting individual doctest results to Bazel via `XML_OUTPUT_FILE`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{self, Command, Output};
+
+use libtest_report::{
+    format_result, format_summary, parse_json_output, parse_list_output, parse_pretty_output,
+    shard_from_env, write_junit_xml, TestResult, TestStatus, UNSTABLE_OPTIONS_ERROR,
+};
+use runfiles::{rlocation, Runfiles};
+
+/// The rustdoc action to run, as written by `rustdoc_test_writer`.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct TestSpec {
+    /// Environment variables for the rustdoc action.
+    env: BTreeMap<String, String>,
+
+    /// The `argv` of the rustdoc action.
+    argv: Vec<String>,
+}
+
+/// Reverse the escaping of newlines and backslashes applied by `rustdoc_test_writer`.
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some(other) => unescaped.push(other),
+                None => unescaped.push('\\'),
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+    unescaped
+}
+
+fn parse_spec(content: &str) -> TestSpec {
+    let mut spec = TestSpec::default();
+    for line in content.lines().filter(|l| !l.is_empty()) {
+        if let Some(entry) = line.strip_prefix("env ") {
+            let (key, value) = entry
+                .split_once('=')
+                .unwrap_or_else(|| panic!("Invalid env entry in test spec: {}", line));
+            spec.env.insert(key.to_owned(), unescape(value));
+        } else if let Some(arg) = line.strip_prefix("arg ") {
+            spec.argv.push(unescape(arg));
+        } else {
+            panic!("Unexpected line in test spec: {}", line);
+        }
+    }
+
+    if spec.argv.is_empty() {
+        panic!("The test spec does not contain a command to run");
+    }
+
+    spec
+}
+
+/// The part of a doctest name which identifies the documented item, e.g. `src/lib.rs - add`.
+fn doctest_item(name: &str) -> &str {
+    name.rsplit_once(" (line ")
+        .map(|(item, _)| item)
+        .unwrap_or(name)
+}
+
+/// Determine the doctests which belong to a shard.
+///
+/// Doctests of the same item are kept in the same shard since their names only differ by line
+/// number which makes them difficult to select independently.
+fn shard_doctests(names: &[String], index: usize, total: usize) -> BTreeSet<String> {
+    let mut items: BTreeMap<&str, Vec<&String>> = BTreeMap::new();
+    for name in names {
+        items.entry(doctest_item(name)).or_default().push(name);
+    }
+
+    items
+        .into_values()
+        .enumerate()
+        .filter(|(i, _)| i % total == index)
+        .flat_map(|(_, names)| names.into_iter().cloned())
+        .collect()
+}
+
+/// Compute libtest filters which select the given doctests.
+///
+/// rustdoc splits `--test-args` on whitespace so doctest names, which contain spaces, cannot be
+/// passed as exact filters. Instead, for each doctest the most specific whitespace free part of
+/// its name which does not match any unselected doctest is used. If no such part exists, some
+/// unselected doctests will also run and their results must be dropped with [retain_selected].
+fn select_filters(names: &[String], selected: &BTreeSet<String>) -> Vec<String> {
+    let unselected: Vec<&String> = names.iter().filter(|n| !selected.contains(*n)).collect();
+
+    let mut filters = BTreeSet::new();
+    for name in selected {
+        let mut tokens: Vec<&str> = name.split_whitespace().filter(|t| *t != "-").collect();
+        tokens.sort_by_key(|t| std::cmp::Reverse(t.len()));
+
+        let token = tokens
+            .iter()
+            .find(|token| !unselected.iter().any(|n| n.contains(**token)))
+            .or_else(|| tokens.first());
+
+        if let Some(token) = token {
+            filters.insert(token.to_string());
+        }
+    }
+
+    filters.into_iter().collect()
+}
+
+/// Drop the results of doctests which ran despite not being selected.
+///
+/// Returns whether or not all remaining doctests passed.
+fn retain_selected(results: &mut Vec<TestResult>, selected: &BTreeSet<String>) -> bool {
+    results.retain(|result| selected.contains(&result.name));
+    results
+        .iter()
+        .all(|result| result.status != TestStatus::Failed)
+}
+
+/// Recreate the `external` directory of `--legacy_external_runfiles` which paths in the
+/// rustdoc action may refer to.
+fn ensure_external_link() {
+    // TODO: Instead of creating a symlink to mimic the behavior of
+    // --legacy_external_runfiles, the action args should be sanitized
+    // to run in a runfiles directory without this link.
+    let external = Path::new("external");
+    if external.exists() {
+        return;
+    }
+
+    #[cfg(unix)]
+    let result = std::os::unix::fs::symlink("../", external);
+    #[cfg(windows)]
+    let result = std::os::windows::fs::symlink_dir("..\\", external);
+
+    if let Err(e) = result {
+        eprintln!("Warning: Failed to create `external` link: {:?}", e);
+    }
+}
+
+fn rustdoc_command(spec: &TestSpec, test_args: &[String]) -> Command {
+    let mut command = Command::new(&spec.argv[0]);
+    command
+        .args(&spec.argv[1..])
+        .args(test_args.iter().map(|arg| format!("--test-args={}", arg)));
+
+    // Like the scripts previously generated for doctests, only the environment of the
+    // action is used on Unix while the test environment is extended on Windows.
+    if cfg!(not(windows)) {
+        command.env_clear();
+    }
+    command.envs(&spec.env);
+    command
+}
+
+fn run(mut command: Command) -> Output {
+    command
+        .output()
+        .unwrap_or_else(|e| panic!("Failed to spawn command: {:#?}\n{:?}", command, e))
+}
+
+/// Forward the captured output of a rustdoc invocation.
+fn forward_output(output: &Output) {
+    io::stdout().write_all(&output.stdout).unwrap();
+    io::stderr().write_all(&output.stderr).unwrap();
+}
+
+/// Print test results in a format resembling the default libtest output.
+fn print_results(results: &[TestResult]) {
+    println!("\nrunning {} tests", results.len());
+    for result in results {
+        println!("{}", format_result(result));
+    }
+    print!("{}", format_summary(results));
+}
+
+fn main() {
+    let runfiles = Runfiles::create().expect("Failed to locate runfiles");
+    let spec_path = rlocation!(
+        runfiles,
+        env::var("RUSTDOC_TEST_SPEC").expect("Failed to find RUSTDOC_TEST_SPEC env var")
+    )
+    .expect("Failed to locate test spec");
+    let spec = parse_spec(
+        &fs::read_to_string(&spec_path)
+            .unwrap_or_else(|e| panic!("Failed to read: {}\n{:?}", spec_path.display(), e)),
+    );
+
+    ensure_external_link();
+
+    let test_filter = env::var("TESTBRIDGE_TEST_ONLY")
+        .ok()
+        .filter(|f| !f.is_empty());
+    let shard = shard_from_env();
+
+    let mut filters = Vec::new();
+    let mut selection = None;
+    if test_filter.is_some() || shard.is_some() {
+        let output = run(rustdoc_command(&spec, &["--list".to_owned()]));
+        if !output.status.success() {
+            forward_output(&output);
+            process::exit(output.status.code().unwrap_or(1));
+        }
+
+        let names = parse_list_output(&String::from_utf8_lossy(&output.stdout));
+        let mut selected: BTreeSet<String> = names
+            .iter()
+            .filter(|name| match &test_filter {
+                Some(filter) => name.contains(filter.as_str()),
+                None => true,
+            })
+            .cloned()
+            .collect();
+        if let Some((index, total)) = shard {
+            let shard_names = shard_doctests(&names, index, total);
+            selected.retain(|name| shard_names.contains(name));
+        }
+
+        if selected.is_empty() {
+            println!("No doctests selected to run.");
+            write_junit_xml(&[]);
+            return;
+        }
+
+        filters = select_filters(&names, &selected);
+        selection = Some(selected);
+    }
+
+    // Prefer machine readable output which is only available on nightly toolchains.
+    let mut json_args: Vec<String> = vec![
+        "-Zunstable-options".to_owned(),
+        "--format=json".to_owned(),
+        "--report-time".to_owned(),
+    ];
+    json_args.extend(filters.iter().cloned());
+    let output = run(rustdoc_command(&spec, &json_args));
+
+    let (output, mut results, is_json) = if !output.status.success()
+        && String::from_utf8_lossy(&output.stderr).contains(UNSTABLE_OPTIONS_ERROR)
+    {
+        let output = run(rustdoc_command(&spec, &filters));
+        forward_output(&output);
+        let results = parse_pretty_output(&String::from_utf8_lossy(&output.stdout));
+        (output, results, false)
+    } else {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        for line in stdout.lines().filter(|l| !l.starts_with('{')) {
+            println!("{}", line);
+        }
+        io::stderr().write_all(&output.stderr).unwrap();
+        let results = parse_json_output(&stdout);
+        (output, results, true)
+    };
+
+    // Only the selected doctests decide the outcome so that each doctest is
+    // reported by exactly one shard.
+    let mut success = output.status.success();
+    if let Some(selected) = &selection {
+        if !results.is_empty() {
+            success = retain_selected(&mut results, selected);
+        }
+    }
+    if is_json {
+        print_results(&results);
+    }
+
+    // Leave reporting to Bazel if rustdoc failed before any doctests ran.
+    if !results.is_empty() || output.status.success() {
+        write_junit_xml(&results);
+    }
+
+    if !success {
+        process::exit(output.status.code().filter(|code| *code != 0).unwrap_or(1));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        vec![
+            "src/lib.rs - add (line 5)".to_owned(),
+            "src/lib.rs - add (line 15)".to_owned(),
+            "src/lib.rs - sub (line 20)".to_owned(),
+            "src/lib.rs - Calc::add (line 30)".to_owned(),
+            "src/lib.rs - Calc::mul (line 40)".to_owned(),
+        ]
+    }
+
+    #[test]
+    fn parse_spec_test() {
+        let spec = parse_spec("env A=1\nenv B=x\\ny\narg wrapper\narg --\narg C:\\\\path\n");
+
+        assert_eq!(
+            spec,
+            TestSpec {
+                env: BTreeMap::from([
+                    ("A".to_owned(), "1".to_owned()),
+                    ("B".to_owned(), "x\ny".to_owned()),
+                ]),
+                argv: vec!["wrapper".to_owned(), "--".to_owned(), "C:\\path".to_owned()],
+            }
+        );
+    }
+
+    #[test]
+    fn shard_doctests_test() {
+        let names = names();
+
+        let shards: Vec<BTreeSet<String>> = (0..2).map(|i| shard_doctests(&names, i, 2)).collect();
+
+        // Items are sorted: `Calc::add`, `Calc::mul`, `add`, `sub`.
+        assert_eq!(
+            shards[0],
+            BTreeSet::from([
+                "src/lib.rs - Calc::add (line 30)".to_owned(),
+                "src/lib.rs - add (line 5)".to_owned(),
+                "src/lib.rs - add (line 15)".to_owned(),
+            ])
+        );
+        assert_eq!(
+            shards[1],
+            BTreeSet::from([
+                "src/lib.rs - Calc::mul (line 40)".to_owned(),
+                "src/lib.rs - sub (line 20)".to_owned(),
+            ])
+        );
+    }
+
+    #[test]
+    fn select_filters_test() {
+        let names = names();
+
+        // `sub` only appears in its own name.
+        let selected = BTreeSet::from(["src/lib.rs - sub (line 20)".to_owned()]);
+        assert_eq!(select_filters(&names, &selected), vec!["sub"]);
+
+        // `add` is also part of `Calc::add` so the line number is used instead.
+        let selected = BTreeSet::from([
+            "src/lib.rs - add (line 5)".to_owned(),
+            "src/lib.rs - add (line 15)".to_owned(),
+        ]);
+        assert_eq!(select_filters(&names, &selected), vec!["15)", "5)"]);
+
+        let selected = BTreeSet::from(["src/lib.rs - Calc::mul (line 40)".to_owned()]);
+        assert_eq!(select_filters(&names, &selected), vec!["Calc::mul"]);
+    }
+
+    #[test]
+    fn select_filters_fallback_test() {
+        let names = vec![
+            "lib.rs - a (line 1)".to_owned(),
+            "lib.rs - a (line 11)".to_owned(),
+        ];
+
+        // No part of the name is unique so the most specific part is used and
+        // `lib.rs - a (line 11)` will also run.
+        let selected = BTreeSet::from(["lib.rs - a (line 1)".to_owned()]);
+        assert_eq!(select_filters(&names, &selected), vec!["lib.rs"]);
+    }
+
+    #[test]
+    fn select_filters_substring_test() {
+        let names = vec![
+            "src/lib.rs - foo (line 1)".to_owned(),
+            "src/lib.rs - foo_bar (line 1)".to_owned(),
+        ];
+
+        // `foo_bar` has a unique part and is selected on its own.
+        let selected = BTreeSet::from(["src/lib.rs - foo_bar (line 1)".to_owned()]);
+        assert_eq!(select_filters(&names, &selected), vec!["foo_bar"]);
+
+        // Every part of `foo` is also part of `foo_bar` which therefore runs as well.
+        let selected = BTreeSet::from(["src/lib.rs - foo (line 1)".to_owned()]);
+        let filters = select_filters(&names, &selected);
+        assert!(names
+            .iter()
+            .all(|name| filters.iter().any(|f| name.contains(f.as_str()))));
+
+        // Its result is dropped so that `foo_bar` is only reported by its own shard.
+        let mut results = vec![
+            TestResult {
+                name: "src/lib.rs - foo (line 1)".to_owned(),
+                status: TestStatus::Passed,
+                duration: None,
+                output: String::new(),
+            },
+            TestResult {
+                name: "src/lib.rs - foo_bar (line 1)".to_owned(),
+                status: TestStatus::Failed,
+                duration: None,
+                output: String::new(),
+            },
+        ];
+        assert!(retain_selected(&mut results, &selected));
+        assert_eq!(
+            results.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(),
+            vec!["src/lib.rs - foo (line 1)"]
+        );
+    }
+
+    #[test]
+    fn unescape_test() {
+        assert_eq!(unescape("a\\nb\\\\c"), "a\nb\\c");
+    }
+}
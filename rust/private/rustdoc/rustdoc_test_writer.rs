@@ -1,6 +1,6 @@
-//! A utility for writing scripts for use as test executables intended to match the
-//! subcommands of Bazel build actions so `rustdoc --test`, which builds and tests
-//! code in a single call, can be run as a test target in a hermetic manner.
+//! A utility for recording the subcommands of Bazel build actions so `rustdoc --test`,
+//! which builds and tests code in a single call, can be run by `rustdoc_test_runner`
+//! as a test target in a hermetic manner.
 
 use std::cmp::Reverse;
 use std::collections::{BTreeMap, BTreeSet};
@@ -17,7 +17,7 @@ struct Options {
     /// A list of substrings to strip from [Options::action_argv].
     strip_substrings: Vec<String>,
 
-    /// The path where the script should be written.
+    /// The path where the test spec should be written.
     output: PathBuf,
 
     /// If Bazel generated a params file, we may need to strip roots from it.
@@ -104,11 +104,7 @@ fn parse_args() -> Options {
 
 /// Expand the Bazel Arg file and write it into our manually defined params file
 fn expand_params_file(mut options: Options) -> Options {
-    let params_extension = if cfg!(target_family = "windows") {
-        ".rustdoc_test.bat-0.params"
-    } else {
-        ".rustdoc_test.sh-0.params"
-    };
+    let params_extension = ".rustdoc_test.spec-0.params";
 
     // We always need to produce the params file, we might overwrite this later though
     fs::write(&options.optional_output_params_file, b"unused")
@@ -149,7 +145,7 @@ fn expand_params_file(mut options: Options) -> Options {
 
     // add all arguments
     fs::write(&options.optional_output_params_file, content.join("\n"))
-        .expect("Failed to write test runner");
+        .expect("Failed to write params file");
 
     // append the path of our new params file
     let formatted_params_path = format!(
@@ -164,119 +160,38 @@ fn expand_params_file(mut options: Options) -> Options {
     options
 }
 
-/// Write a unix compatible test runner
-fn write_test_runner_unix(
-    path: &Path,
-    env: &BTreeMap<String, String>,
-    argv: &[String],
-    strip_substrings: &[String],
-) {
-    let mut content = vec![
-        "#!/usr/bin/env bash".to_owned(),
-        "".to_owned(),
-        // TODO: Instead of creating a symlink to mimic the behavior of
-        // --legacy_external_runfiles, this rule should be able to correctly
-        // sanitize the action args to run in a runfiles without this link.
-        "if [[ ! -e 'external' ]]; then ln -s ../ external ; fi".to_owned(),
-        "".to_owned(),
-        "exec env - \\".to_owned(),
-    ];
-
-    content.extend(env.iter().map(|(key, val)| format!("{key}='{val}' \\")));
-
-    let argv_str = argv
-        .iter()
-        // Remove any substrings found in the argument
-        .map(|arg| {
-            let mut stripped_arg = arg.to_owned();
-            strip_substrings
-                .iter()
-                .for_each(|substring| stripped_arg = stripped_arg.replace(substring, ""));
-            stripped_arg
-        })
-        .map(|arg| format!("'{arg}'"))
-        .collect::<Vec<String>>()
-        .join(" ");
-
-    content.extend(vec![argv_str, "".to_owned()]);
-
-    fs::write(path, content.join("\n")).expect("Failed to write test runner");
+/// Escape newlines and backslashes so each value occupies a single line of the test spec.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n")
 }
 
-/// Write a windows compatible test runner
-fn write_test_runner_windows(
+/// Write the environment and arguments of the rustdoc action for use by `rustdoc_test_runner`.
+fn write_test_spec(
     path: &Path,
     env: &BTreeMap<String, String>,
     argv: &[String],
     strip_substrings: &[String],
 ) {
-    let env_str = env
-        .iter()
-        .map(|(key, val)| format!("$env:{key}='{val}'"))
-        .collect::<Vec<String>>()
-        .join(" ; ");
-
-    let argv_str = argv
+    let mut content: Vec<String> = env
         .iter()
-        // Remove any substrings found in the argument
-        .map(|arg| {
-            let mut stripped_arg = arg.to_owned();
-            strip_substrings
-                .iter()
-                .for_each(|substring| stripped_arg = stripped_arg.replace(substring, ""));
-            stripped_arg
-        })
-        .map(|arg| format!("'{arg}'"))
-        .collect::<Vec<String>>()
-        .join(" ");
-
-    let content = [
-        "@ECHO OFF".to_owned(),
-        "".to_owned(),
-        // TODO: Instead of creating a symlink to mimic the behavior of
-        // --legacy_external_runfiles, this rule should be able to correctly
-        // sanitize the action args to run in a runfiles without this link.
-        "powershell.exe -c \"if (!(Test-Path .\\external)) { New-Item -Path .\\external -ItemType SymbolicLink -Value ..\\ }\""
-            .to_owned(),
-        "".to_owned(),
-        format!("powershell.exe -c \"{env_str} ; & {argv_str}\""),
-        "".to_owned(),
-    ];
-
-    fs::write(path, content.join("\n")).expect("Failed to write test runner");
-}
-
-#[cfg(target_family = "unix")]
-fn set_executable(path: &Path) {
-    use std::os::unix::prelude::PermissionsExt;
-
-    let mut perm = fs::metadata(path)
-        .expect("Failed to get test runner metadata")
-        .permissions();
-
-    perm.set_mode(0o755);
-    fs::set_permissions(path, perm).expect("Failed to set permissions on test runner");
-}
-
-#[cfg(target_family = "windows")]
-fn set_executable(_path: &Path) {
-    // Windows determines whether or not a file is executable via the PATHEXT
-    // environment variable. This function is a no-op for this platform.
-}
+        .map(|(key, val)| format!("env {}={}", key, escape(val)))
+        .collect();
 
-fn write_test_runner(
-    path: &Path,
-    env: &BTreeMap<String, String>,
-    argv: &[String],
-    strip_substrings: &[String],
-) {
-    if cfg!(target_family = "unix") {
-        write_test_runner_unix(path, env, argv, strip_substrings);
-    } else if cfg!(target_family = "windows") {
-        write_test_runner_windows(path, env, argv, strip_substrings);
-    }
+    content.extend(
+        argv.iter()
+            // Remove any substrings found in the argument
+            .map(|arg| {
+                let mut stripped_arg = arg.to_owned();
+                strip_substrings
+                    .iter()
+                    .for_each(|substring| stripped_arg = stripped_arg.replace(substring, ""));
+                stripped_arg
+            })
+            .map(|arg| format!("arg {}", escape(&arg))),
+    );
+    content.push("".to_owned());
 
-    set_executable(path);
+    fs::write(path, content.join("\n")).expect("Failed to write test spec");
 }
 
 fn main() {
@@ -287,5 +202,5 @@ fn main() {
         .filter(|(key, _)| opt.env_keys.iter().any(|k| k == key))
         .collect();
 
-    write_test_runner(&opt.output, &env, &opt.action_argv, &opt.strip_substrings);
+    write_test_spec(&opt.output, &env, &opt.action_argv, &opt.strip_substrings);
 }
@@ -20,7 +20,7 @@ load("//rust/private:providers.bzl", "CrateInfo")
 load("//rust/private:rustdoc.bzl", "rustdoc_compile_action")
 load("//rust/private:utils.bzl", "dedent", "find_toolchain", "transform_deps")
 
-def _construct_writer_arguments(ctx, test_runner, opt_test_params, action, crate_info):
+def _construct_writer_arguments(ctx, test_spec, opt_test_params, action, crate_info):
     """Construct arguments and environment variables specific to `rustdoc_test_writer`.
 
     This is largely solving for the fact that tests run from a runfiles directory
@@ -29,7 +29,7 @@ def _construct_writer_arguments(ctx, test_runner, opt_test_params, action, crate
 
     Args:
         ctx (ctx): The rule's context object.
-        test_runner (File): The test_runner output file declared by `rustdoc_test`.
+        test_spec (File): The test spec output file declared by `rustdoc_test`.
         opt_test_params (File): An output file we can optionally use to store params for `rustdoc`.
         action (struct): Action arguments generated by `rustdoc_compile_action`.
         crate_info (CrateInfo): The provider of the crate who's docs are being tested.
@@ -42,13 +42,13 @@ def _construct_writer_arguments(ctx, test_runner, opt_test_params, action, crate
 
     writer_args = ctx.actions.args()
 
-    # Track the output path where the test writer should write the test
-    writer_args.add("--output={}".format(test_runner.path))
+    # Track the output path where the test writer should write the test spec
+    writer_args.add("--output={}".format(test_spec.path))
 
     # Track where the test writer should move "spilled" Args to
     writer_args.add("--optional_test_params={}".format(opt_test_params.path))
 
-    # Track what environment variables should be written to the test runner
+    # Track what environment variables should be written to the test spec
     writer_args.add("--action_env=DEVELOPER_DIR")
     writer_args.add("--action_env=PATHEXT")
     writer_args.add("--action_env=SDKROOT")
@@ -134,16 +134,28 @@ def _rust_doc_test_impl(ctx):
         owner = ctx.label,
     )
 
-    if toolchain.target_os == "windows":
-        test_runner = ctx.actions.declare_file(ctx.label.name + ".rustdoc_test.bat")
-    else:
-        test_runner = ctx.actions.declare_file(ctx.label.name + ".rustdoc_test.sh")
+    # The executable of a test target must be the output of an action in
+    # the rule implementation. This file is simply a symlink to the real
+    # rustdoc test runner.
+    is_windows = ctx.executable._test_runner.extension == "exe"
+    test_runner = ctx.actions.declare_file("{}{}".format(
+        ctx.label.name,
+        ".exe" if is_windows else "",
+    ))
+    ctx.actions.symlink(
+        output = test_runner,
+        target_file = ctx.executable._test_runner,
+        is_executable = True,
+    )
+
+    # The rustdoc action replayed by the test runner.
+    test_spec = ctx.actions.declare_file(ctx.label.name + ".rustdoc_test.spec")
 
     # Bazel will auto-magically spill params to a file, if they are too many for a given OSes shell
-    # (e.g. Windows ~32k, Linux ~2M). The executable script (aka test_runner) that gets generated,
-    # is run from the runfiles, which is separate from the params_file Bazel generates. To handle
-    # this case, we declare our own params file, that the test_writer will populate, if necessary
-    opt_test_params = ctx.actions.declare_file(ctx.label.name + ".rustdoc_opt_params", sibling = test_runner)
+    # (e.g. Windows ~32k, Linux ~2M). The test runner is run from the runfiles, which is separate
+    # from the params_file Bazel generates. To handle this case, we declare our own params file,
+    # that the test_writer will populate, if necessary
+    opt_test_params = ctx.actions.declare_file(ctx.label.name + ".rustdoc_opt_params", sibling = test_spec)
 
     # Add the current crate as an extern for the compile action
     rustdoc_flags = [
@@ -169,7 +181,7 @@ def _rust_doc_test_impl(ctx):
 
     writer_args, env = _construct_writer_arguments(
         ctx = ctx,
-        test_runner = test_runner,
+        test_spec = test_spec,
         opt_test_params = opt_test_params,
         action = action,
         crate_info = crate_info,
@@ -186,14 +198,31 @@ def _rust_doc_test_impl(ctx):
         tools = tools,
         arguments = [writer_args] + action.arguments,
         env = action.env,
-        outputs = [test_runner, opt_test_params],
+        outputs = [test_spec, opt_test_params],
     )
 
-    return [DefaultInfo(
-        files = depset([test_runner]),
-        runfiles = ctx.runfiles(files = tools + [opt_test_params], transitive_files = action.inputs),
-        executable = test_runner,
-    )]
+    runfiles = ctx.runfiles(
+        files = tools + [test_spec, opt_test_params],
+        transitive_files = action.inputs,
+    ).merge(ctx.attr._test_runner[DefaultInfo].default_runfiles)
+
+    if test_spec.short_path.startswith("../"):
+        test_spec_rlocationpath = test_spec.short_path[len("../"):]
+    else:
+        test_spec_rlocationpath = "{}/{}".format(ctx.workspace_name, test_spec.short_path)
+
+    return [
+        DefaultInfo(
+            files = depset([test_runner]),
+            runfiles = runfiles,
+            executable = test_runner,
+        ),
+        RunEnvironmentInfo(
+            environment = {
+                "RUSTDOC_TEST_SPEC": test_spec_rlocationpath,
+            },
+        ),
+    ]
 
 rust_doc_test = rule(
     implementation = _rust_doc_test_impl,
@@ -244,8 +273,14 @@ rust_doc_test = rule(
             default = Label("//util/process_wrapper"),
             executable = True,
         ),
+        "_test_runner": attr.label(
+            doc = "A binary which runs the rustdoc action recorded by `_test_writer` as a test.",
+            cfg = "target",
+            default = Label("//rust/private/rustdoc:rustdoc_test_runner"),
+            executable = True,
+        ),
         "_test_writer": attr.label(
-            doc = "A binary used for writing script for use as the test executable.",
+            doc = "A binary used for recording the rustdoc action run by `_test_runner`.",
             cfg = "exec",
             default = Label("//rust/private/rustdoc:rustdoc_test_writer"),
             executable = True,
@@ -299,5 +334,9 @@ rust_doc_test = rule(
         ```
 
         Running `bazel test //hello_lib:hello_lib_doc_test` will run all documentation tests for the `hello_lib` library crate.
+
+        Individual doctests are reported to Bazel via `XML_OUTPUT_FILE` and may be selected with
+        `--test_filter` or distributed across shards using `shard_count`. Machine readable output
+        from libtest is used when available (nightly toolchains).
     """),
 )
@@ -118,10 +118,12 @@ def _rustfmt_aspect_impl(target, ctx):
     edition = crate_info.edition
 
     marker = _perform_check(edition, srcs, ctx)
+    manifest = _generate_manifest(edition, srcs, ctx)
 
     return [
         OutputGroupInfo(
             rustfmt_checks = depset([marker]),
+            rustfmt_manifest = depset([manifest]),
         ),
     ]
 
@@ -133,6 +135,7 @@ This aspect is used to gather information about a crate for use in rustfmt and p
 Output Groups:
 
 - `rustfmt_checks`: Executes `rustfmt --check` on the specified target.
+- `rustfmt_manifest`: A manifest used by rustfmt binaries to provide crate specific settings.
 
 The build setting `@rules_rust//rust/settings:rustfmt.toml` is used to control the Rustfmt [configuration settings][cs]
 used at runtime.
@@ -227,6 +230,7 @@ def _rustfmt_test_impl(ctx):
     manifests = depset(transitive = [manifest for manifest in manifests if manifest])
 
     runfiles = ctx.runfiles(
+        files = ctx.files.configs,
         transitive_files = depset(transitive = srcs + [manifests]),
     )
 
@@ -257,6 +261,14 @@ rustfmt_test = rule(
     implementation = _rustfmt_test_impl,
     doc = "A test rule for performing `rustfmt --check` on a set of targets",
     attrs = {
+        "configs": attr.label_list(
+            doc = (
+                "Nested `rustfmt.toml` or `.rustfmt.toml` files which apply to sources of `targets`. " +
+                "The closest config to each source is used, falling back to the global " +
+                "`@rules_rust//rust/settings:rustfmt.toml` config."
+            ),
+            allow_files = ["rustfmt.toml"],
+        ),
         "targets": attr.label_list(
             doc = "Rust targets to run `rustfmt --check` on.",
             providers = [
@@ -29,6 +29,25 @@
 //!
 //! // ...
 //! ```
+//!
+//! 4. Directories and the contents of the runfiles tree can be inspected with
+//!    [Runfiles::list_from] and [Runfiles::iter]:
+//! ```ignore
+//!
+//! let r = Runfiles::create().unwrap();
+//! for (rlocationpath, path) in r.list_from("my_workspace/path/to/my/data", env!("REPOSITORY_NAME")).unwrap() {
+//!     // ...
+//! }
+//! ```
+//!
+//! 5. When spawning another Bazel-built binary, hand over the runfiles environment
+//!    with [Runfiles::set_env_vars]:
+//! ```ignore
+//!
+//! let r = Runfiles::create().unwrap();
+//! let mut command = std::process::Command::new(rlocation!(r, "my_workspace/path/to/my/tool").unwrap());
+//! r.set_env_vars(&mut command).status().unwrap();
+//! ```
 
 use std::collections::HashMap;
 use std::env;
@@ -40,6 +59,7 @@ use std::path::PathBuf;
 const RUNFILES_DIR_ENV_VAR: &str = "RUNFILES_DIR";
 const MANIFEST_FILE_ENV_VAR: &str = "RUNFILES_MANIFEST_FILE";
 const TEST_SRCDIR_ENV_VAR: &str = "TEST_SRCDIR";
+const JAVA_RUNFILES_ENV_VAR: &str = "JAVA_RUNFILES";
 
 #[macro_export]
 macro_rules! rlocation {
@@ -54,6 +74,10 @@ pub enum RunfilesError {
     /// Directory based runfiles could not be found.
     RunfilesDirNotFound,
 
+    /// Directory based runfiles were searched for while the `RUNFILES_MANIFEST_FILE`
+    /// environment variable indicates manifest based runfiles should be used.
+    RunfilesDirUnexpected,
+
     /// An [I/O Error](https://doc.rust-lang.org/std/io/struct.Error.html)
     /// which occurred during the creation of directory-based runfiles.
     RunfilesDirIoError(io::Error),
@@ -87,6 +111,7 @@ impl std::fmt::Display for RunfilesError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             RunfilesError::RunfilesDirNotFound => write!(f, "RunfilesDirNotFound"),
+            RunfilesError::RunfilesDirUnexpected => write!(f, "RunfilesDirUnexpected"),
             RunfilesError::RunfilesDirIoError(err) => write!(f, "RunfilesDirIoError: {:?}", err),
             RunfilesError::RunfilesManifestIoError(err) => {
                 write!(f, "RunfilesManifestIoError: {:?}", err)
@@ -137,7 +162,13 @@ enum Mode {
 
     /// Runfiles represented as a mapping of `rlocationpath` to real paths indicated
     /// by the `RUNFILES_MANIFEST_FILE` environment variable.
-    ManifestBased(HashMap<PathBuf, PathBuf>),
+    ManifestBased {
+        /// The manifest file the mapping was parsed from.
+        manifest_file: PathBuf,
+
+        /// The mapping of `rlocationpath` to real paths.
+        path_mapping: HashMap<PathBuf, PathBuf>,
+    },
 }
 
 type RepoMappingKey = (String, String);
@@ -155,17 +186,45 @@ impl Runfiles {
     /// RUNFILES_MANIFEST_FILE environment variable is present,
     /// or a directory based Runfiles object otherwise.
     pub fn create() -> Result<Self> {
-        let mode = if let Some(manifest_file) = std::env::var_os(MANIFEST_FILE_ENV_VAR) {
-            Self::create_manifest_based(Path::new(&manifest_file))?
+        if let Some(manifest_file) = std::env::var_os(MANIFEST_FILE_ENV_VAR) {
+            Self::create_from_manifest(manifest_file)
         } else {
-            let dir = find_runfiles_dir()?;
-            let manifest_path = dir.join("MANIFEST");
+            Self::create_from(find_runfiles_dir()?)
+        }
+    }
+
+    /// Creates a Runfiles object from an explicit runfiles directory or manifest
+    /// file instead of the environment of the current process.
+    ///
+    /// A runfiles directory containing a `MANIFEST` file is treated as manifest based.
+    pub fn create_from(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            let manifest_path = path.join("MANIFEST");
             match manifest_path.exists() {
-                true => Self::create_manifest_based(&manifest_path)?,
-                false => Mode::DirectoryBased(dir),
+                true => Self::create_from_manifest(manifest_path),
+                false => Self::create_from_directory(path),
             }
-        };
+        } else {
+            Self::create_from_manifest(path)
+        }
+    }
 
+    /// Creates a manifest based Runfiles object from the given manifest file.
+    pub fn create_from_manifest(manifest_file: impl AsRef<Path>) -> Result<Self> {
+        Self::from_mode(Self::create_manifest_based(manifest_file.as_ref())?)
+    }
+
+    /// Creates a directory based Runfiles object from the given runfiles directory.
+    pub fn create_from_directory(runfiles_dir: impl AsRef<Path>) -> Result<Self> {
+        let runfiles_dir = runfiles_dir.as_ref();
+        if !runfiles_dir.is_dir() {
+            return Err(RunfilesError::RunfilesDirNotFound);
+        }
+        Self::from_mode(Mode::DirectoryBased(runfiles_dir.to_path_buf()))
+    }
+
+    fn from_mode(mode: Mode) -> Result<Self> {
         let repo_mapping = raw_rlocation(&mode, "_repo_mapping")
             // This is the only place directory based runfiles might do file IO for a runfile. In the
             // event that a `_repo_mapping` file does not exist, a default map should be created. Otherwise
@@ -190,7 +249,54 @@ impl Runfiles {
                 Ok::<(PathBuf, PathBuf), RunfilesError>((pair.0.into(), pair.1.into()))
             })
             .collect::<HashMap<_, _>>();
-        Ok(Mode::ManifestBased(path_mapping))
+        Ok(Mode::ManifestBased {
+            manifest_file: manifest_path.to_path_buf(),
+            path_mapping,
+        })
+    }
+
+    /// Returns the environment variables a child process needs to locate the
+    /// runfiles of the current process (`RUNFILES_DIR`, `RUNFILES_MANIFEST_FILE`
+    /// and `JAVA_RUNFILES`).
+    ///
+    /// This is useful when spawning other Bazel-built binaries which use
+    /// runfiles libraries of any language.
+    pub fn env_vars(&self) -> Vec<(&'static str, PathBuf)> {
+        let (manifest_file, runfiles_dir) = match &self.mode {
+            Mode::DirectoryBased(runfiles_dir) => (None, Some(runfiles_dir.clone())),
+            Mode::ManifestBased { manifest_file, .. } => (
+                Some(manifest_file.clone()),
+                runfiles_dir_for_manifest(manifest_file),
+            ),
+        };
+
+        let mut env_vars = Vec::new();
+        if let Some(manifest_file) = manifest_file {
+            env_vars.push((MANIFEST_FILE_ENV_VAR, manifest_file));
+        }
+        if let Some(runfiles_dir) = runfiles_dir {
+            env_vars.push((RUNFILES_DIR_ENV_VAR, runfiles_dir.clone()));
+            env_vars.push((JAVA_RUNFILES_ENV_VAR, runfiles_dir));
+        }
+        env_vars
+    }
+
+    /// Configures a [Command](std::process::Command) with the environment variables
+    /// from [Runfiles::env_vars] so the child process can locate these runfiles.
+    ///
+    /// Runfiles variables which do not apply are removed from the child's environment.
+    pub fn set_env_vars<'a>(
+        &self,
+        command: &'a mut std::process::Command,
+    ) -> &'a mut std::process::Command {
+        for var in [
+            MANIFEST_FILE_ENV_VAR,
+            RUNFILES_DIR_ENV_VAR,
+            JAVA_RUNFILES_ENV_VAR,
+        ] {
+            command.env_remove(var);
+        }
+        command.envs(self.env_vars())
     }
 
     /// Returns the runtime path of a runfile.
@@ -213,6 +319,9 @@ impl Runfiles {
     /// The returned path may not be valid. The caller should check the path's
     /// validity and that the path exists.
     ///
+    /// Directories (such as TreeArtifacts or package prefixes) are resolved
+    /// in both directory and manifest based runfiles.
+    ///
     /// Typically this should be used via the `rlocation!` macro to properly set source_repo.
     pub fn rlocation_from(&self, path: impl AsRef<Path>, source_repo: &str) -> Option<PathBuf> {
         let path = path.as_ref();
@@ -220,33 +329,197 @@ impl Runfiles {
             return Some(path.to_path_buf());
         }
 
+        raw_rlocation(&self.mode, self.apply_repo_mapping(path, source_repo))
+    }
+
+    /// Returns all runfiles under a runfiles directory as pairs of their
+    /// `rlocationpath` and runtime path, sorted by `rlocationpath`.
+    ///
+    /// Listing is recursive and works for both directory and manifest based runfiles.
+    /// An empty list is returned if `path` does not exist.
+    pub fn list_from(
+        &self,
+        path: impl AsRef<Path>,
+        source_repo: &str,
+    ) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let prefix = self.apply_repo_mapping(path.as_ref(), source_repo);
+
+        match &self.mode {
+            Mode::DirectoryBased(runfiles_dir) => {
+                let mut entries = Vec::new();
+                let dir = runfiles_dir.join(&prefix);
+                if dir.is_dir() {
+                    walk_runfiles_dir(&dir, &prefix, &mut entries)?;
+                } else if dir.exists() {
+                    entries.push((prefix, dir));
+                }
+                entries.sort();
+                Ok(entries)
+            }
+            Mode::ManifestBased { path_mapping, .. } => {
+                let mut entries = Vec::new();
+                for (rlocationpath, path) in path_mapping {
+                    if rlocationpath.starts_with(&prefix) {
+                        walk_manifest_entry(rlocationpath, path, &mut entries)?;
+                    }
+                }
+
+                // The prefix may be within a directory (TreeArtifact) entry of the manifest.
+                if entries.is_empty() {
+                    if let Some(dir) = manifest_ancestor_rlocation(path_mapping, &prefix) {
+                        if dir.is_dir() {
+                            walk_runfiles_dir(&dir, &prefix, &mut entries)?;
+                        }
+                    }
+                }
+                entries.sort();
+                Ok(entries)
+            }
+        }
+    }
+
+    /// Returns an iterator over all runfiles as pairs of their `rlocationpath`
+    /// and runtime path, sorted by `rlocationpath`.
+    pub fn iter(&self) -> Result<impl Iterator<Item = (PathBuf, PathBuf)>> {
+        let entries = match &self.mode {
+            Mode::DirectoryBased(runfiles_dir) => {
+                let mut entries = Vec::new();
+                walk_runfiles_dir(runfiles_dir, Path::new(""), &mut entries)?;
+                entries.sort();
+                entries
+            }
+            Mode::ManifestBased { path_mapping, .. } => {
+                let mut entries = Vec::new();
+                for (rlocationpath, path) in path_mapping {
+                    walk_manifest_entry(rlocationpath, path, &mut entries)?;
+                }
+                entries.sort();
+                entries
+            }
+        };
+
+        Ok(entries.into_iter())
+    }
+
+    /// Translate the apparent repository name at the start of `path` into
+    /// its canonical name using the repo mapping of `source_repo`.
+    fn apply_repo_mapping(&self, path: &Path, source_repo: &str) -> PathBuf {
         let path_str = path.to_str().expect("Should be valid UTF8");
         let (repo_alias, repo_path): (&str, Option<&str>) = match path_str.split_once('/') {
             Some((name, alias)) => (name, Some(alias)),
             None => (path_str, None),
         };
         let key: (String, String) = (source_repo.into(), repo_alias.into());
-        if let Some(target_repo_directory) = self.repo_mapping.get(&key) {
-            match repo_path {
-                Some(repo_path) => {
-                    raw_rlocation(&self.mode, format!("{target_repo_directory}/{repo_path}"))
-                }
-                None => raw_rlocation(&self.mode, target_repo_directory),
+        match (self.repo_mapping.get(&key), repo_path) {
+            (Some(target_repo_directory), Some(repo_path)) => {
+                PathBuf::from(format!("{target_repo_directory}/{repo_path}"))
             }
+            (Some(target_repo_directory), None) => PathBuf::from(target_repo_directory),
+            (None, _) => path.to_path_buf(),
+        }
+    }
+}
+
+/// Recursively collect all files under `dir`, following symlinks, and record
+/// them relative to the `rlocationpath` of `dir`.
+fn walk_runfiles_dir(
+    dir: &Path,
+    rlocationpath: &Path,
+    entries: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<()> {
+    for entry in fs::read_dir(dir).map_err(RunfilesError::RunfileIoError)? {
+        let entry = entry.map_err(RunfilesError::RunfileIoError)?;
+        let path = entry.path();
+        let entry_rlocationpath = rlocationpath.join(entry.file_name());
+        if fs::metadata(&path)
+            .map_err(RunfilesError::RunfileIoError)?
+            .is_dir()
+        {
+            walk_runfiles_dir(&path, &entry_rlocationpath, entries)?;
         } else {
-            raw_rlocation(&self.mode, path)
+            entries.push((entry_rlocationpath, path));
         }
     }
+
+    Ok(())
+}
+
+/// Collect a manifest entry, or all files under it if it is a directory (e.g. a TreeArtifact).
+fn walk_manifest_entry(
+    rlocationpath: &Path,
+    path: &Path,
+    entries: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<()> {
+    if path.is_dir() {
+        walk_runfiles_dir(path, rlocationpath, entries)
+    } else {
+        entries.push((rlocationpath.to_path_buf(), path.to_path_buf()));
+        Ok(())
+    }
+}
+
+/// Resolve a path within a directory entry (e.g. a TreeArtifact) of a manifest.
+fn manifest_ancestor_rlocation(
+    path_mapping: &HashMap<PathBuf, PathBuf>,
+    path: &Path,
+) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+        .find_map(|ancestor| {
+            path_mapping
+                .get(ancestor)
+                .map(|dir| dir.join(path.strip_prefix(ancestor).unwrap()))
+        })
+}
+
+/// Resolve a directory which only exists as a prefix of entries in a manifest
+/// (e.g. a package) by locating the common runtime directory of its entries.
+fn manifest_prefix_rlocation(
+    path_mapping: &HashMap<PathBuf, PathBuf>,
+    path: &Path,
+) -> Option<PathBuf> {
+    path_mapping
+        .iter()
+        .filter_map(|(rlocationpath, real_path)| {
+            let relative = rlocationpath.strip_prefix(path).ok()?;
+            if relative.as_os_str().is_empty() || !real_path.ends_with(relative) {
+                return None;
+            }
+            let mut dir = real_path.clone();
+            for _ in relative.components() {
+                dir.pop();
+            }
+            Some((rlocationpath, dir))
+        })
+        .min()
+        .map(|(_, dir)| dir)
 }
 
 fn raw_rlocation(mode: &Mode, path: impl AsRef<Path>) -> Option<PathBuf> {
     let path = path.as_ref();
     match mode {
         Mode::DirectoryBased(runfiles_dir) => Some(runfiles_dir.join(path)),
-        Mode::ManifestBased(path_mapping) => path_mapping.get(path).cloned(),
+        Mode::ManifestBased { path_mapping, .. } => path_mapping
+            .get(path)
+            .cloned()
+            .or_else(|| manifest_ancestor_rlocation(path_mapping, path))
+            .or_else(|| manifest_prefix_rlocation(path_mapping, path)),
     }
 }
 
+/// Determine the runfiles directory which accompanies a manifest file, if any.
+fn runfiles_dir_for_manifest(manifest_file: &Path) -> Option<PathBuf> {
+    let file_name = manifest_file.file_name()?.to_str()?;
+    let runfiles_dir = if file_name == "MANIFEST" {
+        manifest_file.parent()?.to_path_buf()
+    } else {
+        manifest_file.with_file_name(file_name.strip_suffix("_manifest")?)
+    };
+
+    runfiles_dir.is_dir().then_some(runfiles_dir)
+}
+
 fn parse_repo_mapping(path: PathBuf) -> Result<RepoMapping> {
     let mut repo_mapping = RepoMapping::new();
 
@@ -265,12 +538,13 @@ fn parse_repo_mapping(path: PathBuf) -> Result<RepoMapping> {
 }
 
 /// Returns the .runfiles directory for the currently executing binary.
+///
+/// An error is returned if no directory could be found or if `RUNFILES_MANIFEST_FILE`
+/// indicates manifest based runfiles are in use.
 pub fn find_runfiles_dir() -> Result<PathBuf> {
-    assert!(
-        std::env::var_os(MANIFEST_FILE_ENV_VAR).is_none(),
-        "Unexpected call when {} exists",
-        MANIFEST_FILE_ENV_VAR
-    );
+    if std::env::var_os(MANIFEST_FILE_ENV_VAR).is_some() {
+        return Err(RunfilesError::RunfilesDirUnexpected);
+    }
 
     // If Bazel told us about the runfiles dir, use that without looking further.
     if let Some(runfiles_dir) = std::env::var_os(RUNFILES_DIR_ENV_VAR).map(PathBuf::from) {
@@ -285,15 +559,19 @@ pub fn find_runfiles_dir() -> Result<PathBuf> {
     }
 
     // Consume the first argument (argv[0])
-    let exec_path = std::env::args().next().expect("arg 0 was not set");
+    let exec_path = std::env::args_os()
+        .next()
+        .ok_or(RunfilesError::RunfilesDirNotFound)?;
 
-    let current_dir =
-        env::current_dir().expect("The current working directory is always expected to be set.");
+    let current_dir = env::current_dir().map_err(RunfilesError::RunfilesDirIoError)?;
 
     let mut binary_path = PathBuf::from(&exec_path);
     loop {
         // Check for our neighboring `${binary}.runfiles` directory.
-        let mut runfiles_name = binary_path.file_name().unwrap().to_owned();
+        let mut runfiles_name = binary_path
+            .file_name()
+            .ok_or(RunfilesError::RunfilesDirNotFound)?
+            .to_owned();
         runfiles_name.push(".runfiles");
 
         let runfiles_path = binary_path.with_file_name(&runfiles_name);
@@ -330,7 +608,9 @@ pub fn find_runfiles_dir() -> Result<PathBuf> {
         binary_path = if link_target.is_absolute() {
             link_target
         } else {
-            let link_dir = binary_path.parent().unwrap();
+            let link_dir = binary_path
+                .parent()
+                .ok_or(RunfilesError::RunfilesDirNotFound)?;
             current_dir.join(link_dir).join(link_target)
         }
     }
@@ -541,7 +821,10 @@ mod test {
         let mut path_mapping = HashMap::new();
         path_mapping.insert("a/b".into(), "c/d".into());
         let r = Runfiles {
-            mode: Mode::ManifestBased(path_mapping),
+            mode: Mode::ManifestBased {
+                manifest_file: PathBuf::from("MANIFEST"),
+                path_mapping,
+            },
             repo_mapping: RepoMapping::new(),
         };
 
@@ -553,13 +836,218 @@ mod test {
         let mut path_mapping = HashMap::new();
         path_mapping.insert("a/b".into(), "c/d".into());
         let r = Runfiles {
-            mode: Mode::ManifestBased(path_mapping),
+            mode: Mode::ManifestBased {
+                manifest_file: PathBuf::from("MANIFEST"),
+                path_mapping,
+            },
             repo_mapping: RepoMapping::new(),
         };
 
         assert_eq!(r.rlocation("does/not/exist"), None);
     }
 
+    #[test]
+    fn test_manifest_based_directories() {
+        let mut path_mapping = HashMap::new();
+        path_mapping.insert("ws/pkg/a.txt".into(), "/real/ws/pkg/a.txt".into());
+        path_mapping.insert("ws/pkg/sub/b.txt".into(), "/real/ws/pkg/sub/b.txt".into());
+        path_mapping.insert("ws/tree".into(), "/out/ws/tree".into());
+        let r = Runfiles {
+            mode: Mode::ManifestBased {
+                manifest_file: PathBuf::from("MANIFEST"),
+                path_mapping,
+            },
+            repo_mapping: RepoMapping::new(),
+        };
+
+        // Package prefixes resolve to the common directory of their entries.
+        assert_eq!(r.rlocation("ws/pkg"), Some(PathBuf::from("/real/ws/pkg")));
+        assert_eq!(
+            r.rlocation("ws/pkg/sub"),
+            Some(PathBuf::from("/real/ws/pkg/sub"))
+        );
+
+        // Paths within directory entries resolve relative to the directory.
+        assert_eq!(r.rlocation("ws/tree"), Some(PathBuf::from("/out/ws/tree")));
+        assert_eq!(
+            r.rlocation("ws/tree/nested/c.txt"),
+            Some(PathBuf::from("/out/ws/tree/nested/c.txt"))
+        );
+
+        assert_eq!(r.rlocation("ws/missing"), None);
+    }
+
+    #[test]
+    fn test_manifest_based_list_and_iter() {
+        let mut path_mapping = HashMap::new();
+        path_mapping.insert("ws/pkg/a.txt".into(), "/real/ws/pkg/a.txt".into());
+        path_mapping.insert("ws/pkg/sub/b.txt".into(), "/real/ws/pkg/sub/b.txt".into());
+        path_mapping.insert("ws/other/c.txt".into(), "/real/ws/other/c.txt".into());
+        let r = Runfiles {
+            mode: Mode::ManifestBased {
+                manifest_file: PathBuf::from("MANIFEST"),
+                path_mapping,
+            },
+            repo_mapping: RepoMapping::from([(
+                ("".to_owned(), "alias".to_owned()),
+                "ws".to_owned(),
+            )]),
+        };
+
+        assert_eq!(
+            r.list_from("alias/pkg", "").unwrap(),
+            vec![
+                (
+                    PathBuf::from("ws/pkg/a.txt"),
+                    PathBuf::from("/real/ws/pkg/a.txt")
+                ),
+                (
+                    PathBuf::from("ws/pkg/sub/b.txt"),
+                    PathBuf::from("/real/ws/pkg/sub/b.txt")
+                ),
+            ]
+        );
+        assert_eq!(r.list_from("ws/missing", "").unwrap(), vec![]);
+
+        assert_eq!(
+            r.iter()
+                .unwrap()
+                .map(|(rlocationpath, _)| rlocationpath)
+                .collect::<Vec<_>>(),
+            vec![
+                PathBuf::from("ws/other/c.txt"),
+                PathBuf::from("ws/pkg/a.txt"),
+                PathBuf::from("ws/pkg/sub/b.txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_manifest_based_list_and_iter_directories() {
+        let temp_dir = PathBuf::from(std::env::var("TEST_TMPDIR").unwrap());
+        let tree = temp_dir.join("test_manifest_based_list_and_iter_directories");
+        std::fs::create_dir_all(tree.join("nested")).unwrap();
+        std::fs::write(tree.join("a.txt"), "a").unwrap();
+        std::fs::write(tree.join("nested/b.txt"), "b").unwrap();
+
+        let mut path_mapping = HashMap::new();
+        path_mapping.insert("ws/pkg/c.txt".into(), "/real/ws/pkg/c.txt".into());
+        path_mapping.insert("ws/pkg/tree".into(), tree.clone());
+        let r = Runfiles {
+            mode: Mode::ManifestBased {
+                manifest_file: PathBuf::from("MANIFEST"),
+                path_mapping,
+            },
+            repo_mapping: RepoMapping::new(),
+        };
+
+        let expected = vec![
+            (
+                PathBuf::from("ws/pkg/c.txt"),
+                PathBuf::from("/real/ws/pkg/c.txt"),
+            ),
+            (PathBuf::from("ws/pkg/tree/a.txt"), tree.join("a.txt")),
+            (
+                PathBuf::from("ws/pkg/tree/nested/b.txt"),
+                tree.join("nested/b.txt"),
+            ),
+        ];
+        assert_eq!(r.list_from("ws/pkg", "").unwrap(), expected);
+        assert_eq!(r.list_from("ws/pkg/tree", "").unwrap(), expected[1..]);
+        assert_eq!(
+            r.list_from("ws/pkg/tree/nested", "").unwrap(),
+            expected[2..]
+        );
+        assert_eq!(r.iter().unwrap().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_directory_based_list_and_iter() {
+        let runfiles_dir = make_runfiles_like_dir("test_directory_based_list_and_iter");
+        let r = Runfiles {
+            mode: Mode::DirectoryBased(PathBuf::from(&runfiles_dir)),
+            repo_mapping: RepoMapping::new(),
+        };
+
+        let sample = PathBuf::from("rules_rust/rust/runfiles/data/sample.txt");
+        let expected = vec![(sample.clone(), PathBuf::from(&runfiles_dir).join(&sample))];
+
+        assert_eq!(
+            r.list_from("rules_rust/rust/runfiles", "").unwrap(),
+            expected
+        );
+        assert_eq!(r.list_from(&sample, "").unwrap(), expected);
+        assert_eq!(r.iter().unwrap().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_create_from_manifest() {
+        let temp_dir = PathBuf::from(std::env::var("TEST_TMPDIR").unwrap());
+        let runfiles_dir = temp_dir.join("test_create_from_manifest.runfiles");
+        std::fs::create_dir_all(&runfiles_dir).unwrap();
+
+        let manifest_file = temp_dir.join("test_create_from_manifest.runfiles_manifest");
+        std::fs::write(&manifest_file, "a/b /c/d\n").unwrap();
+
+        let r = Runfiles::create_from(&manifest_file).unwrap();
+        assert_eq!(r.rlocation("a/b"), Some(PathBuf::from("/c/d")));
+
+        assert_eq!(
+            r.env_vars(),
+            vec![
+                (MANIFEST_FILE_ENV_VAR, manifest_file),
+                (RUNFILES_DIR_ENV_VAR, runfiles_dir.clone()),
+                (JAVA_RUNFILES_ENV_VAR, runfiles_dir),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_create_from_directory() {
+        let runfiles_dir = PathBuf::from(make_runfiles_like_dir("test_create_from_directory"));
+
+        let r = Runfiles::create_from(&runfiles_dir).unwrap();
+        assert_eq!(
+            r.rlocation("rules_rust/rust/runfiles/data/sample.txt"),
+            Some(runfiles_dir.join("rules_rust/rust/runfiles/data/sample.txt"))
+        );
+
+        let mut command = std::process::Command::new("child");
+        r.set_env_vars(&mut command);
+        assert_eq!(
+            command
+                .get_envs()
+                .map(|(k, v)| (k.to_owned(), v.map(OsStr::to_owned)))
+                .collect::<HashMap<_, _>>(),
+            HashMap::from([
+                (OsString::from(MANIFEST_FILE_ENV_VAR), None),
+                (
+                    OsString::from(RUNFILES_DIR_ENV_VAR),
+                    Some(runfiles_dir.clone().into_os_string())
+                ),
+                (
+                    OsString::from(JAVA_RUNFILES_ENV_VAR),
+                    Some(runfiles_dir.into_os_string())
+                ),
+            ])
+        );
+
+        assert_eq!(
+            Runfiles::create_from_directory("does/not/exist").err(),
+            Some(RunfilesError::RunfilesDirNotFound)
+        );
+    }
+
+    #[test]
+    fn test_find_runfiles_dir_with_manifest() {
+        with_mock_env([(MANIFEST_FILE_ENV_VAR, Some("MANIFEST"))], || {
+            assert_eq!(
+                find_runfiles_dir(),
+                Err(RunfilesError::RunfilesDirUnexpected)
+            );
+        });
+    }
+
     fn dedent(text: &str) -> String {
         text.lines()
             .map(|l| l.trim_start())
@@ -8,6 +8,7 @@ load(
     "bool_flag",
     "int_flag",
     "string_flag",
+    "string_list_flag",
 )
 load(
     "//rust/private:clippy.bzl",
@@ -191,6 +192,50 @@ def experimental_use_allocator_libraries_with_mangled_symbols(name):
         },
     )
 
+def coverage_level():
+    """A flag controlling the granularity of coverage instrumentation used for `bazel coverage`.
+
+    - `line`: Line and region coverage (default).
+    - `branch`: Also collects branch coverage via `-Zcoverage-options=branch`.
+
+    `branch` relies on unstable rustc options and thus requires a nightly toolchain.
+
+    https://doc.rust-lang.org/rustc/instrument-coverage.html
+    """
+    string_flag(
+        name = "coverage_level",
+        build_setting_default = "line",
+        values = ["line", "branch"],
+    )
+
+def coverage_ignore_filename_regex():
+    """A flag of regular expressions matching source files to exclude from coverage reports.
+
+    Each value is passed to `llvm-cov` as `-ignore-filename-regex`. By default, sources from
+    external repositories and generated sources in `bazel-out` are excluded.
+    """
+    string_list_flag(
+        name = "coverage_ignore_filename_regex",
+        build_setting_default = [
+            "^external/",
+            "^bazel-out/",
+        ],
+    )
+
+def coverage_report_formats():
+    """A flag of additional coverage report formats to produce for each test during `bazel coverage`.
+
+    Supported values are `cobertura` (Cobertura XML), `html` (an `llvm-cov show` report), and `json`
+    (a summary of coverage per file). Reports are written to the test's undeclared outputs directory
+    in addition to the lcov report consumed by Bazel.
+
+    Per-test lcov reports can be combined with `bazel run @rules_rust//util/collect_coverage:merge_coverage`.
+    """
+    string_list_flag(
+        name = "coverage_report_formats",
+        build_setting_default = [],
+    )
+
 def experimental_use_coverage_metadata_files():
     """A flag to have coverage tooling added as `coverage_common.instrumented_files_info.metadata_files` instead of \
     reporting tools like `llvm-cov` and `llvm-profdata` as runfiles to each test.
@@ -200,6 +245,15 @@ def experimental_use_coverage_metadata_files():
         build_setting_default = True,
     )
 
+def experimental_use_libtest_launcher():
+    """A flag to run `rust_test` targets using the libtest harness through a launcher which supports \
+    test sharding (`shard_count`) and reports individual test results to Bazel via `XML_OUTPUT_FILE`.
+    """
+    bool_flag(
+        name = "experimental_use_libtest_launcher",
+        build_setting_default = False,
+    )
+
 def toolchain_generated_sysroot():
     """A flag to set rustc --sysroot flag to the sysroot generated by rust_toolchain."""
     bool_flag(
@@ -747,12 +747,16 @@ def _rust_toolchain_impl(ctx):
         _experimental_use_cc_common_link = _experimental_use_cc_common_link(ctx),
         _experimental_use_global_allocator = experimental_use_global_allocator,
         _experimental_use_coverage_metadata_files = ctx.attr._experimental_use_coverage_metadata_files[BuildSettingInfo].value,
+        _experimental_use_libtest_launcher = ctx.attr._experimental_use_libtest_launcher[BuildSettingInfo].value,
         _incompatible_change_rust_test_compilation_output_directory = ctx.attr._incompatible_change_rust_test_compilation_output_directory[IncompatibleFlagInfo].enabled,
         _toolchain_generated_sysroot = ctx.attr._toolchain_generated_sysroot[BuildSettingInfo].value,
         _incompatible_do_not_include_data_in_compile_data = ctx.attr._incompatible_do_not_include_data_in_compile_data[IncompatibleFlagInfo].enabled,
         _incompatible_do_not_transform_sources = ctx.attr._incompatible_do_not_transform_sources[IncompatibleFlagInfo].enabled,
         _no_std = no_std,
         _codegen_units = ctx.attr._codegen_units[BuildSettingInfo].value,
+        _coverage_level = ctx.attr._coverage_level[BuildSettingInfo].value,
+        _coverage_ignore_filename_regex = ctx.attr._coverage_ignore_filename_regex[BuildSettingInfo].value,
+        _coverage_report_formats = ctx.attr._coverage_report_formats[BuildSettingInfo].value,
         _experimental_use_allocator_libraries_with_mangled_symbols = ctx.attr.experimental_use_allocator_libraries_with_mangled_symbols,
         _experimental_use_allocator_libraries_with_mangled_symbols_setting = ctx.attr._experimental_use_allocator_libraries_with_mangled_symbols_setting[BuildSettingInfo].value,
     )
@@ -951,6 +955,18 @@ rust_toolchain = rule(
         "_codegen_units": attr.label(
             default = Label("//rust/settings:codegen_units"),
         ),
+        "_coverage_ignore_filename_regex": attr.label(
+            default = Label("//rust/settings:coverage_ignore_filename_regex"),
+            providers = [BuildSettingInfo],
+        ),
+        "_coverage_level": attr.label(
+            default = Label("//rust/settings:coverage_level"),
+            providers = [BuildSettingInfo],
+        ),
+        "_coverage_report_formats": attr.label(
+            default = Label("//rust/settings:coverage_report_formats"),
+            providers = [BuildSettingInfo],
+        ),
         "_experimental_use_allocator_libraries_with_mangled_symbols_setting": attr.label(
             default = Label("//rust/settings:experimental_use_allocator_libraries_with_mangled_symbols"),
             providers = [BuildSettingInfo],
@@ -969,6 +985,10 @@ rust_toolchain = rule(
                 "This flag is only relevant when used together with --@rules_rust//rust/settings:experimental_use_global_allocator."
             ),
         ),
+        "_experimental_use_libtest_launcher": attr.label(
+            default = Label("//rust/settings:experimental_use_libtest_launcher"),
+            providers = [BuildSettingInfo],
+        ),
         "_incompatible_change_rust_test_compilation_output_directory": attr.label(
             default = Label("//rust/settings:incompatible_change_rust_test_compilation_output_directory"),
         ),
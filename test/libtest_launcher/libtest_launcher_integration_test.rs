@@ -0,0 +1,81 @@
+//! Runs a `rust_test` through the libtest launcher the way Bazel does for tests
+//! with a `shard_count` and checks the JUnit reports of each shard.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use runfiles::{rlocation, Runfiles};
+
+/// Extract the names of all test cases from a JUnit report.
+fn testcase_names(report: &str) -> Vec<String> {
+    report
+        .split("<testcase name=\"")
+        .skip(1)
+        .map(|testcase| testcase.split('"').next().unwrap().to_owned())
+        .collect()
+}
+
+/// Run one shard of the test through the launcher and return the reported test cases.
+fn run_shard(index: usize, total: usize, args: &[&str]) -> Vec<String> {
+    let runfiles = Runfiles::create().unwrap();
+    let launcher_path = env::var("LAUNCHER").unwrap();
+    let launcher = rlocation!(runfiles, &launcher_path).unwrap();
+    let launcher_env: BTreeMap<String, String> =
+        fs::read_to_string(rlocation!(runfiles, format!("{}.env", launcher_path)).unwrap())
+            .unwrap()
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+            .collect();
+
+    let tmpdir = PathBuf::from(env::var("TEST_TMPDIR").unwrap());
+    let xml_output_file = tmpdir.join(format!("shard_{}_of_{}.xml", index, total));
+    let status_file = tmpdir.join(format!("shard_{}_of_{}.status", index, total));
+    let status = Command::new(&launcher)
+        .args(args)
+        .envs(&launcher_env)
+        .env("TEST_SHARD_INDEX", index.to_string())
+        .env("TEST_TOTAL_SHARDS", total.to_string())
+        .env("TEST_SHARD_STATUS_FILE", &status_file)
+        .env("XML_OUTPUT_FILE", &xml_output_file)
+        .env_remove("TESTBRIDGE_TEST_ONLY")
+        .status()
+        .unwrap();
+    assert!(status.success(), "shard {} of {} failed", index, total);
+    assert!(
+        status_file.exists(),
+        "shard {} of {} did not acknowledge sharding",
+        index,
+        total
+    );
+
+    testcase_names(&fs::read_to_string(&xml_output_file).unwrap())
+}
+
+#[test]
+fn sharded_junit_reports() {
+    let mut reported = Vec::new();
+    for index in 0..2 {
+        let names = run_shard(index, 2, &[]);
+        assert!(!names.is_empty(), "shard {} ran no tests", index);
+        reported.extend(names);
+    }
+
+    // Each test is reported by exactly one shard, even when its name is part of another.
+    reported.sort();
+    assert_eq!(reported, vec!["first", "first_and_second", "second"]);
+}
+
+#[test]
+fn sharded_exact_arg() {
+    let mut reported = Vec::new();
+    for index in 0..2 {
+        reported.extend(run_shard(index, 2, &["--exact"]));
+    }
+
+    reported.sort();
+    assert_eq!(reported, vec!["first", "first_and_second", "second"]);
+}
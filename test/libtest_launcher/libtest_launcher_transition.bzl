@@ -0,0 +1,63 @@
+"""Internal utility for building a `rust_test` with the libtest launcher enabled."""
+
+load("@bazel_skylib//lib:paths.bzl", "paths")
+
+_LIBTEST_LAUNCHER_SETTING = str(Label("//rust/settings:experimental_use_libtest_launcher"))
+
+def _libtest_launcher_transition_impl(_settings, _attr):
+    return {_LIBTEST_LAUNCHER_SETTING: True}
+
+_libtest_launcher_transition = transition(
+    implementation = _libtest_launcher_transition_impl,
+    inputs = [],
+    outputs = [_LIBTEST_LAUNCHER_SETTING],
+)
+
+def _libtest_launcher_test_impl(ctx):
+    test = ctx.attr.test[0]
+    default_info = test[DefaultInfo]
+    original_executable = default_info.files_to_run.executable
+
+    # Bazel requires the executable to be generated by this rule so it's symlinked
+    # into a subdirectory to retain its basename.
+    executable = ctx.actions.declare_file(paths.join(ctx.label.name, original_executable.basename))
+    ctx.actions.symlink(
+        output = executable,
+        target_file = original_executable,
+        is_executable = True,
+    )
+
+    # The environment of the test is written next to the executable for tests which run it.
+    env_file = ctx.actions.declare_file(paths.join(ctx.label.name, original_executable.basename + ".env"))
+    ctx.actions.write(
+        output = env_file,
+        content = "".join([
+            "{}={}\n".format(key, value)
+            for key, value in test[RunEnvironmentInfo].environment.items()
+        ]),
+    )
+
+    return [
+        DefaultInfo(
+            files = depset([executable, env_file]),
+            runfiles = ctx.runfiles([executable, env_file]).merge(default_info.default_runfiles),
+            executable = executable,
+        ),
+        test[RunEnvironmentInfo],
+    ]
+
+libtest_launcher_test = rule(
+    doc = "Builds and runs a `rust_test` with `experimental_use_libtest_launcher` enabled.",
+    implementation = _libtest_launcher_test_impl,
+    attrs = {
+        "test": attr.label(
+            doc = "The `rust_test` to build.",
+            cfg = _libtest_launcher_transition,
+            mandatory = True,
+        ),
+        "_allowlist_function_transition": attr.label(
+            default = "@bazel_tools//tools/allowlists/function_transition_allowlist",
+        ),
+    },
+    test = True,
+)
@@ -0,0 +1,8 @@
+#[test]
+fn first() {}
+
+#[test]
+fn first_and_second() {}
+
+#[test]
+fn second() {}
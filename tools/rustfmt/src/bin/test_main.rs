@@ -22,19 +22,28 @@ fn run_rustfmt(options: &Config) {
             continue;
         }
 
-        // Run rustfmt
-        let status = Command::new(&options.rustfmt_config.rustfmt)
-            .arg("--check")
-            .arg("--edition")
-            .arg(&manifest.edition)
-            .arg("--config-path")
-            .arg(&options.rustfmt_config.config)
-            .args(&manifest.sources)
-            .status()
-            .expect("Failed to run rustfmt");
+        // Sources sharing a config file are checked in a single invocation
+        let groups = options
+            .rustfmt_config
+            .group_by_config(manifest.sources.iter(), |src| {
+                manifest.nested_configs.get(*src).cloned()
+            });
 
-        if !status.success() {
-            is_failure = true;
+        for (config, sources) in groups {
+            // Run rustfmt
+            let status = Command::new(&options.rustfmt_config.rustfmt)
+                .arg("--check")
+                .arg("--edition")
+                .arg(&manifest.edition)
+                .arg("--config-path")
+                .arg(config)
+                .args(sources)
+                .status()
+                .expect("Failed to run rustfmt");
+
+            if !status.success() {
+                is_failure = true;
+            }
         }
     }
 
@@ -1,3 +1,4 @@
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -5,6 +6,9 @@ use std::path::{Path, PathBuf};
 /// The expected extension of rustfmt manifest files generated by `rustfmt_aspect`.
 pub const RUSTFMT_MANIFEST_EXTENSION: &str = "rustfmt";
 
+/// The names of rustfmt config files in the order rustfmt prefers them.
+pub const RUSTFMT_CONFIG_FILE_NAMES: &[&str] = &[".rustfmt.toml", "rustfmt.toml"];
+
 /// A struct containing details used for executing rustfmt.
 #[derive(Debug)]
 pub struct RustfmtConfig {
@@ -37,6 +41,55 @@ pub fn parse_rustfmt_config() -> RustfmtConfig {
     RustfmtConfig { rustfmt, config }
 }
 
+impl RustfmtConfig {
+    /// Group sources by the rustfmt config file which applies to them. Sources
+    /// for which `find_config` returns `None` use the global config.
+    pub fn group_by_config<T, F>(
+        &self,
+        sources: impl IntoIterator<Item = T>,
+        find_config: F,
+    ) -> BTreeMap<PathBuf, Vec<T>>
+    where
+        F: Fn(&T) -> Option<PathBuf>,
+    {
+        let mut groups: BTreeMap<PathBuf, Vec<T>> = BTreeMap::new();
+        for src in sources {
+            let config = find_config(&src).unwrap_or_else(|| self.config.clone());
+            groups.entry(config).or_default().push(src);
+        }
+        groups
+    }
+}
+
+/// Find the closest rustfmt config file to a source file, the way `cargo fmt` does.
+///
+/// Each parent directory of `source` is searched up to and including `root`.
+/// `None` is returned if none of them contain a config, in which case the global
+/// config applies. `locate` maps a candidate config path to a real path if the
+/// config file exists.
+pub fn find_nested_config<F>(source: &Path, root: &Path, locate: F) -> Option<PathBuf>
+where
+    F: Fn(&Path) -> Option<PathBuf>,
+{
+    for dir in source.ancestors().skip(1) {
+        if !dir.starts_with(root) {
+            break;
+        }
+
+        for name in RUSTFMT_CONFIG_FILE_NAMES {
+            if let Some(config) = locate(&dir.join(name)) {
+                return Some(config);
+            }
+        }
+
+        if dir == root {
+            break;
+        }
+    }
+
+    None
+}
+
 /// A struct of target specific information for use in running `rustfmt`.
 #[derive(Debug)]
 pub struct RustfmtManifest {
@@ -45,10 +98,66 @@ pub struct RustfmtManifest {
 
     /// A list of all (non-generated) source files for formatting.
     pub sources: Vec<PathBuf>,
+
+    /// Nested rustfmt config files found in runfiles, keyed by the sources they
+    /// apply to. Sources without an entry use the global config.
+    pub nested_configs: HashMap<PathBuf, PathBuf>,
 }
 
 /// Parse rustfmt flags from a manifest generated by builds using `rustfmt_aspect`.
 pub fn parse_rustfmt_manifest(manifest: &Path) -> RustfmtManifest {
+    let (edition, lines) = read_rustfmt_manifest(manifest);
+
+    let runfiles = runfiles::Runfiles::create().unwrap();
+
+    let mut sources = Vec::new();
+    let mut nested_configs = HashMap::new();
+    for src in lines {
+        let path = runfiles::rlocation!(runfiles, &src).unwrap();
+
+        // Manifest entries are prefixed with the name of the owning workspace
+        // which is the last directory searched for configs.
+        let src = Path::new(&src);
+        let workspace = src.iter().next().map(Path::new).unwrap_or(src);
+        let config = find_nested_config(src, workspace, |candidate| {
+            runfiles::rlocation!(runfiles, candidate).filter(|config| config.exists())
+        });
+        if let Some(config) = config {
+            nested_configs.insert(path.clone(), config);
+        }
+
+        sources.push(path);
+    }
+
+    RustfmtManifest {
+        edition,
+        sources,
+        nested_configs,
+    }
+}
+
+/// Parse rustfmt flags from a manifest generated by builds using `rustfmt_aspect`
+/// outside of a runfiles tree. Sources are returned relative to the root of the
+/// workspace which owns them and no nested configs are resolved.
+pub fn parse_rustfmt_manifest_sources(manifest: &Path) -> RustfmtManifest {
+    let (edition, lines) = read_rustfmt_manifest(manifest);
+
+    RustfmtManifest {
+        edition,
+        sources: lines
+            .into_iter()
+            .map(|src| {
+                // Manifest entries are prefixed with the name of the owning workspace.
+                let (_workspace, path) = src.split_once('/').unwrap_or(("", &src));
+                PathBuf::from(path)
+            })
+            .collect(),
+        nested_configs: HashMap::new(),
+    }
+}
+
+/// Read the edition and the source entries out of a rustfmt manifest.
+fn read_rustfmt_manifest(manifest: &Path) -> (String, Vec<String>) {
     let content = fs::read_to_string(manifest)
         .unwrap_or_else(|_| panic!("Failed to read rustfmt manifest: {}", manifest.display()));
 
@@ -65,15 +174,7 @@ pub fn parse_rustfmt_manifest(manifest: &Path) -> RustfmtManifest {
         .parse::<i32>()
         .expect("The edition should be a numeric value. eg `2018`.");
 
-    let runfiles = runfiles::Runfiles::create().unwrap();
-
-    RustfmtManifest {
-        edition,
-        sources: lines
-            .into_iter()
-            .map(|src| runfiles::rlocation!(runfiles, src).unwrap())
-            .collect(),
-    }
+    (edition, lines)
 }
 
 #[cfg(target_family = "windows")]
@@ -95,3 +196,90 @@ pub fn find_manifests() -> Vec<PathBuf> {
         })
         .unwrap_or_default()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::collections::BTreeSet;
+
+    /// Locate configs among a fixed set of existing paths.
+    fn locate_in(configs: &[&str]) -> impl Fn(&Path) -> Option<PathBuf> {
+        let configs: BTreeSet<PathBuf> = configs.iter().map(PathBuf::from).collect();
+        move |candidate| configs.get(candidate).cloned()
+    }
+
+    #[test]
+    fn find_nested_config_test() {
+        let locate = locate_in(&["ws/a/rustfmt.toml", "ws/a/b/.rustfmt.toml"]);
+
+        assert_eq!(
+            find_nested_config(Path::new("ws/a/b/c/lib.rs"), Path::new("ws"), &locate),
+            Some(PathBuf::from("ws/a/b/.rustfmt.toml"))
+        );
+        assert_eq!(
+            find_nested_config(Path::new("ws/a/lib.rs"), Path::new("ws"), &locate),
+            Some(PathBuf::from("ws/a/rustfmt.toml"))
+        );
+        assert_eq!(
+            find_nested_config(Path::new("ws/other/lib.rs"), Path::new("ws"), &locate),
+            None
+        );
+    }
+
+    #[test]
+    fn find_nested_config_prefers_dot_rustfmt_test() {
+        let locate = locate_in(&["ws/a/rustfmt.toml", "ws/a/.rustfmt.toml"]);
+
+        assert_eq!(
+            find_nested_config(Path::new("ws/a/lib.rs"), Path::new("ws"), &locate),
+            Some(PathBuf::from("ws/a/.rustfmt.toml"))
+        );
+    }
+
+    #[test]
+    fn find_nested_config_at_root_test() {
+        // Configs at the root are found while ones above it are not.
+        let locate = locate_in(&["ws/rustfmt.toml", "rustfmt.toml"]);
+        assert_eq!(
+            find_nested_config(Path::new("ws/a/lib.rs"), Path::new("ws"), &locate),
+            Some(PathBuf::from("ws/rustfmt.toml"))
+        );
+
+        let locate = locate_in(&["rustfmt.toml"]);
+        assert_eq!(
+            find_nested_config(Path::new("ws/a/lib.rs"), Path::new("ws"), &locate),
+            None
+        );
+
+        // An empty root is the root of a relative path.
+        assert_eq!(
+            find_nested_config(Path::new("a/lib.rs"), Path::new(""), &locate),
+            Some(PathBuf::from("rustfmt.toml"))
+        );
+    }
+
+    #[test]
+    fn group_by_config_test() {
+        let config = RustfmtConfig {
+            rustfmt: PathBuf::from("rustfmt"),
+            config: PathBuf::from("global.toml"),
+        };
+
+        let groups = config.group_by_config(["a/lib.rs", "b/lib.rs", "a/mod.rs"], |src| {
+            src.starts_with("a/")
+                .then(|| PathBuf::from("a/rustfmt.toml"))
+        });
+
+        assert_eq!(
+            groups,
+            BTreeMap::from([
+                (
+                    PathBuf::from("a/rustfmt.toml"),
+                    vec!["a/lib.rs", "a/mod.rs"]
+                ),
+                (PathBuf::from("global.toml"), vec!["b/lib.rs"]),
+            ])
+        );
+    }
+}
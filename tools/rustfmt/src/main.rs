@@ -1,10 +1,15 @@
 //! A tool for querying Rust source files wired into Bazel and running Rustfmt on them.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::env;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::str;
+use std::sync::Mutex;
+use std::thread;
+
+use tinyjson::JsonValue;
 
 /// The Bazel Rustfmt tool entry point
 fn main() {
@@ -12,7 +17,11 @@ fn main() {
     let options = parse_args();
 
     // Gather a list of all formattable targets
-    let targets = query_rustfmt_targets(&options);
+    let targets = if options.files.is_empty() {
+        query_rustfmt_targets(&options)
+    } else {
+        manifest_rustfmt_targets(&options)
+    };
 
     // Run rustfmt on these targets
     apply_rustfmt(&options, &targets);
@@ -31,16 +40,6 @@ fn get_default_edition() -> &'static str {
     }
 }
 
-/// Get a list of all editions to run formatting for
-fn get_editions() -> Vec<String> {
-    vec![
-        "2015".to_owned(),
-        "2018".to_owned(),
-        "2021".to_owned(),
-        "2024".to_owned(),
-    ]
-}
-
 /// Run a bazel command, capturing stdout while streaming stderr to surface errors
 fn bazel_command(bazel_bin: &Path, args: &[String], current_dir: &Path) -> Vec<String> {
     let child = Command::new(bazel_bin)
@@ -56,7 +55,10 @@ fn bazel_command(bazel_bin: &Path, args: &[String], current_dir: &Path) -> Vec<S
         .expect("Failed to wait on spawned command");
 
     if !output.status.success() {
-        eprintln!("Failed to perform `bazel query` command.");
+        eprintln!(
+            "Failed to perform `bazel {}` command.",
+            args.first().map(String::as_str).unwrap_or_default()
+        );
         std::process::exit(output.status.code().unwrap_or(1));
     }
 
@@ -68,30 +70,105 @@ fn bazel_command(bazel_bin: &Path, args: &[String], current_dir: &Path) -> Vec<S
         .collect()
 }
 
-/// The regex representation of an empty `edition` attribute
-const EMPTY_EDITION: &str = "^$";
-
-/// Query for all `*.rs` files in a workspace that are dependencies of targets with the requested edition.
-fn edition_query(bazel_bin: &Path, edition: &str, scope: &str, current_dir: &Path) -> Vec<String> {
+/// Query for all Rust targets in the given scope along with the `*.rs` source files they depend on.
+fn rustfmt_targets_query(bazel_bin: &Path, scope: &str, current_dir: &Path) -> Vec<String> {
     let query_args = vec![
         "query".to_owned(),
         // Query explanation:
-        // Filter all local targets ending in `*.rs`.
-        //     Get all source files.
-        //         Get direct dependencies.
-        //             Get all targets with the specified `edition` attribute.
-        //             Except for targets tagged with `norustfmt`, `no-rustfmt`, or `no-format`.
-        //             And except for targets with a populated `crate` attribute since `crate` defines edition for this target
+        // Get all targets with an `edition` attribute.
+        //     Except for targets tagged with `norustfmt`, `no-rustfmt`, or `no-format`.
+        //     And except for targets with a populated `crate` attribute since `crate` defines edition for this target
+        // Along with all local source files ending in `*.rs` that are direct dependencies of those targets.
         format!(
-            r#"let scope = set({scope}) in filter("^//.*\.rs$", kind("source file", deps(attr(edition, "{edition}", $scope) except attr(tags, "(^\[|, )(no-format|no-rustfmt|norustfmt)(, |\]$)", $scope) except attr(crate, ".*", $scope), 1)))"#,
+            r#"let scope = set({scope}) in let rules = attr(edition, ".*", $scope) except attr(tags, "(^\[|, )(no-format|no-rustfmt|norustfmt)(, |\]$)", $scope) except attr(crate, ".*", $scope) in $rules + filter("^//.*\.rs$", kind("source file", deps($rules, 1)))"#,
         ),
         "--keep_going".to_owned(),
         "--noimplicit_deps".to_owned(),
+        "--output=streamed_jsonproto".to_owned(),
     ];
 
     bazel_command(bazel_bin, &query_args, current_dir)
 }
 
+/// Access a field of a JSON object.
+fn json_field<'a>(value: &'a JsonValue, key: &str) -> Option<&'a JsonValue> {
+    match value {
+        JsonValue::Object(map) => map.get(key),
+        _ => None,
+    }
+}
+
+/// Access a string field of a JSON object.
+fn json_str<'a>(value: &'a JsonValue, key: &str) -> Option<&'a str> {
+    match json_field(value, key)? {
+        JsonValue::String(s) => Some(s),
+        _ => None,
+    }
+}
+
+/// A Rust target as reported by `bazel query --output=streamed_jsonproto`.
+#[derive(Debug, Default)]
+struct QueriedTarget {
+    /// The edition of the target. Empty if the toolchain default is used.
+    edition: String,
+
+    /// The labels of all direct inputs of the target.
+    inputs: Vec<String>,
+}
+
+/// Parse the `streamed_jsonproto` output of [rustfmt_targets_query] into the
+/// queried rules and the set of formattable source file labels.
+fn parse_rustfmt_targets(lines: &[String]) -> (Vec<QueriedTarget>, BTreeSet<String>) {
+    let mut rules = Vec::new();
+    let mut source_files = BTreeSet::new();
+
+    for line in lines {
+        let target: JsonValue = line
+            .parse()
+            .unwrap_or_else(|e| panic!("Failed to parse query output `{}`: {}", line, e));
+
+        match json_str(&target, "type") {
+            Some("SOURCE_FILE") => {
+                if let Some(name) =
+                    json_field(&target, "sourceFile").and_then(|f| json_str(f, "name"))
+                {
+                    source_files.insert(name.to_owned());
+                }
+            }
+            Some("RULE") => {
+                let rule = json_field(&target, "rule");
+                let attributes = match rule.and_then(|r| json_field(r, "attribute")) {
+                    Some(JsonValue::Array(attributes)) => attributes.as_slice(),
+                    _ => &[],
+                };
+                let inputs = match rule.and_then(|r| json_field(r, "ruleInput")) {
+                    Some(JsonValue::Array(inputs)) => inputs.as_slice(),
+                    _ => &[],
+                };
+
+                rules.push(QueriedTarget {
+                    edition: attributes
+                        .iter()
+                        .find(|attribute| json_str(attribute, "name") == Some("edition"))
+                        .and_then(|attribute| json_str(attribute, "stringValue"))
+                        .unwrap_or_default()
+                        .to_owned(),
+                    inputs: inputs
+                        .iter()
+                        .filter_map(|input| match input {
+                            JsonValue::String(s) => Some(s.clone()),
+                            _ => None,
+                        })
+                        .collect(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    (rules, source_files)
+}
+
 /// Perform a `bazel` query to determine all source files which are to be
 /// formatted for particular Rust editions.
 fn query_rustfmt_targets(options: &Config) -> HashMap<String, Vec<String>> {
@@ -102,37 +179,182 @@ fn query_rustfmt_targets(options: &Config) -> HashMap<String, Vec<String>> {
         .reduce(|acc, item| acc + " " + &item)
         .unwrap_or_else(|| "//...:all".to_owned());
 
-    let editions = get_editions();
+    let output = rustfmt_targets_query(&options.bazel, &scope, &options.workspace);
+    let (rules, source_files) = parse_rustfmt_targets(&output);
+
     let default_edition = get_default_edition();
 
-    editions
-        .into_iter()
-        .map(|edition| {
-            let mut targets = edition_query(&options.bazel, &edition, &scope, &options.workspace);
-
-            // For all targets relying on the toolchain for it's edition,
-            // query anything with an unset edition
-            if edition == default_edition {
-                targets.extend(edition_query(
-                    &options.bazel,
-                    EMPTY_EDITION,
-                    &scope,
-                    &options.workspace,
-                ))
-            }
+    let mut editions_and_targets: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for rule in rules {
+        // For all targets relying on the toolchain for it's edition,
+        // use the default edition.
+        let edition = if rule.edition.is_empty() {
+            default_edition.to_owned()
+        } else {
+            rule.edition
+        };
 
-            (edition, targets)
-        })
+        // Like the rustfmt aspect, all `.rs` source files the target consumes directly
+        // (e.g. `srcs` and `crate_root`) are formatted while generated sources are not.
+        editions_and_targets.entry(edition).or_default().extend(
+            rule.inputs
+                .into_iter()
+                .filter(|input| source_files.contains(input)),
+        );
+    }
+
+    editions_and_targets
+        .into_iter()
+        .map(|(edition, targets)| (edition, targets.into_iter().collect()))
         .collect()
 }
 
-/// Run rustfmt on a set of Bazel targets
-fn apply_rustfmt(options: &Config, editions_and_targets: &HashMap<String, Vec<String>>) {
-    // There is no work to do if the list of targets is empty
-    if editions_and_targets.is_empty() {
-        return;
+/// Query for all targets in the same package as the given source files which
+/// directly depend on them.
+fn owning_targets_query(bazel_bin: &Path, files: &[PathBuf], current_dir: &Path) -> Vec<String> {
+    let files = files
+        .iter()
+        .map(|file| file.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let query_args = vec![
+        "query".to_owned(),
+        format!(r#"kind(rule, same_pkg_direct_rdeps(set({files})))"#),
+        "--keep_going".to_owned(),
+        "--noimplicit_deps".to_owned(),
+    ];
+
+    bazel_command(bazel_bin, &query_args, current_dir)
+}
+
+/// Build a list of Bazel targets using the `rustfmt_aspect` to produce the
+/// manifests containing the arguments to use when formatting their sources.
+fn generate_rustfmt_target_manifests(options: &Config, targets: &[String]) {
+    let build_args = vec![
+        "build".to_owned(),
+        format!(
+            "--aspects={}//rust:defs.bzl%rustfmt_aspect",
+            env!("ASPECT_REPOSITORY")
+        ),
+        "--output_groups=rustfmt_manifest".to_owned(),
+        "--keep_going".to_owned(),
+    ];
+
+    bazel_command(
+        &options.bazel,
+        &[build_args, targets.to_vec()].concat(),
+        &options.workspace,
+    );
+}
+
+/// Determine the editions of the requested source files from the `RustfmtManifest`s
+/// of their owning targets instead of querying the whole workspace.
+fn manifest_rustfmt_targets(options: &Config) -> HashMap<String, Vec<String>> {
+    let targets = owning_targets_query(&options.bazel, &options.files, &options.workspace);
+    if targets.is_empty() {
+        eprintln!("No Bazel targets own the requested files.");
+        std::process::exit(1);
     }
 
+    // Ensure a manifest is generated for each of the owning targets
+    generate_rustfmt_target_manifests(options, &targets);
+
+    let bazel_bin = PathBuf::from(
+        bazel_command(
+            &options.bazel,
+            &["info".to_owned(), "bazel-bin".to_owned()],
+            &options.workspace,
+        )
+        .pop()
+        .expect("`bazel info bazel-bin` should always produce output"),
+    );
+
+    let mut editions_and_targets: HashMap<String, Vec<String>> = HashMap::new();
+    let mut remaining: Vec<&PathBuf> = options.files.iter().collect();
+    for target in targets.iter() {
+        let label = match label::analyze(target) {
+            Ok(label) => label,
+            Err(err) => panic!("Failed to parse label `{}`: {:?}", target, err),
+        };
+
+        // Only targets in the current workspace will have a manifest and not all
+        // targets in a package are Rust targets.
+        if label.repo().is_some() {
+            continue;
+        }
+        let manifest = bazel_bin
+            .join(label.package().unwrap_or_default())
+            .join(format!(
+                "{}.{}",
+                label.name(),
+                rustfmt_lib::RUSTFMT_MANIFEST_EXTENSION
+            ));
+        if !manifest.exists() {
+            continue;
+        }
+
+        let manifest = rustfmt_lib::parse_rustfmt_manifest_sources(&manifest);
+        let (owned, unowned): (Vec<&PathBuf>, Vec<&PathBuf>) = remaining
+            .into_iter()
+            .partition(|file| manifest.sources.contains(*file));
+        remaining = unowned;
+
+        editions_and_targets
+            .entry(manifest.edition)
+            .or_default()
+            .extend(
+                owned
+                    .into_iter()
+                    .map(|file| file.to_string_lossy().to_string()),
+            );
+    }
+
+    for file in remaining {
+        eprintln!(
+            "No formattable Rust target owns `{}`, skipping.",
+            file.display()
+        );
+    }
+
+    editions_and_targets
+}
+
+/// Determine how many rustfmt processes may run at once.
+fn get_parallelism() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// The maximum combined length of the sources passed to a single rustfmt invocation.
+/// Windows limits command lines to 32767 characters, far below `ARG_MAX` elsewhere.
+const MAX_SOURCES_LENGTH: usize = 30_000;
+
+/// A set of sources to format with a single rustfmt invocation.
+#[derive(Debug)]
+struct Batch {
+    /// The Rust edition of the sources.
+    edition: String,
+
+    /// The rustfmt config file which applies to the sources.
+    config: PathBuf,
+
+    /// Paths to the sources, relative to the workspace root.
+    sources: Vec<String>,
+}
+
+/// Split the sources of each edition into batches of sources sharing the
+/// same rustfmt config so that the batches can be spread across all available
+/// workers. Batches are split further to keep command lines within
+/// [MAX_SOURCES_LENGTH].
+fn batch_sources(
+    options: &Config,
+    editions_and_targets: &HashMap<String, Vec<String>>,
+    parallelism: usize,
+) -> Vec<Batch> {
+    let mut batches = Vec::new();
+
     for (edition, targets) in editions_and_targets.iter() {
         if targets.is_empty() {
             continue;
@@ -144,21 +366,94 @@ fn apply_rustfmt(options: &Config, editions_and_targets: &HashMap<String, Vec<St
             .map(|target| target.replace(':', "/").trim_start_matches('/').to_owned())
             .collect();
 
-        // Run rustfmt
-        let status = Command::new(&options.rustfmt_config.rustfmt)
-            .current_dir(&options.workspace)
-            .arg("--edition")
-            .arg(edition)
-            .arg("--config-path")
-            .arg(&options.rustfmt_config.config)
-            .args(sources)
-            .status()
-            .expect("Failed to run rustfmt");
-
-        if !status.success() {
-            std::process::exit(status.code().unwrap_or(1));
+        // Nested `rustfmt.toml` files take precedence over the global config
+        let groups = options.rustfmt_config.group_by_config(sources, |src| {
+            rustfmt_lib::find_nested_config(Path::new(src), Path::new(""), |candidate| {
+                let config = options.workspace.join(candidate);
+                config.is_file().then_some(config)
+            })
+        });
+
+        for (config, sources) in groups {
+            let batch_size = sources.len().div_ceil(parallelism);
+            for chunk in sources.chunks(batch_size) {
+                let mut pending: Vec<String> = Vec::new();
+                let mut length = 0;
+                for src in chunk {
+                    if !pending.is_empty() && length + src.len() + 1 > MAX_SOURCES_LENGTH {
+                        batches.push(Batch {
+                            edition: edition.clone(),
+                            config: config.clone(),
+                            sources: std::mem::take(&mut pending),
+                        });
+                        length = 0;
+                    }
+                    length += src.len() + 1;
+                    pending.push(src.clone());
+                }
+                batches.push(Batch {
+                    edition: edition.clone(),
+                    config: config.clone(),
+                    sources: pending,
+                });
+            }
         }
     }
+
+    batches
+}
+
+/// Run rustfmt on a set of Bazel targets
+fn apply_rustfmt(options: &Config, editions_and_targets: &HashMap<String, Vec<String>>) {
+    // There is no work to do if the list of targets is empty
+    if editions_and_targets.is_empty() {
+        return;
+    }
+
+    let parallelism = get_parallelism();
+    let batches = Mutex::new(batch_sources(options, editions_and_targets, parallelism).into_iter());
+
+    // In order to ensure all sources are processed, we separately track the
+    // exit code of the first failure which occurred.
+    let failure: Mutex<Option<i32>> = Mutex::new(None);
+
+    thread::scope(|scope| {
+        for _ in 0..parallelism {
+            scope.spawn(|| loop {
+                let batch = match batches.lock().unwrap().next() {
+                    Some(batch) => batch,
+                    None => break,
+                };
+
+                // Run rustfmt
+                let output = Command::new(&options.rustfmt_config.rustfmt)
+                    .current_dir(&options.workspace)
+                    .args(options.check.then_some("--check"))
+                    .arg("--edition")
+                    .arg(&batch.edition)
+                    .arg("--config-path")
+                    .arg(&batch.config)
+                    .args(&batch.sources)
+                    .output()
+                    .expect("Failed to run rustfmt");
+
+                // Output is captured so that diffs from concurrent batches don't interleave.
+                io::stdout().write_all(&output.stdout).unwrap();
+                io::stderr().write_all(&output.stderr).unwrap();
+
+                if !output.status.success() {
+                    failure
+                        .lock()
+                        .unwrap()
+                        .get_or_insert(output.status.code().unwrap_or(1));
+                }
+            });
+        }
+    });
+
+    if let Some(code) = failure.into_inner().unwrap() {
+        std::process::exit(code);
+    }
 }
 
 /// A struct containing details used for executing rustfmt.
@@ -178,21 +473,235 @@ struct Config {
     /// to be formatted. If empty, all targets in the workspace will
     /// be formatted.
     pub packages: Vec<String>,
+
+    /// Source files (relative to the workspace root) to format, passed
+    /// after `--files`. When set, only these files are formatted and
+    /// `packages` is ignored.
+    pub files: Vec<PathBuf>,
+
+    /// Whether or not to only check formatting (`--check`). Diffs are
+    /// printed and the process exits non-zero instead of writing files.
+    pub check: bool,
 }
 
 /// Parse command line arguments and environment variables to
 /// produce config data for running rustfmt.
+///
+/// Usage: `rustfmt [--check] [PACKAGES...]` or `rustfmt [--check] --files [FILES...]`
 fn parse_args() -> Config {
-    Config{
-        workspace: PathBuf::from(
-            env::var("BUILD_WORKSPACE_DIRECTORY")
-            .expect("The environment variable BUILD_WORKSPACE_DIRECTORY is required for finding the workspace root")
-        ),
-        bazel: PathBuf::from(
-            env::var("BAZEL_REAL")
-            .unwrap_or_else(|_| "bazel".to_owned())
-        ),
+    let workspace = PathBuf::from(
+        env::var("BUILD_WORKSPACE_DIRECTORY")
+        .expect("The environment variable BUILD_WORKSPACE_DIRECTORY is required for finding the workspace root")
+    );
+
+    let mut check = false;
+    let mut files_mode = false;
+    let mut positional = Vec::new();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--files" => files_mode = true,
+            _ => positional.push(arg),
+        }
+    }
+
+    let (packages, files) = if files_mode {
+        if positional.is_empty() {
+            usage_error("`--files` requires at least one source file.");
+        }
+        let working_dir = env::var("BUILD_WORKING_DIRECTORY")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| workspace.clone());
+        let files = positional
+            .iter()
+            .map(|file| workspace_relative_path(&workspace, &working_dir.join(file)))
+            .collect::<Result<_, _>>()
+            .unwrap_or_else(|e| usage_error(&e));
+        (Vec::new(), files)
+    } else {
+        (positional, Vec::new())
+    };
+
+    Config {
+        workspace,
+        bazel: PathBuf::from(env::var("BAZEL_REAL").unwrap_or_else(|_| "bazel".to_owned())),
         rustfmt_config: rustfmt_lib::parse_rustfmt_config(),
-        packages: env::args().skip(1).collect(),
+        packages,
+        files,
+        check,
+    }
+}
+
+/// Print an error about the command line arguments along with the usage and exit.
+fn usage_error(message: &str) -> ! {
+    eprintln!(
+        "{}\n\nUsage: rustfmt [--check] [PACKAGES...]\n       rustfmt [--check] --files FILES...",
+        message
+    );
+    std::process::exit(1);
+}
+
+/// Convert a path to a source file into a path relative to the workspace root.
+fn workspace_relative_path(workspace: &Path, path: &Path) -> Result<PathBuf, String> {
+    let canonicalize = |path: &Path| {
+        path.canonicalize()
+            .map_err(|e| format!("Failed to resolve `{}`: {}", path.display(), e))
+    };
+
+    canonicalize(path)?
+        .strip_prefix(canonicalize(workspace)?)
+        .map(Path::to_path_buf)
+        .map_err(|_| {
+            format!(
+                "`{}` is not within the workspace `{}`",
+                path.display(),
+                workspace.display()
+            )
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::fs;
+
+    fn test_config(workspace: PathBuf) -> Config {
+        Config {
+            workspace,
+            bazel: PathBuf::from("bazel"),
+            rustfmt_config: rustfmt_lib::RustfmtConfig {
+                rustfmt: PathBuf::from("rustfmt"),
+                config: PathBuf::from("global/rustfmt.toml"),
+            },
+            packages: Vec::new(),
+            files: Vec::new(),
+            check: false,
+        }
+    }
+
+    #[test]
+    fn parse_rustfmt_targets_test() {
+        let lines: Vec<String> = [
+            r#"{"type":"RULE","rule":{"name":"//pkg:lib","ruleClass":"rust_library","attribute":[{"name":"edition","type":"STRING","stringValue":"2021"},{"name":"srcs","type":"LABEL_LIST","stringListValue":["//pkg:lib.rs"]}],"ruleInput":["//pkg:gen_rs","//pkg:lib.rs","//pkg:root.rs"]}}"#,
+            r#"{"type":"RULE","rule":{"name":"//pkg:bin","ruleClass":"rust_binary","attribute":[{"name":"edition","type":"STRING","stringValue":""}],"ruleInput":["//pkg:main.rs"]}}"#,
+            r#"{"type":"SOURCE_FILE","sourceFile":{"name":"//pkg:lib.rs"}}"#,
+            r#"{"type":"SOURCE_FILE","sourceFile":{"name":"//pkg:root.rs"}}"#,
+            r#"{"type":"SOURCE_FILE","sourceFile":{"name":"//pkg:main.rs"}}"#,
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+
+        let (rules, source_files) = parse_rustfmt_targets(&lines);
+
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].edition, "2021");
+        assert_eq!(
+            rules[0].inputs,
+            vec!["//pkg:gen_rs", "//pkg:lib.rs", "//pkg:root.rs"]
+        );
+        assert_eq!(rules[1].edition, "");
+        assert_eq!(rules[1].inputs, vec!["//pkg:main.rs"]);
+        assert_eq!(
+            source_files,
+            BTreeSet::from([
+                "//pkg:lib.rs".to_owned(),
+                "//pkg:main.rs".to_owned(),
+                "//pkg:root.rs".to_owned(),
+            ])
+        );
+    }
+
+    #[test]
+    fn batch_sources_test() {
+        let temp_dir = PathBuf::from(env::var("TEST_TMPDIR").unwrap());
+        let workspace = temp_dir.join("batch_sources_test");
+        fs::create_dir_all(workspace.join("nested")).unwrap();
+        fs::write(workspace.join("nested/rustfmt.toml"), "").unwrap();
+        let options = test_config(workspace.clone());
+
+        let editions_and_targets = HashMap::from([
+            ("2015".to_owned(), Vec::new()),
+            (
+                "2021".to_owned(),
+                vec![
+                    "//pkg:a.rs".to_owned(),
+                    "//pkg:b.rs".to_owned(),
+                    "//pkg:c.rs".to_owned(),
+                    "//nested:d.rs".to_owned(),
+                ],
+            ),
+        ]);
+
+        let mut batches = batch_sources(&options, &editions_and_targets, 2)
+            .into_iter()
+            .map(|batch| (batch.edition, batch.config, batch.sources))
+            .collect::<Vec<_>>();
+        batches.sort();
+
+        // Editions without sources produce no batches and sources are grouped by config.
+        assert_eq!(
+            batches,
+            vec![
+                (
+                    "2021".to_owned(),
+                    workspace.join("nested/rustfmt.toml"),
+                    vec!["nested/d.rs".to_owned()]
+                ),
+                (
+                    "2021".to_owned(),
+                    PathBuf::from("global/rustfmt.toml"),
+                    vec!["pkg/a.rs".to_owned(), "pkg/b.rs".to_owned()]
+                ),
+                (
+                    "2021".to_owned(),
+                    PathBuf::from("global/rustfmt.toml"),
+                    vec!["pkg/c.rs".to_owned()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn batch_sources_max_length_test() {
+        let options = test_config(PathBuf::from("/does/not/exist"));
+
+        let long_name = "a".repeat(MAX_SOURCES_LENGTH / 2);
+        let targets = (0..3)
+            .map(|i| format!("//pkg:{}{}.rs", long_name, i))
+            .collect::<Vec<_>>();
+        let editions_and_targets = HashMap::from([("2021".to_owned(), targets)]);
+
+        let batches = batch_sources(&options, &editions_and_targets, 1);
+
+        // Each source is about half the limit so only one fits in a batch.
+        assert_eq!(batches.len(), 3);
+        for batch in batches {
+            assert_eq!(batch.sources.len(), 1);
+        }
+    }
+
+    #[test]
+    fn workspace_relative_path_test() {
+        let temp_dir = PathBuf::from(env::var("TEST_TMPDIR").unwrap());
+        let workspace = temp_dir.join("workspace_relative_path_test/workspace");
+        let outside = temp_dir.join("workspace_relative_path_test/outside.rs");
+        fs::create_dir_all(workspace.join("pkg")).unwrap();
+        fs::write(workspace.join("pkg/lib.rs"), "").unwrap();
+        fs::write(&outside, "").unwrap();
+
+        assert_eq!(
+            workspace_relative_path(&workspace, &workspace.join("pkg/../pkg/lib.rs")),
+            Ok(PathBuf::from("pkg/lib.rs"))
+        );
+        assert!(workspace_relative_path(&workspace, &outside)
+            .unwrap_err()
+            .contains("is not within the workspace"));
+        assert!(
+            workspace_relative_path(&workspace, &workspace.join("pkg/missing.rs"))
+                .unwrap_err()
+                .contains("Failed to resolve")
+        );
     }
 }
@@ -15,16 +15,34 @@
 //! - `RUNFILES_DIR`: Location of the test's runfiles.
 //! - `VERBOSE_COVERAGE`: Print debug info from the coverage scripts
 //! - `COVERAGE_BINARY`: The binary that should be used for coverage (optional by default uses `TEST_BINARY`)
+//! - `RUST_COVERAGE_OBJECTS`: Whitespace separated runfiles paths of additional instrumented
+//!   objects (e.g. helper binaries or shared libraries) to report on. `rust_test` sets this to
+//!   the instrumented Rust binaries and shared libraries in its `data`, which may be none. If
+//!   unset, the runfiles of the test are searched for instrumented objects instead.
+//! - `RUST_COVERAGE_IGNORE_FILENAME_REGEX`: A regular expression of source files to exclude
+//!   from the report (optional).
+//! - `RUST_COVERAGE_REPORT_FORMATS`: Comma separated list of additional report formats
+//!   (`cobertura`, `html`, `json`) to write to `TEST_UNDECLARED_OUTPUTS_DIR` (optional).
 //!
 //! The script looks in $COVERAGE_DIR for the Rust metadata coverage files
 //! (profraw) and uses lcov to get the coverage data. The coverage data
 //! is placed in $COVERAGE_DIR as a `coverage.dat` file.
 
+use std::collections::BTreeSet;
+use std::convert::TryInto;
 use std::env;
 use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
 use std::path::Path;
 use std::path::PathBuf;
 use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use coverage_report::{parse_report_formats, Coverage, ReportFormat};
+
+/// Names of the sections LLVM uses to store coverage function records in
+/// ELF and Mach-O (`__llvm_covfun`) and COFF (`.lcovfun`) objects.
+const COVERAGE_SECTION_NAMES: [&[u8]; 2] = [b"__llvm_covfun", b".lcovfun"];
 
 macro_rules! debug_log {
     ($($arg:tt)*) => {
@@ -86,6 +104,376 @@ fn find_test_binary(execroot: &Path, runfiles_dir: &Path) -> PathBuf {
     }
 }
 
+/// Determine whether or not a file is worth inspecting for coverage data.
+fn is_object_candidate(path: &Path, metadata: &fs::Metadata) -> bool {
+    if let Some(ext) = path.extension() {
+        if ext == "so" || ext == "dylib" || ext == "dll" || ext == "exe" {
+            return true;
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        false
+    }
+}
+
+/// Determine whether or not a file is an object file containing coverage mappings.
+fn is_instrumented_object(path: &Path) -> bool {
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(_) => return false,
+    };
+
+    match object_section_names(&mut file) {
+        Some(names) => names
+            .iter()
+            .any(|name| COVERAGE_SECTION_NAMES.contains(&name.as_slice())),
+        None => false,
+    }
+}
+
+/// Read the names of all sections of an ELF, Mach-O or PE/COFF object.
+///
+/// Only the headers of the object are read. `None` is returned for anything
+/// which is not an object or whose headers could not be parsed.
+fn object_section_names<R: Read + Seek>(reader: &mut R) -> Option<Vec<Vec<u8>>> {
+    let magic = read_at(reader, 0, 4).ok()?;
+    let names = match magic.as_slice() {
+        [0x7f, b'E', b'L', b'F'] => elf_section_names(reader),
+        [0xce, 0xfa, 0xed, 0xfe] | [0xcf, 0xfa, 0xed, 0xfe] => macho_section_names(reader, 0),
+        [0xca, 0xfe, 0xba, 0xbe] => fat_macho_section_names(reader),
+        [b'M', b'Z', ..] => pe_section_names(reader),
+        _ => return None,
+    };
+
+    names.ok()
+}
+
+fn elf_section_names<R: Read + Seek>(reader: &mut R) -> io::Result<Vec<Vec<u8>>> {
+    let header = read_at(reader, 0, 64)?;
+    let is_64 = match header[4] {
+        1 => false,
+        2 => true,
+        _ => return Err(invalid_data("unknown ELF class")),
+    };
+    let big_endian = header[5] == 2;
+
+    let (shoff, shentsize, shnum, shstrndx) = if is_64 {
+        (
+            read_u64(&header, 0x28, big_endian)?,
+            read_u16(&header, 0x3a, big_endian)?,
+            read_u16(&header, 0x3c, big_endian)?,
+            read_u16(&header, 0x3e, big_endian)?,
+        )
+    } else {
+        (
+            u64::from(read_u32(&header, 0x20, big_endian)?),
+            read_u16(&header, 0x2e, big_endian)?,
+            read_u16(&header, 0x30, big_endian)?,
+            read_u16(&header, 0x32, big_endian)?,
+        )
+    };
+
+    let shentsize = u64::from(shentsize);
+    let section_headers = read_at(reader, shoff, shentsize * u64::from(shnum))?;
+
+    // Returns the name offset, file offset and size of a section.
+    let section = |index: u16| -> io::Result<(u32, u64, u64)> {
+        let start = (u64::from(index) * shentsize) as usize;
+        let entry = section_headers
+            .get(start..)
+            .ok_or_else(|| invalid_data("ELF section index out of range"))?;
+        if is_64 {
+            Ok((
+                read_u32(entry, 0x00, big_endian)?,
+                read_u64(entry, 0x18, big_endian)?,
+                read_u64(entry, 0x20, big_endian)?,
+            ))
+        } else {
+            Ok((
+                read_u32(entry, 0x00, big_endian)?,
+                u64::from(read_u32(entry, 0x10, big_endian)?),
+                u64::from(read_u32(entry, 0x14, big_endian)?),
+            ))
+        }
+    };
+
+    let (_, strtab_offset, strtab_size) = section(shstrndx)?;
+    let strtab = read_at(reader, strtab_offset, strtab_size)?;
+
+    (0..shnum)
+        .map(|index| {
+            let (name, _, _) = section(index)?;
+            let name = strtab
+                .get(name as usize..)
+                .ok_or_else(|| invalid_data("ELF section name out of range"))?;
+            Ok(trim_nul(name).to_vec())
+        })
+        .collect()
+}
+
+fn macho_section_names<R: Read + Seek>(reader: &mut R, offset: u64) -> io::Result<Vec<Vec<u8>>> {
+    const LC_SEGMENT: u32 = 0x1;
+    const LC_SEGMENT_64: u32 = 0x19;
+
+    let header = read_at(reader, offset, 32)?;
+    let is_64 = header[0] == 0xcf;
+    let ncmds = read_u32(&header, 16, false)?;
+    let sizeofcmds = read_u32(&header, 20, false)?;
+    let header_size = if is_64 { 32 } else { 28 };
+    let commands = read_at(reader, offset + header_size, u64::from(sizeofcmds))?;
+
+    let mut names = Vec::new();
+    let mut command_offset = 0;
+    for _ in 0..ncmds {
+        let command = commands
+            .get(command_offset..)
+            .ok_or_else(|| invalid_data("Mach-O load command out of range"))?;
+        let cmd = read_u32(command, 0, false)?;
+        let cmdsize = read_u32(command, 4, false)? as usize;
+        if cmdsize == 0 {
+            return Err(invalid_data("empty Mach-O load command"));
+        }
+
+        // Sections directly follow the segment command they belong to.
+        let layout = match cmd {
+            LC_SEGMENT => Some((48, 56, 68)),
+            LC_SEGMENT_64 => Some((64, 72, 80)),
+            _ => None,
+        };
+        if let Some((nsects_offset, segment_size, section_size)) = layout {
+            let nsects = read_u32(command, nsects_offset, false)? as usize;
+            for index in 0..nsects {
+                let start = segment_size + index * section_size;
+                let name = command
+                    .get(start..start + 16)
+                    .ok_or_else(|| invalid_data("Mach-O section out of range"))?;
+                names.push(trim_nul(name).to_vec());
+            }
+        }
+
+        command_offset += cmdsize;
+    }
+
+    Ok(names)
+}
+
+fn fat_macho_section_names<R: Read + Seek>(reader: &mut R) -> io::Result<Vec<Vec<u8>>> {
+    let header = read_at(reader, 0, 8)?;
+    let nfat_arch = read_u32(&header, 4, true)?;
+    let archs = read_at(reader, 8, u64::from(nfat_arch) * 20)?;
+
+    let mut names = Vec::new();
+    for arch in archs.chunks(20) {
+        let offset = read_u32(arch, 8, true)?;
+        names.extend(macho_section_names(reader, u64::from(offset))?);
+    }
+
+    Ok(names)
+}
+
+fn pe_section_names<R: Read + Seek>(reader: &mut R) -> io::Result<Vec<Vec<u8>>> {
+    let dos_header = read_at(reader, 0, 64)?;
+    let pe_offset = u64::from(read_u32(&dos_header, 0x3c, false)?);
+    let pe_header = read_at(reader, pe_offset, 24)?;
+    if !pe_header.starts_with(b"PE\0\0") {
+        return Err(invalid_data("missing PE signature"));
+    }
+
+    let nsections = read_u16(&pe_header, 6, false)?;
+    let optional_header_size = read_u16(&pe_header, 20, false)?;
+    let sections = read_at(
+        reader,
+        pe_offset + 24 + u64::from(optional_header_size),
+        u64::from(nsections) * 40,
+    )?;
+
+    Ok(sections
+        .chunks(40)
+        .map(|section| trim_nul(&section[..8]).to_vec())
+        .collect())
+}
+
+/// Read `len` bytes at `offset`, failing rather than allocating if they lie past the end of the stream.
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+    let stream_len = reader.seek(SeekFrom::End(0))?;
+    match offset.checked_add(len) {
+        Some(end) if end <= stream_len => {}
+        _ => return Err(io::ErrorKind::UnexpectedEof.into()),
+    }
+
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut buf = vec![0; len as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_bytes<const N: usize>(buf: &[u8], offset: usize) -> io::Result<[u8; N]> {
+    buf.get(offset..offset + N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| invalid_data("header field out of range"))
+}
+
+fn read_u16(buf: &[u8], offset: usize, big_endian: bool) -> io::Result<u16> {
+    let bytes = read_bytes(buf, offset)?;
+    Ok(if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    })
+}
+
+fn read_u32(buf: &[u8], offset: usize, big_endian: bool) -> io::Result<u32> {
+    let bytes = read_bytes(buf, offset)?;
+    Ok(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+fn read_u64(buf: &[u8], offset: usize, big_endian: bool) -> io::Result<u64> {
+    let bytes = read_bytes(buf, offset)?;
+    Ok(if big_endian {
+        u64::from_be_bytes(bytes)
+    } else {
+        u64::from_le_bytes(bytes)
+    })
+}
+
+fn trim_nul(name: &[u8]) -> &[u8] {
+    match name.iter().position(|byte| *byte == 0) {
+        Some(end) => &name[..end],
+        None => name,
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Recursively search the runfiles directory for instrumented objects.
+fn find_instrumented_objects(runfiles_dir: &Path) -> Vec<PathBuf> {
+    let mut objects = Vec::new();
+    let mut visited_dirs = BTreeSet::new();
+    let mut visited_files = BTreeSet::new();
+    let mut stack = vec![runfiles_dir.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        // Runfiles are commonly symlinks so directories are tracked by their
+        // real path to avoid traversing them more than once.
+        match dir.canonicalize() {
+            Ok(real_dir) => {
+                if !visited_dirs.insert(real_dir) {
+                    continue;
+                }
+            }
+            Err(_) => continue,
+        }
+
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            if metadata.is_dir() {
+                stack.push(path);
+                continue;
+            }
+
+            if !is_object_candidate(&path, &metadata) {
+                continue;
+            }
+
+            let real_path = path.canonicalize().unwrap_or_else(|_| path.clone());
+            if !visited_files.insert(real_path) {
+                continue;
+            }
+
+            if is_instrumented_object(&path) {
+                debug_log!("Found instrumented object: {}", path.display());
+                objects.push(path);
+            }
+        }
+    }
+
+    objects.sort();
+    objects
+}
+
+/// Locate all objects besides the test binary that coverage should be reported for.
+fn find_coverage_objects(
+    runfiles_dir: &Path,
+    test_binary: &Path,
+    declared_objects: Option<&str>,
+) -> Vec<PathBuf> {
+    let objects = match declared_objects {
+        Some(objects) => objects
+            .split_whitespace()
+            .map(|object| runfiles_dir.join(object))
+            .collect(),
+        None => find_instrumented_objects(runfiles_dir),
+    };
+
+    let test_binary = test_binary
+        .canonicalize()
+        .unwrap_or_else(|_| test_binary.to_path_buf());
+
+    let mut seen = BTreeSet::new();
+    objects
+        .into_iter()
+        .filter(|object| {
+            let real_path = object.canonicalize().unwrap_or_else(|_| object.clone());
+            real_path != test_binary && seen.insert(real_path)
+        })
+        .collect()
+}
+
+/// Create an `llvm-cov` command for the given subcommand over all instrumented objects.
+fn llvm_cov_command(
+    llvm_cov: &Path,
+    subcommand: &str,
+    profdata_file: &Path,
+    execroot: &Path,
+    test_binary: &Path,
+    objects: &[PathBuf],
+) -> process::Command {
+    let mut llvm_cov_cmd = process::Command::new(llvm_cov);
+    llvm_cov_cmd
+        .arg(subcommand)
+        .arg("-instr-profile")
+        .arg(profdata_file)
+        .arg(format!("-path-equivalence=.,'{}'", execroot.display()));
+
+    if let Ok(regex) = env::var("RUST_COVERAGE_IGNORE_FILENAME_REGEX") {
+        if !regex.is_empty() {
+            llvm_cov_cmd.arg(format!("-ignore-filename-regex={}", regex));
+        }
+    }
+
+    llvm_cov_cmd.arg(test_binary);
+    for object in objects {
+        llvm_cov_cmd.arg(format!("-object={}", object.display()));
+    }
+
+    llvm_cov_cmd
+}
+
 fn main() {
     let coverage_dir = PathBuf::from(env::var("COVERAGE_DIR").unwrap());
     let execroot = PathBuf::from(env::var("ROOT").unwrap());
@@ -111,6 +499,11 @@ fn main() {
         &env::var("RUST_LLVM_PROFDATA").unwrap(),
     );
     let test_binary = find_test_binary(&execroot, &runfiles_dir);
+    let objects = find_coverage_objects(
+        &runfiles_dir,
+        &test_binary,
+        env::var("RUST_COVERAGE_OBJECTS").ok().as_deref(),
+    );
     let profraw_files: Vec<PathBuf> = fs::read_dir(coverage_dir)
         .unwrap()
         .flatten()
@@ -142,16 +535,16 @@ fn main() {
         process::exit(status.code().unwrap_or(1));
     }
 
-    let mut llvm_cov_cmd = process::Command::new(llvm_cov);
+    let mut llvm_cov_cmd = llvm_cov_command(
+        &llvm_cov,
+        "export",
+        &profdata_file,
+        &execroot,
+        &test_binary,
+        &objects,
+    );
     llvm_cov_cmd
-        .arg("export")
         .arg("-format=lcov")
-        .arg("-instr-profile")
-        .arg(&profdata_file)
-        .arg("-ignore-filename-regex='.*external/.+'")
-        .arg("-ignore-filename-regex='/tmp/.+'")
-        .arg(format!("-path-equivalence=.,'{}'", execroot.display()))
-        .arg(test_binary)
         .stdout(process::Stdio::piped());
 
     debug_log!("Spawning {:#?}", llvm_cov_cmd);
@@ -165,14 +558,65 @@ fn main() {
     debug_log!("Parsing llvm-cov output");
     let report_str = std::str::from_utf8(&output.stdout).expect("Failed to parse llvm-cov output");
 
+    let report_str = report_str
+        .replace("#/proc/self/cwd/", "")
+        .replace(&execroot.display().to_string(), "");
+
     debug_log!("Writing output to {}", coverage_output_file.display());
-    fs::write(
-        coverage_output_file,
-        report_str
-            .replace("#/proc/self/cwd/", "")
-            .replace(&execroot.display().to_string(), ""),
-    )
-    .unwrap();
+    fs::write(coverage_output_file, &report_str).unwrap();
+
+    let report_formats =
+        parse_report_formats(&env::var("RUST_COVERAGE_REPORT_FORMATS").unwrap_or_default())
+            .unwrap_or_else(|e| panic!("Invalid RUST_COVERAGE_REPORT_FORMATS: {}", e));
+    if !report_formats.is_empty() {
+        let output_dir = PathBuf::from(
+            env::var("TEST_UNDECLARED_OUTPUTS_DIR")
+                .expect("TEST_UNDECLARED_OUTPUTS_DIR is required for additional coverage reports"),
+        );
+        let coverage = Coverage::parse_lcov(&report_str)
+            .unwrap_or_else(|e| panic!("Failed to parse lcov report: {:?}", e));
+
+        for format in report_formats {
+            match format {
+                ReportFormat::Cobertura => {
+                    let timestamp = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or_default();
+                    let path = output_dir.join("coverage.cobertura.xml");
+                    debug_log!("Writing Cobertura report to {}", path.display());
+                    fs::write(path, coverage.to_cobertura(timestamp)).unwrap();
+                }
+                ReportFormat::Json => {
+                    let path = output_dir.join("coverage_summary.json");
+                    debug_log!("Writing JSON summary to {}", path.display());
+                    fs::write(path, coverage.to_json_summary()).unwrap();
+                }
+                ReportFormat::Html => {
+                    let mut llvm_cov_cmd = llvm_cov_command(
+                        &llvm_cov,
+                        "show",
+                        &profdata_file,
+                        &execroot,
+                        &test_binary,
+                        &objects,
+                    );
+                    llvm_cov_cmd.arg("-format=html").arg(format!(
+                        "-output-dir={}",
+                        output_dir.join("coverage_html").display()
+                    ));
+
+                    debug_log!("Spawning {:#?}", llvm_cov_cmd);
+                    let status = llvm_cov_cmd
+                        .status()
+                        .expect("Failed to spawn llvm-cov process");
+                    if !status.success() {
+                        process::exit(status.code().unwrap_or(1));
+                    }
+                }
+            }
+        }
+    }
 
     // Destroy the intermediate binary file so lcov_merger doesn't parse it twice.
     debug_log!("Cleaning up {}", profdata_file.display());
@@ -180,3 +624,228 @@ fn main() {
 
     debug_log!("Success!");
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::io::Cursor;
+
+    /// Build a little endian 64-bit ELF object containing the given sections.
+    fn elf_object(sections: &[&str]) -> Vec<u8> {
+        let mut strtab = vec![0];
+        let mut name_offsets = Vec::new();
+        for name in sections.iter().chain([".shstrtab"].iter()) {
+            name_offsets.push(strtab.len() as u32);
+            strtab.extend_from_slice(name.as_bytes());
+            strtab.push(0);
+        }
+
+        let strtab_offset = 64u64;
+        let shoff = strtab_offset + strtab.len() as u64;
+        let shnum = name_offsets.len() as u16 + 1;
+
+        let mut object = vec![0; 64];
+        object[..4].copy_from_slice(b"\x7fELF");
+        object[4] = 2;
+        object[5] = 1;
+        object[0x28..0x30].copy_from_slice(&shoff.to_le_bytes());
+        object[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+        object[0x3c..0x3e].copy_from_slice(&shnum.to_le_bytes());
+        object[0x3e..0x40].copy_from_slice(&(shnum - 1).to_le_bytes());
+        object.extend_from_slice(&strtab);
+
+        // The first section header is always the null section.
+        object.extend_from_slice(&[0; 64]);
+        for name_offset in name_offsets {
+            let mut header = vec![0; 64];
+            header[..4].copy_from_slice(&name_offset.to_le_bytes());
+            header[0x18..0x20].copy_from_slice(&strtab_offset.to_le_bytes());
+            header[0x20..0x28].copy_from_slice(&(strtab.len() as u64).to_le_bytes());
+            object.extend_from_slice(&header);
+        }
+
+        object
+    }
+
+    /// Build a 64-bit Mach-O object with a single segment containing the given sections.
+    fn macho_object(sections: &[&str]) -> Vec<u8> {
+        let cmdsize = 72 + 80 * sections.len() as u32;
+
+        let mut object = vec![0; 32];
+        object[..4].copy_from_slice(&[0xcf, 0xfa, 0xed, 0xfe]);
+        object[16..20].copy_from_slice(&1u32.to_le_bytes());
+        object[20..24].copy_from_slice(&cmdsize.to_le_bytes());
+
+        let mut command = vec![0; 72];
+        command[..4].copy_from_slice(&0x19u32.to_le_bytes());
+        command[4..8].copy_from_slice(&cmdsize.to_le_bytes());
+        command[64..68].copy_from_slice(&(sections.len() as u32).to_le_bytes());
+        for name in sections {
+            let mut section = vec![0; 80];
+            section[..name.len()].copy_from_slice(name.as_bytes());
+            command.extend_from_slice(&section);
+        }
+        object.extend_from_slice(&command);
+
+        object
+    }
+
+    /// Build a PE image containing the given sections.
+    fn pe_object(sections: &[&str]) -> Vec<u8> {
+        let mut object = vec![0; 64];
+        object[..2].copy_from_slice(b"MZ");
+        object[0x3c..0x40].copy_from_slice(&64u32.to_le_bytes());
+
+        let mut pe_header = vec![0; 24];
+        pe_header[..4].copy_from_slice(b"PE\0\0");
+        pe_header[6..8].copy_from_slice(&(sections.len() as u16).to_le_bytes());
+        pe_header[20..22].copy_from_slice(&16u16.to_le_bytes());
+        object.extend_from_slice(&pe_header);
+        object.extend_from_slice(&[0; 16]);
+
+        for name in sections {
+            let mut section = vec![0; 40];
+            section[..name.len()].copy_from_slice(name.as_bytes());
+            object.extend_from_slice(&section);
+        }
+
+        object
+    }
+
+    fn section_names(object: Vec<u8>) -> Option<Vec<String>> {
+        object_section_names(&mut Cursor::new(object)).map(|names| {
+            names
+                .into_iter()
+                .map(|name| String::from_utf8(name).unwrap())
+                .collect()
+        })
+    }
+
+    fn test_tempdir(name: &str) -> PathBuf {
+        let root = env::var("TEST_TMPDIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| env::temp_dir());
+        let dir = root.join(format!("collect_coverage_{}_{}", name, process::id()));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn elf_section_names() {
+        assert_eq!(
+            section_names(elf_object(&[".text", "__llvm_covfun"])),
+            Some(vec![
+                "".to_owned(),
+                ".text".to_owned(),
+                "__llvm_covfun".to_owned(),
+                ".shstrtab".to_owned(),
+            ])
+        );
+    }
+
+    #[test]
+    fn macho_section_names() {
+        assert_eq!(
+            section_names(macho_object(&["__text", "__llvm_covfun"])),
+            Some(vec!["__text".to_owned(), "__llvm_covfun".to_owned()])
+        );
+    }
+
+    #[test]
+    fn pe_section_names() {
+        assert_eq!(
+            section_names(pe_object(&[".text", ".lcovfun"])),
+            Some(vec![".text".to_owned(), ".lcovfun".to_owned()])
+        );
+    }
+
+    #[test]
+    fn non_object_section_names() {
+        assert_eq!(section_names(b"#!/bin/bash\necho hi\n".to_vec()), None);
+
+        // Headers pointing past the end of the file are rejected.
+        let mut truncated = elf_object(&["__llvm_covfun"]);
+        truncated.truncate(100);
+        assert_eq!(section_names(truncated), None);
+    }
+
+    #[test]
+    fn current_exe_section_names() {
+        let mut file = fs::File::open(env::current_exe().unwrap()).unwrap();
+        let names = object_section_names(&mut file).unwrap();
+        assert!(!names.is_empty());
+    }
+
+    #[test]
+    fn instrumented_objects_in_runfiles() {
+        let runfiles_dir = test_tempdir("instrumented_objects_in_runfiles");
+        let pkg = runfiles_dir.join("ws/pkg");
+        fs::create_dir_all(&pkg).unwrap();
+
+        fs::write(
+            pkg.join("libinstrumented.so"),
+            elf_object(&["__llvm_covfun"]),
+        )
+        .unwrap();
+        fs::write(pkg.join("libplain.so"), elf_object(&[".text"])).unwrap();
+        fs::write(pkg.join("data.so"), "not an object").unwrap();
+        fs::write(pkg.join("instrumented.txt"), elf_object(&["__llvm_covfun"])).unwrap();
+
+        assert_eq!(
+            find_instrumented_objects(&runfiles_dir),
+            vec![pkg.join("libinstrumented.so")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn instrumented_objects_in_runfiles_symlinks() {
+        let runfiles_dir = test_tempdir("instrumented_objects_in_runfiles_symlinks");
+        let pkg = runfiles_dir.join("ws/pkg");
+        fs::create_dir_all(&pkg).unwrap();
+
+        fs::write(
+            pkg.join("libinstrumented.so"),
+            elf_object(&["__llvm_covfun"]),
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(pkg.join("libinstrumented.so"), pkg.join("liblink.so")).unwrap();
+        std::os::unix::fs::symlink(&pkg, runfiles_dir.join("ws/pkg_link")).unwrap();
+
+        assert_eq!(find_instrumented_objects(&runfiles_dir).len(), 1);
+    }
+
+    #[test]
+    fn declared_coverage_objects() {
+        let runfiles_dir = test_tempdir("declared_coverage_objects");
+        let pkg = runfiles_dir.join("ws/pkg");
+        fs::create_dir_all(&pkg).unwrap();
+        fs::write(pkg.join("test"), "").unwrap();
+        fs::write(pkg.join("tool"), "").unwrap();
+
+        // Undeclared instrumented objects are not searched for.
+        fs::write(
+            pkg.join("libinstrumented.so"),
+            elf_object(&["__llvm_covfun"]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            find_coverage_objects(
+                &runfiles_dir,
+                &pkg.join("test"),
+                Some("ws/pkg/tool ws/pkg/test ws/pkg/tool"),
+            ),
+            vec![pkg.join("tool")]
+        );
+        assert!(find_coverage_objects(&runfiles_dir, &pkg.join("test"), Some("")).is_empty());
+        assert_eq!(
+            find_coverage_objects(&runfiles_dir, &pkg.join("test"), None),
+            vec![pkg.join("libinstrumented.so")]
+        );
+    }
+}
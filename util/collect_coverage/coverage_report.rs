@@ -0,0 +1,692 @@
+//! Utilities for merging [lcov](https://github.com/linux-test-project/lcov) coverage
+//! data and rendering it in additional report formats.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use libtest_report::xml_escape;
+
+/// The error type of lcov parsing.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CoverageError {
+    /// The lcov data is malformed and cannot be parsed.
+    InvalidFormat(String),
+}
+
+/// Report formats which can be rendered in addition to lcov.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReportFormat {
+    /// [Cobertura](https://cobertura.github.io/cobertura/) XML.
+    Cobertura,
+    /// An `llvm-cov show -format=html` report.
+    Html,
+    /// A JSON summary of coverage per source file.
+    Json,
+}
+
+impl std::str::FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cobertura" => Ok(ReportFormat::Cobertura),
+            "html" => Ok(ReportFormat::Html),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(format!(
+                "Unknown coverage report format `{}`. Expected one of `cobertura`, `html`, `json`",
+                s
+            )),
+        }
+    }
+}
+
+/// Parse a comma separated list of report formats.
+pub fn parse_report_formats(text: &str) -> Result<Vec<ReportFormat>, String> {
+    let mut formats = text
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::parse)
+        .collect::<Result<Vec<ReportFormat>, String>>()?;
+    formats.sort();
+    formats.dedup();
+    Ok(formats)
+}
+
+/// Coverage data for a single source file.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FileCoverage {
+    /// Execution counts of each line.
+    pub lines: BTreeMap<u32, u64>,
+
+    /// The starting line and execution count of each function.
+    pub functions: BTreeMap<String, (u32, u64)>,
+
+    /// The number of times each `(line, block, branch)` was taken. `None` indicates
+    /// the branch was never evaluated.
+    pub branches: BTreeMap<(u32, String, String), Option<u64>>,
+}
+
+/// Hit and total counts of a coverage metric.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    /// The number of items which were executed at least once.
+    pub covered: usize,
+
+    /// The total number of items.
+    pub total: usize,
+}
+
+impl Summary {
+    /// The ratio of covered items. Empty summaries are considered fully covered.
+    pub fn rate(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.covered as f64 / self.total as f64
+        }
+    }
+
+    fn add(&mut self, other: &Summary) {
+        self.covered += other.covered;
+        self.total += other.total;
+    }
+}
+
+impl FileCoverage {
+    /// Summarize line coverage.
+    pub fn line_summary(&self) -> Summary {
+        Summary {
+            covered: self.lines.values().filter(|count| **count > 0).count(),
+            total: self.lines.len(),
+        }
+    }
+
+    /// Summarize function coverage.
+    pub fn function_summary(&self) -> Summary {
+        Summary {
+            covered: self
+                .functions
+                .values()
+                .filter(|(_, count)| *count > 0)
+                .count(),
+            total: self.functions.len(),
+        }
+    }
+
+    /// Summarize branch coverage.
+    pub fn branch_summary(&self) -> Summary {
+        Summary {
+            covered: self
+                .branches
+                .values()
+                .filter(|count| matches!(count, Some(c) if *c > 0))
+                .count(),
+            total: self.branches.len(),
+        }
+    }
+
+    fn merge(&mut self, other: &FileCoverage) {
+        for (line, count) in other.lines.iter() {
+            *self.lines.entry(*line).or_default() += count;
+        }
+        for (name, (line, count)) in other.functions.iter() {
+            let entry = self.functions.entry(name.clone()).or_insert((*line, 0));
+            entry.1 += count;
+        }
+        for (key, count) in other.branches.iter() {
+            let entry = self.branches.entry(key.clone()).or_insert(None);
+            *entry = match (*entry, count) {
+                (None, None) => None,
+                (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+            };
+        }
+    }
+}
+
+/// Coverage data for a collection of source files.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    /// Coverage data keyed by source file path.
+    pub files: BTreeMap<String, FileCoverage>,
+}
+
+fn parse_count(value: &str, line: &str) -> Result<u64, CoverageError> {
+    // Some tools emit counts as floating point numbers or negative values on overflow.
+    value
+        .parse::<u64>()
+        .or_else(|_| value.parse::<f64>().map(|v| v.max(0.0) as u64))
+        .map_err(|_| CoverageError::InvalidFormat(format!("Invalid count in lcov line: {}", line)))
+}
+
+fn parse_line_number(value: &str, line: &str) -> Result<u32, CoverageError> {
+    value.parse::<u32>().map_err(|_| {
+        CoverageError::InvalidFormat(format!("Invalid line number in lcov line: {}", line))
+    })
+}
+
+impl Coverage {
+    /// Parse lcov tracefile data.
+    pub fn parse_lcov(text: &str) -> Result<Self, CoverageError> {
+        let mut coverage = Coverage::default();
+        let mut current: Option<(String, FileCoverage)> = None;
+
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            if line == "end_of_record" {
+                if let Some((path, file)) = current.take() {
+                    coverage.files.entry(path).or_default().merge(&file);
+                }
+                continue;
+            }
+
+            let (key, value) = match line.split_once(':') {
+                Some(pair) => pair,
+                None => {
+                    return Err(CoverageError::InvalidFormat(format!(
+                        "Invalid lcov line: {}",
+                        line
+                    )))
+                }
+            };
+
+            if key == "SF" {
+                if let Some((path, file)) = current.take() {
+                    coverage.files.entry(path).or_default().merge(&file);
+                }
+                current = Some((value.to_string(), FileCoverage::default()));
+                continue;
+            }
+
+            let file = match current.as_mut() {
+                Some((_, file)) => file,
+                // Records such as `TN` may precede the first source file.
+                None => continue,
+            };
+
+            match key {
+                "DA" => {
+                    let mut parts = value.split(',');
+                    let number = parse_line_number(parts.next().unwrap_or_default(), line)?;
+                    let count = parse_count(parts.next().unwrap_or_default(), line)?;
+                    *file.lines.entry(number).or_default() += count;
+                }
+                "FN" => {
+                    // `FN:<line>,<name>` or `FN:<line>,<end line>,<name>` in newer lcov versions.
+                    let parts: Vec<&str> = value.splitn(3, ',').collect();
+                    let (number, name) = match parts.as_slice() {
+                        [number, name] => (number, name.to_string()),
+                        [number, end, name] if end.parse::<u32>().is_ok() => {
+                            (number, name.to_string())
+                        }
+                        [number, first, rest] => (number, format!("{},{}", first, rest)),
+                        _ => {
+                            return Err(CoverageError::InvalidFormat(format!(
+                                "Invalid lcov line: {}",
+                                line
+                            )))
+                        }
+                    };
+                    let number = parse_line_number(number, line)?;
+                    file.functions.entry(name).or_insert((number, 0)).0 = number;
+                }
+                "FNDA" => {
+                    let (count, name) = value.split_once(',').ok_or_else(|| {
+                        CoverageError::InvalidFormat(format!("Invalid lcov line: {}", line))
+                    })?;
+                    let count = parse_count(count, line)?;
+                    file.functions.entry(name.to_string()).or_insert((0, 0)).1 += count;
+                }
+                "BRDA" => {
+                    let parts: Vec<&str> = value.splitn(4, ',').collect();
+                    if parts.len() != 4 {
+                        return Err(CoverageError::InvalidFormat(format!(
+                            "Invalid lcov line: {}",
+                            line
+                        )));
+                    }
+                    let number = parse_line_number(parts[0], line)?;
+                    let taken = match parts[3] {
+                        "-" => None,
+                        count => Some(parse_count(count, line)?),
+                    };
+                    let entry = file
+                        .branches
+                        .entry((number, parts[1].to_string(), parts[2].to_string()))
+                        .or_insert(None);
+                    if let Some(taken) = taken {
+                        *entry = Some(entry.unwrap_or(0) + taken);
+                    }
+                }
+                // Summary records are recomputed when rendering.
+                _ => {}
+            }
+        }
+
+        if let Some((path, file)) = current.take() {
+            coverage.files.entry(path).or_default().merge(&file);
+        }
+
+        Ok(coverage)
+    }
+
+    /// Merge the coverage data of another report into this one.
+    pub fn merge(&mut self, other: &Coverage) {
+        for (path, file) in other.files.iter() {
+            self.files.entry(path.clone()).or_default().merge(file);
+        }
+    }
+
+    /// Render the coverage data as an lcov tracefile.
+    pub fn to_lcov(&self) -> String {
+        let mut out = String::new();
+        for (path, file) in self.files.iter() {
+            writeln!(out, "SF:{}", path).unwrap();
+
+            let mut functions: Vec<_> = file.functions.iter().collect();
+            functions.sort_by(|(a_name, (a_line, _)), (b_name, (b_line, _))| {
+                a_line.cmp(b_line).then(a_name.cmp(b_name))
+            });
+            for (name, (line, _)) in functions.iter() {
+                writeln!(out, "FN:{},{}", line, name).unwrap();
+            }
+            for (name, (_, count)) in functions.iter() {
+                writeln!(out, "FNDA:{},{}", count, name).unwrap();
+            }
+            let summary = file.function_summary();
+            writeln!(out, "FNF:{}", summary.total).unwrap();
+            writeln!(out, "FNH:{}", summary.covered).unwrap();
+
+            for ((line, block, branch), taken) in file.branches.iter() {
+                let taken = taken.map_or_else(|| "-".to_string(), |t| t.to_string());
+                writeln!(out, "BRDA:{},{},{},{}", line, block, branch, taken).unwrap();
+            }
+            let summary = file.branch_summary();
+            writeln!(out, "BRF:{}", summary.total).unwrap();
+            writeln!(out, "BRH:{}", summary.covered).unwrap();
+
+            for (line, count) in file.lines.iter() {
+                writeln!(out, "DA:{},{}", line, count).unwrap();
+            }
+            let summary = file.line_summary();
+            writeln!(out, "LF:{}", summary.total).unwrap();
+            writeln!(out, "LH:{}", summary.covered).unwrap();
+
+            out.push_str("end_of_record\n");
+        }
+        out
+    }
+
+    /// Summarize the coverage of all files.
+    pub fn totals(&self) -> (Summary, Summary, Summary) {
+        let mut lines = Summary::default();
+        let mut functions = Summary::default();
+        let mut branches = Summary::default();
+        for file in self.files.values() {
+            lines.add(&file.line_summary());
+            functions.add(&file.function_summary());
+            branches.add(&file.branch_summary());
+        }
+        (lines, functions, branches)
+    }
+
+    /// Render the coverage data as Cobertura XML. Files are grouped into packages by directory.
+    pub fn to_cobertura(&self, timestamp: u64) -> String {
+        let (lines, _, branches) = self.totals();
+
+        let mut packages: BTreeMap<&str, Vec<(&String, &FileCoverage)>> = BTreeMap::new();
+        for (path, file) in self.files.iter() {
+            let package = path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or(".");
+            packages.entry(package).or_default().push((path, file));
+        }
+
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str(
+            "<!DOCTYPE coverage SYSTEM \"http://cobertura.sourceforge.net/xml/coverage-04.dtd\">\n",
+        );
+        writeln!(
+            out,
+            "<coverage line-rate=\"{:.4}\" branch-rate=\"{:.4}\" lines-covered=\"{}\" lines-valid=\"{}\" branches-covered=\"{}\" branches-valid=\"{}\" complexity=\"0\" version=\"1.9\" timestamp=\"{}\">",
+            lines.rate(),
+            branches.rate(),
+            lines.covered,
+            lines.total,
+            branches.covered,
+            branches.total,
+            timestamp,
+        )
+        .unwrap();
+        out.push_str("  <sources>\n    <source>.</source>\n  </sources>\n");
+        out.push_str("  <packages>\n");
+
+        for (package, files) in packages.iter() {
+            let mut package_lines = Summary::default();
+            let mut package_branches = Summary::default();
+            for (_, file) in files.iter() {
+                package_lines.add(&file.line_summary());
+                package_branches.add(&file.branch_summary());
+            }
+
+            writeln!(
+                out,
+                "    <package name=\"{}\" line-rate=\"{:.4}\" branch-rate=\"{:.4}\" complexity=\"0\">",
+                xml_escape(&package.replace('/', ".")),
+                package_lines.rate(),
+                package_branches.rate(),
+            )
+            .unwrap();
+            out.push_str("      <classes>\n");
+
+            for (path, file) in files.iter() {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                writeln!(
+                    out,
+                    "        <class name=\"{}\" filename=\"{}\" line-rate=\"{:.4}\" branch-rate=\"{:.4}\" complexity=\"0\">",
+                    xml_escape(name),
+                    xml_escape(path),
+                    file.line_summary().rate(),
+                    file.branch_summary().rate(),
+                )
+                .unwrap();
+                out.push_str("          <methods/>\n");
+                out.push_str("          <lines>\n");
+
+                for (line, count) in file.lines.iter() {
+                    let branches: Vec<&Option<u64>> = file
+                        .branches
+                        .range(
+                            (*line, String::new(), String::new())
+                                ..(*line + 1, String::new(), String::new()),
+                        )
+                        .map(|(_, taken)| taken)
+                        .collect();
+
+                    if branches.is_empty() {
+                        writeln!(
+                            out,
+                            "            <line number=\"{}\" hits=\"{}\" branch=\"false\"/>",
+                            line, count
+                        )
+                        .unwrap();
+                    } else {
+                        let covered = branches
+                            .iter()
+                            .filter(|t| matches!(t, Some(c) if *c > 0))
+                            .count();
+                        writeln!(
+                            out,
+                            "            <line number=\"{}\" hits=\"{}\" branch=\"true\" condition-coverage=\"{}% ({}/{})\"/>",
+                            line,
+                            count,
+                            covered * 100 / branches.len(),
+                            covered,
+                            branches.len(),
+                        )
+                        .unwrap();
+                    }
+                }
+
+                out.push_str("          </lines>\n");
+                out.push_str("        </class>\n");
+            }
+
+            out.push_str("      </classes>\n");
+            out.push_str("    </package>\n");
+        }
+
+        out.push_str("  </packages>\n");
+        out.push_str("</coverage>\n");
+        out
+    }
+
+    /// Render a JSON summary of line, function, and branch coverage for each file.
+    pub fn to_json_summary(&self) -> String {
+        fn summary_json(summary: &Summary) -> String {
+            format!(
+                "{{\"covered\": {}, \"total\": {}, \"percent\": {:.2}}}",
+                summary.covered,
+                summary.total,
+                summary.rate() * 100.0
+            )
+        }
+
+        let mut out = String::from("{\n  \"files\": {");
+        for (i, (path, file)) in self.files.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write!(
+                out,
+                "\n    \"{}\": {{\"lines\": {}, \"functions\": {}, \"branches\": {}}}",
+                json_escape(path),
+                summary_json(&file.line_summary()),
+                summary_json(&file.function_summary()),
+                summary_json(&file.branch_summary()),
+            )
+            .unwrap();
+        }
+        if !self.files.is_empty() {
+            out.push_str("\n  ");
+        }
+
+        let (lines, functions, branches) = self.totals();
+        write!(
+            out,
+            "}},\n  \"totals\": {{\"lines\": {}, \"functions\": {}, \"branches\": {}}}\n}}\n",
+            summary_json(&lines),
+            summary_json(&functions),
+            summary_json(&branches),
+        )
+        .unwrap();
+        out
+    }
+}
+
+fn json_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LCOV_A: &str = "\
+SF:pkg/lib.rs
+FN:1,_RNvCs_3lib3add
+FN:5,_RNvCs_3lib3sub
+FNDA:2,_RNvCs_3lib3add
+FNDA:0,_RNvCs_3lib3sub
+FNF:2
+FNH:1
+BRDA:2,0,0,1
+BRDA:2,0,1,-
+BRF:2
+BRH:1
+DA:1,2
+DA:2,2
+DA:5,0
+DA:6,0
+LF:4
+LH:2
+end_of_record
+";
+
+    const LCOV_B: &str = "\
+TN:
+SF:pkg/lib.rs
+FN:1,_RNvCs_3lib3add
+FN:5,_RNvCs_3lib3sub
+FNDA:0,_RNvCs_3lib3add
+FNDA:1,_RNvCs_3lib3sub
+BRDA:2,0,0,-
+BRDA:2,0,1,-
+DA:1,0
+DA:2,0
+DA:5,1
+DA:6,1
+end_of_record
+SF:pkg/sub/main.rs
+DA:1,1
+end_of_record
+";
+
+    #[test]
+    fn parse_report_formats_test() {
+        assert_eq!(
+            parse_report_formats("json, cobertura,,json").unwrap(),
+            vec![ReportFormat::Cobertura, ReportFormat::Json]
+        );
+        assert_eq!(parse_report_formats("").unwrap(), vec![]);
+        assert!(parse_report_formats("xml").is_err());
+    }
+
+    #[test]
+    fn parse_lcov_test() {
+        let coverage = Coverage::parse_lcov(LCOV_A).unwrap();
+        let file = &coverage.files["pkg/lib.rs"];
+
+        assert_eq!(
+            file.line_summary(),
+            Summary {
+                covered: 2,
+                total: 4
+            }
+        );
+        assert_eq!(
+            file.function_summary(),
+            Summary {
+                covered: 1,
+                total: 2
+            }
+        );
+        assert_eq!(
+            file.branch_summary(),
+            Summary {
+                covered: 1,
+                total: 2
+            }
+        );
+        assert_eq!(file.functions["_RNvCs_3lib3sub"], (5, 0));
+    }
+
+    #[test]
+    fn parse_lcov_invalid_test() {
+        assert_eq!(
+            Coverage::parse_lcov("SF:lib.rs\nDA:one,1\nend_of_record\n"),
+            Err(CoverageError::InvalidFormat(
+                "Invalid line number in lcov line: DA:one,1".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn merge_test() {
+        let mut coverage = Coverage::parse_lcov(LCOV_A).unwrap();
+        coverage.merge(&Coverage::parse_lcov(LCOV_B).unwrap());
+
+        let file = &coverage.files["pkg/lib.rs"];
+        assert_eq!(
+            file.line_summary(),
+            Summary {
+                covered: 4,
+                total: 4
+            }
+        );
+        assert_eq!(
+            file.function_summary(),
+            Summary {
+                covered: 2,
+                total: 2
+            }
+        );
+        assert_eq!(
+            file.branch_summary(),
+            Summary {
+                covered: 1,
+                total: 2
+            }
+        );
+        assert_eq!(file.lines[&1], 2);
+        assert!(coverage.files.contains_key("pkg/sub/main.rs"));
+    }
+
+    #[test]
+    fn lcov_round_trip_test() {
+        let coverage = Coverage::parse_lcov(LCOV_A).unwrap();
+        assert_eq!(coverage.to_lcov(), LCOV_A);
+    }
+
+    #[test]
+    fn to_cobertura_test() {
+        let coverage = Coverage::parse_lcov(LCOV_A).unwrap();
+
+        assert_eq!(
+            coverage.to_cobertura(1234),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE coverage SYSTEM "http://cobertura.sourceforge.net/xml/coverage-04.dtd">
+<coverage line-rate="0.5000" branch-rate="0.5000" lines-covered="2" lines-valid="4" branches-covered="1" branches-valid="2" complexity="0" version="1.9" timestamp="1234">
+  <sources>
+    <source>.</source>
+  </sources>
+  <packages>
+    <package name="pkg" line-rate="0.5000" branch-rate="0.5000" complexity="0">
+      <classes>
+        <class name="lib.rs" filename="pkg/lib.rs" line-rate="0.5000" branch-rate="0.5000" complexity="0">
+          <methods/>
+          <lines>
+            <line number="1" hits="2" branch="false"/>
+            <line number="2" hits="2" branch="true" condition-coverage="50% (1/2)"/>
+            <line number="5" hits="0" branch="false"/>
+            <line number="6" hits="0" branch="false"/>
+          </lines>
+        </class>
+      </classes>
+    </package>
+  </packages>
+</coverage>
+"#
+        );
+    }
+
+    #[test]
+    fn to_json_summary_test() {
+        let coverage = Coverage::parse_lcov(LCOV_B).unwrap();
+
+        assert_eq!(
+            coverage.to_json_summary(),
+            r#"{
+  "files": {
+    "pkg/lib.rs": {"lines": {"covered": 2, "total": 4, "percent": 50.00}, "functions": {"covered": 1, "total": 2, "percent": 50.00}, "branches": {"covered": 0, "total": 2, "percent": 0.00}},
+    "pkg/sub/main.rs": {"lines": {"covered": 1, "total": 1, "percent": 100.00}, "functions": {"covered": 0, "total": 0, "percent": 100.00}, "branches": {"covered": 0, "total": 0, "percent": 100.00}}
+  },
+  "totals": {"lines": {"covered": 3, "total": 5, "percent": 60.00}, "functions": {"covered": 1, "total": 2, "percent": 50.00}, "branches": {"covered": 0, "total": 2, "percent": 0.00}}
+}
+"#
+        );
+    }
+
+    #[test]
+    fn to_json_summary_empty_test() {
+        assert_eq!(
+            Coverage::default().to_json_summary(),
+            "{\n  \"files\": {},\n  \"totals\": {\"lines\": {\"covered\": 0, \"total\": 0, \"percent\": 100.00}, \"functions\": {\"covered\": 0, \"total\": 0, \"percent\": 100.00}, \"branches\": {\"covered\": 0, \"total\": 0, \"percent\": 100.00}}\n}\n"
+        );
+    }
+
+    #[test]
+    fn escape_test() {
+        assert_eq!(json_escape("a\"b\\c\n\u{1}"), "a\\\"b\\\\c\\n\\u0001");
+    }
+}
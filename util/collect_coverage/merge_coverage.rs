@@ -0,0 +1,155 @@
+//! A tool for combining the per-test coverage outputs of a `bazel coverage` run.
+//!
+//! Each test's lcov `coverage.dat` file is merged into a single report which is
+//! then written to the output directory as `coverage.dat` along with any of the
+//! additional requested report formats.
+//!
+//! Usage:
+//!
+//! ```text
+//! bazel run @rules_rust//util/collect_coverage:merge_coverage -- \
+//!     --output_dir=<dir> [--formats=cobertura,json] [<file or directory>...]
+//! ```
+//!
+//! Directories are searched recursively for `coverage.dat` files. When no inputs are
+//! provided, `bazel-testlogs` of the current workspace is searched. Relative paths are
+//! resolved against the workspace when run through `bazel run`.
+
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use coverage_report::{parse_report_formats, Coverage, ReportFormat};
+
+struct Args {
+    output_dir: PathBuf,
+    formats: Vec<ReportFormat>,
+    inputs: Vec<PathBuf>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let workspace_dir = env::var("BUILD_WORKSPACE_DIRECTORY")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| env::current_dir().expect("Failed to get current directory"));
+
+    let mut output_dir = None;
+    let mut formats = vec![ReportFormat::Cobertura, ReportFormat::Json];
+    let mut inputs = Vec::new();
+
+    for arg in env::args().skip(1) {
+        if let Some(value) = arg.strip_prefix("--output_dir=") {
+            output_dir = Some(workspace_dir.join(value));
+        } else if let Some(value) = arg.strip_prefix("--formats=") {
+            formats = parse_report_formats(value)?;
+        } else if arg.starts_with("--") {
+            return Err(format!("Unknown argument: {}", arg));
+        } else {
+            inputs.push(workspace_dir.join(arg));
+        }
+    }
+
+    if formats.contains(&ReportFormat::Html) {
+        return Err(
+            "HTML reports require profile data and can only be generated per test".to_string(),
+        );
+    }
+
+    if inputs.is_empty() {
+        inputs.push(workspace_dir.join("bazel-testlogs"));
+    }
+
+    Ok(Args {
+        output_dir: output_dir
+            .ok_or_else(|| "Missing required argument `--output_dir`".to_string())?,
+        formats,
+        inputs,
+    })
+}
+
+/// Recursively collect all `coverage.dat` files in a directory.
+fn find_coverage_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("Failed to read directory: {}\n{:?}", dir.display(), e));
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            find_coverage_files(&path, files);
+        } else if path.file_name() == Some(OsStr::new("coverage.dat")) {
+            files.push(path);
+        }
+    }
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    let mut coverage_files = Vec::new();
+    for input in args.inputs.iter() {
+        if input.is_dir() {
+            find_coverage_files(input, &mut coverage_files);
+        } else {
+            coverage_files.push(input.clone());
+        }
+    }
+    coverage_files.sort();
+
+    let mut coverage = Coverage::default();
+    for path in coverage_files.iter() {
+        let content = fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read: {}\n{:?}", path.display(), e));
+        let report = Coverage::parse_lcov(&content)
+            .unwrap_or_else(|e| panic!("Failed to parse: {}\n{:?}", path.display(), e));
+        coverage.merge(&report);
+    }
+
+    fs::create_dir_all(&args.output_dir).unwrap_or_else(|e| {
+        panic!(
+            "Failed to create directory: {}\n{:?}",
+            args.output_dir.display(),
+            e
+        )
+    });
+
+    fs::write(args.output_dir.join("coverage.dat"), coverage.to_lcov())
+        .expect("Failed to write lcov report");
+
+    for format in args.formats {
+        match format {
+            ReportFormat::Cobertura => {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default();
+                fs::write(
+                    args.output_dir.join("coverage.cobertura.xml"),
+                    coverage.to_cobertura(timestamp),
+                )
+                .expect("Failed to write Cobertura report");
+            }
+            ReportFormat::Json => {
+                fs::write(
+                    args.output_dir.join("coverage_summary.json"),
+                    coverage.to_json_summary(),
+                )
+                .expect("Failed to write JSON summary");
+            }
+            ReportFormat::Html => unreachable!("HTML reports are rejected when parsing arguments"),
+        }
+    }
+
+    let (lines, _, _) = coverage.totals();
+    eprintln!(
+        "Merged {} coverage reports into {} ({}/{} lines covered)",
+        coverage_files.len(),
+        args.output_dir.display(),
+        lines.covered,
+        lines.total,
+    );
+}
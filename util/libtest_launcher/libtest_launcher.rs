@@ -0,0 +1,267 @@
+//! A launcher for `rust_test` targets using the libtest harness.
+//!
+//! Bazel communicates test sharding and reporting requirements through environment
+//! variables which libtest does not understand. This launcher runs the test binary
+//! named by `RUST_LIBTEST_LAUNCHER_TEST_BINARY` and adds support for:
+//!
+//! - `--test_filter` via `TESTBRIDGE_TEST_ONLY`.
+//! - Test sharding via `TEST_TOTAL_SHARDS`, `TEST_SHARD_INDEX` and `TEST_SHARD_STATUS_FILE`.
+//! - Reporting individual test results via `XML_OUTPUT_FILE`.
+//!
+//! Timings and captured output are reported when the test binary accepts libtest's
+//! unstable json output (nightly toolchains or `RUSTC_BOOTSTRAP=1`). Otherwise the
+//! default output of the test binary is parsed instead.
+
+use std::env;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+
+use libtest_report::{
+    format_result, format_summary, parse_json_output, parse_list_output, parse_pretty_output,
+    shard_from_env, write_junit_xml, TestResult, UNSTABLE_OPTIONS_ERROR,
+};
+use runfiles::{rlocation, Runfiles};
+
+/// The arguments which enable libtest's json output.
+const JSON_ARGS: [&str; 3] = ["-Zunstable-options", "--format=json", "--report-time"];
+
+/// Returns true for arguments which change what libtest prints. The test binary is run
+/// directly when any are passed since its output can not be interpreted.
+fn is_passthrough_arg(arg: &str) -> bool {
+    matches!(
+        arg,
+        "--list" | "--help" | "-h" | "--bench" | "--format" | "-Z"
+    ) || arg.starts_with("--format=")
+        || arg.starts_with("-Z")
+}
+
+/// Remove positional test name filters from libtest arguments.
+fn strip_filters(args: &[String]) -> Vec<String> {
+    let mut options = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') {
+            continue;
+        }
+        options.push(arg.clone());
+
+        // Options which take a separate value.
+        if matches!(
+            arg.as_str(),
+            "--skip" | "--test-threads" | "--logfile" | "--color" | "--shuffle-seed"
+        ) {
+            options.extend(args.next().cloned());
+        }
+    }
+    options
+}
+
+/// Replace any test name filters in libtest arguments with exact filters for the given tests.
+fn exact_filters(args: &[String], tests: Vec<String>) -> Vec<String> {
+    let mut args = strip_filters(args);
+
+    // libtest rejects options which are passed more than once.
+    if !args.iter().any(|arg| arg == "--exact") {
+        args.push("--exact".to_owned());
+    }
+    args.extend(tests);
+    args
+}
+
+/// Determine the tests which belong to a shard.
+fn shard_tests(names: &[String], index: usize, total: usize) -> Vec<String> {
+    let mut names = names.to_vec();
+    names.sort();
+    names
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| i % total == index)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// Run the test binary and stream its output while collecting test results.
+///
+/// In json mode, test events are printed the way the default libtest output reports them.
+fn run_tests(test_binary: &Path, args: &[String], json: bool) -> (i32, Vec<TestResult>) {
+    let mut command = Command::new(test_binary);
+    if json {
+        command.args(JSON_ARGS);
+    }
+    command.args(args).stdout(Stdio::piped());
+
+    let mut child = command
+        .spawn()
+        .unwrap_or_else(|e| panic!("Failed to spawn command: {:#?}\n{:?}", command, e));
+
+    let mut output = String::new();
+    let mut results = Vec::new();
+    let stdout = child.stdout.take().unwrap();
+    for line in BufReader::new(stdout).lines() {
+        let line = line.expect("Failed to read test output");
+        if !json {
+            println!("{}", line);
+            output.push_str(&line);
+            output.push('\n');
+        } else if line.starts_with('{') {
+            for result in parse_json_output(&line) {
+                println!("{}", format_result(&result));
+                results.push(result);
+            }
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    let status = child.wait().expect("Failed to wait for test binary");
+    if json {
+        print!("{}", format_summary(&results));
+    } else {
+        results = parse_pretty_output(&output);
+    }
+
+    (status.code().unwrap_or(1), results)
+}
+
+fn main() {
+    let runfiles = Runfiles::create().expect("Failed to locate runfiles");
+    let test_binary: PathBuf = rlocation!(
+        runfiles,
+        env::var("RUST_LIBTEST_LAUNCHER_TEST_BINARY")
+            .expect("Failed to find RUST_LIBTEST_LAUNCHER_TEST_BINARY env var")
+    )
+    .expect("Failed to locate test binary");
+
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let test_filter = env::var("TESTBRIDGE_TEST_ONLY")
+        .ok()
+        .filter(|f| !f.is_empty());
+    if let Some(filter) = test_filter {
+        args.push(filter);
+    }
+
+    let shard = shard_from_env();
+
+    // Without anything to report or select there is no need to interpret the output.
+    if args.iter().any(|arg| is_passthrough_arg(arg))
+        || (shard.is_none() && env::var_os("XML_OUTPUT_FILE").is_none())
+    {
+        let status = Command::new(&test_binary)
+            .args(&args)
+            .status()
+            .unwrap_or_else(|e| panic!("Failed to spawn: {}\n{:?}", test_binary.display(), e));
+        process::exit(status.code().unwrap_or(1));
+    }
+
+    // Listing the tests is cheap and reveals whether json output is supported.
+    let list = Command::new(&test_binary)
+        .args(JSON_ARGS)
+        .arg("--list")
+        .args(&args)
+        .output()
+        .unwrap_or_else(|e| panic!("Failed to spawn: {}\n{:?}", test_binary.display(), e));
+    let json = list.status.success();
+    if !json && !String::from_utf8_lossy(&list.stderr).contains(UNSTABLE_OPTIONS_ERROR) {
+        eprint!("{}", String::from_utf8_lossy(&list.stderr));
+        process::exit(list.status.code().unwrap_or(1));
+    }
+
+    if let Some((index, total)) = shard {
+        let names = if json {
+            parse_list_output(&String::from_utf8_lossy(&list.stdout))
+        } else {
+            let list = Command::new(&test_binary)
+                .arg("--list")
+                .args(&args)
+                .output()
+                .unwrap_or_else(|e| panic!("Failed to spawn: {}\n{:?}", test_binary.display(), e));
+            if !list.status.success() {
+                eprint!("{}", String::from_utf8_lossy(&list.stderr));
+                process::exit(list.status.code().unwrap_or(1));
+            }
+            parse_list_output(&String::from_utf8_lossy(&list.stdout))
+        };
+
+        let selected = shard_tests(&names, index, total);
+        if selected.is_empty() {
+            println!("No tests selected to run in shard {} of {}.", index, total);
+            write_junit_xml(&[]);
+            return;
+        }
+
+        // Exact filters of the selected tests replace any filters passed to the launcher.
+        args = exact_filters(&args, selected);
+    }
+
+    let (code, results) = run_tests(&test_binary, &args, json);
+
+    // Leave reporting to Bazel if the test binary failed before any tests ran.
+    if !results.is_empty() || code == 0 {
+        write_junit_xml(&results);
+    }
+
+    process::exit(code);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn is_passthrough_arg_test() {
+        assert!(is_passthrough_arg("--list"));
+        assert!(is_passthrough_arg("--format=terse"));
+        assert!(is_passthrough_arg("-Zunstable-options"));
+        assert!(!is_passthrough_arg("--nocapture"));
+        assert!(!is_passthrough_arg("tests::format"));
+    }
+
+    #[test]
+    fn strip_filters_test() {
+        let args: Vec<String> = ["tests::", "--skip", "slow", "--nocapture", "--color=never"]
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+
+        assert_eq!(
+            strip_filters(&args),
+            vec!["--skip", "slow", "--nocapture", "--color=never"]
+        );
+    }
+
+    #[test]
+    fn exact_filters_test() {
+        let to_args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            exact_filters(
+                &to_args(&["tests::", "--nocapture"]),
+                to_args(&["tests::a"])
+            ),
+            vec!["--nocapture", "--exact", "tests::a"]
+        );
+        assert_eq!(
+            exact_filters(
+                &to_args(&["--exact", "tests::b"]),
+                to_args(&["tests::a", "tests::b"])
+            ),
+            vec!["--exact", "tests::a", "tests::b"]
+        );
+    }
+
+    #[test]
+    fn shard_tests_test() {
+        let names: Vec<String> = ["tests::d", "tests::a", "tests::c", "tests::b", "tests::e"]
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+
+        assert_eq!(
+            shard_tests(&names, 0, 2),
+            vec!["tests::a", "tests::c", "tests::e"]
+        );
+        assert_eq!(shard_tests(&names, 1, 2), vec!["tests::b", "tests::d"]);
+        assert!(shard_tests(&names, 5, 6).is_empty());
+    }
+}
@@ -0,0 +1,626 @@
+//! Utilities for interpreting the output of [libtest](https://doc.rust-lang.org/rustc/tests/index.html)
+//! harnesses and reporting results to Bazel as JUnit XML.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use tinyjson::JsonValue;
+
+/// The error printed by libtest when unstable options are used on a stable toolchain.
+pub const UNSTABLE_OPTIONS_ERROR: &str = "is only accepted on the nightly compiler";
+
+/// The outcome of a single test.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TestStatus {
+    /// The test passed.
+    Passed,
+
+    /// The test failed.
+    Failed,
+
+    /// The test was not run. Contains the reason if one was given.
+    Ignored(Option<String>),
+}
+
+/// The result of a single test.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestResult {
+    /// The name of the test.
+    pub name: String,
+
+    /// The outcome of the test.
+    pub status: TestStatus,
+
+    /// The time in seconds the test took to run, if reported.
+    pub duration: Option<f64>,
+
+    /// Output captured for the test. This is only populated for failures.
+    pub output: String,
+}
+
+impl TestResult {
+    fn new(name: &str, status: TestStatus) -> Self {
+        Self {
+            name: name.to_owned(),
+            status,
+            duration: None,
+            output: String::new(),
+        }
+    }
+}
+
+fn get_str<'a>(
+    map: &'a std::collections::HashMap<String, JsonValue>,
+    key: &str,
+) -> Option<&'a str> {
+    match map.get(key) {
+        Some(JsonValue::String(s)) => Some(s.as_str()),
+        _ => None,
+    }
+}
+
+fn get_number(map: &std::collections::HashMap<String, JsonValue>, key: &str) -> Option<f64> {
+    match map.get(key) {
+        Some(JsonValue::Number(n)) => Some(*n),
+        _ => None,
+    }
+}
+
+/// Parse the names of tests from the output of a libtest harness run with `--list`.
+///
+/// Both the default (`<name>: test`) and json (`"event": "discovered"`) formats are supported.
+///
+/// ```
+/// let output = "tests::a: test\ntests::b: test\n\n2 tests, 0 benchmarks\n";
+/// assert_eq!(
+///     libtest_report::parse_list_output(output),
+///     vec!["tests::a".to_owned(), "tests::b".to_owned()],
+/// );
+/// ```
+pub fn parse_list_output(output: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    for line in output.lines() {
+        if line.starts_with('{') {
+            if let Ok(JsonValue::Object(map)) = line.parse::<JsonValue>() {
+                if get_str(&map, "type") == Some("test")
+                    && get_str(&map, "event") == Some("discovered")
+                {
+                    if let Some(name) = get_str(&map, "name") {
+                        names.push(name.to_owned());
+                    }
+                }
+            }
+        } else if let Some(name) = line.strip_suffix(": test") {
+            names.push(name.to_owned());
+        }
+    }
+    names
+}
+
+/// Parse test results from the output of a libtest harness run with `--format=json`.
+///
+/// Lines which are not json test events are ignored.
+pub fn parse_json_output(output: &str) -> Vec<TestResult> {
+    let mut results = Vec::new();
+    for line in output.lines().filter(|l| l.starts_with('{')) {
+        let map = match line.parse::<JsonValue>() {
+            Ok(JsonValue::Object(map)) => map,
+            _ => continue,
+        };
+
+        if get_str(&map, "type") != Some("test") {
+            continue;
+        }
+
+        let name = match get_str(&map, "name") {
+            Some(name) => name,
+            None => continue,
+        };
+
+        let message = get_str(&map, "message").filter(|m| !m.is_empty());
+        let status = match get_str(&map, "event") {
+            Some("ok") => TestStatus::Passed,
+            Some("failed") | Some("timeout") => TestStatus::Failed,
+            Some("ignored") => TestStatus::Ignored(message.map(str::to_owned)),
+            _ => continue,
+        };
+
+        let mut result = TestResult::new(name, status);
+        result.duration = get_number(&map, "exec_time");
+        if result.status == TestStatus::Failed {
+            let mut output = get_str(&map, "stdout").unwrap_or_default().to_owned();
+            if let Some(message) = message {
+                if !output.is_empty() && !output.ends_with('\n') {
+                    output.push('\n');
+                }
+                output.push_str(message);
+            }
+            result.output = output.trim_end().to_owned();
+        }
+        results.push(result);
+    }
+    results
+}
+
+/// Parse test results from the default (pretty) output of a libtest harness.
+///
+/// Tests are reported as `test <name> ... <ok|FAILED|ignored[, reason]>` and the captured
+/// output of failed tests follows in `---- <name> stdout ----` sections. The output of
+/// `wasm-bindgen-test`, which reports failures as `FAIL` in `---- <name> output ----`
+/// sections, is understood as well.
+pub fn parse_pretty_output(output: &str) -> Vec<TestResult> {
+    let mut results: Vec<TestResult> = Vec::new();
+    let mut failure_outputs: BTreeMap<String, String> = BTreeMap::new();
+    let mut current_failure: Option<(String, Vec<&str>)> = None;
+
+    for line in output.lines() {
+        if let Some(name) = line.strip_prefix("---- ").and_then(|l| {
+            l.strip_suffix(" stdout ----")
+                .or_else(|| l.strip_suffix(" output ----"))
+        }) {
+            if let Some((name, lines)) = current_failure.take() {
+                failure_outputs.insert(name, lines.join("\n").trim_end().to_owned());
+            }
+            current_failure = Some((name.to_owned(), Vec::new()));
+            continue;
+        }
+
+        if line == "failures:" || line.starts_with("test result: ") {
+            if let Some((name, lines)) = current_failure.take() {
+                failure_outputs.insert(name, lines.join("\n").trim_end().to_owned());
+            }
+            continue;
+        }
+
+        if let Some((_, lines)) = current_failure.as_mut() {
+            lines.push(line);
+            continue;
+        }
+
+        let (name, outcome) = match line
+            .strip_prefix("test ")
+            .and_then(|l| l.rsplit_once(" ... "))
+        {
+            Some(pair) => pair,
+            None => continue,
+        };
+
+        let status = match outcome.trim_end() {
+            "ok" => TestStatus::Passed,
+            "FAILED" | "FAIL" => TestStatus::Failed,
+            "ignored" => TestStatus::Ignored(None),
+            other => match other.strip_prefix("ignored, ") {
+                Some(reason) => TestStatus::Ignored(Some(reason.to_owned())),
+                None => continue,
+            },
+        };
+
+        results.push(TestResult::new(name, status));
+    }
+
+    if let Some((name, lines)) = current_failure.take() {
+        failure_outputs.insert(name, lines.join("\n").trim_end().to_owned());
+    }
+
+    for result in results.iter_mut() {
+        if let Some(output) = failure_outputs.remove(&result.name) {
+            result.output = output;
+        }
+    }
+
+    results
+}
+
+/// Parse the Bazel sharding environment variables (`TEST_SHARD_INDEX`, `TEST_TOTAL_SHARDS`).
+///
+/// Returns `None` if sharding is not enabled.
+pub fn shard_from_env() -> Option<(usize, usize)> {
+    let total = std::env::var("TEST_TOTAL_SHARDS")
+        .ok()?
+        .parse::<usize>()
+        .ok()?;
+    let index = std::env::var("TEST_SHARD_INDEX")
+        .ok()?
+        .parse::<usize>()
+        .ok()?;
+    if total <= 1 {
+        return None;
+    }
+
+    // Inform Bazel that sharding is supported.
+    if let Ok(status_file) = std::env::var("TEST_SHARD_STATUS_FILE") {
+        std::fs::write(&status_file, "").unwrap_or_else(|e| {
+            panic!(
+                "Failed to write shard status file: {}\n{:?}",
+                status_file, e
+            )
+        });
+    }
+
+    Some((index, total))
+}
+
+/// Format a test result the way the default libtest output reports it, e.g. `test add ... ok`.
+pub fn format_result(result: &TestResult) -> String {
+    let status = match &result.status {
+        TestStatus::Passed => "ok".to_owned(),
+        TestStatus::Failed => "FAILED".to_owned(),
+        TestStatus::Ignored(None) => "ignored".to_owned(),
+        TestStatus::Ignored(Some(reason)) => format!("ignored, {}", reason),
+    };
+    format!("test {} ... {}", result.name, status)
+}
+
+/// Format the failure output and summary which the default libtest output prints once all
+/// tests have run.
+pub fn format_summary(results: &[TestResult]) -> String {
+    let failures: Vec<&TestResult> = results
+        .iter()
+        .filter(|r| r.status == TestStatus::Failed)
+        .collect();
+    let ignored = results
+        .iter()
+        .filter(|r| matches!(r.status, TestStatus::Ignored(_)))
+        .count();
+
+    let mut summary = String::new();
+    if !failures.is_empty() {
+        summary.push_str("\nfailures:\n\n");
+        for failure in failures.iter() {
+            writeln!(
+                summary,
+                "---- {} stdout ----\n{}\n",
+                failure.name, failure.output
+            )
+            .unwrap();
+        }
+        summary.push_str("\nfailures:\n");
+        for failure in failures.iter() {
+            writeln!(summary, "    {}", failure.name).unwrap();
+        }
+    }
+
+    writeln!(
+        summary,
+        "\ntest result: {}. {} passed; {} failed; {} ignored",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        results.len() - failures.len() - ignored,
+        failures.len(),
+        ignored,
+    )
+    .unwrap();
+    summary
+}
+
+/// Escape text for use in XML attributes and content.
+pub fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than whitespace are not valid in XML 1.0.
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Render test results as a JUnit XML report in the format Bazel expects for `XML_OUTPUT_FILE`.
+///
+/// The time of the suite defaults to the sum of the durations of its tests.
+pub fn render_junit_xml(suite: &str, results: &[TestResult], duration: Option<f64>) -> String {
+    let failures = results
+        .iter()
+        .filter(|r| r.status == TestStatus::Failed)
+        .count();
+    let skipped = results
+        .iter()
+        .filter(|r| matches!(r.status, TestStatus::Ignored(_)))
+        .count();
+    let duration = duration.unwrap_or_else(|| {
+        results
+            .iter()
+            .filter_map(|r| r.duration)
+            .fold(0.0, |total, d| total + d)
+    });
+    let suite = xml_escape(suite);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n");
+    writeln!(
+        xml,
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{:.3}\">",
+        suite,
+        results.len(),
+        failures,
+        skipped,
+        duration,
+    )
+    .unwrap();
+
+    for result in results {
+        write!(
+            xml,
+            "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+            xml_escape(&result.name),
+            suite,
+            result.duration.unwrap_or_default(),
+        )
+        .unwrap();
+
+        match &result.status {
+            TestStatus::Passed => xml.push_str("/>\n"),
+            TestStatus::Failed => {
+                writeln!(
+                    xml,
+                    ">\n      <failure message=\"{} failed\">{}</failure>\n    </testcase>",
+                    xml_escape(&result.name),
+                    xml_escape(&result.output),
+                )
+                .unwrap();
+            }
+            TestStatus::Ignored(reason) => {
+                let skipped = match reason {
+                    Some(reason) => format!("<skipped message=\"{}\"/>", xml_escape(reason)),
+                    None => "<skipped/>".to_owned(),
+                };
+                writeln!(xml, ">\n      {}\n    </testcase>", skipped).unwrap();
+            }
+        }
+    }
+
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// Write a JUnit XML report to `XML_OUTPUT_FILE` if Bazel requested one.
+pub fn write_junit_xml(results: &[TestResult]) {
+    let xml_output_file = match std::env::var_os("XML_OUTPUT_FILE") {
+        Some(path) => std::path::PathBuf::from(path),
+        None => return,
+    };
+
+    let suite = std::env::var("TEST_TARGET").unwrap_or_else(|_| "rust_test".to_owned());
+    std::fs::write(&xml_output_file, render_junit_xml(&suite, results, None)).unwrap_or_else(|e| {
+        panic!(
+            "Failed to write test report: {}\n{:?}",
+            xml_output_file.display(),
+            e
+        )
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_list_output_test() {
+        let pretty = "\
+lib.rs - add (line 5): test
+lib.rs - sub (line 12): test
+bench_add: bench
+
+2 tests, 1 benchmarks
+";
+        assert_eq!(
+            parse_list_output(pretty),
+            vec!["lib.rs - add (line 5)", "lib.rs - sub (line 12)"]
+        );
+
+        let json = r#"{ "type": "suite", "event": "discovery" }
+{ "type": "test", "event": "discovered", "name": "tests::one", "ignore": false }
+{ "type": "suite", "event": "completed", "tests": 1, "benchmarks": 0, "total": 1, "ignored": 0 }
+"#;
+        assert_eq!(parse_list_output(json), vec!["tests::one"]);
+    }
+
+    #[test]
+    fn parse_json_output_test() {
+        let output = r#"{ "type": "suite", "event": "started", "test_count": 3 }
+{ "type": "test", "event": "started", "name": "lib.rs - add (line 5)" }
+{ "type": "test", "name": "lib.rs - add (line 5)", "event": "ok", "exec_time": 0.5 }
+{ "type": "test", "name": "lib.rs - sub (line 12)", "event": "failed", "exec_time": 0.25, "stdout": "assertion failed\n" }
+{ "type": "test", "name": "tests::slow", "event": "ignored", "message": "too slow" }
+{ "type": "suite", "event": "failed", "passed": 1, "failed": 1, "ignored": 1, "measured": 0, "filtered_out": 0, "exec_time": 0.75 }
+"#;
+
+        assert_eq!(
+            parse_json_output(output),
+            vec![
+                TestResult {
+                    name: "lib.rs - add (line 5)".to_owned(),
+                    status: TestStatus::Passed,
+                    duration: Some(0.5),
+                    output: String::new(),
+                },
+                TestResult {
+                    name: "lib.rs - sub (line 12)".to_owned(),
+                    status: TestStatus::Failed,
+                    duration: Some(0.25),
+                    output: "assertion failed".to_owned(),
+                },
+                TestResult {
+                    name: "tests::slow".to_owned(),
+                    status: TestStatus::Ignored(Some("too slow".to_owned())),
+                    duration: None,
+                    output: String::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_pretty_output_test() {
+        let output = "\
+running 3 tests
+test lib.rs - add (line 5) ... ok
+test lib.rs - sub (line 12) ... FAILED
+test lib.rs - mul (line 20) ... ignored
+
+failures:
+
+---- lib.rs - sub (line 12) stdout ----
+Test executable failed (exit status: 101).
+
+stderr:
+assertion failed
+
+
+failures:
+    lib.rs - sub (line 12)
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.22s
+";
+
+        assert_eq!(
+            parse_pretty_output(output),
+            vec![
+                TestResult::new("lib.rs - add (line 5)", TestStatus::Passed),
+                TestResult {
+                    name: "lib.rs - sub (line 12)".to_owned(),
+                    status: TestStatus::Failed,
+                    duration: None,
+                    output:
+                        "Test executable failed (exit status: 101).\n\nstderr:\nassertion failed"
+                            .to_owned(),
+                },
+                TestResult::new("lib.rs - mul (line 20)", TestStatus::Ignored(None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_pretty_output_wasm_bindgen_test() {
+        let output = "\
+running 4 tests
+
+test hello_world::tests::test_double_four ... ok
+test hello_world::tests::test_double_two ... ignored
+test hello_world::tests::test_skipped ... ignored, not on ci
+test hello_world::tests::test_broken ... FAIL
+
+failures:
+
+---- hello_world::tests::test_broken output ----
+    error output:
+        panicked at main.rs:12:9:
+        assertion `left == right` failed
+
+    JS exception that was thrown:
+        RuntimeError: unreachable
+
+failures:
+
+    hello_world::tests::test_broken
+
+test result: FAILED. 1 passed; 1 failed; 2 ignored; 0 filtered out; finished in 0.01s
+";
+
+        assert_eq!(
+            parse_pretty_output(output),
+            vec![
+                TestResult::new("hello_world::tests::test_double_four", TestStatus::Passed),
+                TestResult::new(
+                    "hello_world::tests::test_double_two",
+                    TestStatus::Ignored(None)
+                ),
+                TestResult::new(
+                    "hello_world::tests::test_skipped",
+                    TestStatus::Ignored(Some("not on ci".to_owned()))
+                ),
+                TestResult {
+                    name: "hello_world::tests::test_broken".to_owned(),
+                    status: TestStatus::Failed,
+                    duration: None,
+                    output: "    error output:\n        panicked at main.rs:12:9:\n        assertion `left == right` failed\n\n    JS exception that was thrown:\n        RuntimeError: unreachable".to_owned(),
+                },
+            ]
+        );
+
+        let skipped = "this test suite is only configured to run in a browser, \
+                       but we're only testing node.js tests so skipping\n";
+        assert!(parse_pretty_output(skipped).is_empty());
+    }
+
+    #[test]
+    fn format_results_test() {
+        let results = vec![
+            TestResult::new("tests::ok", TestStatus::Passed),
+            TestResult {
+                name: "tests::bad".to_owned(),
+                status: TestStatus::Failed,
+                duration: None,
+                output: "assertion failed".to_owned(),
+            },
+            TestResult::new("tests::skip", TestStatus::Ignored(Some("slow".to_owned()))),
+        ];
+
+        assert_eq!(
+            results.iter().map(format_result).collect::<Vec<_>>(),
+            vec![
+                "test tests::ok ... ok",
+                "test tests::bad ... FAILED",
+                "test tests::skip ... ignored, slow",
+            ]
+        );
+        assert_eq!(
+            format_summary(&results),
+            "\nfailures:\n\n\
+             ---- tests::bad stdout ----\nassertion failed\n\n\
+             \nfailures:\n    tests::bad\n\
+             \ntest result: FAILED. 1 passed; 1 failed; 1 ignored\n"
+        );
+    }
+
+    #[test]
+    fn render_junit_xml_test() {
+        let results = vec![
+            TestResult {
+                name: "tests::ok".to_owned(),
+                status: TestStatus::Passed,
+                duration: Some(0.5),
+                output: String::new(),
+            },
+            TestResult {
+                name: "tests::<bad>".to_owned(),
+                status: TestStatus::Failed,
+                duration: Some(0.25),
+                output: "left & right\u{1b}[0m".to_owned(),
+            },
+            TestResult::new("tests::skip", TestStatus::Ignored(Some("slow".to_owned()))),
+        ];
+
+        assert_eq!(
+            render_junit_xml("//pkg:test", &results, None),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="//pkg:test" tests="3" failures="1" errors="0" skipped="1" time="0.750">
+    <testcase name="tests::ok" classname="//pkg:test" time="0.500"/>
+    <testcase name="tests::&lt;bad&gt;" classname="//pkg:test" time="0.250">
+      <failure message="tests::&lt;bad&gt; failed">left &amp; right[0m</failure>
+    </testcase>
+    <testcase name="tests::skip" classname="//pkg:test" time="0.000">
+      <skipped message="slow"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+
+        assert!(render_junit_xml("//pkg:test", &results, Some(1.5))
+            .contains(r#"skipped="1" time="1.500">"#));
+    }
+
+    #[test]
+    fn xml_escape_test() {
+        assert_eq!(
+            xml_escape("<a href=\"x\">'&'</a>\u{1b}[0m"),
+            "&lt;a href=&quot;x&quot;&gt;&apos;&amp;&apos;&lt;/a&gt;[0m"
+        );
+    }
+}